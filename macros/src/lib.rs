@@ -0,0 +1,296 @@
+//! Derive macros exposing Rust types to ruspy scripts
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Data, DeriveInput, Error, Fields, FnArg, ImplItem, ItemImpl, Result, Type,
+    parse_macro_input,
+};
+
+/// Implements `ScriptClass`, making the named fields attributes of the object.
+///
+/// Fields must be `Clone + IntoValue + FromValue`, unless marked `#[script(skip)]`.
+/// Fields marked `#[script(readonly)]` can't be set by scripts.
+/// The type also needs a `#[script_methods]` block, which may be empty.
+#[proc_macro_derive(ScriptClass, attributes(script))]
+pub fn derive_script_class(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_class(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Implements `ScriptMethods` for the methods of the block taking `&self` or `&mut self`,
+/// which become bound methods of the object. Their arguments must be `FromValue`
+/// and their result `IntoValue`, possibly in a `RuntimeResult`. A last `NativeArgs`
+/// parameter receives the arguments, to call back into scripts.
+///
+/// Methods marked `#[script(skip)]` and associated functions are left out.
+#[proc_macro_attribute]
+pub fn script_methods(_args: TokenStream, input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ItemImpl);
+    expand_methods(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Options given with `#[script(...)]`
+#[derive(Default)]
+struct Options {
+    skip: bool,
+    readonly: bool,
+}
+
+impl Options {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut options = Options::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("script")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    options.skip = true;
+                } else if meta.path.is_ident("readonly") {
+                    options.readonly = true;
+                } else {
+                    return Err(meta.error("expected `skip` or `readonly`"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(options)
+    }
+}
+
+fn expand_class(input: DeriveInput) -> Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            "ScriptClass can only be derived for structs",
+        ));
+    };
+
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new(
+            data.fields.span(),
+            "ScriptClass needs named fields",
+        ));
+    };
+
+    let mut names = Vec::new();
+    let mut getters = Vec::new();
+    let mut setters = Vec::new();
+
+    for field in &fields.named {
+        let options = Options::parse(&field.attrs)?;
+
+        if options.skip {
+            continue;
+        }
+
+        let ident = field.ident.as_ref().expect("Fields are named");
+        let name = ident.to_string();
+        names.push(name.clone());
+
+        getters.push(quote! {
+            #name => ::core::option::Option::Some(
+                ::ruspy::runtime::convert::IntoValue::into_value(
+                    ::core::clone::Clone::clone(&self.#ident),
+                ),
+            ),
+        });
+
+        setters.push(match options.readonly {
+            true => quote! {
+                #name => ::core::option::Option::Some(::core::result::Result::Err(
+                    ::ruspy::runtime::convert::ConversionError {
+                        kind: ::ruspy::runtime::error::ExceptionKind::AttributeError,
+                        path: ::std::vec::Vec::new(),
+                        message: ::std::string::String::from("is read-only"),
+                    },
+                )),
+            },
+            false => quote! {
+                #name => ::core::option::Option::Some(
+                    ::ruspy::runtime::convert::FromValue::from_value(value)
+                        .map(|value| self.#ident = value),
+                ),
+            },
+        });
+    }
+
+    let ident = &input.ident;
+    let name = ident.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::ruspy::runtime::native::ScriptClass for #ident #ty_generics
+        #where_clause
+        {
+            const NAME: &'static str = #name;
+
+            const FIELDS: &'static [&'static str] = &[#(#names),*];
+
+            fn get_field(
+                &self,
+                name: &str,
+            ) -> ::core::option::Option<
+                ::core::result::Result<
+                    ::ruspy::runtime::value::RuntimeValue,
+                    ::ruspy::runtime::convert::ConversionError,
+                >,
+            > {
+                match name {
+                    #(#getters)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn set_field(
+                &mut self,
+                name: &str,
+                value: &::ruspy::runtime::value::RuntimeValue,
+            ) -> ::core::option::Option<
+                ::core::result::Result<(), ::ruspy::runtime::convert::ConversionError>,
+            > {
+                match name {
+                    #(#setters)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+        }
+
+        impl #impl_generics ::ruspy::runtime::convert::IntoValue for #ident #ty_generics
+        #where_clause
+        {
+            fn into_value(
+                self,
+            ) -> ::core::result::Result<
+                ::ruspy::runtime::value::RuntimeValue,
+                ::ruspy::runtime::convert::ConversionError,
+            > {
+                ::core::result::Result::Ok(::ruspy::runtime::value::RuntimeValue::NativeObject(
+                    ::std::rc::Rc::new(::ruspy::runtime::native::NativeObject::new(self)),
+                ))
+            }
+        }
+    })
+}
+
+fn is_native_args(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "NativeArgs"),
+        _ => false,
+    }
+}
+
+fn expand_methods(mut input: ItemImpl) -> Result<TokenStream2> {
+    let mut methods = Vec::new();
+
+    for item in &mut input.items {
+        let ImplItem::Fn(function) = item else {
+            continue;
+        };
+
+        let options = Options::parse(&function.attrs)?;
+        function
+            .attrs
+            .retain(|attr| !attr.path().is_ident("script"));
+
+        let Some(receiver) = function.sig.receiver() else {
+            continue;
+        };
+
+        if options.skip {
+            continue;
+        }
+
+        if receiver.reference.is_none() {
+            return Err(Error::new(
+                receiver.span(),
+                "methods exposed to scripts must take `&self` or `&mut self`",
+            ));
+        }
+
+        let ident = &function.sig.ident;
+        let name = ident.to_string();
+        let mut types = function
+            .sig
+            .inputs
+            .iter()
+            .filter_map(|arg| match arg {
+                FnArg::Typed(arg) => Some(&*arg.ty),
+                FnArg::Receiver(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        // A last `NativeArgs` parameter gets the arguments themselves
+        let native_args = types.last().is_some_and(|ty| is_native_args(ty));
+
+        if native_args {
+            types.pop();
+        }
+
+        if let Some(ty) = types.iter().find(|ty| is_native_args(ty)) {
+            return Err(Error::new(
+                ty.span(),
+                "`NativeArgs` must be the last parameter",
+            ));
+        }
+
+        let len = types.len();
+        let mut args = types
+            .iter()
+            .enumerate()
+            .map(|(idx, ty)| quote! { args.convert::<#ty>(#idx)? })
+            .collect::<Vec<_>>();
+
+        if native_args {
+            args.push(quote! { args });
+        }
+
+        let (variant, this_ty) = match receiver.mutability {
+            Some(_) => (quote! { Mut }, quote! { &mut Self }),
+            None => (quote! { Ref }, quote! { &Self }),
+        };
+
+        methods.push(quote! {
+            #name => ::core::option::Option::Some(
+                ::ruspy::runtime::native::ScriptMethod::#variant(
+                    |this: #this_ty, args: ::ruspy::runtime::native::NativeArgs| {
+                        args.expect_len(#len)?;
+                        ::ruspy::runtime::native::IntoNativeResult::into_result(
+                            Self::#ident(this, #(#args),*),
+                        )
+                    },
+                ),
+            ),
+        });
+    }
+
+    let self_ty = &input.self_ty;
+    let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        #input
+
+        impl #impl_generics ::ruspy::runtime::native::ScriptMethods for #self_ty #where_clause {
+            fn method(
+                name: &str,
+            ) -> ::core::option::Option<::ruspy::runtime::native::ScriptMethod<Self>> {
+                match name {
+                    #(#methods)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+        }
+    })
+}
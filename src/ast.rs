@@ -1,3 +1,4 @@
+mod names;
 pub mod node;
 mod pretty_print;
 pub mod source;
@@ -5,253 +6,2354 @@ pub mod source;
 mod tests;
 pub mod utils;
 
+use core::fmt;
 use std::collections::VecDeque;
+use std::rc::Rc;
 
-use ariadne::{Color, Label};
-use node::{AstBinaryOp, AstExpr, AstScope, AstStatement, AstUnaryOp};
-use source::SourceAst;
+use corosensei::stack::DefaultStack;
+use node::{
+    AstAlias, AstBinaryOp, AstComprehension, AstComprehensionKind, AstExceptHandler, AstExpr,
+    AstForClause, AstFunction, AstKeyword, AstLogicalOp, AstMatchCase, AstNames, AstParam,
+    AstParamKind, AstPattern, AstScope, AstStatement, AstUnaryOp, Spanned, SpannedExpr,
+    SpannedName, SpannedPattern, SpannedStatement,
+};
+use source::{AstResult, SourceAst};
 
-use crate::lexer::token::{SpannedToken, Token};
-use crate::{T, kw, scope};
+use crate::diagnostic::{Diagnostic, codes};
+use crate::lexer::span::Span;
+use crate::lexer::token::{SpannedToken, Token, TokenLiteral};
+use crate::source_map::SourceFile;
+use crate::{T, kw};
+
+/// Soft keywords, they are names everywhere else
+const MATCH: &str = "match";
+const CASE: &str = "case";
+
+/// Deepest nesting of expressions, patterns and blocks, so that parsing,
+/// running and dropping the tree can't overflow the stack
+pub const MAX_NESTING: usize = 200;
+
+/// Size of the stack the parser runs on, enough for [`MAX_NESTING`] levels in debug builds
+const PARSER_STACK_SIZE: usize = 8 << 20;
 
 impl AstScope {
-    pub fn from_tokens(base: &str, tokens: VecDeque<SpannedToken>) -> AstScope {
-        SourceAst::new(base, tokens).parse_scope(0)
+    pub fn from_tokens(file: &SourceFile, tokens: VecDeque<SpannedToken>) -> AstResult<AstScope> {
+        Self::from_tokens_with_warnings(file, tokens).map(|(scope, _)| scope)
+    }
+
+    /// Same as [`AstScope::from_tokens`], also returning the warnings found
+    pub fn from_tokens_with_warnings(
+        file: &SourceFile,
+        tokens: VecDeque<SpannedToken>,
+    ) -> AstResult<(AstScope, Vec<Diagnostic>)> {
+        on_parser_stack(|| {
+            let mut source = SourceAst::new(file, tokens);
+            let scope = source.parse_scope(0)?;
+
+            if let Some(name) = source.nonlocals.first() {
+                return Err(source.invalid_stmt(
+                    name.span,
+                    format!("no binding for nonlocal '{}' found", name.node),
+                ));
+            }
+
+            Ok((scope, source.warnings))
+        })
     }
 }
 
-macro_rules! fn_bin_op {
-    ($fn:ident, $base:ident; $($tk:ident => $op:ident),+  ) => {
-        fn $fn(&mut self) -> AstExpr {
-            let Some(mut tokens) = self
-                .tokens
-                .iter()
-                .take_while(|t| **t != T![Newline])
-                .position(|t| false $(|| *t == T![$tk])+)
-                .map(|idx| self.tokens.split_off(idx + 1))
-                .map(|tokens| self.with(tokens))
-            else {
-                return self.$base();
-            };
+impl SpannedExpr {
+    /// Parses a single expression, statements are rejected
+    pub fn from_tokens(
+        file: &SourceFile,
+        tokens: VecDeque<SpannedToken>,
+    ) -> AstResult<SpannedExpr> {
+        on_parser_stack(|| {
+            let mut source = SourceAst::new(file, tokens);
 
-            let op = self.tokens.pop_back().unwrap();
+            source.skip_blank_lines()?;
+            let expr = source.parse_expr()?;
+            source.skip_blank_lines()?;
 
-            let left = self.$base().into();
+            match source.tokens.pop_front() {
+                None => Ok(expr),
+                Some(token) => Err(source.error_at(
+                    token.span,
+                    format!(
+                        "Unexpected token: {:?}. Expected end of expression",
+                        token.token
+                    ),
+                )),
+            }
+        })
+    }
+}
 
-            let op = match *op {
-                $(T![$tk] => AstBinaryOp::$op,)+
-                _ => unreachable!(),
-            };
+/// Runs `parse` on a stack of its own, the host's may be too small for [`MAX_NESTING`]
+fn on_parser_stack<T>(parse: impl FnOnce() -> AstResult<T>) -> AstResult<T> {
+    match DefaultStack::new(PARSER_STACK_SIZE) {
+        Ok(mut stack) => corosensei::on_stack(&mut stack, parse),
+        // Code that isn't deeply nested still parses on the current stack
+        Err(_) => parse(),
+    }
+}
+
+macro_rules! fn_bin_op {
+    ($fn:ident, $base:ident; $($tk:pat => $op:ident),+  ) => {
+        /// Left-associative binary operators of the same precedence
+        fn $fn(&mut self) -> AstResult<SpannedExpr> {
+            let depth = self.depth;
+            let mut left = self.$base()?;
 
-            let right = tokens.$fn().into();
+            while let Some(token) = self.peek() {
+                let op = match **token {
+                    $($tk => AstBinaryOp::$op,)+
+                    _ => {
+                        token.recover();
+                        break;
+                    }
+                };
 
-            *self = tokens;
+                token.accept();
+                // Each operator nests the expression so far one level deeper
+                self.nest()?;
 
-            AstExpr::BinaryOp {
-                op,
-                left,
-                right,
+                let right = self.$base()?;
+
+                left = SpannedExpr::new(
+                    left.span.join(right.span),
+                    AstExpr::BinaryOp {
+                        op,
+                        left: left.into(),
+                        right: right.into(),
+                    },
+                );
             }
+
+            self.depth = depth;
+            Ok(left)
         }
     };
 }
 
 impl SourceAst<'_> {
-    fn parse_scope(&mut self, level: usize) -> AstScope {
-        let mut nodes = Vec::new();
+    fn parse_scope(&mut self, level: usize) -> AstResult<AstScope> {
+        let mut nodes = Vec::<SpannedStatement>::new();
 
-        loop {
-            if !self.parse_pre_statement(level) {
-                break;
+        self.nested(|source| {
+            while source.parse_pre_statement(level) {
+                nodes.push(source.parse_statement(level)?);
             }
 
-            let stmt = self.parse_statement(level);
-
-            nodes.push(stmt);
-        }
-
-        AstScope(nodes)
-    }
-
-    fn eat_indent(&mut self, level: usize) -> Option<bool> {
-        for _ in 0..level {
-            let tk = self.peek()?;
+            Ok(())
+        })?;
 
-            match *tk.token {
-                T![Indentation] => continue,
-                // Next line
-                T![Newline] => return Some(true),
-                // Exit from scope
-                _ => {
-                    tk.recover();
-                    return None;
-                }
-            }
-        }
+        let span = match (nodes.first(), nodes.last()) {
+            (Some(first), Some(last)) => first.span.join(last.span),
+            _ => self.span_in_place(),
+        };
 
-        Some(false)
+        Ok(AstScope::new(span, nodes))
     }
 
-    /// Prepare for statement. Returns false if there're no relevant tokens at same level
+    /// Prepare for statement. Returns false if there're no relevant tokens at same level,
+    /// leaving the line breaks and indentation for the enclosing scopes
     fn parse_pre_statement(&mut self, level: usize) -> bool {
-        let Some(first) = self.peek() else {
-            return false;
-        };
-
-        if *first != T![Newline] {
-            first.recover();
-            return true;
+        match self.tokens.front() {
+            None => return false,
+            Some(first) if *first != T![Newline] => return true,
+            Some(_) => {}
         }
 
+        // Points to the newline before each line
+        let mut idx = 0;
+
         loop {
-            if level != 0 {
-                match self.eat_indent(level) {
-                    Some(false) => {}
-                    // Next line
-                    Some(true) => continue,
-                    None => break false,
-                }
-            }
+            let line = idx + 1;
+            let indent = self
+                .tokens
+                .range(line..)
+                .take_while(|t| **t == T![Indentation])
+                .count();
 
-            let Some(first) = self.peek() else {
-                break false;
-            };
+            match self.tokens.get(line + indent) {
+                None => return false,
+                // Empty line
+                Some(token) if *token == T![Newline] => idx = line + indent,
+                Some(_) if indent >= level => {
+                    let last = self.tokens.drain(..line + level).next_back();
 
-            if *first == T![Newline] {
-                continue;
-            }
+                    if let Some(last) = last {
+                        self.last_offset = last.span.to;
+                    }
 
-            first.recover();
-            break true;
+                    return true;
+                }
+                // Exit from scope
+                Some(_) => return false,
+            }
         }
     }
 
     fn peek_stmt<T>(
         &mut self,
         level: usize,
-        callback: impl Fn(&mut SourceAst<'_>, SpannedToken) -> Option<T>,
-    ) -> Option<T> {
+        callback: impl Fn(&mut SourceAst<'_>, SpannedToken) -> Option<AstResult<T>>,
+    ) -> AstResult<Option<T>> {
         let mut peek = self.clone();
 
-        peek.parse_pre_statement(level)
+        let result = peek
+            .parse_pre_statement(level)
             .then(|| peek.tokens.pop_front())
             .flatten()
             .and_then(|tk| callback(&mut peek, tk))
-            .inspect(move |_| *self = peek)
+            .transpose()?;
+
+        if result.is_some() {
+            *self = peek;
+        }
+
+        Ok(result)
     }
 
-    fn parse_statement(&mut self, level: usize) -> AstStatement {
-        let first = self.peek_expect();
+    fn parse_statement(&mut self, level: usize) -> AstResult<SpannedStatement> {
+        if self.next_is(Token::Ident(MATCH.to_owned()))
+            && let Some(stmt) = self.parse_stmt_match(level)?
+        {
+            return Ok(stmt);
+        }
+
+        let first = self.peek_expect()?;
+        let start = first.span;
+
+        match **first {
+            Token::Ident(_) if first.source.next_is(T![Equal]) => {
+                let var = first
+                    .accept()
+                    .token
+                    .into_ident()
+                    .map(|var| SpannedName::new(start, var))
+                    .expect("Already checked above");
+                self.expect()?;
+                let expr = self.parse_expr()?;
+
+                self.end_stmt(SpannedStatement::new(
+                    start.join(expr.span),
+                    AstStatement::VariableDeclaration(var, expr.into()),
+                ))
+            }
 
-        let stmt = match **first {
             Token::Ident(_) => {
-                let token = first.source.peek_expect();
-
-                if let T![Equal] = *token.token {
-                    let var = first
-                        .accept()
-                        .token
-                        .into_ident()
-                        .expect("Already checked above");
-                    AstStatement::VariableDeclaration(var, self.parse_expr().into())
-                } else {
-                    token.recover();
-                    first.recover();
+                first.recover();
+                self.parse_stmt_expr()
+            }
+
+            kw!(Global) => {
+                first.accept();
+
+                let (vars, end) = self.parse_name_list()?;
+
+                self.end_stmt(SpannedStatement::new(
+                    start.join(end),
+                    AstStatement::Global(vars),
+                ))
+            }
 
-                    AstStatement::Expresion(self.parse_expr().into())
+            kw!(Nonlocal) => {
+                first.accept();
+
+                if !self.in_nested_scope {
+                    return Err(self
+                        .invalid_stmt(start, "nonlocal declaration not allowed at module level"));
                 }
+
+                let (vars, end) = self.parse_name_list()?;
+                self.nonlocals.extend(vars.iter().cloned());
+
+                self.end_stmt(SpannedStatement::new(
+                    start.join(end),
+                    AstStatement::Nonlocal(vars),
+                ))
             }
 
-            kw!(Global) => {
-                let mut vars = Vec::new();
+            kw!(If) => {
+                first.accept();
+                self.parse_stmt_if(level, start)
+            }
 
-                loop {
-                    let token = self.expect_match("Ident", |t| t.token.into_ident());
+            kw!(While) => {
+                first.accept();
+                self.parse_stmt_while(level, start)
+            }
 
-                    vars.push(token);
+            kw!(Def) => {
+                first.accept();
+                self.parse_stmt_def(level, start)
+            }
 
-                    let Some(token) = self.tokens.pop_front() else {
-                        break;
-                    };
+            kw!(Try) => {
+                first.accept();
+                self.parse_stmt_try(level, start)
+            }
 
-                    match *token {
-                        T![Newline] => break,
-                        T![Comma] => continue,
-                        _ => self.error_at(
-                            token.span,
-                            format!("Unexpected token: {:?}. Expected ','", token.token),
-                        ),
+            kw!(Class) => {
+                first.accept();
+                self.parse_stmt_class(level, start)
+            }
+
+            kw!(Return) => {
+                first.accept();
+
+                if !self.in_function {
+                    return Err(self.invalid_stmt(start, "'return' outside function"));
+                }
+
+                let value = self.parse_optional_expr()?;
+                let end = value.as_ref().map_or(start, |value| value.span);
+
+                self.end_stmt(SpannedStatement::new(
+                    start.join(end),
+                    AstStatement::Return(value.map(Into::into)),
+                ))
+            }
+
+            kw!(Raise) => {
+                first.accept();
+
+                let exception = self.parse_optional_expr()?;
+                let cause = match (&exception, self.peek()) {
+                    (Some(_), Some(token)) if *token == kw!(From) => {
+                        token.accept();
+                        Some(self.parse_expr()?)
+                    }
+                    (_, Some(token)) => {
+                        token.recover();
+                        None
+                    }
+                    (_, None) => None,
+                };
+
+                let end = cause
+                    .as_ref()
+                    .or(exception.as_ref())
+                    .map_or(start, |e| e.span);
+
+                self.end_stmt(SpannedStatement::new(
+                    start.join(end),
+                    AstStatement::Raise {
+                        exception: exception.map(Into::into),
+                        cause: cause.map(Into::into),
+                    },
+                ))
+            }
+
+            kw!(Import) => {
+                first.accept();
+
+                let mut names = Vec::new();
+
+                let end = loop {
+                    let (name, span) = self.parse_dotted_name()?;
+                    let (alias, end) = self.parse_alias(span)?;
+
+                    names.push(AstAlias { name, alias });
+
+                    if !self.next_is(T![Comma]) {
+                        break end;
+                    }
+
+                    self.expect()?;
+                };
+
+                self.end_stmt(SpannedStatement::new(
+                    start.join(end),
+                    AstStatement::Import(names),
+                ))
+            }
+
+            kw!(From) => {
+                first.accept();
+                self.parse_stmt_import_from(start)
+            }
+
+            kw!(Pass) => {
+                first.accept();
+                self.end_stmt(SpannedStatement::new(start, AstStatement::Pass))
+            }
+
+            kw!(Assert) => {
+                first.accept();
+
+                let test = self.parse_expr()?;
+                let message = match self.next_is(T![Comma]) {
+                    true => {
+                        self.expect()?;
+                        Some(self.parse_expr()?)
+                    }
+                    false => None,
+                };
+
+                let source = self
+                    .file
+                    .contents()
+                    .get(test.span.from..test.span.to)
+                    .unwrap_or_default()
+                    .to_owned();
+                let end = message.as_ref().unwrap_or(&test).span;
+
+                self.end_stmt(SpannedStatement::new(
+                    start.join(end),
+                    AstStatement::Assert {
+                        test: test.into(),
+                        message: message.map(Into::into),
+                        source,
+                    },
+                ))
+            }
+
+            kw!(Del) => {
+                first.accept();
+
+                let mut targets = Vec::from([self.parse_expr_postfix()?]);
+
+                while self.next_is(T![Comma]) {
+                    self.expect()?;
+                    targets.push(self.parse_expr_postfix()?);
+                }
+
+                for target in &targets {
+                    if !matches!(
+                        target.node,
+                        AstExpr::Ident(_) | AstExpr::Attribute { .. } | AstExpr::Subscript { .. }
+                    ) {
+                        return Err(self.invalid_stmt(target.span, "cannot delete expression"));
                     }
                 }
 
-                AstStatement::Global(vars)
+                let end = targets[targets.len() - 1].span;
+
+                self.end_stmt(SpannedStatement::new(
+                    start.join(end),
+                    AstStatement::Delete(targets),
+                ))
             }
 
-            kw!(If) => self.parse_stmt_if(level),
+            kw!(Break) | kw!(Continue) => {
+                let (stmt, keyword) = match first.accept().token {
+                    kw!(Break) => (AstStatement::Break, "break"),
+                    _ => (AstStatement::Continue, "continue"),
+                };
+
+                if !self.in_loop {
+                    return Err(self.invalid_stmt(start, format!("'{keyword}' outside loop")));
+                }
+
+                self.end_stmt(SpannedStatement::new(start, stmt))
+            }
 
-            T![Bang] | Token::Literal(_) => {
+            T![Bang]
+            | T![Minus]
+            | T![LeftParen]
+            | T![LeftBracket]
+            | T![LeftBrace]
+            | kw!(Lambda)
+            | kw!(Not)
+            | kw!(Yield)
+            | Token::Literal(_) => {
                 first.recover();
-                AstStatement::Expresion(self.parse_expr().into())
+                self.parse_stmt_expr()
             }
 
             _ => {
                 let first = first.accept();
-                self.error_at(first.span, format!("Unexpected token: {:?}.", first.token))
+                Err(self.error_at(first.span, format!("Unexpected token: {:?}.", first.token)))
             }
+        }
+    }
+
+    /// Skips newlines and indentation, which don't matter around a lone expression
+    fn skip_blank_lines(&mut self) -> AstResult<()> {
+        while self
+            .tokens
+            .front()
+            .is_some_and(|token| matches!(token.token, T![Newline] | T![Indentation]))
+        {
+            self.expect()?;
+        }
+
+        Ok(())
+    }
+
+    /// Simple statements end at a newline, at the end of the input,
+    /// or at a `;` followed by another statement on the same line
+    fn end_stmt(&mut self, stmt: SpannedStatement) -> AstResult<SpannedStatement> {
+        let Some(token) = self.peek() else {
+            return Ok(stmt);
         };
 
-        stmt
+        if *token == T![Newline] {
+            token.recover();
+            return Ok(stmt);
+        }
+
+        if *token == T![Semicolon] {
+            token.accept();
+            return Ok(stmt);
+        }
+
+        let token = token.accept();
+
+        Err(self.error_at(
+            token.span,
+            format!(
+                "Unexpected token: {:?}. Expected newline or ';'",
+                token.token
+            ),
+        ))
+    }
+
+    fn invalid_stmt(&self, span: Span, msg: impl fmt::Display) -> Diagnostic {
+        Diagnostic::error(codes::INVALID_STATEMENT, &msg).with_label(span, msg)
     }
 
-    fn parse_stmt_if(&mut self, level: usize) -> AstStatement {
-        let test = self.parse_expr().into();
+    /// Goes one level of nesting deeper, failing past [`MAX_NESTING`]
+    fn nest(&mut self) -> AstResult<()> {
+        if self.depth >= MAX_NESTING {
+            let span = self
+                .tokens
+                .front()
+                .map_or_else(|| self.span_in_place(), |token| token.span);
+            let msg = format!("code is nested more than {MAX_NESTING} levels deep");
 
-        self.expect_token(T![Colon]);
+            return Err(Diagnostic::error(codes::TOO_DEEPLY_NESTED, &msg).with_label(span, msg));
+        }
 
-        let body = self.parse_scope(level + 1);
+        self.depth += 1;
+        Ok(())
+    }
 
-        let otherwise = self.peek_stmt(level, |source, keyword| match keyword.token {
-            kw!(Else) => {
-                source.expect_token(T![Colon]);
+    /// Runs `parse` one level of nesting deeper, see [`SourceAst::nest`]
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> AstResult<T>) -> AstResult<T> {
+        let depth = self.depth;
+        self.nest()?;
+        let result = parse(self);
+        self.depth = depth;
+        result
+    }
+
+    /// Whether the next token is `token`, without consuming it
+    fn next_is(&self, token: Token) -> bool {
+        self.tokens.front().is_some_and(|next| *next == token)
+    }
 
-                Some(source.parse_scope(level + 1))
+    /// Comma-separated names, with the span of the last one
+    fn parse_name_list(&mut self) -> AstResult<(Vec<SpannedName>, Span)> {
+        let mut names = Vec::new();
+
+        loop {
+            let (span, name) =
+                self.expect_match("Ident", |t| t.token.into_ident().map(|i| (t.span, i)))?;
+
+            names.push(SpannedName::new(span, name));
+
+            match self.peek() {
+                Some(token) if *token == T![Comma] => continue,
+                Some(token) => token.recover(),
+                None => {}
             }
-            kw!(Elif) => Some(scope![source.parse_stmt_if(level)]),
-            _ => None,
-        });
 
-        AstStatement::Conditional {
-            test,
-            body,
-            otherwise,
+            return Ok((names, span));
         }
     }
 
-    fn parse_expr(&mut self) -> AstExpr {
-        self.parse_expr_bin_2()
+    /// `name.name...`, with the span of the whole name
+    fn parse_dotted_name(&mut self) -> AstResult<(String, Span)> {
+        let (start, mut name) =
+            self.expect_match("Ident", |t| t.token.into_ident().map(|i| (t.span, i)))?;
+        let mut end = start;
+
+        while self.next_is(T![Dot]) {
+            self.expect()?;
+
+            let (span, part) =
+                self.expect_match("Ident", |t| t.token.into_ident().map(|i| (t.span, i)))?;
+
+            name.push('.');
+            name.push_str(&part);
+            end = span;
+        }
+
+        Ok((name, start.join(end)))
     }
 
-    fn parse_expr_base(&mut self) -> AstExpr {
-        let first = self.expect();
+    /// Optional `as alias` after an imported name ending at `end`, with the new end
+    fn parse_alias(&mut self, end: Span) -> AstResult<(Option<String>, Span)> {
+        if !self.next_is(kw!(As)) {
+            return Ok((None, end));
+        }
 
-        match first.token {
-            Token::Ident(ident) => AstExpr::Ident(ident),
-            Token::Literal(lit) => AstExpr::Literal(lit),
-            T![Bang] => AstExpr::UnaryOp {
-                op: AstUnaryOp::Not,
-                right: self.parse_expr_base().into(),
+        self.expect()?;
+
+        let (span, alias) =
+            self.expect_match("Ident", |t| t.token.into_ident().map(|i| (t.span, i)))?;
+
+        Ok((Some(alias), span))
+    }
+
+    /// `from module import a as b, c`, names may be wrapped in parentheses
+    fn parse_stmt_import_from(&mut self, start: Span) -> AstResult<SpannedStatement> {
+        let mut level = 0;
+
+        while self.next_is(T![Dot]) {
+            self.expect()?;
+            level += 1;
+        }
+
+        let module = match level == 0
+            || self
+                .tokens
+                .front()
+                .is_some_and(|token| matches!(token.token, Token::Ident(_)))
+        {
+            true => Some(self.parse_dotted_name()?.0),
+            false => None,
+        };
+
+        self.expect_token(kw!(Import))?;
+
+        let parenthesized = self.next_is(T![LeftParen]);
+
+        if parenthesized {
+            self.expect()?;
+        }
+
+        let mut names = Vec::new();
+
+        let mut end = loop {
+            let (span, name) =
+                self.expect_match("Ident", |t| t.token.into_ident().map(|i| (t.span, i)))?;
+            let (alias, end) = self.parse_alias(span)?;
+
+            names.push(AstAlias { name, alias });
+
+            if !self.next_is(T![Comma]) {
+                break end;
+            }
+
+            self.expect()?;
+
+            if parenthesized && self.next_is(T![RightParen]) {
+                break end;
+            }
+        };
+
+        if parenthesized {
+            end = self.expect_token(T![RightParen])?.span;
+        }
+
+        self.end_stmt(SpannedStatement::new(
+            start.join(end),
+            AstStatement::ImportFrom {
+                module,
+                level,
+                names,
+            },
+        ))
+    }
+
+    /// Expression statement, or assignment if it's followed by `=`
+    fn parse_stmt_expr(&mut self) -> AstResult<SpannedStatement> {
+        let expr = self.parse_expr()?;
+
+        match self.peek() {
+            Some(token) if *token == T![Equal] => token.accept(),
+            Some(token) => {
+                token.recover();
+
+                return self.end_stmt(SpannedStatement::new(
+                    expr.span,
+                    AstStatement::Expresion(expr.into()),
+                ));
+            }
+            None => {
+                return Ok(SpannedStatement::new(
+                    expr.span,
+                    AstStatement::Expresion(expr.into()),
+                ));
+            }
+        };
+
+        let value = self.parse_expr()?;
+        let span = expr.span.join(value.span);
+
+        let stmt = match expr.node {
+            AstExpr::Ident(var) => {
+                AstStatement::VariableDeclaration(SpannedName::new(expr.span, var), value.into())
+            }
+            AstExpr::Attribute { .. } | AstExpr::Subscript { .. } => AstStatement::Assignment {
+                target: expr.into(),
+                value: value.into(),
+            },
+            _ => return Err(self.invalid_stmt(expr.span, "cannot assign to expression")),
+        };
+
+        self.end_stmt(SpannedStatement::new(span, stmt))
+    }
+
+    /// Parses the rest of an `if`/`elif` after its keyword, located at `start`
+    fn parse_stmt_if(&mut self, level: usize, start: Span) -> AstResult<SpannedStatement> {
+        let test = self.parse_expr()?.into();
+
+        self.expect_token(T![Colon])?;
+
+        let body = self.parse_scope(level + 1)?;
+
+        let otherwise = self.peek_stmt(level, |source, keyword| match keyword.token {
+            kw!(Else) => Some(
+                source
+                    .expect_token(T![Colon])
+                    .and_then(|_| source.parse_scope(level + 1)),
+            ),
+            kw!(Elif) => Some(
+                source
+                    .nested(|source| source.parse_stmt_if(level, keyword.span))
+                    .map(Into::into),
+            ),
+            _ => None,
+        })?;
+
+        let end = otherwise.as_ref().unwrap_or(&body).span;
+
+        Ok(SpannedStatement::new(
+            start.join(end),
+            AstStatement::Conditional {
+                test,
+                body,
+                otherwise,
             },
-            _ => self.error_build(first.span, |b| {
-                b.with_message(format!("Unexpected token: {:?}", first.token))
-                    .with_label(
-                        Label::new(first.span)
-                            .with_color(Color::BrightRed)
-                            .with_message("Expected expression"),
-                    )
-            }),
+        ))
+    }
+
+    fn parse_stmt_while(&mut self, level: usize, start: Span) -> AstResult<SpannedStatement> {
+        let test = self.parse_expr()?.into();
+
+        self.expect_token(T![Colon])?;
+
+        let in_loop = std::mem::replace(&mut self.in_loop, true);
+        let body = self.parse_scope(level + 1);
+        self.in_loop = in_loop;
+        let body = body?;
+
+        Ok(SpannedStatement::new(
+            start.join(body.span),
+            AstStatement::While { test, body },
+        ))
+    }
+
+    fn parse_stmt_def(&mut self, level: usize, start: Span) -> AstResult<SpannedStatement> {
+        let name = self.expect_match("function name", |t| t.token.into_ident())?;
+
+        self.expect_token(T![LeftParen])?;
+
+        let params = self.parse_params(T![RightParen], "')'")?;
+
+        self.expect_token(T![Colon])?;
+
+        let in_function = std::mem::replace(&mut self.in_function, true);
+        let in_nested_scope = std::mem::replace(&mut self.in_nested_scope, true);
+        let in_loop = std::mem::replace(&mut self.in_loop, false);
+        let has_yield = std::mem::replace(&mut self.has_yield, false);
+        let nonlocals = std::mem::take(&mut self.nonlocals);
+        let body = self.parse_scope(level + 1);
+        self.in_function = in_function;
+        self.in_nested_scope = in_nested_scope;
+        self.in_loop = in_loop;
+        let is_generator = std::mem::replace(&mut self.has_yield, has_yield);
+        let nonlocals = std::mem::replace(&mut self.nonlocals, nonlocals);
+        let body = body?;
+        let names = self.function_names(&params, &body, nonlocals)?;
+
+        Ok(SpannedStatement::new(
+            start.join(body.span),
+            AstStatement::FunctionDef(Rc::new(AstFunction {
+                name,
+                params,
+                body,
+                is_generator,
+                names,
+            })),
+        ))
+    }
+
+    /// Names of a function whose body declared or left unbound the `nonlocals`.
+    /// Those the function doesn't bind are left for the enclosing ones
+    fn function_names(
+        &mut self,
+        params: &[AstParam],
+        body: &AstScope,
+        nonlocals: Vec<SpannedName>,
+    ) -> AstResult<AstNames> {
+        let names = AstNames::of_function(params, body);
+
+        for param in params {
+            let declared = if names.globals.contains(&param.name) {
+                "global"
+            } else if names.nonlocals.contains(&param.name) {
+                "nonlocal"
+            } else {
+                continue;
+            };
+
+            let span = nonlocals
+                .iter()
+                .find(|name| name.node == param.name)
+                .map_or(body.span, |name| name.span);
+
+            return Err(self.invalid_stmt(
+                span,
+                format!("name '{}' is parameter and {declared}", param.name),
+            ));
+        }
+
+        for name in nonlocals {
+            if names.locals.contains(&name.node) {
+                continue;
+            }
+
+            // Bound by the module, not by this function
+            if names.globals.contains(&name.node) {
+                return Err(self.invalid_stmt(
+                    name.span,
+                    format!("no binding for nonlocal '{}' found", name.node),
+                ));
+            }
+
+            self.nonlocals.push(name);
         }
+
+        Ok(names)
     }
 
-    fn_bin_op! {parse_expr_bin_1, parse_expr_base; Star => Mul, Slash => Div}
-    fn_bin_op! {parse_expr_bin_2, parse_expr_bin_1; Plus => Add, Minus => Sub}
+    /// Parameters until `close`, which is consumed
+    fn parse_params(&mut self, close: Token, close_name: &str) -> AstResult<Vec<AstParam>> {
+        let mut params = Vec::<AstParam>::new();
+        // Kind of the next named parameter
+        let mut kind = AstParamKind::Normal;
+        // `*` waiting for a keyword-only parameter
+        let mut bare_star = None;
+
+        loop {
+            let token = self.expect_msg(format!("Expected {close_name}"))?;
+            let span = token.span;
+
+            if token.token == close {
+                break;
+            }
+
+            if kind == AstParamKind::VarKeywords {
+                return Err(self.invalid_stmt(span, "arguments cannot follow var-keyword argument"));
+            }
+
+            match token.token {
+                T![Slash] => {
+                    if kind != AstParamKind::Normal {
+                        return Err(self.invalid_stmt(span, "/ must be ahead of *"));
+                    }
+
+                    if params
+                        .first()
+                        .is_some_and(|p| p.kind == AstParamKind::PositionalOnly)
+                    {
+                        return Err(self.invalid_stmt(span, "/ may appear only once"));
+                    }
+
+                    if params.is_empty() {
+                        return Err(self.invalid_stmt(span, "at least one argument must precede /"));
+                    }
+
+                    for param in &mut params {
+                        param.kind = AstParamKind::PositionalOnly;
+                    }
+                }
+                T![Star] => {
+                    if kind != AstParamKind::Normal {
+                        return Err(self.invalid_stmt(span, "* argument may appear only once"));
+                    }
+
+                    match self.peek_expect()? {
+                        token if matches!(*token.token, Token::Ident(_)) => {
+                            let (span, name) = token.accept().parts();
+                            let name = name.into_ident().expect("Already checked above");
+                            self.push_param(&mut params, span, name, AstParamKind::VarArgs, None)?;
+                        }
+                        token => {
+                            token.recover();
+                            bare_star = Some(span);
+                        }
+                    }
+
+                    kind = AstParamKind::KeywordOnly;
+                }
+                T![StarStar] => {
+                    let (span, name) =
+                        self.expect_match("Ident", |t| t.token.into_ident().map(|i| (t.span, i)))?;
+
+                    self.push_param(&mut params, span, name, AstParamKind::VarKeywords, None)?;
+                    kind = AstParamKind::VarKeywords;
+                }
+                Token::Ident(name) => {
+                    let default = match self.peek_expect()? {
+                        token if *token == T![Equal] => {
+                            token.accept();
+                            Some(self.parse_expr()?)
+                        }
+                        token => {
+                            token.recover();
+                            None
+                        }
+                    };
+
+                    let follows_default = params
+                        .iter()
+                        .any(|p| p.kind <= AstParamKind::Normal && p.default.is_some());
+
+                    if kind == AstParamKind::Normal && default.is_none() && follows_default {
+                        return Err(self.invalid_stmt(
+                            span,
+                            "parameter without a default follows parameter with a default",
+                        ));
+                    }
+
+                    bare_star = None;
+                    self.push_param(&mut params, span, name, kind, default)?;
+                }
+                _ => {
+                    return Err(self.error_at(
+                        span,
+                        format!("Unexpected token: {:?}. Expected parameter", token.token),
+                    ));
+                }
+            }
+
+            let token = self.expect_msg(format!("Expected {close_name}"))?;
+
+            match token.token {
+                token if token == close => break,
+                T![Comma] => continue,
+                _ => {
+                    return Err(self.error_at(
+                        token.span,
+                        format!(
+                            "Unexpected token: {:?}. Expected ',' or {close_name}",
+                            token.token
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if let Some(span) = bare_star {
+            return Err(self.invalid_stmt(span, "named arguments must follow bare *"));
+        }
+
+        Ok(params)
+    }
+
+    fn push_param(
+        &self,
+        params: &mut Vec<AstParam>,
+        span: Span,
+        name: String,
+        kind: AstParamKind,
+        default: Option<SpannedExpr>,
+    ) -> AstResult<()> {
+        if params.iter().any(|param| param.name == name) {
+            return Err(self.invalid_stmt(
+                span,
+                format!("duplicate argument '{name}' in function definition"),
+            ));
+        }
+
+        params.push(AstParam {
+            name,
+            kind,
+            default,
+        });
+
+        Ok(())
+    }
+
+    fn parse_stmt_class(&mut self, level: usize, start: Span) -> AstResult<SpannedStatement> {
+        let name = self.expect_match("class name", |t| t.token.into_ident())?;
+
+        let bases = match self.peek_expect()? {
+            token if *token == T![LeftParen] => {
+                token.accept();
+                self.parse_expr_list(T![RightParen])?.0
+            }
+            token => {
+                token.recover();
+                Vec::new()
+            }
+        };
+
+        self.expect_token(T![Colon])?;
+
+        let in_function = std::mem::replace(&mut self.in_function, false);
+        let in_loop = std::mem::replace(&mut self.in_loop, false);
+        let has_yield = std::mem::replace(&mut self.has_yield, false);
+        let body = self.parse_scope(level + 1);
+        self.in_function = in_function;
+        self.in_loop = in_loop;
+        self.has_yield = has_yield;
+        let body = body?;
+
+        Ok(SpannedStatement::new(
+            start.join(body.span),
+            AstStatement::ClassDef { name, bases, body },
+        ))
+    }
+
+    fn parse_stmt_try(&mut self, level: usize, start: Span) -> AstResult<SpannedStatement> {
+        self.expect_token(T![Colon])?;
+
+        let body = self.parse_scope(level + 1)?;
+        let mut end = body.span;
+
+        let mut handlers = Vec::<AstExceptHandler>::new();
+
+        while let Some((span, handler)) =
+            self.peek_stmt(level, |source, keyword| match keyword.token {
+                kw!(Except) => Some(
+                    source
+                        .parse_except_handler(level)
+                        .map(|handler| (keyword.span, handler)),
+                ),
+                _ => None,
+            })?
+        {
+            if handlers.last().is_some_and(|last| last.class.is_none()) {
+                return Err(self.invalid_stmt(span, "default 'except:' must be last"));
+            }
+
+            end = handler.body.span;
+            handlers.push(handler);
+        }
+
+        let block = |keyword| {
+            move |source: &mut SourceAst<'_>, token: SpannedToken| {
+                (token.token == keyword).then(|| {
+                    source
+                        .expect_token(T![Colon])
+                        .and_then(|_| source.parse_scope(level + 1))
+                        .map(|block| (token.span, block))
+                })
+            }
+        };
+
+        let otherwise = self.peek_stmt(level, block(kw!(Else)))?;
+
+        if let Some((span, otherwise)) = &otherwise {
+            if handlers.is_empty() {
+                return Err(self.invalid_stmt(*span, "'else' requires an 'except' block"));
+            }
+
+            end = otherwise.span;
+        }
+
+        let finally = self.peek_stmt(level, block(kw!(Finally)))?;
+
+        if let Some((_, finally)) = &finally {
+            end = finally.span;
+        } else if handlers.is_empty() {
+            return Err(self.invalid_stmt(start, "expected 'except' or 'finally' block"));
+        }
+
+        Ok(SpannedStatement::new(
+            start.join(end),
+            AstStatement::Try {
+                body,
+                handlers,
+                otherwise: otherwise.map(|(_, block)| block),
+                finally: finally.map(|(_, block)| block),
+            },
+        ))
+    }
+
+    /// `match subject:` and its `case` blocks, `None` if `match` is used as a name
+    fn parse_stmt_match(&mut self, level: usize) -> AstResult<Option<SpannedStatement>> {
+        let mut peek = self.clone();
+        let start = peek.expect()?.span;
+
+        let subject = match peek.parse_match_subject() {
+            Ok(subject) if peek.next_is(T![Colon]) => subject,
+            _ => return Ok(None),
+        };
+
+        *self = peek;
+        self.expect_token(T![Colon])?;
+
+        let mut cases = Vec::<AstMatchCase>::new();
+
+        while let Some(case) = self.peek_stmt(level + 1, |source, keyword| {
+            (keyword.token == Token::Ident(CASE.to_owned()))
+                .then(|| source.parse_match_case(level + 1))
+        })? {
+            if let Some(last) = cases.last()
+                && last.guard.is_none()
+                && last.pattern.is_irrefutable()
+            {
+                let msg = match &last.pattern.node {
+                    AstPattern::Capture(name) => {
+                        format!("name capture '{name}' makes remaining patterns unreachable")
+                    }
+                    _ => "wildcard makes remaining patterns unreachable".to_owned(),
+                };
+
+                return Err(self.invalid_stmt(last.pattern.span, msg));
+            }
+
+            cases.push(case);
+        }
+
+        let Some(last) = cases.last() else {
+            return Err(self.invalid_stmt(start, "expected 'case' block"));
+        };
+
+        let span = start.join(last.body.span);
+        self.check_exhaustive(&subject, &cases);
+
+        Ok(Some(SpannedStatement::new(
+            span,
+            AstStatement::Match {
+                subject: subject.into(),
+                cases,
+            },
+        )))
+    }
+
+    /// Subject of a `match`, a comma-separated list is a tuple
+    fn parse_match_subject(&mut self) -> AstResult<SpannedExpr> {
+        let first = self.parse_expr()?;
+
+        if !self.next_is(T![Comma]) {
+            return Ok(first);
+        }
+
+        let mut span = first.span;
+        let mut items = Vec::from([first]);
+
+        while self.next_is(T![Comma]) {
+            span = span.join(self.expect()?.span);
+
+            if self.next_is(T![Colon]) {
+                break;
+            }
+
+            let item = self.parse_expr()?;
+            span = span.join(item.span);
+            items.push(item);
+        }
+
+        Ok(SpannedExpr::new(span, AstExpr::Tuple(items)))
+    }
+
+    /// Parses the rest of a `case` block after its keyword
+    fn parse_match_case(&mut self, level: usize) -> AstResult<AstMatchCase> {
+        let pattern = self.parse_pattern_top()?;
+
+        let guard = match self.next_is(kw!(If)) {
+            true => {
+                self.expect()?;
+                Some(self.parse_expr()?)
+            }
+            false => None,
+        };
+
+        self.expect_token(T![Colon])?;
+
+        let body = self.parse_scope(level + 1)?;
+
+        Ok(AstMatchCase {
+            pattern,
+            guard,
+            body,
+        })
+    }
+
+    /// Warns about matches that only compare with literals and don't cover both booleans,
+    /// since subjects with other values would silently run no case
+    fn check_exhaustive(&mut self, subject: &SpannedExpr, cases: &[AstMatchCase]) {
+        fn literals<'a>(pattern: &'a AstPattern, found: &mut Vec<&'a TokenLiteral>) -> bool {
+            match pattern {
+                AstPattern::Value(Spanned {
+                    node: AstExpr::Literal(literal),
+                    ..
+                }) => {
+                    found.push(literal);
+                    true
+                }
+                AstPattern::Or(alternatives) => alternatives
+                    .iter()
+                    .all(|alternative| literals(&alternative.node, found)),
+                AstPattern::As { pattern, .. } => literals(&pattern.node, found),
+                _ => false,
+            }
+        }
+
+        let mut covered = Vec::new();
+
+        for case in cases {
+            let mut found = Vec::new();
+
+            if !literals(&case.pattern.node, &mut found) {
+                return;
+            }
+
+            if case.guard.is_none() {
+                covered.extend(found);
+            }
+        }
+
+        let missing = [true, false]
+            .into_iter()
+            .filter(|value| !covered.contains(&&TokenLiteral::Bool(*value)))
+            .map(|value| if value { "True" } else { "False" })
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            return;
+        }
+
+        let only_bools = covered
+            .iter()
+            .all(|literal| matches!(literal, TokenLiteral::Bool(_) | TokenLiteral::Nil));
+
+        let label = match only_bools {
+            true => format!("{} not covered", missing.join(" and ")),
+            false => "values other than the listed literals are not covered".to_owned(),
+        };
+
+        self.warnings.push(
+            Diagnostic::warning(
+                codes::NON_EXHAUSTIVE_MATCH,
+                "match statement is not exhaustive",
+            )
+            .with_label(subject.span, label)
+            .with_help("add a `case _:` block to handle the remaining values"),
+        );
+    }
+
+    /// Pattern of a `case`, a bare comma-separated list is a sequence pattern
+    fn parse_pattern_top(&mut self) -> AstResult<SpannedPattern> {
+        let first = self.parse_pattern_item()?;
+
+        if !self.next_is(T![Comma]) {
+            if let AstPattern::Star(_) = first.node {
+                return Err(self.invalid_stmt(first.span, "starred pattern outside a sequence"));
+            }
+
+            return Ok(first);
+        }
+
+        let mut span = first.span;
+        let mut items = Vec::from([first]);
+
+        while self.next_is(T![Comma]) {
+            span = span.join(self.expect()?.span);
+
+            if self.next_is(T![Colon]) || self.next_is(kw!(If)) {
+                break;
+            }
+
+            let item = self.parse_pattern_item()?;
+            span = span.join(item.span);
+            items.push(item);
+        }
+
+        self.sequence_pattern(span, items)
+    }
+
+    /// Checks that at most one item is starred
+    fn sequence_pattern(
+        &self,
+        span: Span,
+        items: Vec<SpannedPattern>,
+    ) -> AstResult<SpannedPattern> {
+        let mut stars = items
+            .iter()
+            .filter(|item| matches!(item.node, AstPattern::Star(_)));
+
+        if let (Some(_), Some(second)) = (stars.next(), stars.next()) {
+            return Err(
+                self.invalid_stmt(second.span, "multiple starred names in sequence pattern")
+            );
+        }
+
+        Ok(SpannedPattern::new(span, AstPattern::Sequence(items)))
+    }
+
+    /// Item of a sequence pattern, which may be `*name`
+    fn parse_pattern_item(&mut self) -> AstResult<SpannedPattern> {
+        if !self.next_is(T![Star]) {
+            return self.parse_pattern();
+        }
+
+        let start = self.expect()?.span;
+        let (end, name) =
+            self.expect_match("Ident", |t| t.token.into_ident().map(|i| (t.span, i)))?;
+
+        Ok(SpannedPattern::new(
+            start.join(end),
+            AstPattern::Star((name != "_").then_some(name)),
+        ))
+    }
+
+    /// `pattern [as name]`
+    fn parse_pattern(&mut self) -> AstResult<SpannedPattern> {
+        let pattern = self.nested(Self::parse_pattern_or)?;
+
+        if !self.next_is(kw!(As)) {
+            return Ok(pattern);
+        }
+
+        self.expect()?;
+
+        let (end, name) =
+            self.expect_match("Ident", |t| t.token.into_ident().map(|i| (t.span, i)))?;
+
+        if name == "_" {
+            return Err(self.invalid_stmt(end, "cannot use '_' as a target"));
+        }
+
+        Ok(SpannedPattern::new(
+            pattern.span.join(end),
+            AstPattern::As {
+                pattern: pattern.into(),
+                name,
+            },
+        ))
+    }
+
+    /// `a | b | ...`, every alternative must bind the same names
+    fn parse_pattern_or(&mut self) -> AstResult<SpannedPattern> {
+        let first = self.parse_pattern_closed()?;
+
+        if !self.next_is(T![Pipe]) {
+            return Ok(first);
+        }
+
+        let mut alternatives = Vec::from([first]);
+
+        while self.next_is(T![Pipe]) {
+            self.expect()?;
+            alternatives.push(self.parse_pattern_closed()?);
+        }
+
+        fn names(pattern: &SpannedPattern) -> Vec<&str> {
+            let mut names = pattern.bound_names();
+            names.sort_unstable();
+            names
+        }
+
+        let expected = names(&alternatives[0]);
+
+        for (idx, alternative) in alternatives.iter().enumerate() {
+            if idx + 1 < alternatives.len() && alternative.is_irrefutable() {
+                return Err(self.invalid_stmt(
+                    alternative.span,
+                    "alternative makes remaining patterns unreachable",
+                ));
+            }
+
+            if names(alternative) != expected {
+                return Err(self.invalid_stmt(
+                    alternative.span,
+                    "alternative patterns bind different names",
+                ));
+            }
+        }
+
+        let span = alternatives[0]
+            .span
+            .join(alternatives[alternatives.len() - 1].span);
+
+        Ok(SpannedPattern::new(span, AstPattern::Or(alternatives)))
+    }
+
+    /// Patterns that don't need parentheses around them
+    fn parse_pattern_closed(&mut self) -> AstResult<SpannedPattern> {
+        let token = self.expect_msg("Expected pattern")?;
+        let span = token.span;
+
+        match token.token {
+            Token::Literal(literal) => Ok(SpannedPattern::new(
+                span,
+                AstPattern::Value(SpannedExpr::new(span, AstExpr::Literal(literal))),
+            )),
+            T![Minus] => {
+                let (end, value) = self.expect_match("number", |t| match t.token {
+                    Token::Literal(TokenLiteral::Number(n)) => Some((t.span, n)),
+                    _ => None,
+                })?;
+                let span = span.join(end);
+
+                Ok(SpannedPattern::new(
+                    span,
+                    AstPattern::Value(SpannedExpr::new(
+                        span,
+                        AstExpr::Literal(TokenLiteral::Number(-value)),
+                    )),
+                ))
+            }
+            Token::Ident(name) if name == "_" => {
+                Ok(SpannedPattern::new(span, AstPattern::Wildcard))
+            }
+            Token::Ident(name) => {
+                let mut value = SpannedExpr::new(span, AstExpr::Ident(name.clone()));
+
+                while self.next_is(T![Dot]) {
+                    self.expect()?;
+
+                    let (end, attr) =
+                        self.expect_match("Ident", |t| t.token.into_ident().map(|i| (t.span, i)))?;
+
+                    value = SpannedExpr::new(
+                        value.span.join(end),
+                        AstExpr::Attribute {
+                            value: value.into(),
+                            name: attr,
+                        },
+                    );
+                }
+
+                if self.next_is(T![LeftParen]) {
+                    self.expect()?;
+                    return self.parse_pattern_class(value);
+                }
+
+                Ok(match value.node {
+                    AstExpr::Ident(name) => SpannedPattern::new(span, AstPattern::Capture(name)),
+                    _ => SpannedPattern::new(value.span, AstPattern::Value(value)),
+                })
+            }
+            T![LeftParen] => {
+                let (items, comma, end) = self.parse_pattern_list(T![RightParen])?;
+
+                match <[_; 1]>::try_from(items) {
+                    Ok([item]) if !comma && !matches!(item.node, AstPattern::Star(_)) => Ok(item),
+                    Ok(items) => self.sequence_pattern(span.join(end), items.into()),
+                    Err(items) => self.sequence_pattern(span.join(end), items),
+                }
+            }
+            T![LeftBracket] => {
+                let (items, _, end) = self.parse_pattern_list(T![RightBracket])?;
+                self.sequence_pattern(span.join(end), items)
+            }
+            T![LeftBrace] => self.parse_pattern_mapping(span),
+            token => Err(self.error_at(
+                span,
+                format!("Unexpected token: {token:?}. Expected pattern"),
+            )),
+        }
+    }
+
+    /// Comma-separated sequence items until `close`, which is consumed.
+    /// Also returns whether there was a comma and the span of `close`
+    fn parse_pattern_list(&mut self, close: Token) -> AstResult<(Vec<SpannedPattern>, bool, Span)> {
+        let mut items = Vec::new();
+        let mut comma = false;
+
+        loop {
+            if self.next_is(close.clone()) {
+                return Ok((items, comma, self.expect()?.span));
+            }
+
+            items.push(self.parse_pattern_item()?);
+
+            if !self.next_is(T![Comma]) {
+                return Ok((items, comma, self.expect_token(close)?.span));
+            }
+
+            self.expect()?;
+            comma = true;
+        }
+    }
+
+    /// `{key: pattern, **rest}`, after the `{` located at `start`
+    fn parse_pattern_mapping(&mut self, start: Span) -> AstResult<SpannedPattern> {
+        let mut entries = Vec::new();
+        let mut rest = None;
+
+        let end = loop {
+            if self.next_is(T![RightBrace]) {
+                break self.expect()?.span;
+            }
+
+            if rest.is_some() {
+                let token = self.expect_token(T![RightBrace])?;
+                break token.span;
+            }
+
+            if self.next_is(T![StarStar]) {
+                self.expect()?;
+                rest = Some(self.expect_match("Ident", |t| t.token.into_ident())?);
+            } else {
+                let key = self.parse_pattern_closed()?;
+
+                let AstPattern::Value(key) = key.node else {
+                    return Err(self.invalid_stmt(
+                        key.span,
+                        "mapping pattern keys may only match literals and attribute lookups",
+                    ));
+                };
+
+                self.expect_token(T![Colon])?;
+                entries.push((key, self.parse_pattern()?));
+            }
+
+            if !self.next_is(T![Comma]) {
+                break self.expect_token(T![RightBrace])?.span;
+            }
+
+            self.expect()?;
+        };
+
+        Ok(SpannedPattern::new(
+            start.join(end),
+            AstPattern::Mapping { entries, rest },
+        ))
+    }
+
+    /// `Class(pattern, name=pattern)`, after the `(` following the class
+    fn parse_pattern_class(&mut self, class: SpannedExpr) -> AstResult<SpannedPattern> {
+        let mut args = Vec::new();
+        let mut keywords = Vec::<(String, SpannedPattern)>::new();
+
+        let end = loop {
+            if self.next_is(T![RightParen]) {
+                break self.expect()?.span;
+            }
+
+            let is_keyword = matches!(self.tokens.front(), Some(t) if matches!(t.token, Token::Ident(_)))
+                && self.tokens.get(1).is_some_and(|t| *t == T![Equal]);
+
+            if is_keyword {
+                let name = self.expect_match("Ident", |t| t.token.into_ident())?;
+                self.expect_token(T![Equal])?;
+                keywords.push((name, self.parse_pattern()?));
+            } else {
+                let pattern = self.parse_pattern()?;
+
+                if !keywords.is_empty() {
+                    return Err(self.invalid_stmt(
+                        pattern.span,
+                        "positional patterns follow keyword patterns",
+                    ));
+                }
+
+                args.push(pattern);
+            }
+
+            if !self.next_is(T![Comma]) {
+                break self.expect_token(T![RightParen])?.span;
+            }
+
+            self.expect()?;
+        };
+
+        Ok(SpannedPattern::new(
+            class.span.join(end),
+            AstPattern::Class {
+                class,
+                args,
+                keywords,
+            },
+        ))
+    }
+
+    /// Parses the rest of an `except` clause after its keyword
+    fn parse_except_handler(&mut self, level: usize) -> AstResult<AstExceptHandler> {
+        let class = match self.peek_expect()? {
+            token if *token == T![Colon] => {
+                token.recover();
+                None
+            }
+            token => {
+                token.recover();
+                Some(self.parse_expr()?)
+            }
+        };
+
+        let name = match self.peek() {
+            Some(token) if class.is_some() && *token == kw!(As) => {
+                token.accept();
+                Some(self.expect_match("Ident", |t| t.token.into_ident())?)
+            }
+            Some(token) => {
+                token.recover();
+                None
+            }
+            None => None,
+        };
+
+        self.expect_token(T![Colon])?;
+
+        let body = self.parse_scope(level + 1)?;
+
+        Ok(AstExceptHandler { class, name, body })
+    }
+
+    /// Expression that may be omitted at the end of a statement
+    fn parse_optional_expr(&mut self) -> AstResult<Option<SpannedExpr>> {
+        match self.peek() {
+            Some(token) if *token != T![Newline] && *token != T![Semicolon] => {
+                token.recover();
+                self.parse_expr().map(Some)
+            }
+            Some(token) => {
+                token.recover();
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn parse_expr(&mut self) -> AstResult<SpannedExpr> {
+        self.nested(|source| match source.peek_expect()? {
+            token if *token == kw!(Lambda) => {
+                let start = token.accept().span;
+                source.parse_expr_lambda(start)
+            }
+            token if *token == kw!(Yield) => {
+                let start = token.accept().span;
+                source.parse_expr_yield(start)
+            }
+            token => {
+                token.recover();
+                source.parse_expr_conditional()
+            }
+        })
+    }
+
+    /// `body if test else otherwise`, binding looser than `or`
+    fn parse_expr_conditional(&mut self) -> AstResult<SpannedExpr> {
+        let body = self.parse_expr_or()?;
+
+        match self.peek() {
+            Some(token) if *token == kw!(If) => {
+                token.accept();
+            }
+            Some(token) => {
+                token.recover();
+                return Ok(body);
+            }
+            None => return Ok(body),
+        }
+
+        let test = self.parse_expr_or()?;
+
+        let token = self.expect_msg("Expected 'else' after 'if' expression")?;
+        if token != kw!(Else) {
+            return Err(self.invalid_stmt(token.span, "expected 'else' after 'if' expression"));
+        }
+
+        let otherwise = self.parse_expr()?;
+
+        Ok(SpannedExpr::new(
+            body.span.join(otherwise.span),
+            AstExpr::Conditional {
+                test: test.into(),
+                body: body.into(),
+                otherwise: otherwise.into(),
+            },
+        ))
+    }
+
+    /// `lambda params: expr`, after the `lambda` keyword located at `start`
+    fn parse_expr_lambda(&mut self, start: Span) -> AstResult<SpannedExpr> {
+        let params = self.parse_params(T![Colon], "':'")?;
+
+        let in_function = std::mem::replace(&mut self.in_function, true);
+        let in_nested_scope = std::mem::replace(&mut self.in_nested_scope, true);
+        let has_yield = std::mem::replace(&mut self.has_yield, false);
+        let nonlocals = std::mem::take(&mut self.nonlocals);
+        let value = self.parse_expr();
+        self.in_function = in_function;
+        self.in_nested_scope = in_nested_scope;
+        let is_generator = std::mem::replace(&mut self.has_yield, has_yield);
+        let nonlocals = std::mem::replace(&mut self.nonlocals, nonlocals);
+        let value = value?;
+        let span = start.join(value.span);
+
+        let body = AstScope::new(
+            value.span,
+            Vec::from([SpannedStatement::new(
+                value.span,
+                AstStatement::Return(Some(value.into())),
+            )]),
+        );
+
+        let names = self.function_names(&params, &body, nonlocals)?;
+
+        Ok(SpannedExpr::new(
+            span,
+            AstExpr::Lambda(Rc::new(AstFunction {
+                name: "<lambda>".to_owned(),
+                params,
+                body,
+                is_generator,
+                names,
+            })),
+        ))
+    }
+
+    /// `yield [value]` or `yield from iterable`, after the `yield` keyword located at `start`
+    fn parse_expr_yield(&mut self, start: Span) -> AstResult<SpannedExpr> {
+        if !self.in_function {
+            return Err(self.invalid_stmt(start, "'yield' outside function"));
+        }
+
+        self.has_yield = true;
+
+        let ends_expr = |token: &Token| {
+            matches!(
+                token,
+                T![Newline]
+                    | T![Semicolon]
+                    | T![RightParen]
+                    | T![RightBracket]
+                    | T![RightBrace]
+                    | T![Comma]
+                    | T![Colon]
+                    | T![Equal]
+            )
+        };
+
+        match self.peek() {
+            Some(token) if *token == kw!(From) => {
+                token.accept();
+                let iterable = self.parse_expr()?;
+
+                Ok(SpannedExpr::new(
+                    start.join(iterable.span),
+                    AstExpr::YieldFrom(iterable.into()),
+                ))
+            }
+            Some(token) if !ends_expr(&token.token) => {
+                token.recover();
+                let value = self.parse_expr()?;
+
+                Ok(SpannedExpr::new(
+                    start.join(value.span),
+                    AstExpr::Yield(Some(value.into())),
+                ))
+            }
+            Some(token) => {
+                token.recover();
+                Ok(SpannedExpr::new(start, AstExpr::Yield(None)))
+            }
+            None => Ok(SpannedExpr::new(start, AstExpr::Yield(None))),
+        }
+    }
+
+    fn parse_expr_or(&mut self) -> AstResult<SpannedExpr> {
+        self.parse_logical(AstLogicalOp::Or, kw!(Or), Self::parse_expr_and)
+    }
+
+    fn parse_expr_and(&mut self) -> AstResult<SpannedExpr> {
+        self.parse_logical(AstLogicalOp::And, kw!(And), Self::parse_expr_not)
+    }
+
+    fn parse_logical(
+        &mut self,
+        op: AstLogicalOp,
+        keyword: Token,
+        base: fn(&mut Self) -> AstResult<SpannedExpr>,
+    ) -> AstResult<SpannedExpr> {
+        let depth = self.depth;
+        let mut left = base(self)?;
+
+        while let Some(token) = self.peek() {
+            if *token != keyword {
+                token.recover();
+                break;
+            }
+
+            token.accept();
+            self.nest()?;
+
+            let right = base(self)?;
+
+            left = SpannedExpr::new(
+                left.span.join(right.span),
+                AstExpr::Logical {
+                    op: op.clone(),
+                    left: left.into(),
+                    right: right.into(),
+                },
+            );
+        }
+
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn parse_expr_not(&mut self) -> AstResult<SpannedExpr> {
+        let Some(token) = self.peek() else {
+            return self.parse_expr_comparison();
+        };
+
+        if *token != kw!(Not) {
+            token.recover();
+            return self.parse_expr_comparison();
+        }
+
+        let span = token.accept().span;
+        let right = self.nested(Self::parse_expr_not)?;
+
+        Ok(SpannedExpr::new(
+            span.join(right.span),
+            AstExpr::UnaryOp {
+                op: AstUnaryOp::Not,
+                right: right.into(),
+            },
+        ))
+    }
+
+    fn parse_expr_comparison(&mut self) -> AstResult<SpannedExpr> {
+        let left = self.parse_expr_comparison_op()?;
+
+        // Comparisons don't chain, `a < b < c` would silently mean `(a < b) < c`
+        if let AstExpr::BinaryOp {
+            op, left: inner, ..
+        } = &left.node
+            && op.is_comparison()
+            && matches!(&inner.node, AstExpr::BinaryOp { op, .. } if op.is_comparison())
+        {
+            return Err(self.invalid_stmt(left.span, "chained comparisons are not supported"));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_expr_unary(&mut self) -> AstResult<SpannedExpr> {
+        let first = self.peek_expect()?;
+
+        let op = match **first {
+            T![Bang] => AstUnaryOp::Not,
+            T![Minus] => AstUnaryOp::Neg,
+            _ => {
+                first.recover();
+                return self.parse_expr_postfix();
+            }
+        };
+
+        let span = first.accept().span;
+        let right = self.nested(Self::parse_expr_unary)?;
+
+        Ok(SpannedExpr::new(
+            span.join(right.span),
+            AstExpr::UnaryOp {
+                op,
+                right: right.into(),
+            },
+        ))
+    }
+
+    /// Calls, attribute accesses and subscripts applied to an atom
+    fn parse_expr_postfix(&mut self) -> AstResult<SpannedExpr> {
+        let depth = self.depth;
+        let mut expr = self.parse_expr_atom()?;
+
+        while let Some(token) = self.peek() {
+            match **token {
+                T![LeftParen] => {
+                    token.accept();
+                    self.nest()?;
+
+                    let (args, keywords, end) = self.parse_call_args()?;
+
+                    expr = SpannedExpr::new(
+                        expr.span.join(end),
+                        AstExpr::Call {
+                            func: expr.into(),
+                            args,
+                            keywords,
+                        },
+                    );
+                }
+                T![LeftBracket] => {
+                    token.accept();
+                    self.nest()?;
+
+                    let index = self.parse_expr()?;
+                    let end = self.expect_token(T![RightBracket])?.span;
+
+                    expr = SpannedExpr::new(
+                        expr.span.join(end),
+                        AstExpr::Subscript {
+                            value: expr.into(),
+                            index: index.into(),
+                        },
+                    );
+                }
+                T![Dot] => {
+                    token.accept();
+                    self.nest()?;
+
+                    let (end, name) = self.expect_match("attribute name", |t| {
+                        t.token.into_ident().map(|name| (t.span, name))
+                    })?;
+
+                    expr = SpannedExpr::new(
+                        expr.span.join(end),
+                        AstExpr::Attribute {
+                            value: expr.into(),
+                            name,
+                        },
+                    );
+                }
+                _ => {
+                    token.recover();
+                    break;
+                }
+            }
+        }
+
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    /// Arguments of a call until `)`, positional ones first.
+    /// `*iterable` can follow keywords, but not `**mapping`.
+    /// Returns them with the span of the closing parenthesis
+    fn parse_call_args(&mut self) -> AstResult<(Vec<SpannedExpr>, Vec<AstKeyword>, Span)> {
+        let mut args = Vec::new();
+        let mut keywords = Vec::<AstKeyword>::new();
+
+        loop {
+            let token = self.peek_expect()?;
+
+            if *token == T![RightParen] {
+                return Ok((args, keywords, token.accept().span));
+            }
+
+            let unpacking = keywords.iter().any(|keyword| keyword.name.is_none());
+
+            if *token == T![StarStar] {
+                token.accept();
+
+                let value = self.parse_expr()?;
+                keywords.push(AstKeyword { name: None, value });
+            } else if *token == T![Star] {
+                let start = token.accept().span;
+                let value = self.parse_expr()?;
+
+                if unpacking {
+                    return Err(self.invalid_stmt(
+                        start.join(value.span),
+                        "iterable argument unpacking follows keyword argument unpacking",
+                    ));
+                }
+
+                args.push(SpannedExpr::new(
+                    start.join(value.span),
+                    AstExpr::Starred(value.into()),
+                ));
+            } else {
+                token.recover();
+
+                let is_keyword = matches!(
+                    (self.tokens.front(), self.tokens.get(1)),
+                    (Some(SpannedToken { token: Token::Ident(_), .. }), Some(eq)) if *eq == T![Equal]
+                );
+
+                if is_keyword {
+                    let (span, name) =
+                        self.expect_match("Ident", |t| t.token.into_ident().map(|i| (t.span, i)))?;
+                    self.expect_token(T![Equal])?;
+
+                    if keywords.iter().any(|k| k.name.as_ref() == Some(&name)) {
+                        return Err(
+                            self.invalid_stmt(span, format!("keyword argument repeated: {name}"))
+                        );
+                    }
+
+                    let value = self.parse_expr()?;
+                    keywords.push(AstKeyword {
+                        name: Some(name),
+                        value,
+                    });
+                } else {
+                    let mut arg = self.parse_expr()?;
+
+                    // `f(x for x in xs)` needs no extra parentheses when it's the only argument
+                    if self.next_is(kw!(For)) {
+                        let comprehension =
+                            self.parse_comprehension(AstComprehensionKind::Generator, arg, None)?;
+                        let span = comprehension
+                            .clauses
+                            .last()
+                            .map_or(comprehension.element.span, |clause| {
+                                comprehension.element.span.join(clause.span())
+                            });
+
+                        if !args.is_empty() || !keywords.is_empty() || !self.next_is(T![RightParen])
+                        {
+                            return Err(self
+                                .invalid_stmt(span, "Generator expression must be parenthesized"));
+                        }
+
+                        arg = SpannedExpr::new(span, AstExpr::Comprehension(comprehension.into()));
+                    }
+
+                    if unpacking {
+                        return Err(self.invalid_stmt(
+                            arg.span,
+                            "positional argument follows keyword argument unpacking",
+                        ));
+                    }
+
+                    if !keywords.is_empty() {
+                        return Err(self.invalid_stmt(
+                            arg.span,
+                            "positional argument follows keyword argument",
+                        ));
+                    }
+
+                    args.push(arg);
+                }
+            }
+
+            let token = self.expect()?;
+
+            match token.token {
+                T![RightParen] => return Ok((args, keywords, token.span)),
+                T![Comma] => {}
+                _ => {
+                    return Err(self.error_at(
+                        token.span,
+                        format!("Unexpected token: {:?}. Expected ',' or ')'", token.token),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Comma-separated expressions until `close`, which may have a trailing comma.
+    /// Returns them with the span of the closing token
+    fn parse_expr_list(&mut self, close: Token) -> AstResult<(Vec<SpannedExpr>, Span)> {
+        let mut exprs = Vec::new();
+
+        loop {
+            let token = self.peek_expect()?;
+
+            if *token == close {
+                return Ok((exprs, token.accept().span));
+            }
+
+            token.recover();
+            exprs.push(self.parse_expr()?);
+
+            let token = self.expect()?;
+
+            match token.token {
+                T![Comma] => continue,
+                ref t if *t == close => return Ok((exprs, token.span)),
+                _ => {
+                    return Err(self.error_at(
+                        token.span,
+                        format!(
+                            "Unexpected token: {:?}. Expected ',' or {close:?}",
+                            token.token
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn parse_expr_atom(&mut self) -> AstResult<SpannedExpr> {
+        let first = self.expect()?;
+        let span = first.span;
+
+        match first.token {
+            Token::Ident(ident) => Ok(SpannedExpr::new(span, AstExpr::Ident(ident))),
+            Token::Literal(lit) => Ok(SpannedExpr::new(span, AstExpr::Literal(lit))),
+            T![LeftParen] => self.parse_expr_paren(span),
+            T![LeftBracket] => self.parse_expr_brackets(span),
+            T![LeftBrace] => self.parse_expr_braces(span),
+            _ => Err(Diagnostic::error(
+                codes::UNEXPECTED_TOKEN,
+                format!("Unexpected token: {:?}", first.token),
+            )
+            .with_label(first.span, "Expected expression")),
+        }
+    }
+
+    /// List or list comprehension, after its `[` located at `start`
+    fn parse_expr_brackets(&mut self, start: Span) -> AstResult<SpannedExpr> {
+        let token = self.peek_expect()?;
+
+        if *token == T![RightBracket] {
+            let end = token.accept().span;
+            return Ok(SpannedExpr::new(start.join(end), AstExpr::List(Vec::new())));
+        }
+
+        token.recover();
+
+        let first = self.parse_expr()?;
+
+        if self.next_is(kw!(For)) {
+            let comprehension =
+                self.parse_comprehension(AstComprehensionKind::List, first, None)?;
+            let end = self.expect_token(T![RightBracket])?.span;
+
+            return Ok(SpannedExpr::new(
+                start.join(end),
+                AstExpr::Comprehension(comprehension.into()),
+            ));
+        }
+
+        let token = self.expect()?;
+
+        match token.token {
+            T![RightBracket] => Ok(SpannedExpr::new(
+                start.join(token.span),
+                AstExpr::List(Vec::from([first])),
+            )),
+            T![Comma] => {
+                let (mut items, end) = self.parse_expr_list(T![RightBracket])?;
+                items.insert(0, first);
+
+                Ok(SpannedExpr::new(start.join(end), AstExpr::List(items)))
+            }
+            _ => Err(self.error_at(
+                token.span,
+                format!("Unexpected token: {:?}. Expected ',' or ']'", token.token),
+            )),
+        }
+    }
+
+    /// Dict, set or their comprehensions, after the `{` located at `start`.
+    /// `{}` is an empty dict, like in Python
+    fn parse_expr_braces(&mut self, start: Span) -> AstResult<SpannedExpr> {
+        let token = self.peek_expect()?;
+
+        if *token == T![RightBrace] {
+            let end = token.accept().span;
+            return Ok(SpannedExpr::new(start.join(end), AstExpr::Dict(Vec::new())));
+        }
+
+        token.recover();
+
+        let first = self.parse_expr()?;
+
+        if !self.next_is(T![Colon]) {
+            if self.next_is(kw!(For)) {
+                let comprehension =
+                    self.parse_comprehension(AstComprehensionKind::Set, first, None)?;
+                let end = self.expect_token(T![RightBrace])?.span;
+
+                return Ok(SpannedExpr::new(
+                    start.join(end),
+                    AstExpr::Comprehension(comprehension.into()),
+                ));
+            }
+
+            let token = self.expect()?;
+
+            return match token.token {
+                T![RightBrace] => Ok(SpannedExpr::new(
+                    start.join(token.span),
+                    AstExpr::Set(Vec::from([first])),
+                )),
+                T![Comma] => {
+                    let (mut items, end) = self.parse_expr_list(T![RightBrace])?;
+                    items.insert(0, first);
+
+                    Ok(SpannedExpr::new(start.join(end), AstExpr::Set(items)))
+                }
+                _ => Err(self.error_at(
+                    token.span,
+                    format!("Unexpected token: {:?}. Expected ',' or '}}'", token.token),
+                )),
+            };
+        }
+
+        self.expect_token(T![Colon])?;
+        let value = self.parse_expr()?;
+
+        if self.next_is(kw!(For)) {
+            let comprehension =
+                self.parse_comprehension(AstComprehensionKind::Dict, first, Some(value))?;
+            let end = self.expect_token(T![RightBrace])?.span;
+
+            return Ok(SpannedExpr::new(
+                start.join(end),
+                AstExpr::Comprehension(comprehension.into()),
+            ));
+        }
+
+        let mut items = Vec::from([(first, value)]);
+
+        loop {
+            let token = self.expect()?;
+
+            match token.token {
+                T![Comma] => {}
+                T![RightBrace] => {
+                    return Ok(SpannedExpr::new(
+                        start.join(token.span),
+                        AstExpr::Dict(items),
+                    ));
+                }
+                _ => {
+                    return Err(self.error_at(
+                        token.span,
+                        format!("Unexpected token: {:?}. Expected ',' or '}}'", token.token),
+                    ));
+                }
+            }
+
+            let token = self.peek_expect()?;
+
+            if *token == T![RightBrace] {
+                let end = token.accept().span;
+                return Ok(SpannedExpr::new(start.join(end), AstExpr::Dict(items)));
+            }
+
+            token.recover();
+
+            let key = self.parse_expr()?;
+            self.expect_token(T![Colon])?;
+            let value = self.parse_expr()?;
+            items.push((key, value));
+        }
+    }
+
+    /// `for` clauses following the `element` of a comprehension, until its closing token
+    fn parse_comprehension(
+        &mut self,
+        kind: AstComprehensionKind,
+        element: SpannedExpr,
+        value: Option<SpannedExpr>,
+    ) -> AstResult<AstComprehension> {
+        let mut clauses = Vec::new();
+
+        while self.next_is(kw!(For)) {
+            self.expect()?;
+
+            let target = self.parse_target()?;
+            self.expect_token(kw!(In))?;
+            let iter = self.parse_expr_or()?;
+
+            let mut conditions = Vec::new();
+
+            while self.next_is(kw!(If)) {
+                self.expect()?;
+                conditions.push(self.parse_expr_or()?);
+            }
+
+            clauses.push(AstForClause {
+                target,
+                iter,
+                conditions,
+            });
+        }
+
+        Ok(AstComprehension {
+            kind,
+            element,
+            value,
+            clauses,
+        })
+    }
+
+    /// Name, or comma-separated names to unpack into, of a `for` clause
+    fn parse_target(&mut self) -> AstResult<SpannedExpr> {
+        let mut targets = Vec::from([self.parse_expr_postfix()?]);
+
+        while self.next_is(T![Comma]) {
+            self.expect()?;
+            targets.push(self.parse_expr_postfix()?);
+        }
+
+        for target in &targets {
+            self.check_target(target)?;
+        }
+
+        Ok(match <[_; 1]>::try_from(targets) {
+            Ok([target]) => target,
+            Err(targets) => SpannedExpr::new(
+                targets[0].span.join(targets[targets.len() - 1].span),
+                AstExpr::Tuple(targets),
+            ),
+        })
+    }
+
+    /// Names can be bound by a `for` clause, along with tuples of them
+    fn check_target(&self, target: &SpannedExpr) -> AstResult<()> {
+        match &target.node {
+            AstExpr::Ident(_) => Ok(()),
+            AstExpr::Tuple(items) => items.iter().try_for_each(|item| self.check_target(item)),
+            _ => Err(self.invalid_stmt(target.span, "cannot assign to expression")),
+        }
+    }
+
+    /// Parenthesized expression or tuple, after its `(` located at `start`
+    fn parse_expr_paren(&mut self, start: Span) -> AstResult<SpannedExpr> {
+        let token = self.peek_expect()?;
+
+        if *token == T![RightParen] {
+            let end = token.accept().span;
+            return Ok(SpannedExpr::new(
+                start.join(end),
+                AstExpr::Tuple(Vec::new()),
+            ));
+        }
+
+        token.recover();
+
+        let first = self.parse_expr()?;
+
+        if self.next_is(kw!(For)) {
+            let comprehension =
+                self.parse_comprehension(AstComprehensionKind::Generator, first, None)?;
+            let end = self.expect_token(T![RightParen])?.span;
+
+            return Ok(SpannedExpr::new(
+                start.join(end),
+                AstExpr::Comprehension(comprehension.into()),
+            ));
+        }
+
+        let token = self.expect()?;
+
+        match token.token {
+            T![RightParen] => Ok(SpannedExpr::new(start.join(token.span), first.node)),
+            T![Comma] => {
+                let (mut items, end) = self.parse_expr_list(T![RightParen])?;
+                items.insert(0, first);
+
+                Ok(SpannedExpr::new(start.join(end), AstExpr::Tuple(items)))
+            }
+            _ => Err(self.error_at(
+                token.span,
+                format!("Unexpected token: {:?}. Expected ',' or ')'", token.token),
+            )),
+        }
+    }
+
+    fn_bin_op! {parse_expr_comparison_op, parse_expr_sum;
+        T![EqualEqual] => Equal,
+        T![BangEqual] => NotEqual,
+        T![Less] => Less,
+        T![LessEqual] => LessEqual,
+        T![Greater] => Greater,
+        T![GreaterEqual] => GreaterEqual
+    }
+    fn_bin_op! {parse_expr_sum, parse_expr_term; T![Plus] => Add, T![Minus] => Sub}
+    fn_bin_op! {parse_expr_term, parse_expr_unary; T![Star] => Mul, T![Slash] => Div}
 }
@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+
+use super::node::{AstExpr, AstNames, AstParam, AstPattern, AstScope, AstStatement};
+
+impl AstNames {
+    /// Names of a function taking `params` and running `body`.
+    /// Nested functions and classes have their own names, only theirs are bound here
+    pub fn of_function(params: &[AstParam], body: &AstScope) -> Self {
+        let mut names = AstNames::default();
+        let mut bound = params
+            .iter()
+            .map(|param| param.name.clone())
+            .collect::<HashSet<_>>();
+
+        names.collect(body, &mut bound);
+
+        names.locals = bound
+            .into_iter()
+            .filter(|name| !names.globals.contains(name) && !names.nonlocals.contains(name))
+            .collect();
+
+        names
+    }
+
+    fn collect(&mut self, scope: &AstScope, bound: &mut HashSet<String>) {
+        for stmt in &scope.stmts {
+            match &stmt.node {
+                AstStatement::VariableDeclaration(name, _) => {
+                    bound.insert(name.node.clone());
+                }
+                AstStatement::Global(names) => {
+                    self.globals
+                        .extend(names.iter().map(|name| name.node.clone()));
+                }
+                AstStatement::Nonlocal(names) => {
+                    self.nonlocals
+                        .extend(names.iter().map(|name| name.node.clone()));
+                }
+                AstStatement::Conditional {
+                    body, otherwise, ..
+                } => {
+                    self.collect(body, bound);
+                    if let Some(otherwise) = otherwise {
+                        self.collect(otherwise, bound);
+                    }
+                }
+                AstStatement::While { body, .. } => self.collect(body, bound),
+                AstStatement::FunctionDef(def) => {
+                    bound.insert(def.name.clone());
+                }
+                AstStatement::ClassDef { name, .. } => {
+                    bound.insert(name.clone());
+                }
+                AstStatement::Try {
+                    body,
+                    handlers,
+                    otherwise,
+                    finally,
+                } => {
+                    self.collect(body, bound);
+
+                    for handler in handlers {
+                        bound.extend(handler.name.clone());
+                        self.collect(&handler.body, bound);
+                    }
+
+                    for block in otherwise.iter().chain(finally) {
+                        self.collect(block, bound);
+                    }
+                }
+                AstStatement::Delete(targets) => {
+                    for target in targets {
+                        if let AstExpr::Ident(name) = &target.node {
+                            bound.insert(name.clone());
+                        }
+                    }
+                }
+                AstStatement::Match { cases, .. } => {
+                    for case in cases {
+                        case.pattern.bind_names(bound);
+                        self.collect(&case.body, bound);
+                    }
+                }
+                AstStatement::Import(names) => {
+                    for name in names {
+                        let top = name.name.split('.').next().unwrap_or_default();
+                        bound.insert(name.alias.clone().unwrap_or_else(|| top.to_owned()));
+                    }
+                }
+                AstStatement::ImportFrom { names, .. } => {
+                    for name in names {
+                        bound.insert(name.alias.as_ref().unwrap_or(&name.name).clone());
+                    }
+                }
+                AstStatement::Assignment { .. }
+                | AstStatement::Expresion(_)
+                | AstStatement::Break
+                | AstStatement::Continue
+                | AstStatement::Return(_)
+                | AstStatement::Raise { .. }
+                | AstStatement::Assert { .. }
+                | AstStatement::Pass => {}
+            }
+        }
+    }
+}
+
+impl AstPattern {
+    /// Adds the names captured by the pattern to `bound`
+    fn bind_names(&self, bound: &mut HashSet<String>) {
+        match self {
+            AstPattern::Wildcard | AstPattern::Value(_) => {}
+            AstPattern::Capture(name) => {
+                bound.insert(name.clone());
+            }
+            AstPattern::Sequence(items) | AstPattern::Or(items) => {
+                items.iter().for_each(|item| item.bind_names(bound));
+            }
+            AstPattern::Star(name) => bound.extend(name.clone()),
+            AstPattern::Mapping { entries, rest } => {
+                entries
+                    .iter()
+                    .for_each(|(_, value)| value.bind_names(bound));
+                bound.extend(rest.clone());
+            }
+            AstPattern::Class { args, keywords, .. } => {
+                args.iter().for_each(|arg| arg.bind_names(bound));
+                keywords
+                    .iter()
+                    .for_each(|(_, value)| value.bind_names(bound));
+            }
+            AstPattern::As { pattern, name } => {
+                pattern.bind_names(bound);
+                bound.insert(name.clone());
+            }
+        }
+    }
+}
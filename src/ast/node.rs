@@ -1,10 +1,32 @@
 use core::fmt;
+use std::collections::HashSet;
 use std::fmt::Write;
+use std::ops;
+use std::rc::Rc;
 
+use crate::lexer::span::Span;
 use crate::lexer::token::TokenLiteral;
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct AstScope(pub Vec<AstStatement>);
+/// Node with the source span it was parsed from.
+///
+/// Equality ignores the span, so trees can be compared structurally.
+#[derive(Clone)]
+pub struct Spanned<T> {
+    pub span: Span,
+    pub node: T,
+}
+
+pub type SpannedExpr = Spanned<AstExpr>;
+pub type SpannedStatement = Spanned<AstStatement>;
+pub type SpannedPattern = Spanned<AstPattern>;
+/// Name declared or assigned by a statement, with its own span
+pub type SpannedName = Spanned<String>;
+
+#[derive(Clone)]
+pub struct AstScope {
+    pub span: Span,
+    pub stmts: Vec<SpannedStatement>,
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AstBinaryOp {
@@ -12,38 +34,418 @@ pub enum AstBinaryOp {
     Div,
     Mul,
     Sub,
+
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+/// Short-circuiting operators, which evaluate to one of their operands
+#[derive(Debug, Clone, PartialEq)]
+pub enum AstLogicalOp {
+    And,
+    Or,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AstExpr {
+    Attribute {
+        value: Box<SpannedExpr>,
+        name: String,
+    },
     BinaryOp {
         op: AstBinaryOp,
-        left: Box<AstExpr>,
-        right: Box<AstExpr>,
+        left: Box<SpannedExpr>,
+        right: Box<SpannedExpr>,
     },
+    Call {
+        func: Box<SpannedExpr>,
+        args: Vec<SpannedExpr>,
+        keywords: Vec<AstKeyword>,
+    },
+    Comprehension(Rc<AstComprehension>),
+    /// `body if test else otherwise`, only one of the branches is evaluated
+    Conditional {
+        test: Box<SpannedExpr>,
+        body: Box<SpannedExpr>,
+        otherwise: Box<SpannedExpr>,
+    },
+    Dict(Vec<(SpannedExpr, SpannedExpr)>),
     Ident(String),
+    /// Anonymous function, its body returns the expression
+    Lambda(Rc<AstFunction>),
+    List(Vec<SpannedExpr>),
     Literal(TokenLiteral),
+    Logical {
+        op: AstLogicalOp,
+        left: Box<SpannedExpr>,
+        right: Box<SpannedExpr>,
+    },
+    Set(Vec<SpannedExpr>),
+    /// `*iterable` argument of a call
+    Starred(Box<SpannedExpr>),
+    Subscript {
+        value: Box<SpannedExpr>,
+        index: Box<SpannedExpr>,
+    },
+    Tuple(Vec<SpannedExpr>),
     UnaryOp {
         op: AstUnaryOp,
-        right: Box<AstExpr>,
+        right: Box<SpannedExpr>,
     },
+    /// Suspends the generator, evaluating to the value it's resumed with
+    Yield(Option<Box<SpannedExpr>>),
+    /// Delegates to another generator, evaluating to its return value
+    YieldFrom(Box<SpannedExpr>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AstUnaryOp {
+    Neg,
     Not,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AstStatement {
-    VariableDeclaration(String, Box<AstExpr>),
-    Expresion(Box<AstExpr>),
-    Global(Vec<String>),
+    VariableDeclaration(SpannedName, Box<SpannedExpr>),
+    /// Assignment to anything but a plain name, like `obj.attr = value` or `obj[key] = value`
+    Assignment {
+        target: Box<SpannedExpr>,
+        value: Box<SpannedExpr>,
+    },
+    Expresion(Box<SpannedExpr>),
+    Global(Vec<SpannedName>),
+    Nonlocal(Vec<SpannedName>),
     Conditional {
-        test: Box<AstExpr>,
+        test: Box<SpannedExpr>,
+        body: AstScope,
+        otherwise: Option<AstScope>,
+    },
+    While {
+        test: Box<SpannedExpr>,
+        body: AstScope,
+    },
+    Break,
+    Continue,
+    /// Shared with the function values created from it
+    FunctionDef(Rc<AstFunction>),
+    Return(Option<Box<SpannedExpr>>),
+    ClassDef {
+        name: String,
+        bases: Vec<SpannedExpr>,
+        body: AstScope,
+    },
+    Try {
         body: AstScope,
+        handlers: Vec<AstExceptHandler>,
         otherwise: Option<AstScope>,
+        finally: Option<AstScope>,
+    },
+    /// `raise` without exception re-raises the one being handled
+    Raise {
+        exception: Option<Box<SpannedExpr>>,
+        cause: Option<Box<SpannedExpr>>,
+    },
+    /// Raises `AssertionError` if `test` is falsy
+    Assert {
+        test: Box<SpannedExpr>,
+        message: Option<Box<SpannedExpr>>,
+        /// Source text of `test`, shown when there's no message
+        source: String,
+    },
+    Pass,
+    /// `del a, xs[i], obj.attr`
+    Delete(Vec<SpannedExpr>),
+    /// Runs the first case whose pattern matches the subject
+    Match {
+        subject: Box<SpannedExpr>,
+        cases: Vec<AstMatchCase>,
+    },
+    /// `import a.b, c as d`
+    Import(Vec<AstAlias>),
+    /// `from ..a.b import c as d`
+    ImportFrom {
+        /// Missing in `from . import c`
+        module: Option<String>,
+        /// Number of leading dots, `0` for absolute imports
+        level: usize,
+        names: Vec<AstAlias>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstFunction {
+    pub name: String,
+    pub params: Vec<AstParam>,
+    pub body: AstScope,
+    /// Whether the body contains `yield`, calls return a generator instead of running it
+    pub is_generator: bool,
+    pub names: AstNames,
+}
+
+/// How a function binds the names it uses, found when it's parsed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AstNames {
+    /// Parameters and names bound in the body, reading them before assignment
+    /// raises `UnboundLocalError` instead of looking outside
+    pub locals: HashSet<String>,
+    /// Declared `global`
+    pub globals: HashSet<String>,
+    /// Declared `nonlocal`, bound by an enclosing function
+    pub nonlocals: HashSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstParam {
+    pub name: String,
+    pub kind: AstParamKind,
+    /// Evaluated once, when the function is defined
+    pub default: Option<SpannedExpr>,
+}
+
+/// How arguments are bound to a parameter, in the order they must be declared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AstParamKind {
+    /// Before `/`
+    PositionalOnly,
+    Normal,
+    /// `*args`
+    VarArgs,
+    /// After `*` or `*args`
+    KeywordOnly,
+    /// `**kwargs`
+    VarKeywords,
+}
+
+/// `name=value` argument of a call, or `**mapping` without a name
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstKeyword {
+    pub name: Option<String>,
+    pub value: SpannedExpr,
+}
+
+/// `[x for ...]`, `{x for ...}`, `{k: v for ...}` or `(x for ...)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstComprehension {
+    pub kind: AstComprehensionKind,
+    /// Item produced on each iteration, the key in dict comprehensions
+    pub element: SpannedExpr,
+    /// Set only in dict comprehensions
+    pub value: Option<SpannedExpr>,
+    /// Outermost first, its iterable is evaluated in the enclosing scope
+    pub clauses: Vec<AstForClause>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AstComprehensionKind {
+    List,
+    Set,
+    Dict,
+    /// Evaluated lazily, one item at a time
+    Generator,
+}
+
+/// `for target in iter if condition ...` clause of a comprehension
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstForClause {
+    /// Name or tuple of targets to unpack the items into
+    pub target: SpannedExpr,
+    pub iter: SpannedExpr,
+    pub conditions: Vec<SpannedExpr>,
+}
+
+/// `except [class [as name]]:` clause of a `try` statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstExceptHandler {
+    /// Class or tuple of classes to catch, everything if missing
+    pub class: Option<SpannedExpr>,
+    pub name: Option<String>,
+    pub body: AstScope,
+}
+
+/// Name imported by an `import` statement, dotted in `import a.b`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstAlias {
+    pub name: String,
+    /// Set by `as alias`
+    pub alias: Option<String>,
+}
+
+/// `case pattern [if guard]:` block of a `match` statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstMatchCase {
+    pub pattern: SpannedPattern,
+    /// Evaluated after binding the names captured by the pattern
+    pub guard: Option<SpannedExpr>,
+    pub body: AstScope,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AstPattern {
+    /// `_`, matches anything without binding it
+    Wildcard,
+    /// Bare name, matches anything and binds it
+    Capture(String),
+    /// Literal or dotted name compared with `==`, `nil` and booleans must be the same value
+    Value(SpannedExpr),
+    /// `[a, *rest]` or `(a, b)`, matching lists and tuples
+    Sequence(Vec<SpannedPattern>),
+    /// `*name` inside a sequence pattern, `None` for `*_`
+    Star(Option<String>),
+    /// `{key: pattern, **rest}`, matching dicts with at least those keys
+    Mapping {
+        entries: Vec<(SpannedExpr, SpannedPattern)>,
+        rest: Option<String>,
+    },
+    /// `Class(pattern, name=pattern)`, positional patterns are matched
+    /// against the attributes listed in `__match_args__`
+    Class {
+        class: SpannedExpr,
+        args: Vec<SpannedPattern>,
+        keywords: Vec<(String, SpannedPattern)>,
     },
+    /// `a | b`, the first alternative that matches
+    Or(Vec<SpannedPattern>),
+    /// `pattern as name`
+    As {
+        pattern: Box<SpannedPattern>,
+        name: String,
+    },
+}
+
+impl AstPattern {
+    /// Whether it matches any subject, making the cases after it unreachable
+    pub fn is_irrefutable(&self) -> bool {
+        match self {
+            AstPattern::Wildcard | AstPattern::Capture(_) => true,
+            AstPattern::Or(alternatives) => alternatives.iter().any(|alt| alt.is_irrefutable()),
+            AstPattern::As { pattern, .. } => pattern.is_irrefutable(),
+            _ => false,
+        }
+    }
+
+    /// Names bound when it matches, in the order they appear
+    pub fn bound_names(&self) -> Vec<&str> {
+        match self {
+            AstPattern::Wildcard | AstPattern::Value(_) | AstPattern::Star(None) => Vec::new(),
+            AstPattern::Capture(name) | AstPattern::Star(Some(name)) => Vec::from([name.as_str()]),
+            AstPattern::Sequence(items) => items.iter().flat_map(|i| i.bound_names()).collect(),
+            AstPattern::Mapping { entries, rest } => entries
+                .iter()
+                .flat_map(|(_, pattern)| pattern.bound_names())
+                .chain(rest.as_deref())
+                .collect(),
+            AstPattern::Class { args, keywords, .. } => args
+                .iter()
+                .chain(keywords.iter().map(|(_, pattern)| pattern))
+                .flat_map(|pattern| pattern.bound_names())
+                .collect(),
+            AstPattern::Or(alternatives) => alternatives
+                .first()
+                .map(|alt| alt.bound_names())
+                .unwrap_or_default(),
+            AstPattern::As { pattern, name } => {
+                let mut names = pattern.bound_names();
+                names.push(name);
+                names
+            }
+        }
+    }
+}
+
+impl AstForClause {
+    /// From the target to the last condition
+    pub fn span(&self) -> Span {
+        let end = self.conditions.last().unwrap_or(&self.iter);
+        self.target.span.join(end.span)
+    }
+}
+
+impl AstParam {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            kind: AstParamKind::Normal,
+            default: None,
+        }
+    }
+}
+
+impl<T> Spanned<T> {
+    pub fn new(span: Span, node: T) -> Self {
+        Self { span, node }
+    }
+
+    pub fn parts(self) -> (Span, T) {
+        (self.span, self.node)
+    }
+}
+
+impl<T> From<T> for Spanned<T> {
+    fn from(node: T) -> Self {
+        Self::new(Span::ZERO, node)
+    }
+}
+
+impl<T> ops::Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.node
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Spanned<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt::Debug::fmt(&self.node, f)
+    }
+}
+
+impl AstBinaryOp {
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            AstBinaryOp::Equal
+                | AstBinaryOp::NotEqual
+                | AstBinaryOp::Less
+                | AstBinaryOp::LessEqual
+                | AstBinaryOp::Greater
+                | AstBinaryOp::GreaterEqual
+        )
+    }
+}
+
+impl AstScope {
+    pub fn new(span: Span, stmts: Vec<SpannedStatement>) -> Self {
+        Self { span, stmts }
+    }
+}
+
+impl From<SpannedStatement> for AstScope {
+    fn from(stmt: SpannedStatement) -> Self {
+        Self::new(stmt.span, Vec::from([stmt]))
+    }
+}
+
+impl PartialEq for AstScope {
+    fn eq(&self, other: &Self) -> bool {
+        self.stmts == other.stmts
+    }
+}
+
+impl fmt::Debug for AstScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("AstScope").field(&self.stmts).finish()
+    }
 }
 
 impl fmt::Display for AstBinaryOp {
@@ -53,6 +455,12 @@ impl fmt::Display for AstBinaryOp {
             AstBinaryOp::Div => f.write_char('/'),
             AstBinaryOp::Mul => f.write_char('*'),
             AstBinaryOp::Sub => f.write_char('-'),
+            AstBinaryOp::Equal => f.write_str("=="),
+            AstBinaryOp::NotEqual => f.write_str("!="),
+            AstBinaryOp::Less => f.write_char('<'),
+            AstBinaryOp::LessEqual => f.write_str("<="),
+            AstBinaryOp::Greater => f.write_char('>'),
+            AstBinaryOp::GreaterEqual => f.write_str(">="),
         }
     }
 }
@@ -60,7 +468,17 @@ impl fmt::Display for AstBinaryOp {
 impl fmt::Display for AstUnaryOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            AstUnaryOp::Neg => f.write_char('-'),
             AstUnaryOp::Not => f.write_char('!'),
         }
     }
 }
+
+impl fmt::Display for AstLogicalOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AstLogicalOp::And => f.write_str("and"),
+            AstLogicalOp::Or => f.write_str("or"),
+        }
+    }
+}
@@ -2,13 +2,16 @@ use core::fmt;
 
 use crate::pretty_print::*;
 
-use super::node::{AstExpr, AstScope, AstStatement};
+use super::node::{
+    AstAlias, AstComprehension, AstComprehensionKind, AstExpr, AstParam, AstParamKind, AstPattern,
+    AstScope, AstStatement, Spanned, SpannedExpr,
+};
 
 impl fmt::Display for AstScope {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let level = f.width().unwrap_or(0);
 
-        for stmt in &self.0 {
+        for stmt in &self.stmts {
             f.write_fmt(format_args!("{stmt:level$}\n"))?;
         }
 
@@ -16,6 +19,12 @@ impl fmt::Display for AstScope {
     }
 }
 
+impl<T: fmt::Display> fmt::Display for Spanned<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt::Display::fmt(&self.node, f)
+    }
+}
+
 impl fmt::Display for AstStatement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let level = f.width().map_or(0, |l| l + 1);
@@ -26,10 +35,16 @@ impl fmt::Display for AstStatement {
 
         match self {
             AstStatement::VariableDeclaration(var, expr) => {
-                f.write_fmt(format_args!("{VARIABLE}{var}{R} {PUNCTUATION}={R} {expr}"))
+                f.write_fmt(format_args!("{VARIABLE}{}{R} {PUNCTUATION}={R} {expr}", var.node))
+            }
+            AstStatement::Assignment { target, value } => {
+                f.write_fmt(format_args!("{target} {PUNCTUATION}={R} {value}"))
             }
             AstStatement::Expresion(expr) => f.write_fmt(format_args!("{expr}")),
             AstStatement::Global(vec) => f.write_fmt(format_args!("{KEYWORD}global{R} {vec:?}")),
+            AstStatement::Nonlocal(vec) => {
+                f.write_fmt(format_args!("{KEYWORD}nonlocal{R} {vec:?}"))
+            }
             AstStatement::Conditional {
                 test,
                 body,
@@ -44,6 +59,146 @@ impl fmt::Display for AstStatement {
             } => f.write_fmt(format_args!(
                 "{KEYWORD}if{R} {test}{PUNCTUATION}:{R}\n{body:level$}{indent}{KEYWORD}else{R}{PUNCTUATION}:{R}\n{otherwise:level$}"
             )),
+            AstStatement::While { test, body } => f.write_fmt(format_args!(
+                "{KEYWORD}while{R} {test}{PUNCTUATION}:{R}\n{body:level$}"
+            )),
+            AstStatement::Break => f.write_fmt(format_args!("{KEYWORD}break{R}")),
+            AstStatement::Continue => f.write_fmt(format_args!("{KEYWORD}continue{R}")),
+            AstStatement::FunctionDef(function) => {
+                f.write_fmt(format_args!(
+                    "{KEYWORD}def{R} {VARIABLE}{}{R}{PUNCTUATION}({R}",
+                    function.name
+                ))?;
+
+                write_params(f, &function.params)?;
+
+                f.write_fmt(format_args!(
+                    "{PUNCTUATION}):{R}\n{:level$}",
+                    function.body
+                ))
+            }
+            AstStatement::Return(None) => f.write_fmt(format_args!("{KEYWORD}return{R}")),
+            AstStatement::Return(Some(value)) => {
+                f.write_fmt(format_args!("{KEYWORD}return{R} {value}"))
+            }
+            AstStatement::ClassDef { name, bases, body } => {
+                f.write_fmt(format_args!("{KEYWORD}class{R} {VARIABLE}{name}{R}"))?;
+
+                if !bases.is_empty() {
+                    f.write_fmt(format_args!("{PUNCTUATION}({R}"))?;
+                    write_list(f, bases)?;
+                    f.write_fmt(format_args!("{PUNCTUATION}){R}"))?;
+                }
+
+                f.write_fmt(format_args!("{PUNCTUATION}:{R}\n{body:level$}"))
+            }
+            AstStatement::Try {
+                body,
+                handlers,
+                otherwise,
+                finally,
+            } => {
+                f.write_fmt(format_args!(
+                    "{KEYWORD}try{R}{PUNCTUATION}:{R}\n{body:level$}"
+                ))?;
+
+                for handler in handlers {
+                    f.write_fmt(format_args!("{indent}{KEYWORD}except{R}"))?;
+
+                    if let Some(class) = &handler.class {
+                        f.write_fmt(format_args!(" {class}"))?;
+                    }
+
+                    if let Some(name) = &handler.name {
+                        f.write_fmt(format_args!(" {KEYWORD}as{R} {VARIABLE}{name}{R}"))?;
+                    }
+
+                    f.write_fmt(format_args!(
+                        "{PUNCTUATION}:{R}\n{:level$}",
+                        handler.body
+                    ))?;
+                }
+
+                if let Some(otherwise) = otherwise {
+                    f.write_fmt(format_args!(
+                        "{indent}{KEYWORD}else{R}{PUNCTUATION}:{R}\n{otherwise:level$}"
+                    ))?;
+                }
+
+                if let Some(finally) = finally {
+                    f.write_fmt(format_args!(
+                        "{indent}{KEYWORD}finally{R}{PUNCTUATION}:{R}\n{finally:level$}"
+                    ))?;
+                }
+
+                Ok(())
+            }
+            AstStatement::Raise { exception, cause } => {
+                f.write_fmt(format_args!("{KEYWORD}raise{R}"))?;
+
+                if let Some(exception) = exception {
+                    f.write_fmt(format_args!(" {exception}"))?;
+                }
+
+                if let Some(cause) = cause {
+                    f.write_fmt(format_args!(" {KEYWORD}from{R} {cause}"))?;
+                }
+
+                Ok(())
+            }
+            AstStatement::Assert { test, message, .. } => {
+                f.write_fmt(format_args!("{KEYWORD}assert{R} {test}"))?;
+
+                if let Some(message) = message {
+                    f.write_fmt(format_args!("{PUNCTUATION},{R} {message}"))?;
+                }
+
+                Ok(())
+            }
+            AstStatement::Pass => f.write_fmt(format_args!("{KEYWORD}pass{R}")),
+            AstStatement::Import(names) => {
+                f.write_fmt(format_args!("{KEYWORD}import{R} "))?;
+                write_aliases(f, names)
+            }
+            AstStatement::ImportFrom {
+                module,
+                level,
+                names,
+            } => {
+                f.write_fmt(format_args!(
+                    "{KEYWORD}from{R} {}{} {KEYWORD}import{R} ",
+                    ".".repeat(*level),
+                    module.as_deref().unwrap_or_default()
+                ))?;
+                write_aliases(f, names)
+            }
+            AstStatement::Delete(targets) => {
+                f.write_fmt(format_args!("{KEYWORD}del{R} "))?;
+                write_list(f, targets)
+            }
+            AstStatement::Match { subject, cases } => {
+                f.write_fmt(format_args!("{KEYWORD}match{R} {subject}{PUNCTUATION}:{R}"))?;
+
+                let body_level = level + 1;
+
+                for case in cases {
+                    f.write_fmt(format_args!(
+                        "\n{indent} {KEYWORD}case{R} {}",
+                        case.pattern
+                    ))?;
+
+                    if let Some(guard) = &case.guard {
+                        f.write_fmt(format_args!(" {KEYWORD}if{R} {guard}"))?;
+                    }
+
+                    f.write_fmt(format_args!(
+                        "{PUNCTUATION}:{R}\n{:body_level$}",
+                        case.body
+                    ))?;
+                }
+
+                Ok(())
+            }
         }
     }
 }
@@ -51,14 +206,273 @@ impl fmt::Display for AstStatement {
 impl fmt::Display for AstExpr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            AstExpr::Attribute { value, name } => {
+                f.write_fmt(format_args!("{value}{PUNCTUATION}.{R}{VARIABLE}{name}{R}"))
+            }
+            AstExpr::Starred(value) => f.write_fmt(format_args!("{PUNCTUATION}*{R}{value}")),
+            AstExpr::Subscript { value, index } => f.write_fmt(format_args!(
+                "{value}{PUNCTUATION}[{R}{index}{PUNCTUATION}]{R}"
+            )),
             AstExpr::BinaryOp { op, left, right } => {
                 f.write_fmt(format_args!("{left} {PUNCTUATION}{op}{R} {right}"))
             }
+            AstExpr::Call {
+                func,
+                args,
+                keywords,
+            } => {
+                f.write_fmt(format_args!("{func}{PUNCTUATION}({R}"))?;
+                write_list(f, args)?;
+
+                for (idx, keyword) in keywords.iter().enumerate() {
+                    if idx != 0 || !args.is_empty() {
+                        f.write_fmt(format_args!("{PUNCTUATION},{R} "))?;
+                    }
+
+                    match &keyword.name {
+                        Some(name) => f.write_fmt(format_args!(
+                            "{VARIABLE}{name}{R}{PUNCTUATION}={R}{}",
+                            keyword.value
+                        ))?,
+                        None => f.write_fmt(format_args!("{PUNCTUATION}**{R}{}", keyword.value))?,
+                    }
+                }
+
+                f.write_fmt(format_args!("{PUNCTUATION}){R}"))
+            }
+            AstExpr::Comprehension(comprehension) => f.write_fmt(format_args!("{comprehension}")),
+            AstExpr::Dict(items) => {
+                f.write_fmt(format_args!("{PUNCTUATION}{{{R}"))?;
+
+                for (idx, (key, value)) in items.iter().enumerate() {
+                    if idx != 0 {
+                        f.write_fmt(format_args!("{PUNCTUATION},{R} "))?;
+                    }
+
+                    f.write_fmt(format_args!("{key}{PUNCTUATION}:{R} {value}"))?;
+                }
+
+                f.write_fmt(format_args!("{PUNCTUATION}}}{R}"))
+            }
             AstExpr::Ident(ident) => f.write_fmt(format_args!("{VARIABLE}{ident}{R}")),
+            AstExpr::Lambda(function) => {
+                f.write_fmt(format_args!("{KEYWORD}lambda{R}"))?;
+
+                if !function.params.is_empty() {
+                    f.write_str(" ")?;
+                    write_params(f, &function.params)?;
+                }
+
+                f.write_fmt(format_args!("{PUNCTUATION}:{R}"))?;
+
+                match function.body.stmts.first().map(|stmt| &stmt.node) {
+                    Some(AstStatement::Return(Some(value))) => {
+                        f.write_fmt(format_args!(" {value}"))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            AstExpr::List(items) => {
+                f.write_fmt(format_args!("{PUNCTUATION}[{R}"))?;
+                write_list(f, items)?;
+                f.write_fmt(format_args!("{PUNCTUATION}]{R}"))
+            }
+            AstExpr::Set(items) => {
+                f.write_fmt(format_args!("{PUNCTUATION}{{{R}"))?;
+                write_list(f, items)?;
+                f.write_fmt(format_args!("{PUNCTUATION}}}{R}"))
+            }
             AstExpr::Literal(token_literal) => f.write_fmt(format_args!("{token_literal}")),
+            AstExpr::Conditional {
+                test,
+                body,
+                otherwise,
+            } => f.write_fmt(format_args!(
+                "{body} {KEYWORD}if{R} {test} {KEYWORD}else{R} {otherwise}"
+            )),
+            AstExpr::Logical { op, left, right } => {
+                f.write_fmt(format_args!("{left} {KEYWORD}{op}{R} {right}"))
+            }
+            AstExpr::Tuple(items) => {
+                f.write_fmt(format_args!("{PUNCTUATION}({R}"))?;
+                write_list(f, items)?;
+
+                if items.len() == 1 {
+                    f.write_fmt(format_args!("{PUNCTUATION},{R}"))?;
+                }
+
+                f.write_fmt(format_args!("{PUNCTUATION}){R}"))
+            }
             AstExpr::UnaryOp { op, right } => {
                 f.write_fmt(format_args!("{PUNCTUATION}{op}{R} {right}"))
             }
+            AstExpr::Yield(None) => f.write_fmt(format_args!("{KEYWORD}yield{R}")),
+            AstExpr::Yield(Some(value)) => f.write_fmt(format_args!("{KEYWORD}yield{R} {value}")),
+            AstExpr::YieldFrom(value) => {
+                f.write_fmt(format_args!("{KEYWORD}yield from{R} {value}"))
+            }
+        }
+    }
+}
+
+impl fmt::Display for AstPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let write_items = |f: &mut fmt::Formatter<'_>, items: &[String]| {
+            f.write_str(&items.join(&format!("{PUNCTUATION},{R} ")))
+        };
+
+        match self {
+            AstPattern::Wildcard => f.write_fmt(format_args!("{VARIABLE}_{R}")),
+            AstPattern::Capture(name) => f.write_fmt(format_args!("{VARIABLE}{name}{R}")),
+            AstPattern::Value(value) => f.write_fmt(format_args!("{value}")),
+            AstPattern::Sequence(items) => {
+                let items = items.iter().map(ToString::to_string).collect::<Vec<_>>();
+                f.write_fmt(format_args!("{PUNCTUATION}[{R}"))?;
+                write_items(f, &items)?;
+                f.write_fmt(format_args!("{PUNCTUATION}]{R}"))
+            }
+            AstPattern::Star(name) => f.write_fmt(format_args!(
+                "{PUNCTUATION}*{R}{VARIABLE}{}{R}",
+                name.as_deref().unwrap_or("_")
+            )),
+            AstPattern::Mapping { entries, rest } => {
+                let items = entries
+                    .iter()
+                    .map(|(key, value)| format!("{key}{PUNCTUATION}:{R} {value}"))
+                    .chain(
+                        rest.iter()
+                            .map(|rest| format!("{PUNCTUATION}**{R}{VARIABLE}{rest}{R}")),
+                    )
+                    .collect::<Vec<_>>();
+                f.write_fmt(format_args!("{PUNCTUATION}{{{R}"))?;
+                write_items(f, &items)?;
+                f.write_fmt(format_args!("{PUNCTUATION}}}{R}"))
+            }
+            AstPattern::Class {
+                class,
+                args,
+                keywords,
+            } => {
+                let items = args
+                    .iter()
+                    .map(ToString::to_string)
+                    .chain(
+                        keywords
+                            .iter()
+                            .map(|(name, value)| format!("{name}{PUNCTUATION}={R}{value}")),
+                    )
+                    .collect::<Vec<_>>();
+                f.write_fmt(format_args!("{class}{PUNCTUATION}({R}"))?;
+                write_items(f, &items)?;
+                f.write_fmt(format_args!("{PUNCTUATION}){R}"))
+            }
+            AstPattern::Or(alternatives) => {
+                for (idx, alternative) in alternatives.iter().enumerate() {
+                    if idx != 0 {
+                        f.write_fmt(format_args!(" {PUNCTUATION}|{R} "))?;
+                    }
+
+                    f.write_fmt(format_args!("{alternative}"))?;
+                }
+
+                Ok(())
+            }
+            AstPattern::As { pattern, name } => {
+                f.write_fmt(format_args!("{pattern} {KEYWORD}as{R} {VARIABLE}{name}{R}"))
+            }
         }
     }
 }
+
+impl fmt::Display for AstComprehension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (open, close) = match self.kind {
+            AstComprehensionKind::List => ('[', ']'),
+            AstComprehensionKind::Set | AstComprehensionKind::Dict => ('{', '}'),
+            AstComprehensionKind::Generator => ('(', ')'),
+        };
+
+        f.write_fmt(format_args!("{PUNCTUATION}{open}{R}{}", self.element))?;
+
+        if let Some(value) = &self.value {
+            f.write_fmt(format_args!("{PUNCTUATION}:{R} {value}"))?;
+        }
+
+        for clause in &self.clauses {
+            f.write_fmt(format_args!(
+                " {KEYWORD}for{R} {} {KEYWORD}in{R} {}",
+                clause.target, clause.iter
+            ))?;
+
+            for condition in &clause.conditions {
+                f.write_fmt(format_args!(" {KEYWORD}if{R} {condition}"))?;
+            }
+        }
+
+        f.write_fmt(format_args!("{PUNCTUATION}{close}{R}"))
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, exprs: &[SpannedExpr]) -> fmt::Result {
+    for (idx, expr) in exprs.iter().enumerate() {
+        if idx != 0 {
+            f.write_fmt(format_args!("{PUNCTUATION},{R} "))?;
+        }
+
+        f.write_fmt(format_args!("{expr}"))?;
+    }
+
+    Ok(())
+}
+
+fn write_aliases(f: &mut fmt::Formatter<'_>, names: &[AstAlias]) -> fmt::Result {
+    for (idx, name) in names.iter().enumerate() {
+        if idx != 0 {
+            f.write_fmt(format_args!("{PUNCTUATION},{R} "))?;
+        }
+
+        f.write_str(&name.name)?;
+
+        if let Some(alias) = &name.alias {
+            f.write_fmt(format_args!(" {KEYWORD}as{R} {alias}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parameters with their `/` and `*` markers
+fn write_params(f: &mut fmt::Formatter<'_>, params: &[AstParam]) -> fmt::Result {
+    let mut items = Vec::new();
+
+    for (idx, param) in params.iter().enumerate() {
+        let next = params.get(idx + 1).map(|param| param.kind);
+
+        if param.kind == AstParamKind::KeywordOnly
+            && (idx == 0 || params[idx - 1].kind < AstParamKind::VarArgs)
+        {
+            items.push(format!("{PUNCTUATION}*{R}"));
+        }
+
+        let prefix = match param.kind {
+            AstParamKind::VarArgs => "*",
+            AstParamKind::VarKeywords => "**",
+            _ => "",
+        };
+
+        let mut item = format!("{PUNCTUATION}{prefix}{R}{VARIABLE}{}{R}", param.name);
+
+        if let Some(default) = &param.default {
+            item += &format!("{PUNCTUATION}={R}{default}");
+        }
+
+        items.push(item);
+
+        if param.kind == AstParamKind::PositionalOnly && next != Some(AstParamKind::PositionalOnly)
+        {
+            items.push(format!("{PUNCTUATION}/{R}"));
+        }
+    }
+
+    f.write_str(&items.join(&format!("{PUNCTUATION},{R} ")))
+}
@@ -2,16 +2,34 @@ use core::fmt;
 use std::collections::VecDeque;
 use std::ops;
 
-use ariadne::{Color, Label, Report, ReportBuilder, ReportKind, Source};
-
+use super::node::SpannedName;
+use crate::diagnostic::{Diagnostic, codes};
 use crate::lexer::span::Span;
 use crate::lexer::token::{SpannedToken, Token};
+use crate::source_map::SourceFile;
+
+pub type AstResult<T> = Result<T, Diagnostic>;
 
 #[derive(Clone, Debug)]
 pub struct SourceAst<'i> {
-    pub base: &'i str,
+    pub file: &'i SourceFile,
     pub last_offset: usize,
     pub tokens: VecDeque<SpannedToken>,
+    /// Whether `return` is allowed
+    pub in_function: bool,
+    /// Whether a function encloses the code, even through class bodies,
+    /// so `nonlocal` is allowed
+    pub in_nested_scope: bool,
+    /// Whether `break` and `continue` are allowed
+    pub in_loop: bool,
+    /// Whether a `yield` was found in the function being parsed
+    pub has_yield: bool,
+    /// Expressions, patterns and blocks enclosing the code being parsed
+    pub depth: usize,
+    /// `nonlocal` names not bound yet by a function enclosing their declaration
+    pub nonlocals: Vec<SpannedName>,
+    /// Problems that don't stop parsing
+    pub warnings: Vec<Diagnostic>,
 }
 
 pub struct PeekedToken<'i, 's> {
@@ -21,19 +39,33 @@ pub struct PeekedToken<'i, 's> {
 }
 
 impl<'i> SourceAst<'i> {
-    pub fn new(base: &'i str, tokens: VecDeque<SpannedToken>) -> Self {
+    pub fn new(file: &'i SourceFile, tokens: VecDeque<SpannedToken>) -> Self {
         Self {
-            base,
+            file,
             tokens,
             last_offset: 0,
+            in_function: false,
+            in_nested_scope: false,
+            in_loop: false,
+            has_yield: false,
+            depth: 0,
+            nonlocals: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 
     pub fn with(&self, tokens: VecDeque<SpannedToken>) -> Self {
         Self {
             tokens,
-            base: self.base,
+            file: self.file,
             last_offset: self.last_offset,
+            in_function: self.in_function,
+            in_nested_scope: self.in_nested_scope,
+            in_loop: self.in_loop,
+            has_yield: self.has_yield,
+            depth: self.depth,
+            nonlocals: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 
@@ -49,89 +81,72 @@ impl<'i> SourceAst<'i> {
         })
     }
 
-    pub fn peek_expect<'a>(&'a mut self) -> PeekedToken<'i, 'a> {
+    pub fn peek_expect<'a>(&'a mut self) -> AstResult<PeekedToken<'i, 'a>> {
         // The implementation cannot be done with `peek` call
         // because of borrow checker :|
         let Some(token) = self.tokens.pop_front() else {
-            self.error_in_place("Unexpected EOF");
+            return Err(self.error_in_place("Unexpected EOF"));
         };
 
         let last_offset = self.last_offset;
         self.last_offset = token.span.to;
-        PeekedToken {
+        Ok(PeekedToken {
             token,
             last_offset,
             source: self,
-        }
+        })
     }
 
-    pub fn expect(&mut self) -> SpannedToken {
+    pub fn expect(&mut self) -> AstResult<SpannedToken> {
         self.tokens
             .pop_front()
             .inspect(|t| self.last_offset = t.span.to)
-            .unwrap_or_else(|| self.error_in_place("Unexpected EOF"))
+            .ok_or_else(|| self.error_in_place("Unexpected EOF"))
     }
 
-    pub fn expect_msg(&mut self, msg: impl fmt::Display) -> SpannedToken {
+    pub fn expect_msg(&mut self, msg: impl fmt::Display) -> AstResult<SpannedToken> {
         self.tokens
             .pop_front()
             .inspect(|t| self.last_offset = t.span.to)
-            .unwrap_or_else(|| self.error_in_place(format!("Unexpected EOF. {msg}")))
+            .ok_or_else(|| self.error_in_place(format!("Unexpected EOF. {msg}")))
     }
 
     pub fn expect_match<T>(
         &mut self,
         msg: impl fmt::Display,
         predicate: impl Fn(SpannedToken) -> Option<T>,
-    ) -> T {
+    ) -> AstResult<T> {
         let expected_err = format!("Expected {msg}");
-        let first = self.expect_msg(&expected_err);
+        let first = self.expect_msg(&expected_err)?;
 
         let span = first.span;
         let unexpected_err = format!("Unexpected token: {first:?}");
 
         if let Some(t) = predicate(first) {
             self.last_offset = span.to;
-            t
+            Ok(t)
         } else {
-            self.error_build(span, |b| {
-                b.with_message(unexpected_err).with_label(
-                    Label::new(span)
-                        .with_color(Color::BrightRed)
-                        .with_message(expected_err),
-                )
-            });
+            Err(Diagnostic::error(codes::UNEXPECTED_TOKEN, unexpected_err)
+                .with_label(span, expected_err))
         }
     }
 
-    pub fn expect_token(&mut self, token: Token) -> SpannedToken {
+    pub fn expect_token(&mut self, token: Token) -> AstResult<SpannedToken> {
         self.expect_match(format!("{token:#?}"), |t| (t == token).then_some(t))
     }
 
-    pub fn error_in_place(&self, msg: impl fmt::Display) -> ! {
-        self.error_build(Span::char(self.last_offset), |b| b.with_message(&msg))
+    /// Empty span right after the last consumed token
+    pub fn span_in_place(&self) -> Span {
+        Span::new(self.file.id(), self.last_offset..self.last_offset)
     }
 
-    pub fn error_at(&self, span: Span, msg: impl fmt::Display) -> ! {
-        self.error_build(span, |b| {
-            b.with_message(&msg).with_label(
-                Label::new(span)
-                    .with_message(msg)
-                    .with_color(ariadne::Color::BrightRed),
-            )
-        })
+    /// Input ran out right after the last consumed token
+    pub fn error_in_place(&self, msg: impl fmt::Display) -> Diagnostic {
+        Diagnostic::error(codes::UNEXPECTED_EOF, msg).with_span(self.span_in_place())
     }
 
-    pub fn error_build(
-        &self,
-        span: Span,
-        fun: impl FnOnce(ReportBuilder<Span>) -> ReportBuilder<Span>,
-    ) -> ! {
-        _ = fun(Report::build(ReportKind::Error, span))
-            .finish()
-            .eprint(Source::from(self.base));
-
-        std::process::exit(1);
+    pub fn error_at(&self, span: Span, msg: impl fmt::Display) -> Diagnostic {
+        Diagnostic::error(codes::UNEXPECTED_TOKEN, &msg).with_label(span, msg)
     }
 }
 
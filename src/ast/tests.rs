@@ -1,10 +1,18 @@
-use crate::ast::node::{AstScope, AstStatement};
+use crate::ast::MAX_NESTING;
+use crate::ast::node::{
+    AstAlias, AstComprehensionKind, AstExceptHandler, AstExpr, AstForClause, AstLogicalOp,
+    AstParam, AstParamKind, AstPattern, AstScope, AstStatement, SpannedExpr, SpannedPattern,
+    SpannedStatement,
+};
 use crate::ast::utils::{bin_op, scope};
+use crate::diagnostic::codes;
 use crate::lexer::Lexer;
 use crate::lexer::utils::{ident, literal};
+use crate::source_map::SourceFile;
 
 fn create_scope(content: &str) -> AstScope {
-    AstScope::from_tokens(content, Lexer::from_str(content).unwrap())
+    let file = SourceFile::anonymous(content);
+    AstScope::from_tokens(&file, Lexer::from_source(&file).unwrap()).unwrap()
 }
 
 #[test]
@@ -13,7 +21,7 @@ fn simple() {
     assert_eq!(
         res,
         scope![AstStatement::VariableDeclaration(
-            ident!(@raw a),
+            ident!(@name a),
             literal!(@ast 1).into()
         )]
     )
@@ -26,13 +34,13 @@ fn expression() {
         res,
         scope![AstStatement::Expresion(
             bin_op!(
-                ident!(@ast a),
-                Add,
                 bin_op!(
-                    bin_op!(ident!(@ast b), Mul, ident!(@ast c)),
-                    Sub,
-                    bin_op!(ident!(@ast d), Div, ident!(@ast e))
-                )
+                    ident!(@ast a),
+                    Add,
+                    bin_op!(ident!(@ast b), Mul, ident!(@ast c))
+                ),
+                Sub,
+                bin_op!(ident!(@ast d), Div, ident!(@ast e))
             )
             .into()
         )]
@@ -45,8 +53,8 @@ fn global_stmt() {
     assert_eq!(
         res,
         scope![AstStatement::Global(Vec::from([
-            ident!(@raw a),
-            ident!(@raw b),
+            ident!(@name a),
+            ident!(@name b),
         ]))]
     )
 }
@@ -59,7 +67,7 @@ fn conditional() {
         scope![AstStatement::Conditional {
             test: literal!(@ast true).into(),
             body: scope!(AstStatement::VariableDeclaration(
-                ident!(@raw a),
+                ident!(@name a),
                 literal!(@ast 1).into()
             )),
             otherwise: None
@@ -75,7 +83,7 @@ fn conditional_inline() {
         scope![AstStatement::Conditional {
             test: literal!(@ast true).into(),
             body: scope!(AstStatement::VariableDeclaration(
-                ident!(@raw a),
+                ident!(@name a),
                 literal!(@ast 1).into()
             )),
             otherwise: None
@@ -91,13 +99,821 @@ fn conditional_else() {
         scope![AstStatement::Conditional {
             test: literal!(@ast true).into(),
             body: scope!(AstStatement::VariableDeclaration(
-                ident!(@raw a),
+                ident!(@name a),
                 literal!(@ast 1).into()
             )),
             otherwise: Some(scope!(AstStatement::VariableDeclaration(
-                ident!(@raw a),
+                ident!(@name a),
                 literal!(@ast 2).into()
             )))
         }]
     )
 }
+
+#[test]
+fn spans() {
+    let res = create_scope("a = 1\nif b:\n  c = 2 + d");
+    let [assign, cond] = res.stmts.as_slice() else {
+        panic!("Expected two statements, got {res:?}")
+    };
+
+    assert_eq!((assign.span.from, assign.span.to), (0, 5));
+    assert_eq!((cond.span.from, cond.span.to), (6, 23));
+    assert_eq!((res.span.from, res.span.to), (0, 23));
+
+    let AstStatement::Conditional { test, body, .. } = &cond.node else {
+        panic!("Expected conditional, got {cond:?}")
+    };
+
+    assert_eq!((test.span.from, test.span.to), (9, 10));
+    assert_eq!((body.span.from, body.span.to), (14, 23));
+
+    let AstStatement::VariableDeclaration(name, _) = &body.stmts[0].node else {
+        panic!("Expected declaration, got {body:?}")
+    };
+
+    assert_eq!((name.span.from, name.span.to), (14, 15));
+
+    let res = create_scope("def f():\n  global a, bc");
+    let AstStatement::FunctionDef(function) = &res.stmts[0].node else {
+        panic!("Expected function, got {res:?}")
+    };
+    let AstStatement::Global(names) = &function.body.stmts[0].node else {
+        panic!("Expected global, got {res:?}")
+    };
+
+    let spans = names.iter().map(|name| (name.span.from, name.span.to));
+    assert_eq!(spans.collect::<Vec<_>>(), [(18, 19), (21, 23)]);
+}
+
+#[test]
+fn left_associative() {
+    let res = create_scope("a - b - c");
+    assert_eq!(
+        res,
+        scope![AstStatement::Expresion(
+            bin_op!(
+                bin_op!(ident!(@ast a), Sub, ident!(@ast b)),
+                Sub,
+                ident!(@ast c)
+            )
+            .into()
+        )]
+    )
+}
+
+#[test]
+fn nested_scope_dedent() {
+    let res = create_scope("if a:\n  if b:\n    c = 1\nd = 2");
+    assert_eq!(
+        res,
+        scope![
+            AstStatement::Conditional {
+                test: ident!(@ast a).into(),
+                body: scope!(AstStatement::Conditional {
+                    test: ident!(@ast b).into(),
+                    body: scope!(AstStatement::VariableDeclaration(
+                        ident!(@name c),
+                        literal!(@ast 1).into()
+                    )),
+                    otherwise: None
+                }),
+                otherwise: None
+            },
+            AstStatement::VariableDeclaration(ident!(@name d), literal!(@ast 2).into())
+        ]
+    )
+}
+
+#[test]
+fn try_stmt() {
+    let res = create_scope(
+        "try:\n  a = 1\nexcept (KeyError, ValueError) as e:\n  a = 2\nexcept:\n  raise\nelse:\n  a = 3\nfinally:\n  a = 4",
+    );
+    let assign = |n: i64| {
+        scope!(AstStatement::VariableDeclaration(
+            ident!(@name a),
+            literal!(@ast n).into()
+        ))
+    };
+
+    assert_eq!(
+        res,
+        scope![AstStatement::Try {
+            body: assign(1),
+            handlers: Vec::from([
+                AstExceptHandler {
+                    class: Some(SpannedExpr::from(AstExpr::Tuple(Vec::from([
+                        ident!(@ast KeyError),
+                        ident!(@ast ValueError)
+                    ])))),
+                    name: Some(ident!(@raw e)),
+                    body: assign(2),
+                },
+                AstExceptHandler {
+                    class: None,
+                    name: None,
+                    body: scope!(AstStatement::Raise {
+                        exception: None,
+                        cause: None
+                    }),
+                },
+            ]),
+            otherwise: Some(assign(3)),
+            finally: Some(assign(4)),
+        }]
+    )
+}
+
+#[test]
+fn raise_from() {
+    let res = create_scope("raise ValueError(\"bad\") from e");
+    assert_eq!(
+        res,
+        scope![AstStatement::Raise {
+            exception: Some(
+                SpannedExpr::from(AstExpr::Call {
+                    func: ident!(@ast ValueError).into(),
+                    args: Vec::from([literal!(@ast "bad")]),
+                    keywords: Vec::new(),
+                })
+                .into()
+            ),
+            cause: Some(ident!(@ast e).into()),
+        }]
+    )
+}
+
+#[test]
+fn invalid_statements() {
+    let error = |content: &str| {
+        let file = SourceFile::anonymous(content);
+        let tokens = Lexer::from_source(&file).unwrap();
+        AstScope::from_tokens(&file, tokens)
+            .unwrap_err()
+            .message
+            .clone()
+    };
+
+    assert_eq!(error("break"), "'break' outside loop");
+    assert_eq!(error("def f():\n  continue"), "'continue' outside loop");
+    assert_eq!(error("return 1"), "'return' outside function");
+    assert_eq!(
+        error("try:\n  a = 1\nb = 2"),
+        "expected 'except' or 'finally' block"
+    );
+    assert_eq!(
+        error("try:\n  a = 1\nexcept:\n  a = 2\nexcept E:\n  a = 3"),
+        "default 'except:' must be last"
+    );
+    assert_eq!(error("a < b < c"), "chained comparisons are not supported");
+    assert_eq!(error("f() = 1"), "cannot assign to expression");
+    assert_eq!(
+        error("nonlocal x"),
+        "nonlocal declaration not allowed at module level"
+    );
+    assert_eq!(
+        error("class A:\n  nonlocal x"),
+        "nonlocal declaration not allowed at module level"
+    );
+    assert_eq!(error("class A:\n  return 1"), "'return' outside function");
+    assert_eq!(
+        error("x = 1\ndef f():\n  nonlocal x\n  x = 2"),
+        "no binding for nonlocal 'x' found"
+    );
+    assert_eq!(
+        error("def f():\n  x = 1\n  def g():\n    global x\n    def h():\n      nonlocal x"),
+        "no binding for nonlocal 'x' found"
+    );
+    assert_eq!(
+        error("def f(x):\n  global x"),
+        "name 'x' is parameter and global"
+    );
+    assert_eq!(
+        error("def f(x):\n  nonlocal x"),
+        "name 'x' is parameter and nonlocal"
+    );
+}
+
+#[test]
+fn class_def() {
+    let res = create_scope("class B(A):\n  def f(self):\n    self.x.y = 1");
+
+    let AstStatement::ClassDef { name, bases, body } = &res.stmts[0].node else {
+        panic!("expected a class definition");
+    };
+
+    assert_eq!(name, "B");
+    assert_eq!(bases, &Vec::from([ident!(@ast A)]));
+
+    let AstStatement::FunctionDef(def) = &body.stmts[0].node else {
+        panic!("expected a method");
+    };
+
+    assert_eq!(
+        def.body.stmts[0],
+        SpannedStatement::from(AstStatement::Assignment {
+            target: SpannedExpr::from(AstExpr::Attribute {
+                value: SpannedExpr::from(AstExpr::Attribute {
+                    value: ident!(@ast self).into(),
+                    name: "x".to_owned(),
+                })
+                .into(),
+                name: "y".to_owned(),
+            })
+            .into(),
+            value: literal!(@ast 1).into(),
+        })
+    );
+}
+
+#[test]
+fn subscript() {
+    let res = create_scope("a[1] = b[c](2)");
+    assert_eq!(
+        res,
+        scope![AstStatement::Assignment {
+            target: SpannedExpr::from(AstExpr::Subscript {
+                value: ident!(@ast a).into(),
+                index: literal!(@ast 1).into(),
+            })
+            .into(),
+            value: SpannedExpr::from(AstExpr::Call {
+                func: SpannedExpr::from(AstExpr::Subscript {
+                    value: ident!(@ast b).into(),
+                    index: ident!(@ast c).into(),
+                })
+                .into(),
+                args: Vec::from([literal!(@ast 2)]),
+                keywords: Vec::new(),
+            })
+            .into(),
+        }]
+    )
+}
+
+#[test]
+fn lambda_and_keywords() {
+    let res = create_scope("f(xs, key=lambda x, y: x)");
+
+    let AstStatement::Expresion(call) = &res.stmts[0].node else {
+        panic!("expected an expression");
+    };
+    let AstExpr::Call { args, keywords, .. } = &call.node else {
+        panic!("expected a call");
+    };
+
+    assert_eq!(args, &Vec::from([ident!(@ast xs)]));
+    assert_eq!(keywords[0].name.as_deref(), Some("key"));
+
+    let AstExpr::Lambda(function) = &keywords[0].value.node else {
+        panic!("expected a lambda");
+    };
+
+    assert_eq!(function.params, [AstParam::new("x"), AstParam::new("y")]);
+    assert_eq!(
+        function.body,
+        scope![AstStatement::Return(Some(ident!(@ast x).into()))]
+    );
+
+    let error = |content: &str| {
+        let file = SourceFile::anonymous(content);
+        let tokens = Lexer::from_source(&file).unwrap();
+        AstScope::from_tokens(&file, tokens)
+            .unwrap_err()
+            .message
+            .clone()
+    };
+
+    assert_eq!(
+        error("f(a=1, 2)"),
+        "positional argument follows keyword argument"
+    );
+    assert_eq!(error("f(a=1, a=2)"), "keyword argument repeated: a");
+}
+
+#[test]
+fn parameters() {
+    let res = create_scope("def f(a, /, b=1, *args, c, d=2, **kw):\n  return a");
+
+    let AstStatement::FunctionDef(function) = &res.stmts[0].node else {
+        panic!("expected a function");
+    };
+
+    let kinds = function
+        .params
+        .iter()
+        .map(|param| (param.name.as_str(), param.kind, param.default.is_some()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        kinds,
+        [
+            ("a", AstParamKind::PositionalOnly, false),
+            ("b", AstParamKind::Normal, true),
+            ("args", AstParamKind::VarArgs, false),
+            ("c", AstParamKind::KeywordOnly, false),
+            ("d", AstParamKind::KeywordOnly, true),
+            ("kw", AstParamKind::VarKeywords, false),
+        ]
+    );
+
+    let res = create_scope("f(*xs, 1, **d, k=2)");
+
+    let AstStatement::Expresion(call) = &res.stmts[0].node else {
+        panic!("expected an expression");
+    };
+    let AstExpr::Call { args, keywords, .. } = &call.node else {
+        panic!("expected a call");
+    };
+
+    assert_eq!(
+        args,
+        &Vec::from([
+            SpannedExpr::from(AstExpr::Starred(ident!(@ast xs).into())),
+            literal!(@ast 1)
+        ])
+    );
+    assert_eq!(keywords[0].name, None);
+    assert_eq!(keywords[1].name.as_deref(), Some("k"));
+
+    let error = |content: &str| {
+        let file = SourceFile::anonymous(content);
+        let tokens = Lexer::from_source(&file).unwrap();
+        AstScope::from_tokens(&file, tokens)
+            .unwrap_err()
+            .message
+            .clone()
+    };
+
+    assert_eq!(
+        error("def f(a=1, b):\n  return a"),
+        "parameter without a default follows parameter with a default"
+    );
+    assert_eq!(error("def f(*, a, /):\n  return a"), "/ must be ahead of *");
+    assert_eq!(
+        error("def f(a, a):\n  return a"),
+        "duplicate argument 'a' in function definition"
+    );
+    assert_eq!(
+        error("def f(**kw, a):\n  return a"),
+        "arguments cannot follow var-keyword argument"
+    );
+    assert_eq!(
+        error("def f(*):\n  return 1"),
+        "named arguments must follow bare *"
+    );
+    assert_eq!(
+        error("f(**d, *xs)"),
+        "iterable argument unpacking follows keyword argument unpacking"
+    );
+}
+
+#[test]
+fn conditional_expr() {
+    let res = create_scope("x = a or b if c else d if e else f");
+
+    let conditional = |test, body, otherwise| {
+        SpannedExpr::from(AstExpr::Conditional {
+            test: Box::new(test),
+            body: Box::new(body),
+            otherwise: Box::new(otherwise),
+        })
+    };
+
+    assert_eq!(
+        res,
+        scope![AstStatement::VariableDeclaration(
+            ident!(@name x),
+            conditional(
+                ident!(@ast c),
+                SpannedExpr::from(AstExpr::Logical {
+                    op: AstLogicalOp::Or,
+                    left: ident!(@ast a).into(),
+                    right: ident!(@ast b).into(),
+                }),
+                conditional(ident!(@ast e), ident!(@ast d), ident!(@ast f)),
+            )
+            .into()
+        )]
+    );
+
+    let res = create_scope("f = lambda: a if b else c");
+
+    let AstStatement::VariableDeclaration(_, lambda) = &res.stmts[0].node else {
+        panic!("expected an assignment");
+    };
+    let AstExpr::Lambda(function) = &lambda.node else {
+        panic!("expected a lambda");
+    };
+
+    assert_eq!(
+        function.body,
+        scope![AstStatement::Return(Some(
+            conditional(ident!(@ast b), ident!(@ast a), ident!(@ast c)).into()
+        ))]
+    );
+
+    let file = SourceFile::anonymous("x = a if b");
+    let tokens = Lexer::from_source(&file).unwrap();
+    assert_eq!(
+        AstScope::from_tokens(&file, tokens).unwrap_err().message,
+        "Unexpected EOF. Expected 'else' after 'if' expression"
+    );
+}
+
+#[test]
+fn comprehensions() {
+    let comprehension = |content: &str| {
+        let res = create_scope(content);
+
+        let AstStatement::Expresion(expr) = &res.stmts[0].node else {
+            panic!("expected an expression");
+        };
+
+        match &expr.node {
+            AstExpr::Comprehension(comprehension) => (**comprehension).clone(),
+            AstExpr::Call { args, .. } => match &args[0].node {
+                AstExpr::Comprehension(comprehension) => (**comprehension).clone(),
+                node => panic!("expected a comprehension, got {node:?}"),
+            },
+            node => panic!("expected a comprehension, got {node:?}"),
+        }
+    };
+
+    let res = comprehension("[x for x in xs if x if y for z in x]");
+    assert_eq!(res.kind, AstComprehensionKind::List);
+    assert_eq!(
+        res.clauses,
+        [
+            AstForClause {
+                target: ident!(@ast x),
+                iter: ident!(@ast xs),
+                conditions: Vec::from([ident!(@ast x), ident!(@ast y)]),
+            },
+            AstForClause {
+                target: ident!(@ast z),
+                iter: ident!(@ast x),
+                conditions: Vec::new(),
+            },
+        ]
+    );
+
+    let res = comprehension("{k: v for k, v in items}");
+    assert_eq!(res.kind, AstComprehensionKind::Dict);
+    assert_eq!(res.value, Some(ident!(@ast v)));
+    assert_eq!(
+        res.clauses[0].target,
+        SpannedExpr::from(AstExpr::Tuple(Vec::from([ident!(@ast k), ident!(@ast v)])))
+    );
+
+    assert_eq!(
+        comprehension("{x for x in xs}").kind,
+        AstComprehensionKind::Set
+    );
+    assert_eq!(
+        comprehension("(x for x in xs)").kind,
+        AstComprehensionKind::Generator
+    );
+    assert_eq!(
+        comprehension("f(x for x in xs)").kind,
+        AstComprehensionKind::Generator
+    );
+
+    let res = create_scope("s = {a, b}");
+    assert_eq!(
+        res,
+        scope![AstStatement::VariableDeclaration(
+            ident!(@name s),
+            SpannedExpr::from(AstExpr::Set(Vec::from([ident!(@ast a), ident!(@ast b)]))).into()
+        )]
+    );
+
+    let error = |content: &str| {
+        let file = SourceFile::anonymous(content);
+        let tokens = Lexer::from_source(&file).unwrap();
+        AstScope::from_tokens(&file, tokens)
+            .unwrap_err()
+            .message
+            .clone()
+    };
+
+    assert_eq!(
+        error("f(x for x in xs, 1)"),
+        "Generator expression must be parenthesized"
+    );
+    assert_eq!(
+        error("f(1, x for x in xs)"),
+        "Generator expression must be parenthesized"
+    );
+    assert_eq!(error("[x for f(x) in xs]"), "cannot assign to expression");
+}
+
+#[test]
+fn yield_expr() {
+    let res = create_scope("def f():\n  yield\n  x = yield a\n  yield from g()\n  return (yield)");
+
+    let AstStatement::FunctionDef(function) = &res.stmts[0].node else {
+        panic!("expected a function");
+    };
+
+    assert!(function.is_generator);
+    assert_eq!(
+        function.body.stmts[0].node,
+        AstStatement::Expresion(SpannedExpr::from(AstExpr::Yield(None)).into())
+    );
+    assert_eq!(
+        function.body.stmts[1].node,
+        AstStatement::VariableDeclaration(
+            ident!(@name x),
+            SpannedExpr::from(AstExpr::Yield(Some(ident!(@ast a).into()))).into()
+        )
+    );
+    assert!(matches!(
+        &function.body.stmts[2].node,
+        AstStatement::Expresion(expr) if matches!(expr.node, AstExpr::YieldFrom(_))
+    ));
+    assert_eq!(
+        function.body.stmts[3].node,
+        AstStatement::Return(Some(SpannedExpr::from(AstExpr::Yield(None)).into()))
+    );
+
+    let res = create_scope("def f():\n  def g():\n    yield\n  return g");
+    let AstStatement::FunctionDef(function) = &res.stmts[0].node else {
+        panic!("expected a function");
+    };
+    assert!(!function.is_generator);
+
+    let file = SourceFile::anonymous("yield 1");
+    let tokens = Lexer::from_source(&file).unwrap();
+    assert_eq!(
+        AstScope::from_tokens(&file, tokens).unwrap_err().message,
+        "'yield' outside function"
+    );
+}
+
+#[test]
+fn match_stmt() {
+    let res = create_scope(
+        "match p:\n  case [1, *rest] | (rest, 2):\n    a\n  case Point(x, y=0) as q if x:\n    b\n  case {\"k\": _, **kw}:\n    c\n  case _:\n    d",
+    );
+
+    let AstStatement::Match { subject, cases } = &res.stmts[0].node else {
+        panic!("expected a match");
+    };
+
+    let pattern = |pattern| SpannedPattern::from(pattern);
+    let capture = |name: &str| pattern(AstPattern::Capture(name.to_owned()));
+
+    assert_eq!(**subject, ident!(@ast p));
+    assert_eq!(cases.len(), 4);
+    assert_eq!(
+        cases[0].pattern,
+        pattern(AstPattern::Or(Vec::from([
+            pattern(AstPattern::Sequence(Vec::from([
+                pattern(AstPattern::Value(literal!(@ast 1))),
+                pattern(AstPattern::Star(Some("rest".to_owned()))),
+            ]))),
+            pattern(AstPattern::Sequence(Vec::from([
+                capture("rest"),
+                pattern(AstPattern::Value(literal!(@ast 2))),
+            ]))),
+        ])))
+    );
+    assert_eq!(
+        cases[1].pattern,
+        pattern(AstPattern::As {
+            pattern: pattern(AstPattern::Class {
+                class: ident!(@ast Point),
+                args: Vec::from([capture("x")]),
+                keywords: Vec::from([(
+                    "y".to_owned(),
+                    pattern(AstPattern::Value(literal!(@ast 0)))
+                )]),
+            })
+            .into(),
+            name: "q".to_owned(),
+        })
+    );
+    assert_eq!(cases[1].guard, Some(ident!(@ast x)));
+    assert_eq!(
+        cases[2].pattern,
+        pattern(AstPattern::Mapping {
+            entries: Vec::from([(literal!(@ast "k"), pattern(AstPattern::Wildcard))]),
+            rest: Some("kw".to_owned()),
+        })
+    );
+    assert_eq!(cases[3].pattern, pattern(AstPattern::Wildcard));
+
+    let res = create_scope("match = 1\nmatch(x)\ncase = match");
+    assert!(matches!(
+        res.stmts[..],
+        [
+            SpannedStatement {
+                node: AstStatement::VariableDeclaration(..),
+                ..
+            },
+            SpannedStatement {
+                node: AstStatement::Expresion(_),
+                ..
+            },
+            SpannedStatement {
+                node: AstStatement::VariableDeclaration(..),
+                ..
+            },
+        ]
+    ));
+
+    let error = |content: &str| {
+        let file = SourceFile::anonymous(content);
+        let tokens = Lexer::from_source(&file).unwrap();
+        AstScope::from_tokens(&file, tokens)
+            .unwrap_err()
+            .message
+            .clone()
+    };
+
+    assert_eq!(
+        error("match x:\n  case y:\n    a\n  case 1:\n    b"),
+        "name capture 'y' makes remaining patterns unreachable"
+    );
+    assert_eq!(
+        error("match x:\n  case [a] | b:\n    a"),
+        "alternative patterns bind different names"
+    );
+    assert_eq!(
+        error("match x:\n  case [*a, *b]:\n    a"),
+        "multiple starred names in sequence pattern"
+    );
+    assert_eq!(error("match x:\n  y = 1"), "expected 'case' block");
+}
+
+#[test]
+fn match_exhaustiveness() {
+    let warnings = |content: &str| {
+        let file = SourceFile::anonymous(content);
+        let tokens = Lexer::from_source(&file).unwrap();
+        let (_, warnings) = AstScope::from_tokens_with_warnings(&file, tokens).unwrap();
+        warnings
+            .iter()
+            .map(|warning| warning.labels[0].message.clone().unwrap_or_default())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        warnings("match x:\n  case True:\n    a"),
+        ["False not covered"]
+    );
+    assert_eq!(
+        warnings("match x:\n  case 1 | 2:\n    a\n  case 3:\n    b"),
+        ["values other than the listed literals are not covered"]
+    );
+    assert!(warnings("match x:\n  case True:\n    a\n  case False:\n    b").is_empty());
+    assert!(warnings("match x:\n  case 1:\n    a\n  case _:\n    b").is_empty());
+    assert!(warnings("match x:\n  case 1:\n    a\n  case [y]:\n    b").is_empty());
+}
+
+#[test]
+fn simple_statements() {
+    let res = create_scope("a = 1; pass; del a, b[0], c.d\nassert a, \"msg\"");
+
+    assert_eq!(
+        res,
+        scope![
+            AstStatement::VariableDeclaration(ident!(@name a), literal!(@ast 1).into()),
+            AstStatement::Pass,
+            AstStatement::Delete(Vec::from([
+                ident!(@ast a),
+                SpannedExpr::from(AstExpr::Subscript {
+                    value: ident!(@ast b).into(),
+                    index: literal!(@ast 0).into(),
+                }),
+                SpannedExpr::from(AstExpr::Attribute {
+                    value: ident!(@ast c).into(),
+                    name: "d".to_owned(),
+                }),
+            ])),
+            AstStatement::Assert {
+                test: ident!(@ast a).into(),
+                message: Some(literal!(@ast "msg").into()),
+                source: "a".to_owned(),
+            }
+        ]
+    );
+
+    let res = create_scope("assert x  ==  (1 + 2)");
+    let AstStatement::Assert { source, .. } = &res.stmts[0].node else {
+        panic!("expected an assert");
+    };
+    assert_eq!(source, "x  ==  (1 + 2)");
+
+    let res = create_scope("def f():\n  yield; raise; return; x = 1");
+    let AstStatement::FunctionDef(def) = &res.stmts[0].node else {
+        panic!("expected a function");
+    };
+    let body = def
+        .body
+        .stmts
+        .iter()
+        .map(|stmt| &stmt.node)
+        .collect::<Vec<_>>();
+    assert!(matches!(
+        body[..],
+        [
+            AstStatement::Expresion(yield_expr),
+            AstStatement::Raise {
+                exception: None,
+                cause: None
+            },
+            AstStatement::Return(None),
+            AstStatement::VariableDeclaration(..),
+        ] if yield_expr.node == AstExpr::Yield(None)
+    ));
+
+    let error = |content: &str| {
+        let file = SourceFile::anonymous(content);
+        let tokens = Lexer::from_source(&file).unwrap();
+        AstScope::from_tokens(&file, tokens)
+            .unwrap_err()
+            .message
+            .clone()
+    };
+
+    assert_eq!(error("del f()"), "cannot delete expression");
+    assert_eq!(error("a = 1;; b = 2"), "Unexpected token: Semicolon.");
+}
+
+#[test]
+fn imports() {
+    let alias = |name: &str, alias: Option<&str>| AstAlias {
+        name: name.to_owned(),
+        alias: alias.map(str::to_owned),
+    };
+
+    let res = create_scope(
+        "import a.b, c as d\nfrom ..e.f import (g as h, i,)\nfrom . import j\nfrom k import l",
+    );
+
+    assert_eq!(
+        res,
+        scope![
+            AstStatement::Import(Vec::from([alias("a.b", None), alias("c", Some("d"))])),
+            AstStatement::ImportFrom {
+                module: Some("e.f".to_owned()),
+                level: 2,
+                names: Vec::from([alias("g", Some("h")), alias("i", None)]),
+            },
+            AstStatement::ImportFrom {
+                module: None,
+                level: 1,
+                names: Vec::from([alias("j", None)]),
+            },
+            AstStatement::ImportFrom {
+                module: Some("k".to_owned()),
+                level: 0,
+                names: Vec::from([alias("l", None)]),
+            }
+        ]
+    );
+
+    let file = SourceFile::anonymous("from a b");
+    let tokens = Lexer::from_source(&file).unwrap();
+    assert!(AstScope::from_tokens(&file, tokens).is_err());
+}
+
+#[test]
+fn nesting_limit() {
+    let parse = |content: String| {
+        let file = SourceFile::anonymous(content);
+        let tokens = Lexer::from_source(&file).unwrap();
+        AstScope::from_tokens(&file, tokens).map_err(|err| err.code)
+    };
+    let nested = |depth: usize| {
+        [
+            format!("a = {}1{}", "(".repeat(depth), ")".repeat(depth)),
+            format!("a = {}1{}", "[".repeat(depth), "]".repeat(depth)),
+            format!("a = {}1", "-".repeat(depth)),
+            format!("a = 1{}", " + 1".repeat(depth)),
+            format!("a = f{}", "(1)".repeat(depth)),
+            (0..depth)
+                .map(|level| format!("{}if a:\n", "  ".repeat(level)))
+                .collect::<String>()
+                + &"  ".repeat(depth)
+                + "pass",
+        ]
+    };
+
+    for content in nested(MAX_NESTING / 2 - 1) {
+        assert!(parse(content).is_ok());
+    }
+
+    let deeper = [
+        format!("{}1{}", "(".repeat(500), ")".repeat(500)),
+        format!("{}1", "-".repeat(20_000)),
+        format!("1{}", "+1".repeat(20_000)),
+    ];
+
+    for content in nested(MAX_NESTING).into_iter().chain(deeper) {
+        assert_eq!(parse(content), Err(codes::TOO_DEEPLY_NESTED));
+    }
+}
@@ -1,28 +1,31 @@
 #[macro_export]
 macro_rules! scope {
     ($($expr:expr),*) => {
-        $crate::ast::node::AstScope(Vec::from([ $($expr),* ]))
+        $crate::ast::node::AstScope::new(
+            $crate::lexer::span::Span::ZERO,
+            Vec::from([ $($expr.into()),* ]),
+        )
     };
 }
 
 #[macro_export]
 macro_rules! bin_op {
     ($a:expr, $op:ident, $b:expr) => {
-        $crate::ast::node::AstExpr::BinaryOp {
+        $crate::ast::node::SpannedExpr::from($crate::ast::node::AstExpr::BinaryOp {
             op: $crate::ast::node::AstBinaryOp::$op,
             left: $a.into(),
             right: $b.into(),
-        }
+        })
     };
 }
 
 #[macro_export]
 macro_rules! unary_op {
     ($op:ident, $b:expr) => {
-        $crate::ast::node::AstExpr::UnaryOp {
+        $crate::ast::node::SpannedExpr::from($crate::ast::node::AstExpr::UnaryOp {
             op: $crate::ast::node::AstUnaryOp::$op,
-            right: $a.into(),
-        }
+            right: $b.into(),
+        })
     };
 }
 
@@ -1,6 +1,92 @@
 use core::fmt;
-use std::env;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::{env, io, process};
+
+use crate::diagnostic::{self, Diagnostic, DiagnosticFormat};
+use crate::runtime::error::RuntimeError;
+use crate::source_map::SourceLookup;
+
+/// Environment variable to pick the [`DiagnosticFormat`] used by the binaries
+pub const DIAGNOSTIC_FORMAT_ENV: &str = "RUSPY_DIAGNOSTIC_FORMAT";
+
+/// Environment variable with extra directories to import modules from,
+/// separated like `PATH`
+pub const SEARCH_PATH_ENV: &str = "RUSPY_PATH";
+
+/// Arguments of the interpreter: `[--path DIRS]... FILE`
+pub struct RunArgs {
+    pub file: PathBuf,
+    /// The directory of `file`, then the `--path` directories, then [`SEARCH_PATH_ENV`]
+    pub search_path: Vec<PathBuf>,
+}
+
+impl RunArgs {
+    pub fn from_env() -> Self {
+        let mut args = env::args().skip(1);
+        let mut file = None;
+        let mut extra = Vec::new();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--path" => {
+                    let dirs = args.next().expect("--path requires a value");
+                    extra.extend(env::split_paths(&dirs));
+                }
+                _ => file = Some(PathBuf::from(arg)),
+            }
+        }
+
+        let file = file.expect("Requires at least one argument");
+
+        let dir = match file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_owned(),
+            _ => PathBuf::from("."),
+        };
+
+        let search_path = std::iter::once(dir)
+            .chain(extra)
+            .chain(
+                env::var_os(SEARCH_PATH_ENV)
+                    .iter()
+                    .flat_map(env::split_paths),
+            )
+            .collect();
+
+        Self { file, search_path }
+    }
+}
+
+/// Arguments of the config binary: `[--format json|toml|yaml] FILE`
+#[cfg(feature = "config")]
+pub struct ConfigArgs {
+    pub file: PathBuf,
+    pub format: crate::config::ConfigFormat,
+}
+
+#[cfg(feature = "config")]
+impl ConfigArgs {
+    pub fn from_env() -> Self {
+        let mut args = env::args().skip(1);
+        let mut file = None;
+        let mut format = Default::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--format" => {
+                    let value = args.next().expect("--format requires a value");
+                    format = value.parse().unwrap_or_else(|err| panic!("{err}"));
+                }
+                _ => file = Some(PathBuf::from(arg)),
+            }
+        }
+
+        Self {
+            file: file.expect("Requires at least one argument"),
+            format,
+        }
+    }
+}
 
 pub fn required_first_arg<T>() -> T
 where
@@ -10,3 +96,43 @@ where
     T::from_str(&env::args().nth(1).expect("Requires at least one argument"))
         .expect("Cannot parse argument")
 }
+
+/// Format requested through [`DIAGNOSTIC_FORMAT_ENV`]
+pub fn diagnostic_format() -> DiagnosticFormat {
+    env::var(DIAGNOSTIC_FORMAT_ENV)
+        .map(|format| DiagnosticFormat::from_str(&format).unwrap_or_else(|err| panic!("{err}")))
+        .unwrap_or_default()
+}
+
+/// Prints `diagnostic` to stderr and exits with an error code
+pub fn exit_with(diagnostic: impl Into<Diagnostic>, sources: &dyn SourceLookup) -> ! {
+    _ = diagnostic::render(
+        &[diagnostic.into()],
+        diagnostic_format(),
+        sources,
+        &mut io::stderr(),
+    );
+
+    process::exit(1)
+}
+
+/// Prints `warnings` to stderr, if any
+pub fn report_warnings(warnings: &[Diagnostic], sources: &dyn SourceLookup) {
+    if !warnings.is_empty() {
+        _ = diagnostic::render(warnings, diagnostic_format(), sources, &mut io::stderr());
+    }
+}
+
+/// Prints an uncaught exception to stderr and exits with an error code.
+///
+/// The terminal format shows a Python-like traceback, the others
+/// render it as a regular diagnostic.
+pub fn exit_with_exception(error: RuntimeError, sources: &dyn SourceLookup) -> ! {
+    if diagnostic_format() != DiagnosticFormat::Terminal {
+        exit_with(error, sources);
+    }
+
+    eprintln!("{}", error.traceback(sources));
+
+    process::exit(1)
+}
@@ -1,24 +1,30 @@
-use std::fs;
 use std::path::PathBuf;
 
 use ruspy::ast::node::AstScope;
-use ruspy::bin::required_first_arg;
+use ruspy::bin::{exit_with, report_warnings, required_first_arg};
 use ruspy::lexer::Lexer;
+use ruspy::source_map::SourceMap;
 
 fn main() {
     let file_path: PathBuf = required_first_arg();
-    let file_content = fs::read_to_string(&file_path)
+    let mut sources = SourceMap::new();
+    let file = sources
+        .load(&file_path)
         .unwrap_or_else(|err| panic!("Cannot read {file_path:?}: {err}"));
+    let file = &sources[file];
 
     println!(" -- LEXER --");
 
-    let tokens = Lexer::from_str(&file_content)
+    let tokens = Lexer::from_source(file)
         .inspect(Lexer::pretty_print)
-        .unwrap_or_else(|err| panic!("{err:#?}"));
+        .unwrap_or_else(|err| exit_with(err, &sources));
 
     println!(" -- AST --");
 
-    let tree = AstScope::from_tokens(&file_content, tokens);
+    let (tree, warnings) = AstScope::from_tokens_with_warnings(file, tokens)
+        .unwrap_or_else(|err| exit_with(err, &sources));
+
+    report_warnings(&warnings, &sources);
 
     println!("{tree:#?}");
 
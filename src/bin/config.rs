@@ -0,0 +1,21 @@
+use std::fs;
+
+use ruspy::bin::{ConfigArgs, exit_with};
+use ruspy::config::Config;
+
+fn main() {
+    let ConfigArgs { file, format } = ConfigArgs::from_env();
+    let source =
+        fs::read_to_string(&file).unwrap_or_else(|err| panic!("Cannot read {file:?}: {err}"));
+
+    let mut config = Config::new();
+
+    if let Err(err) = config.run(&file, &source) {
+        exit_with(err, &*config.sources());
+    }
+
+    match config.dump(format) {
+        Ok(output) => print!("{output}"),
+        Err(err) => exit_with(err, &*config.sources()),
+    }
+}
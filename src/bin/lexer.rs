@@ -1,15 +1,18 @@
-use std::fs;
 use std::path::PathBuf;
 
-use ruspy::bin::required_first_arg;
+use ruspy::bin::{exit_with, required_first_arg};
 use ruspy::lexer::Lexer;
+use ruspy::source_map::SourceMap;
 
 fn main() {
     let file_path: PathBuf = required_first_arg();
-    let file_content = fs::read_to_string(&file_path)
+    let mut sources = SourceMap::new();
+    let file = sources
+        .load(&file_path)
         .unwrap_or_else(|err| panic!("Cannot read {file_path:?}: {err}"));
+    let file = &sources[file];
 
-    _ = Lexer::from_str(&file_content)
+    _ = Lexer::from_source(file)
         .inspect(Lexer::pretty_print)
-        .inspect_err(|err| eprintln!("{err:#?}"));
+        .unwrap_or_else(|err| exit_with(err, &sources));
 }
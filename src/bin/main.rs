@@ -1,34 +1,50 @@
-use std::fs;
-use std::path::PathBuf;
+use std::rc::Rc;
 
 use ruspy::ast::node::AstScope;
-use ruspy::bin::required_first_arg;
+use ruspy::bin::{RunArgs, exit_with, exit_with_exception, report_warnings};
 use ruspy::lexer::Lexer;
 use ruspy::runtime::Scope;
+use ruspy::runtime::loader::FileLoader;
+use ruspy::runtime::module::{Importer, MAIN_MODULE};
 
 fn main() {
-    let file_path: PathBuf = required_first_arg();
-    let file_content = fs::read_to_string(&file_path)
-        .unwrap_or_else(|err| panic!("Cannot read {file_path:?}: {err}"));
+    let RunArgs { file, search_path } = RunArgs::from_env();
+    let importer = Rc::new(Importer::new(FileLoader::new(search_path)));
+    let id = importer
+        .sources_mut()
+        .load(&file)
+        .unwrap_or_else(|err| panic!("Cannot read {file:?}: {err}"));
 
-    println!(" -- LEXER --");
+    let tree = {
+        let sources = importer.sources();
+        let file = &sources[id];
 
-    let tokens = Lexer::from_str(&file_content)
-        .inspect(Lexer::pretty_print)
-        .unwrap_or_else(|err| panic!("{err:#?}"));
+        println!(" -- LEXER --");
 
-    println!(" -- AST --");
+        let tokens = Lexer::from_source(file)
+            .inspect(Lexer::pretty_print)
+            .unwrap_or_else(|err| exit_with(err, &*sources));
 
-    let tree = AstScope::from_tokens(&file_content, tokens);
+        println!(" -- AST --");
+
+        let (tree, warnings) = AstScope::from_tokens_with_warnings(file, tokens)
+            .unwrap_or_else(|err| exit_with(err, &*sources));
+
+        report_warnings(&warnings, &*sources);
+
+        tree
+    };
 
     println!("{tree:#?}");
 
     println!(" -- AST (pretty) --");
     println!("{tree}");
 
-    let scope = Scope::new();
+    let scope = Scope::module(&importer, MAIN_MODULE, None);
 
-    scope.run(tree);
+    scope
+        .run(&tree)
+        .unwrap_or_else(|err| exit_with_exception(err, &*importer.sources()));
 
     println!("{scope:#?}");
 }
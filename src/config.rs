@@ -0,0 +1,368 @@
+//! Configuration files written as scripts: their top-level variables are the configuration
+
+#[cfg(test)]
+mod tests;
+
+use core::fmt;
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::ser::{Serialize, Serializer};
+
+use crate::ast::node::{AstScope, AstStatement};
+use crate::diagnostic::{Diagnostic, codes};
+use crate::lexer::Lexer;
+use crate::lexer::span::Span;
+use crate::runtime::Scope;
+use crate::runtime::convert::ConversionError;
+use crate::runtime::dict::RuntimeDict;
+use crate::runtime::error::ExceptionKind;
+use crate::runtime::limits::Limits;
+use crate::runtime::module::{Importer, MAIN_MODULE};
+use crate::runtime::serde::from_value;
+use crate::runtime::value::RuntimeValue;
+use crate::source_map::SourceMap;
+
+/// Limits of every [`Config`] until [`Config::with_limits`] replaces them,
+/// roomy for configuration but not enough to hang or exhaust the host
+pub const DEFAULT_LIMITS: Limits = Limits {
+    max_steps: Some(1_000_000),
+    max_call_depth: Some(100),
+    max_allocated_bytes: Some(64 << 20),
+    timeout: None,
+    interrupt: None,
+};
+
+/// Checks the variables of a [`Config`] once its script ran
+pub type Schema = dyn Fn(&Config) -> Result<(), SchemaError>;
+
+/// Runs configuration scripts without I/O: there are no host functions and imports find nothing
+pub struct Config {
+    importer: Rc<Importer>,
+    schemas: Vec<Box<Schema>>,
+    entries: Vec<ConfigEntry>,
+}
+
+/// Exported variable of a [`Config`]
+#[derive(Debug)]
+pub struct ConfigEntry {
+    pub name: String,
+    pub value: RuntimeValue,
+    /// Last top-level assignment to the variable, if it has one
+    pub span: Option<Span>,
+}
+
+/// Output formats of [`Config::dump`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+}
+
+/// Why a [`Schema`] rejected a configuration
+#[derive(Clone, Debug)]
+pub struct SchemaError {
+    /// Variable holding the bad value, `None` if the problem isn't about a single variable
+    pub name: Option<String>,
+    pub error: ConversionError,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        let importer = Rc::<Importer>::default();
+        importer.budget().set_limits(DEFAULT_LIMITS);
+
+        Self {
+            importer,
+            schemas: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds a check run by [`Config::run`], failures point at the variable they name
+    pub fn with_schema(
+        mut self,
+        schema: impl Fn(&Config) -> Result<(), SchemaError> + 'static,
+    ) -> Self {
+        self.schemas.push(Box::new(schema));
+        self
+    }
+
+    /// Caps the resources used by each run, instead of the [`DEFAULT_LIMITS`]
+    pub fn with_limits(self, limits: Limits) -> Self {
+        self.importer.budget().set_limits(limits);
+        self
+    }
+
+    /// Runs the script `source`, shown as `path` in errors, then validates its variables
+    pub fn run(&mut self, path: impl Into<PathBuf>, source: &str) -> Result<(), Diagnostic> {
+        let id = self.importer.sources_mut().add(path, source);
+        let tree = {
+            let sources = self.importer.sources();
+            let file = &sources[id];
+            AstScope::from_tokens(file, Lexer::from_source(file)?)?
+        };
+
+        let scope = Scope::module(&self.importer, MAIN_MODULE, None);
+        self.importer.budget().reset();
+        scope.run(&tree)?;
+
+        let mut spans = HashMap::new();
+        definitions(&tree, &mut spans);
+
+        self.entries = scope
+            .local_variables()
+            .into_iter()
+            .filter(|(name, value)| is_exported(name, value))
+            .map(|(name, value)| ConfigEntry {
+                span: spans.get(&name).map(|(_, last)| *last),
+                name,
+                value,
+            })
+            .collect();
+
+        // In the order the script defines them, the others after
+        self.entries.sort_by_key(|entry| {
+            spans
+                .get(&entry.name)
+                .map_or(usize::MAX, |(first, _)| *first)
+        });
+
+        for schema in &self.schemas {
+            schema(self).map_err(|err| self.diagnostic(&err))?;
+        }
+
+        Ok(())
+    }
+
+    /// Exported variables: no `_private` names, functions, classes or modules
+    pub fn entries(&self) -> &[ConfigEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RuntimeValue> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| &entry.value)
+    }
+
+    /// Deserializes the variables as a map into `T`, errors name the variable
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, SchemaError> {
+        let variables = self
+            .entries
+            .iter()
+            .map(|entry| (entry.name.clone(), entry.value.clone()))
+            .collect::<RuntimeDict>();
+
+        from_value(&RuntimeValue::Dict(Rc::new(RefCell::new(variables)))).map_err(|mut error| {
+            let name = error.path.first().and_then(|first| {
+                self.entries
+                    .iter()
+                    .find(|entry| *first == format!("[{}]", key_repr(&entry.name)))
+            });
+
+            match name {
+                Some(entry) => {
+                    error.path.remove(0);
+                    SchemaError {
+                        name: Some(entry.name.clone()),
+                        error,
+                    }
+                }
+                None => SchemaError { name: None, error },
+            }
+        })
+    }
+
+    /// The variables written as `format`
+    pub fn dump(&self, format: ConfigFormat) -> Result<String, Diagnostic> {
+        format.write(&self.entries).map_err(|message| {
+            // Finds the variable that can't be written to point at it
+            let entry = self
+                .entries
+                .iter()
+                .find(|entry| format.write(std::slice::from_ref(entry)).is_err());
+
+            let error = SchemaError {
+                name: entry.map(|entry| entry.name.clone()),
+                error: ConversionError {
+                    kind: ExceptionKind::ValueError,
+                    path: Vec::new(),
+                    message: format!("can't be written as {format}: {message}"),
+                },
+            };
+
+            self.diagnostic(&error)
+        })
+    }
+
+    /// Scripts run so far, to render errors
+    pub fn sources(&self) -> Ref<'_, SourceMap> {
+        self.importer.sources()
+    }
+
+    fn diagnostic(&self, error: &SchemaError) -> Diagnostic {
+        let diagnostic = Diagnostic::error(codes::INVALID_CONFIG, error);
+        let span = error.name.as_ref().and_then(|name| {
+            self.entries
+                .iter()
+                .find(|entry| entry.name == *name)
+                .and_then(|entry| entry.span)
+        });
+
+        match span {
+            Some(span) => diagnostic.with_label(span, "defined here"),
+            None => diagnostic,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SchemaError {
+    /// The value of variable `name` is wrong, `message` reads like `must be positive`
+    pub fn new(name: &str, message: impl fmt::Display) -> Self {
+        Self {
+            name: Some(name.to_owned()),
+            error: ConversionError {
+                kind: ExceptionKind::ValueError,
+                path: Vec::new(),
+                message: message.to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            &self
+                .error
+                .describe(self.name.as_deref().unwrap_or("config")),
+        )
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl ConfigFormat {
+    fn write(self, entries: &[ConfigEntry]) -> Result<String, String> {
+        let entries = Entries(entries);
+
+        match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(&entries)
+                .map(|json| json + "\n")
+                .map_err(|err| err.to_string()),
+            ConfigFormat::Toml => toml::to_string(&entries).map_err(|err| err.to_string()),
+            ConfigFormat::Yaml => serde_yaml::to_string(&entries).map_err(|err| err.to_string()),
+        }
+    }
+}
+
+impl FromStr for ConfigFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ConfigFormat::Json),
+            "toml" => Ok(ConfigFormat::Toml),
+            "yaml" => Ok(ConfigFormat::Yaml),
+            _ => Err(format!(
+                "Unknown config format '{s}', expected json, toml or yaml"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ConfigFormat::Json => "JSON",
+            ConfigFormat::Toml => "TOML",
+            ConfigFormat::Yaml => "YAML",
+        })
+    }
+}
+
+/// Serializes entries as a map keeping their order
+struct Entries<'a>(&'a [ConfigEntry]);
+
+impl Serialize for Entries<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|entry| (&entry.name, &entry.value)))
+    }
+}
+
+fn is_exported(name: &str, value: &RuntimeValue) -> bool {
+    !name.starts_with('_')
+        && !matches!(
+            value,
+            RuntimeValue::Function(_)
+                | RuntimeValue::Builtin(_)
+                | RuntimeValue::NativeFunction(_)
+                | RuntimeValue::Method(_)
+                | RuntimeValue::Class(_)
+                | RuntimeValue::Module(_)
+        )
+}
+
+fn key_repr(name: &str) -> String {
+    RuntimeValue::String(name.to_owned()).repr()
+}
+
+/// Offset of the first assignment to each top-level variable, and span of the last one
+fn definitions(scope: &AstScope, spans: &mut HashMap<String, (usize, Span)>) {
+    fn define(spans: &mut HashMap<String, (usize, Span)>, name: &str, span: Span) {
+        spans
+            .entry(name.to_owned())
+            .and_modify(|(_, last)| *last = span)
+            .or_insert((span.from, span));
+    }
+
+    for stmt in &scope.stmts {
+        match &stmt.node {
+            AstStatement::VariableDeclaration(name, _) => define(spans, name, stmt.span),
+            AstStatement::Conditional {
+                body, otherwise, ..
+            } => {
+                definitions(body, spans);
+                otherwise
+                    .iter()
+                    .for_each(|otherwise| definitions(otherwise, spans));
+            }
+            AstStatement::While { body, .. } => definitions(body, spans),
+            AstStatement::Try {
+                body,
+                handlers,
+                otherwise,
+                finally,
+            } => {
+                definitions(body, spans);
+                handlers
+                    .iter()
+                    .for_each(|handler| definitions(&handler.body, spans));
+                otherwise
+                    .iter()
+                    .for_each(|otherwise| definitions(otherwise, spans));
+                finally
+                    .iter()
+                    .for_each(|finally| definitions(finally, spans));
+            }
+            AstStatement::Match { cases, .. } => {
+                cases.iter().for_each(|case| definitions(&case.body, spans));
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use serde::Deserialize;
+
+use crate::config::{Config, ConfigFormat, SchemaError};
+use crate::diagnostic::{Diagnostic, DiagnosticFormat, codes, render};
+use crate::runtime::limits::Limits;
+use crate::runtime::value::RuntimeValue;
+
+const SOURCE: &str = "\
+def _port(base):
+  return base + 80
+name = \"api\"
+port = _port(8000)
+hosts = [\"a\", \"b\"]
+limits = {\"cpu\": 2, \"mem\": 512}
+_secret = 1
+class Unused:
+  pass
+if port > 0:
+  name = name + \"-1\"
+";
+
+fn render_plain(diagnostic: Diagnostic, config: &Config) -> String {
+    let mut out = Vec::new();
+    render(
+        &[diagnostic],
+        DiagnosticFormat::Plain,
+        &*config.sources(),
+        &mut out,
+    )
+    .unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn dump() {
+    let mut config = Config::new();
+    config.run("config.pi", SOURCE).unwrap();
+
+    let names = config
+        .entries()
+        .iter()
+        .map(|entry| entry.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["name", "port", "hosts", "limits"]);
+    assert!(matches!(
+        config.get("port"),
+        Some(RuntimeValue::Number(8080))
+    ));
+
+    assert_eq!(
+        config.dump(ConfigFormat::Json).unwrap(),
+        "{\n  \"name\": \"api-1\",\n  \"port\": 8080,\n  \"hosts\": [\n    \"a\",\n    \"b\"\n  ],\n  \
+         \"limits\": {\n    \"cpu\": 2,\n    \"mem\": 512\n  }\n}\n"
+    );
+    assert_eq!(
+        config.dump(ConfigFormat::Toml).unwrap(),
+        "name = \"api-1\"\nport = 8080\nhosts = [\"a\", \"b\"]\n\n[limits]\ncpu = 2\nmem = 512\n"
+    );
+    assert_eq!(
+        config.dump(ConfigFormat::Yaml).unwrap(),
+        "name: api-1\nport: 8080\nhosts:\n- a\n- b\nlimits:\n  cpu: 2\n  mem: 512\n"
+    );
+    assert_eq!("yaml".parse(), Ok(ConfigFormat::Yaml));
+    assert!("xml".parse::<ConfigFormat>().is_err());
+}
+
+#[test]
+fn restricted() {
+    let mut config = Config::new();
+    let err = config.run("config.pi", "import os").unwrap_err();
+
+    assert_eq!(err.code, codes::UNCAUGHT_EXCEPTION);
+    assert_eq!(err.message, "ModuleNotFoundError: No module named 'os'");
+}
+
+#[test]
+fn limits() {
+    let mut config = Config::new();
+    let err = config.run("config.pi", "while True:\n  pass").unwrap_err();
+    assert_eq!(err.code, codes::LIMIT_EXCEEDED);
+
+    let source = "i = 0\nwhile i < 100:\n  i = i + 1";
+    Config::new().run("config.pi", source).unwrap();
+
+    let mut config = Config::new().with_limits(Limits {
+        max_steps: Some(100),
+        ..Limits::default()
+    });
+    let err = config.run("config.pi", source).unwrap_err();
+    assert_eq!(err.code, codes::LIMIT_EXCEEDED);
+    assert_eq!(
+        err.message,
+        "RuntimeError: script exceeded the limit of 100 steps"
+    );
+}
+
+#[test]
+fn schemas() {
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Service {
+        name: String,
+        port: u16,
+    }
+
+    let mut config = Config::new().with_schema(|config| match config.get("port") {
+        Some(RuntimeValue::Number(port)) if *port >= 1024 => Ok(()),
+        _ => Err(SchemaError::new("port", "must be at least 1024")),
+    });
+    let err = config
+        .run("config.pi", "name = \"api\"\nport = 80\n")
+        .unwrap_err();
+
+    assert_eq!(err.code, codes::INVALID_CONFIG);
+    assert_eq!(
+        render_plain(err, &config),
+        "error[E0301]: port must be at least 1024\n  --> config.pi:2:1: defined here\n"
+    );
+
+    let mut config = Config::new().with_schema(|config| config.deserialize::<Service>().map(drop));
+    let err = config
+        .run("config.pi", "name = \"api\"\nport = [80]\n")
+        .unwrap_err();
+    assert_eq!(
+        render_plain(err, &config),
+        "error[E0301]: port must be u16, not sequence\n  --> config.pi:2:1: defined here\n"
+    );
+
+    let err = config.run("other.pi", "name = \"api\"\n").unwrap_err();
+    assert_eq!(
+        render_plain(err, &config),
+        "error[E0301]: config is missing field 'port'\n"
+    );
+}
+
+#[test]
+fn unwritable_values() {
+    let mut config = Config::new();
+    config
+        .run("config.pi", "a = 1\nb = [(x for x in [1])]\n")
+        .unwrap();
+
+    let err = config.dump(ConfigFormat::Json).unwrap_err();
+    assert_eq!(
+        render_plain(err, &config),
+        "error[E0301]: b can't be written as JSON: Object of type generator is not serializable\n  \
+           --> config.pi:2:1: defined here\n"
+    );
+}
+
+#[test]
+fn circular_values() {
+    let mut config = Config::new();
+    config
+        .run(
+            "config.pi",
+            "a = [1]\na.append(a)\nb = {}\nb[\"self\"] = [b]\n",
+        )
+        .unwrap();
+
+    let err = config.dump(ConfigFormat::Json).unwrap_err();
+    assert_eq!(
+        render_plain(err, &config),
+        "error[E0301]: a can't be written as JSON: circular reference detected\n  \
+           --> config.pi:1:1: defined here\n"
+    );
+
+    let err = config.deserialize::<RuntimeValue>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "a[1] is invalid: circular reference detected"
+    );
+}
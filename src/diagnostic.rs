@@ -0,0 +1,241 @@
+mod json;
+mod render;
+#[cfg(test)]
+mod tests;
+
+use core::fmt;
+use std::str::FromStr;
+use std::{io, ops};
+
+use crate::lexer::span::Span;
+use crate::source_map::SourceLookup;
+
+/// Error codes shared by every stage.
+///
+/// `E00xx` are produced by the lexer, `E01xx` by the parser, `E02xx` by the runtime
+/// and `E03xx` by config validation. Warnings use `W` with the same numbering.
+pub mod codes {
+    pub const UNEXPECTED_CHAR: &str = "E0001";
+    pub const INVALID_LITERAL: &str = "E0002";
+
+    pub const UNEXPECTED_TOKEN: &str = "E0101";
+    pub const UNEXPECTED_EOF: &str = "E0102";
+    pub const INVALID_STATEMENT: &str = "E0103";
+    pub const TOO_DEEPLY_NESTED: &str = "E0104";
+
+    pub const NON_EXHAUSTIVE_MATCH: &str = "W0101";
+
+    pub const UNCAUGHT_EXCEPTION: &str = "E0201";
+    pub const LIMIT_EXCEEDED: &str = "E0202";
+
+    pub const INVALID_CONFIG: &str = "E0301";
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+#[derive(Clone, Debug)]
+pub struct DiagnosticLabel {
+    pub span: Span,
+    pub message: Option<String>,
+    /// Primary labels point at the cause, secondary ones give context
+    pub primary: bool,
+}
+
+/// Machine-applicable fix: replace `span` with `replacement`
+#[derive(Clone, Debug)]
+pub struct Suggestion {
+    pub span: Span,
+    pub replacement: String,
+    pub message: String,
+}
+
+/// Boxed so `Result<T, Diagnostic>` stays small on the happy path
+#[derive(Clone)]
+pub struct Diagnostic(Box<DiagnosticData>);
+
+#[derive(Clone, Debug)]
+pub struct DiagnosticData {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub labels: Vec<DiagnosticLabel>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DiagnosticFormat {
+    /// Coloured ariadne reports
+    #[default]
+    Terminal,
+    /// Ariadne-free text, one location per line
+    Plain,
+    /// One JSON object per diagnostic, inside an array
+    Json,
+    /// SARIF 2.1.0 log
+    Sarif,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: impl fmt::Display) -> Self {
+        Self(Box::new(DiagnosticData {
+            code,
+            severity,
+            message: message.to_string(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+            suggestions: Vec::new(),
+        }))
+    }
+
+    pub fn error(code: &'static str, message: impl fmt::Display) -> Self {
+        Self::new(Severity::Error, code, message)
+    }
+
+    pub fn warning(code: &'static str, message: impl fmt::Display) -> Self {
+        Self::new(Severity::Warning, code, message)
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl fmt::Display) -> Self {
+        self.labels.push(DiagnosticLabel {
+            span,
+            message: Some(message.to_string()),
+            primary: true,
+        });
+        self
+    }
+
+    /// Primary label without message
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.labels.push(DiagnosticLabel {
+            span,
+            message: None,
+            primary: true,
+        });
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl fmt::Display) -> Self {
+        self.labels.push(DiagnosticLabel {
+            span,
+            message: Some(message.to_string()),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl fmt::Display) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl fmt::Display) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    pub fn with_suggestion(
+        mut self,
+        span: Span,
+        replacement: impl Into<String>,
+        message: impl fmt::Display,
+    ) -> Self {
+        self.suggestions.push(Suggestion {
+            span,
+            replacement: replacement.into(),
+            message: message.to_string(),
+        });
+        self
+    }
+
+    /// Span of the first primary label, if any
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels.iter().find(|l| l.primary).map(|l| l.span)
+    }
+
+    /// Renders this diagnostic to stderr as a coloured report
+    pub fn eprint(&self, sources: &dyn SourceLookup) {
+        _ = render(
+            std::slice::from_ref(self),
+            DiagnosticFormat::Terminal,
+            sources,
+            &mut io::stderr(),
+        );
+    }
+}
+
+/// Writes `diagnostics` to `out` in the requested format
+pub fn render(
+    diagnostics: &[Diagnostic],
+    format: DiagnosticFormat,
+    sources: &dyn SourceLookup,
+    out: &mut dyn io::Write,
+) -> io::Result<()> {
+    match format {
+        DiagnosticFormat::Terminal => render::terminal(diagnostics, sources, out, true),
+        DiagnosticFormat::Plain => render::plain(diagnostics, sources, out),
+        DiagnosticFormat::Json => render::json(diagnostics, sources, out),
+        DiagnosticFormat::Sarif => render::sarif(diagnostics, sources, out),
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+            Severity::Note => f.write_str("note"),
+        }
+    }
+}
+
+impl ops::Deref for Diagnostic {
+    type Target = DiagnosticData;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl ops::DerefMut for Diagnostic {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl fmt::Debug for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+impl FromStr for DiagnosticFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "terminal" => Ok(Self::Terminal),
+            "plain" => Ok(Self::Plain),
+            "json" => Ok(Self::Json),
+            "sarif" => Ok(Self::Sarif),
+            _ => Err(format!(
+                "Unknown diagnostic format {s:?}. Expected terminal, plain, json or sarif"
+            )),
+        }
+    }
+}
@@ -0,0 +1,403 @@
+use std::io;
+
+use ariadne::{Color, Config, IndexType, Label, Report, ReportKind};
+
+use super::json::Json;
+use super::{Diagnostic, Severity};
+use crate::lexer::span::Span;
+use crate::source_map::{LineCol, SourceLookup};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Resolved position of a span, if its file is known
+struct Location {
+    file: String,
+    start: LineCol,
+    end: LineCol,
+}
+
+fn locate(sources: &dyn SourceLookup, span: Span) -> Option<Location> {
+    let file = sources.lookup(span.source)?;
+
+    Some(Location {
+        file: file.name().to_string(),
+        start: file.line_col(span.from),
+        end: file.line_col(span.to),
+    })
+}
+
+fn severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Error => Color::BrightRed,
+        Severity::Warning => Color::BrightYellow,
+        Severity::Note => Color::BrightBlue,
+    }
+}
+
+pub fn terminal(
+    diagnostics: &[Diagnostic],
+    sources: &dyn SourceLookup,
+    out: &mut dyn io::Write,
+    color: bool,
+) -> io::Result<()> {
+    for diagnostic in diagnostics {
+        let known_sources = diagnostic
+            .labels
+            .iter()
+            .all(|label| sources.lookup(label.span.source).is_some());
+
+        // Ariadne needs every source it points to
+        let Some(span) = diagnostic.primary_span().filter(|_| known_sources) else {
+            plain(std::slice::from_ref(diagnostic), sources, out)?;
+            continue;
+        };
+
+        let kind = match diagnostic.severity {
+            Severity::Error => ReportKind::Error,
+            Severity::Warning => ReportKind::Warning,
+            Severity::Note => ReportKind::Advice,
+        };
+
+        let mut report = Report::build(kind, span)
+            .with_config(
+                Config::default()
+                    .with_index_type(IndexType::Byte)
+                    .with_color(color),
+            )
+            .with_code(diagnostic.code)
+            .with_message(&diagnostic.message)
+            .with_labels(diagnostic.labels.iter().map(|label| {
+                let color = if label.primary {
+                    severity_color(diagnostic.severity)
+                } else {
+                    Color::BrightBlue
+                };
+
+                let ariadne_label = Label::new(label.span).with_color(color);
+
+                match &label.message {
+                    Some(message) => ariadne_label.with_message(message),
+                    None => ariadne_label,
+                }
+            }));
+
+        for note in &diagnostic.notes {
+            report.add_note(note);
+        }
+
+        for suggestion in &diagnostic.suggestions {
+            report.add_note(format!(
+                "{}: `{}`",
+                suggestion.message, suggestion.replacement
+            ));
+        }
+
+        if let Some(help) = &diagnostic.help {
+            report.set_help(help);
+        }
+
+        report.finish().write(sources, &mut *out)?;
+    }
+
+    Ok(())
+}
+
+pub fn plain(
+    diagnostics: &[Diagnostic],
+    sources: &dyn SourceLookup,
+    out: &mut dyn io::Write,
+) -> io::Result<()> {
+    for diagnostic in diagnostics {
+        writeln!(out, "{diagnostic}")?;
+
+        for label in &diagnostic.labels {
+            let arrow = if label.primary { "-->" } else { ":::" };
+            let message = label.message.as_deref().unwrap_or_default();
+
+            match locate(sources, label.span) {
+                Some(loc) => writeln!(out, "  {arrow} {}:{}: {message}", loc.file, loc.start)?,
+                None => writeln!(
+                    out,
+                    "  {arrow} <unknown>@{}..{}: {message}",
+                    label.span.from, label.span.to
+                )?,
+            }
+        }
+
+        for note in &diagnostic.notes {
+            writeln!(out, "  = note: {note}")?;
+        }
+
+        if let Some(help) = &diagnostic.help {
+            writeln!(out, "  = help: {help}")?;
+        }
+
+        for suggestion in &diagnostic.suggestions {
+            writeln!(
+                out,
+                "  = suggestion: {}: `{}`",
+                suggestion.message, suggestion.replacement
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn json_location(sources: &dyn SourceLookup, span: Span) -> Vec<(&'static str, Json)> {
+    let mut fields = Vec::from([
+        ("byte_start", Json::from(span.from)),
+        ("byte_end", Json::from(span.to)),
+    ]);
+
+    let position = |pos: LineCol| {
+        Json::object([
+            ("line", Json::from(pos.line + 1)),
+            ("column", Json::from(pos.char + 1)),
+        ])
+    };
+
+    match locate(sources, span) {
+        Some(loc) => fields.extend([
+            ("file", Json::from(loc.file)),
+            ("start", position(loc.start)),
+            ("end", position(loc.end)),
+        ]),
+        None => fields.push(("file", Json::Null)),
+    }
+
+    fields
+}
+
+/// Lines and columns are one-based, columns are counted in characters
+pub fn json(
+    diagnostics: &[Diagnostic],
+    sources: &dyn SourceLookup,
+    out: &mut dyn io::Write,
+) -> io::Result<()> {
+    let diagnostics: Json = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            Json::object([
+                ("code", diagnostic.code.into()),
+                ("severity", diagnostic.severity.to_string().into()),
+                ("message", diagnostic.message.as_str().into()),
+                (
+                    "labels",
+                    diagnostic
+                        .labels
+                        .iter()
+                        .map(|label| {
+                            let mut fields = json_location(sources, label.span);
+                            fields.push(("primary", label.primary.into()));
+                            fields.push(("message", label.message.as_deref().into()));
+                            Json::Object(fields)
+                        })
+                        .collect(),
+                ),
+                (
+                    "notes",
+                    diagnostic.notes.iter().map(String::as_str).collect(),
+                ),
+                ("help", diagnostic.help.as_deref().into()),
+                (
+                    "suggestions",
+                    diagnostic
+                        .suggestions
+                        .iter()
+                        .map(|suggestion| {
+                            let mut fields = json_location(sources, suggestion.span);
+                            fields.push(("message", suggestion.message.as_str().into()));
+                            fields.push(("replacement", suggestion.replacement.as_str().into()));
+                            Json::Object(fields)
+                        })
+                        .collect(),
+                ),
+            ])
+        })
+        .collect();
+
+    writeln!(out, "{diagnostics}")
+}
+
+/// SARIF regions use one-based lines and UTF-16 columns
+fn sarif_location(sources: &dyn SourceLookup, span: Span) -> (Json, Json) {
+    let mut region = Vec::from([
+        ("byteOffset", Json::from(span.from)),
+        ("byteLength", Json::from(span.to - span.from)),
+    ]);
+
+    let uri = match locate(sources, span) {
+        Some(loc) => {
+            region.extend([
+                ("startLine", Json::from(loc.start.line + 1)),
+                ("startColumn", Json::from(loc.start.utf16 + 1)),
+                ("endLine", Json::from(loc.end.line + 1)),
+                ("endColumn", Json::from(loc.end.utf16 + 1)),
+            ]);
+            sarif_uri(&loc.file)
+        }
+        None => sarif_uri("<anonymous>"),
+    };
+
+    (Json::object([("uri", uri.into())]), Json::Object(region))
+}
+
+/// URI reference of a file: pseudo-files like `<string>` become `urn:ruspy:string`,
+/// paths are percent-encoded
+fn sarif_uri(name: &str) -> String {
+    if let Some(pseudo) = name
+        .strip_prefix('<')
+        .and_then(|name| name.strip_suffix('>'))
+    {
+        return format!("urn:ruspy:{}", sarif_uri(pseudo));
+    }
+
+    let mut uri = String::with_capacity(name.len());
+
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/!$&'()*+,;=:@".contains(&byte) {
+            uri.push(char::from(byte));
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    uri
+}
+
+pub fn sarif(
+    diagnostics: &[Diagnostic],
+    sources: &dyn SourceLookup,
+    out: &mut dyn io::Write,
+) -> io::Result<()> {
+    let mut rules = diagnostics.iter().map(|d| d.code).collect::<Vec<_>>();
+    rules.sort_unstable();
+    rules.dedup();
+
+    let physical = |span: Span, message: Option<&str>| {
+        let (artifact, region) = sarif_location(sources, span);
+        let mut fields = Vec::from([(
+            "physicalLocation",
+            Json::object([("artifactLocation", artifact), ("region", region)]),
+        )]);
+
+        if let Some(message) = message {
+            fields.push(("message", Json::object([("text", message.into())])));
+        }
+
+        Json::Object(fields)
+    };
+
+    let results: Json = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let level = match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Note => "note",
+            };
+
+            let (primary, secondary): (Vec<_>, Vec<_>) =
+                diagnostic.labels.iter().partition(|label| label.primary);
+
+            let fixes: Json = diagnostic
+                .suggestions
+                .iter()
+                .map(|suggestion| {
+                    let (artifact, region) = sarif_location(sources, suggestion.span);
+
+                    Json::object([
+                        (
+                            "description",
+                            Json::object([("text", suggestion.message.as_str().into())]),
+                        ),
+                        (
+                            "artifactChanges",
+                            Json::Array(Vec::from([Json::object([
+                                ("artifactLocation", artifact),
+                                (
+                                    "replacements",
+                                    Json::Array(Vec::from([Json::object([
+                                        ("deletedRegion", region),
+                                        (
+                                            "insertedContent",
+                                            Json::object([(
+                                                "text",
+                                                suggestion.replacement.as_str().into(),
+                                            )]),
+                                        ),
+                                    ])])),
+                                ),
+                            ])])),
+                        ),
+                    ])
+                })
+                .collect();
+
+            Json::object([
+                ("ruleId", diagnostic.code.into()),
+                ("level", level.into()),
+                (
+                    "message",
+                    Json::object([("text", diagnostic.message.as_str().into())]),
+                ),
+                (
+                    "locations",
+                    primary
+                        .iter()
+                        .map(|label| physical(label.span, label.message.as_deref()))
+                        .collect(),
+                ),
+                (
+                    "relatedLocations",
+                    secondary
+                        .iter()
+                        .map(|label| physical(label.span, label.message.as_deref()))
+                        .collect(),
+                ),
+                ("fixes", fixes),
+                (
+                    "properties",
+                    Json::object([
+                        (
+                            "notes",
+                            diagnostic.notes.iter().map(String::as_str).collect(),
+                        ),
+                        ("help", diagnostic.help.as_deref().into()),
+                    ]),
+                ),
+            ])
+        })
+        .collect();
+
+    let log = Json::object([
+        ("$schema", SARIF_SCHEMA.into()),
+        ("version", "2.1.0".into()),
+        (
+            "runs",
+            Json::Array(Vec::from([Json::object([
+                (
+                    "tool",
+                    Json::object([(
+                        "driver",
+                        Json::object([
+                            ("name", env!("CARGO_PKG_NAME").into()),
+                            ("version", env!("CARGO_PKG_VERSION").into()),
+                            (
+                                "rules",
+                                rules
+                                    .into_iter()
+                                    .map(|id| Json::object([("id", id.into())]))
+                                    .collect(),
+                            ),
+                        ]),
+                    )]),
+                ),
+                ("results", results),
+            ])])),
+        ),
+    ]);
+
+    writeln!(out, "{log}")
+}
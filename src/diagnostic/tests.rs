@@ -0,0 +1,129 @@
+use crate::ast::node::AstScope;
+use crate::diagnostic::{Diagnostic, DiagnosticFormat, codes, render};
+use crate::lexer::Lexer;
+use crate::lexer::span::Span;
+use crate::source_map::{SourceId, SourceMap};
+
+fn parse(sources: &SourceMap, id: SourceId) -> Result<AstScope, Diagnostic> {
+    let tokens = Lexer::from_source(&sources[id])?;
+    AstScope::from_tokens(&sources[id], tokens)
+}
+
+fn render_to_string(
+    diagnostic: Diagnostic,
+    format: DiagnosticFormat,
+    sources: &SourceMap,
+) -> String {
+    let mut out = Vec::new();
+    render(&[diagnostic], format, sources, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn sample() -> (SourceMap, Diagnostic) {
+    let mut sources = SourceMap::new();
+    let id = sources.add("main.pi", "x = \"é\" + if True: 1");
+    let err = parse(&sources, id).unwrap_err();
+
+    let err = err
+        .with_note("`if` is a statement")
+        .with_help("assign inside the branches")
+        .with_suggestion(Span::new(id, 0..1), "y", "rename");
+
+    (sources, err)
+}
+
+#[test]
+fn lexer_error() {
+    let err = Lexer::from_str("if %").unwrap_err();
+
+    assert_eq!(err.code, codes::UNEXPECTED_CHAR);
+    assert_eq!(err.primary_span().map(|s| (s.from, s.to)), Some((3, 4)));
+}
+
+#[test]
+fn parser_error() {
+    let (_, err) = sample();
+
+    assert_eq!(err.code, codes::UNEXPECTED_TOKEN);
+    assert_eq!(err.to_string(), "error[E0101]: Unexpected token: If");
+    assert_eq!(err.primary_span().map(|s| (s.from, s.to)), Some((11, 13)));
+}
+
+#[test]
+fn render_plain() {
+    let (sources, err) = sample();
+
+    assert_eq!(
+        render_to_string(err, DiagnosticFormat::Plain, &sources),
+        "error[E0101]: Unexpected token: If\n  --> main.pi:1:11: Expected expression\n  = note: `if` is a statement\n  = help: assign inside the branches\n  = suggestion: rename: `y`\n"
+    );
+}
+
+#[test]
+fn render_terminal() {
+    let (sources, err) = sample();
+    let out = render_to_string(err, DiagnosticFormat::Terminal, &sources);
+
+    assert!(out.contains("[E0101]"), "{out}");
+    assert!(out.contains("main.pi:1:11"), "{out}");
+}
+
+#[test]
+fn render_json() {
+    let (sources, err) = sample();
+    let out = render_to_string(err, DiagnosticFormat::Json, &sources);
+
+    assert!(
+        out.starts_with(r#"[{"code":"E0101","severity":"error""#),
+        "{out}"
+    );
+    assert!(
+        out.contains(r#""labels":[{"byte_start":11,"byte_end":13,"file":"main.pi","start":{"line":1,"column":11},"end":{"line":1,"column":13},"primary":true,"message":"Expected expression"}]"#),
+        "{out}"
+    );
+    assert!(out.contains(r#""replacement":"y""#), "{out}");
+}
+
+#[test]
+fn render_sarif() {
+    let (sources, err) = sample();
+    let out = render_to_string(err, DiagnosticFormat::Sarif, &sources);
+
+    assert!(out.contains(r#""version":"2.1.0""#), "{out}");
+    assert!(out.contains(r#""rules":[{"id":"E0101"}]"#), "{out}");
+    assert!(
+        out.contains(r#""region":{"byteOffset":11,"byteLength":2,"startLine":1,"startColumn":11,"endLine":1,"endColumn":13}"#),
+        "{out}"
+    );
+    assert!(out.contains(r#""insertedContent":{"text":"y"}"#), "{out}");
+    assert!(out.contains(r#""uri":"main.pi""#), "{out}");
+}
+
+#[test]
+fn sarif_uris() {
+    let mut sources = SourceMap::new();
+    let id = sources.add("<string>", "x = ");
+    let err = parse(&sources, id).unwrap_err();
+    let out = render_to_string(err, DiagnosticFormat::Sarif, &sources);
+    assert!(out.contains(r#""uri":"urn:ruspy:string""#), "{out}");
+
+    let id = sources.add("my scripts/main.pi", "x = ");
+    let err = parse(&sources, id).unwrap_err();
+    let out = render_to_string(err, DiagnosticFormat::Sarif, &sources);
+    assert!(out.contains(r#""uri":"my%20scripts/main.pi""#), "{out}");
+
+    let err = Lexer::from_str("if %").unwrap_err();
+    let out = render_to_string(err, DiagnosticFormat::Sarif, &SourceMap::new());
+    assert!(out.contains(r#""uri":"urn:ruspy:anonymous""#), "{out}");
+}
+
+#[test]
+fn unknown_source_falls_back_to_plain() {
+    let err = Lexer::from_str("if %").unwrap_err();
+    let out = render_to_string(err, DiagnosticFormat::Terminal, &SourceMap::new());
+
+    assert!(
+        out.starts_with("error[E0001]: Unexpected char: '%'\n  --> <unknown>@3..4"),
+        "{out}"
+    );
+}
@@ -0,0 +1,251 @@
+//! Single expressions evaluated against records, for rule engines and filters
+
+#[cfg(test)]
+mod tests;
+
+use std::cell::{Ref, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::{Rc, Weak};
+
+use crate::ast::node::SpannedExpr;
+use crate::diagnostic::{Diagnostic, codes};
+use crate::kw;
+use crate::lexer::Lexer;
+use crate::runtime::convert::IntoValue;
+use crate::runtime::error::{RuntimeError, RuntimeResult};
+use crate::runtime::limits::Limits;
+use crate::runtime::module::Importer;
+use crate::runtime::value::RuntimeValue;
+use crate::runtime::{MODULE_FRAME, Scope};
+use crate::source_map::{SourceFile, SourceId, SourceMap};
+
+/// Name of the files created for the expressions given to [`Evaluator::compile`]
+pub const EXPRESSION_PATH: &str = "<expression>";
+
+/// Builtins of [`Evaluator::new`], none of them can reach the host
+pub const DEFAULT_BUILTINS: &[&str] = &[
+    "isinstance",
+    "len",
+    "list",
+    "repr",
+    "set",
+    "sorted",
+    "str",
+    "tuple",
+];
+
+/// Limits of every [`Evaluator`] until [`Evaluator::with_limits`] replaces them,
+/// enough for rules over a record but not to hang or exhaust the host
+pub const DEFAULT_LIMITS: Limits = Limits {
+    max_steps: Some(100_000),
+    max_call_depth: Some(50),
+    max_allocated_bytes: Some(16 << 20),
+    timeout: None,
+    interrupt: None,
+};
+
+/// Expressions kept by [`Evaluator::compile`], the cache starts over once it's full
+pub const CACHE_CAPACITY: usize = 1024;
+
+/// Parses expressions once and evaluates them with only a whitelist of builtins
+pub struct Evaluator {
+    importer: Rc<Importer>,
+    builtins: Rc<HashSet<String>>,
+    /// Whether expressions may define functions, see [`Evaluator::with_lambda`]
+    allow_lambda: bool,
+    /// Expressions compiled so far, by source, see [`CACHE_CAPACITY`]
+    cache: RefCell<HashMap<String, Rc<Expression>>>,
+    /// Files of the expressions that were dropped or didn't compile, reused by the next ones
+    spare_sources: Rc<RefCell<Vec<SourceId>>>,
+}
+
+/// Parsed expression, see [`Evaluator::compile`]
+#[derive(Debug)]
+pub struct Expression {
+    pub source: String,
+    expr: SpannedExpr,
+    /// Where to give the file of the expression back once it's dropped
+    spare_sources: Weak<RefCell<Vec<SourceId>>>,
+}
+
+impl Evaluator {
+    /// Evaluator allowing the [`DEFAULT_BUILTINS`]
+    pub fn new() -> Self {
+        Self::with_builtins(DEFAULT_BUILTINS.iter().copied())
+    }
+
+    /// Evaluator allowing only the builtins in `names`
+    pub fn with_builtins<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let importer = Rc::<Importer>::default();
+        importer.budget().set_limits(DEFAULT_LIMITS);
+
+        Self {
+            importer,
+            builtins: Rc::new(names.into_iter().map(str::to_owned).collect()),
+            allow_lambda: false,
+            cache: RefCell::default(),
+            spare_sources: Rc::default(),
+        }
+    }
+
+    /// Accepts `lambda` in expressions, like `sorted(xs, key=lambda x: -x)`.
+    /// Off by default, functions make it easy to recurse
+    pub fn with_lambda(mut self) -> Self {
+        self.allow_lambda = true;
+        self
+    }
+
+    /// Caps the resources used by each evaluation, instead of the [`DEFAULT_LIMITS`]
+    pub fn with_limits(self, limits: Limits) -> Self {
+        self.importer.budget().set_limits(limits);
+        self
+    }
+
+    /// Parses `source`, which must be a single expression, or returns the cached parse
+    pub fn compile(&self, source: &str) -> Result<Rc<Expression>, Diagnostic> {
+        if let Some(expression) = self.cache.borrow().get(source) {
+            return Ok(expression.clone());
+        }
+
+        let id = match self.spare_sources.borrow_mut().pop() {
+            Some(id) => {
+                self.importer.sources_mut().replace(id, source);
+                id
+            }
+            None => self.importer.sources_mut().add(EXPRESSION_PATH, source),
+        };
+        let expr = parse(&self.importer.sources()[id], self.allow_lambda);
+        let expr = expr.inspect_err(|_| self.spare_sources.borrow_mut().push(id))?;
+
+        let expression = Rc::new(Expression {
+            source: source.to_owned(),
+            expr,
+            spare_sources: Rc::downgrade(&self.spare_sources),
+        });
+
+        let mut cache = self.cache.borrow_mut();
+
+        if cache.len() >= CACHE_CAPACITY {
+            cache.clear();
+        }
+
+        cache.insert(source.to_owned(), expression.clone());
+
+        Ok(expression)
+    }
+
+    /// Forgets the compiled expressions, their files are reused once nothing holds them
+    pub fn clear_cache(&self) {
+        self.cache.borrow_mut().clear();
+    }
+
+    /// Value of `expression` with the entries of a dict, or the attributes
+    /// of an object, as variables
+    pub fn eval(
+        &self,
+        expression: &Expression,
+        variables: impl IntoValue,
+    ) -> RuntimeResult<RuntimeValue> {
+        self.run(expression, variables).map(|(_, value)| value)
+    }
+
+    /// Whether `expression` is truthy, see [`Evaluator::eval`]
+    pub fn test(&self, expression: &Expression, variables: impl IntoValue) -> RuntimeResult<bool> {
+        let (scope, value) = self.run(expression, variables)?;
+
+        scope
+            .truthy(&value)
+            .map_err(|err| err.locate(MODULE_FRAME, expression.expr.span))
+    }
+
+    /// Expressions that are still alive, to render errors.
+    ///
+    /// The file of an expression that didn't compile is reused by the next
+    /// one, so render its errors before compiling more.
+    pub fn sources(&self) -> Ref<'_, SourceMap> {
+        self.importer.sources()
+    }
+
+    /// Value of `expression` and the scope it was evaluated in
+    fn run(
+        &self,
+        expression: &Expression,
+        variables: impl IntoValue,
+    ) -> RuntimeResult<(Rc<Scope>, RuntimeValue)> {
+        let scope =
+            Scope::restricted_module(&self.importer, EXPRESSION_PATH, self.builtins.clone());
+        bind(&scope, variables.into_value()?)?;
+        self.importer.budget().reset();
+
+        let value = scope
+            .visit_expr(&expression.expr)
+            .map_err(|err| err.locate(MODULE_FRAME, expression.expr.span))?;
+
+        Ok((scope, value))
+    }
+}
+
+impl Drop for Expression {
+    fn drop(&mut self) {
+        if let Some(spare_sources) = self.spare_sources.upgrade() {
+            spare_sources.borrow_mut().push(self.expr.span.source);
+        }
+    }
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses the single expression in `file`
+fn parse(file: &SourceFile, allow_lambda: bool) -> Result<SpannedExpr, Diagnostic> {
+    let tokens = Lexer::from_source(file)?;
+
+    if !allow_lambda && let Some(lambda) = tokens.iter().find(|token| token.token == kw!(Lambda)) {
+        let message = "lambda is not allowed in expressions";
+        return Err(
+            Diagnostic::error(codes::INVALID_STATEMENT, message).with_label(lambda.span, message)
+        );
+    }
+
+    SpannedExpr::from_tokens(file, tokens)
+}
+
+/// Sets the variables of `scope` from a dict with string keys or from the attributes of an object
+fn bind(scope: &Rc<Scope>, variables: RuntimeValue) -> RuntimeResult {
+    match &variables {
+        RuntimeValue::Dict(dict) => {
+            for (key, value) in dict.borrow().iter() {
+                let RuntimeValue::String(name) = key else {
+                    return Err(RuntimeError::type_error(format!(
+                        "variable names must be str, not {}",
+                        key.type_name()
+                    )));
+                };
+
+                scope.set_variable(name.clone(), value.clone());
+            }
+        }
+        RuntimeValue::Object(object) => {
+            for (name, value) in object.attrs.borrow().iter() {
+                scope.set_variable(name.clone(), value.clone());
+            }
+        }
+        RuntimeValue::NativeObject(object) => {
+            for name in object.fields {
+                let value = scope.get_attribute(&variables, name)?;
+                scope.set_variable((*name).to_owned(), value);
+            }
+        }
+        value => {
+            return Err(RuntimeError::type_error(format!(
+                "variables must be a dict or an object, not {}",
+                value.type_name()
+            )));
+        }
+    }
+
+    Ok(())
+}
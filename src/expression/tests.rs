@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::diagnostic::codes;
+use crate::expression::Evaluator;
+use crate::runtime::error::ExceptionKind;
+use crate::runtime::limits::{Limit, Limits};
+use crate::runtime::value::RuntimeValue;
+use crate::{ScriptClass, script_methods};
+
+#[derive(Clone, ScriptClass)]
+struct Order {
+    total: i64,
+    country: String,
+    #[script(skip)]
+    #[allow(dead_code)]
+    notes: String,
+}
+
+#[script_methods]
+impl Order {
+    fn is_local(&self) -> bool {
+        self.country == "FR"
+    }
+}
+
+#[test]
+fn records() {
+    let evaluator = Evaluator::new();
+    let rule = evaluator
+        .compile("total > 100 and (country == \"FR\" or country == \"BE\")")
+        .unwrap();
+
+    let order = |total, country: &str| Order {
+        total,
+        country: country.to_owned(),
+        notes: String::new(),
+    };
+
+    assert!(evaluator.test(&rule, order(150, "FR")).unwrap());
+    assert!(!evaluator.test(&rule, order(150, "DE")).unwrap());
+    assert!(!evaluator.test(&rule, order(50, "BE")).unwrap());
+
+    let variables = HashMap::from([("total".to_owned(), 120), ("country".to_owned(), 0)]);
+    let total = evaluator.compile("total * 2 + len([country])").unwrap();
+    assert!(matches!(
+        evaluator.eval(&total, variables),
+        Ok(RuntimeValue::Number(241))
+    ));
+
+    let err = evaluator
+        .eval(&evaluator.compile("notes").unwrap(), order(1, "FR"))
+        .unwrap_err();
+    assert_eq!(err.kind(), Some(ExceptionKind::NameError));
+
+    let err = evaluator.eval(&total, 42).unwrap_err();
+    assert_eq!(err.kind(), Some(ExceptionKind::TypeError));
+    assert_eq!(
+        err.message(),
+        "variables must be a dict or an object, not int"
+    );
+}
+
+#[test]
+fn rejects_statements() {
+    let evaluator = Evaluator::new();
+
+    for source in ["x = 1", "import os", "1\n2", "if x:\n  1"] {
+        let err = evaluator.compile(source).unwrap_err();
+        assert!(
+            err.code.starts_with("E0"),
+            "{source} should be rejected, got {}",
+            err.code
+        );
+    }
+
+    assert_eq!(
+        evaluator.compile("1 +").unwrap_err().code,
+        codes::UNEXPECTED_EOF
+    );
+    assert_eq!(
+        evaluator.compile("99999999999999999999").unwrap_err().code,
+        codes::INVALID_LITERAL
+    );
+}
+
+#[test]
+fn cached() {
+    let evaluator = Evaluator::new();
+    let first = evaluator.compile("a + 1").unwrap();
+    let second = evaluator.compile("a + 1").unwrap();
+
+    assert!(Rc::ptr_eq(&first, &second));
+    assert!(!Rc::ptr_eq(&first, &evaluator.compile("a + 2").unwrap()));
+
+    evaluator.clear_cache();
+    assert!(!Rc::ptr_eq(&first, &evaluator.compile("a + 1").unwrap()));
+    assert_eq!(evaluator.sources().files().count(), 2);
+
+    // Files of failed and dropped expressions are reused
+    drop((first, second));
+    for idx in 0..10 {
+        evaluator.compile(&format!("a + {idx} +")).unwrap_err();
+        evaluator.clear_cache();
+        evaluator.compile(&format!("a * {idx}")).unwrap();
+    }
+    assert_eq!(evaluator.sources().files().count(), 2);
+}
+
+#[test]
+fn builtins_whitelist() {
+    let evaluator = Evaluator::with_builtins(["len"]);
+    let variables = || HashMap::from([("xs".to_owned(), vec![3, 1, 2])]);
+
+    let expression = evaluator.compile("len(xs)").unwrap();
+    assert!(matches!(
+        evaluator.eval(&expression, variables()),
+        Ok(RuntimeValue::Number(3))
+    ));
+
+    let expression = evaluator.compile("sorted(xs)").unwrap();
+    let err = evaluator.eval(&expression, variables()).unwrap_err();
+    assert_eq!(err.kind(), Some(ExceptionKind::NameError));
+
+    let evaluator = Evaluator::new();
+    let expression = evaluator.compile("sorted(xs)[0]").unwrap();
+    assert!(matches!(
+        evaluator.eval(&expression, variables()),
+        Ok(RuntimeValue::Number(1))
+    ));
+    let expression = evaluator.compile("next(xs)").unwrap();
+    let err = evaluator.eval(&expression, variables()).unwrap_err();
+    assert_eq!(err.kind(), Some(ExceptionKind::NameError));
+}
+
+#[test]
+fn limits() {
+    let evaluator = Evaluator::new().with_limits(Limits {
+        max_steps: Some(100),
+        ..Limits::default()
+    });
+    let expression = evaluator.compile("[x * 2 for x in xs]").unwrap();
+    let variables = |len| HashMap::from([("xs".to_owned(), vec![1; len])]);
+
+    // Each evaluation gets its own budget
+    for _ in 0..10 {
+        evaluator.eval(&expression, variables(10)).unwrap();
+    }
+
+    let err = evaluator.eval(&expression, variables(100)).unwrap_err();
+    assert_eq!(err.limit(), Some(Limit::Steps));
+}
+
+#[test]
+fn default_limits() {
+    let evaluator = Evaluator::new();
+    let no_variables = || HashMap::<String, i64>::new();
+
+    let err = evaluator
+        .compile("sorted(xs, key=lambda x: -x)")
+        .unwrap_err();
+    assert_eq!(err.code, codes::INVALID_STATEMENT);
+    assert_eq!(err.message, "lambda is not allowed in expressions");
+
+    let expression = evaluator.compile("len(\"x\" * 100000000 * 100)").unwrap();
+    let err = evaluator.eval(&expression, no_variables()).unwrap_err();
+    assert_eq!(err.limit(), Some(Limit::Memory));
+
+    let evaluator = Evaluator::new().with_lambda();
+    let expression = evaluator
+        .compile("(lambda f: f(f))(lambda f: f(f))")
+        .unwrap();
+    let err = evaluator.eval(&expression, no_variables()).unwrap_err();
+    assert_eq!(err.limit(), Some(Limit::CallDepth));
+
+    let expression = evaluator.compile("sorted(xs, key=lambda x: -x)").unwrap();
+    let variables = HashMap::from([("xs".to_owned(), vec![1, 3, 2])]);
+    assert_eq!(
+        evaluator.eval(&expression, variables).unwrap().repr(),
+        "[3, 2, 1]"
+    );
+}
@@ -0,0 +1,215 @@
+#[cfg(test)]
+mod tests;
+
+use core::fmt;
+use std::cell::{Cell, Ref};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use crate::ast::node::{AstScope, SpannedExpr};
+use crate::diagnostic::Diagnostic;
+use crate::lexer::Lexer;
+use crate::lexer::token::{SpannedToken, Token, TokenKeyword};
+use crate::runtime::error::{RuntimeError, RuntimeResult};
+use crate::runtime::limits::Limits;
+use crate::runtime::loader::ModuleLoader;
+use crate::runtime::module::{Importer, MAIN_MODULE};
+use crate::runtime::native::{NativeArgs, NativeFunction, NativeModule, TypedFn};
+use crate::runtime::value::RuntimeValue;
+use crate::runtime::{MODULE_FRAME, Scope};
+use crate::source_map::{SourceFile, SourceId, SourceMap};
+
+/// Name of the files created for the code given to [`Interpreter::exec`] and [`Interpreter::eval`]
+pub const SNIPPET_PATH: &str = "<string>";
+
+/// Runs snippets of code in a main module whose globals persist between them
+pub struct Interpreter {
+    importer: Rc<Importer>,
+    scope: Rc<Scope>,
+    /// File of the last snippet when nothing it defined can refer to it anymore
+    spare_snippet: Cell<Option<SourceId>>,
+}
+
+/// Why a snippet failed
+#[derive(Debug)]
+pub enum Error {
+    /// The code doesn't lex or parse
+    Syntax(Diagnostic),
+    /// The code raised an exception it didn't catch
+    Runtime(RuntimeError),
+    /// The code went over one of the [`Limits`], see [`RuntimeError::limit`]
+    Limit(RuntimeError),
+}
+
+impl Interpreter {
+    /// Interpreter that can't import modules
+    pub fn new() -> Self {
+        Self::with_importer(Rc::default())
+    }
+
+    /// Interpreter importing modules from `loader`
+    pub fn with_loader(loader: impl ModuleLoader + 'static) -> Self {
+        Self::with_importer(Rc::new(Importer::new(loader)))
+    }
+
+    fn with_importer(importer: Rc<Importer>) -> Self {
+        let scope = Scope::module(&importer, MAIN_MODULE, None);
+        Self {
+            importer,
+            scope,
+            spare_snippet: Cell::new(None),
+        }
+    }
+
+    /// Caps the resources used by each call to [`Interpreter::exec`] and [`Interpreter::eval`]
+    pub fn set_limits(&self, limits: Limits) {
+        self.importer.budget().set_limits(limits);
+    }
+
+    /// Runs `source` as statements of the main module
+    pub fn exec(&self, source: &str) -> Result<(), Error> {
+        let tree = self.parse_snippet(source, AstScope::from_tokens)?;
+
+        self.importer.budget().reset();
+        Ok(self.scope.run(&tree)?)
+    }
+
+    /// Value of the single expression `source`, evaluated in the main module
+    pub fn eval(&self, source: &str) -> Result<RuntimeValue, Error> {
+        let expr = self.parse_snippet(source, SpannedExpr::from_tokens)?;
+
+        self.importer.budget().reset();
+        self.scope
+            .visit_expr(&expr)
+            .map_err(|err| err.locate(MODULE_FRAME, expr.span).into())
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<RuntimeValue> {
+        self.scope
+            .get_variable(name)
+            .map(|var| var.0.borrow().clone())
+    }
+
+    pub fn set_global(&self, name: &str, value: RuntimeValue) {
+        self.scope.set_variable(name.to_owned(), value);
+    }
+
+    /// Makes `function` a global of the main module, errors it returns
+    /// are raised as exceptions where it's called
+    pub fn register_fn(
+        &self,
+        name: &str,
+        function: impl Fn(NativeArgs) -> RuntimeResult<RuntimeValue> + 'static,
+    ) {
+        let function = NativeFunction::new(name, function);
+        self.set_global(name, RuntimeValue::NativeFunction(Rc::new(function)));
+    }
+
+    /// Deserializes the globals, except the `__dunder__` ones, as a map into `T`
+    #[cfg(feature = "serde")]
+    pub fn deserialize_globals<T: serde::de::DeserializeOwned>(
+        &self,
+    ) -> Result<T, crate::runtime::convert::ConversionError> {
+        crate::runtime::serde::from_scope(&self.scope)
+    }
+
+    /// Same as [`Interpreter::register_fn`], converting the arguments and result of `function`
+    pub fn register_typed<Args>(&self, name: &str, function: impl TypedFn<Args>) {
+        let function = function.into_native(name);
+        self.set_global(name, RuntimeValue::NativeFunction(Rc::new(function)));
+    }
+
+    /// Makes `module` importable by its name from every module
+    pub fn register_module(&self, module: NativeModule) {
+        self.importer.register(module);
+    }
+
+    /// Scope of the main module, holding the globals
+    pub fn scope(&self) -> &Rc<Scope> {
+        &self.scope
+    }
+
+    /// Modules and snippets run so far, to render errors.
+    ///
+    /// Snippets that don't define functions, lambdas or comprehensions
+    /// share their file with the next one, so render their errors before
+    /// running more code.
+    pub fn sources(&self) -> Ref<'_, SourceMap> {
+        self.importer.sources()
+    }
+
+    /// Registers and parses a snippet, its file is reused by the next
+    /// snippet unless it has code that runs after it, like functions
+    fn parse_snippet<T>(
+        &self,
+        source: &str,
+        parse: impl FnOnce(&SourceFile, VecDeque<SpannedToken>) -> Result<T, Diagnostic>,
+    ) -> Result<T, Diagnostic> {
+        let id = match self.spare_snippet.take() {
+            Some(id) => {
+                self.importer.sources_mut().replace(id, source);
+                id
+            }
+            None => self.importer.sources_mut().add(SNIPPET_PATH, source),
+        };
+        self.spare_snippet.set(Some(id));
+
+        let sources = self.importer.sources();
+        let file = &sources[id];
+        let tokens = Lexer::from_source(file)?;
+        let outlives_run = tokens.iter().any(|token| {
+            matches!(
+                token.token,
+                Token::Keyword(TokenKeyword::Def | TokenKeyword::Lambda | TokenKeyword::For)
+            )
+        });
+        let parsed = parse(file, tokens)?;
+
+        if outlives_run {
+            self.spare_snippet.set(None);
+        }
+
+        Ok(parsed)
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Diagnostic> for Error {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Self::Syntax(diagnostic)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(error: RuntimeError) -> Self {
+        match error.limit() {
+            Some(_) => Self::Limit(error),
+            None => Self::Runtime(error),
+        }
+    }
+}
+
+impl From<Error> for Diagnostic {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Syntax(diagnostic) => diagnostic,
+            Error::Runtime(error) | Error::Limit(error) => error.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Syntax(diagnostic) => diagnostic.fmt(f),
+            Error::Runtime(error) | Error::Limit(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,752 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use crate::diagnostic::{Diagnostic, codes};
+use crate::interpreter::{Error, Interpreter};
+use crate::runtime::convert::{FromValue, IntoValue};
+use crate::runtime::error::{ExceptionKind, RuntimeError, RuntimeResult};
+use crate::runtime::limits::{Limit, Limits};
+use crate::runtime::loader::MemoryLoader;
+use crate::runtime::native::NativeArgs;
+use crate::runtime::native::NativeModule;
+use crate::runtime::value::RuntimeValue;
+use crate::{ScriptClass, script_methods};
+
+#[test]
+fn exec_and_eval() {
+    let interpreter = Interpreter::new();
+
+    interpreter.exec("a = 20").unwrap();
+    interpreter.exec("def f(x):\n  return x + a").unwrap();
+
+    assert!(matches!(
+        interpreter.eval("f(22)"),
+        Ok(RuntimeValue::Number(42))
+    ));
+    assert!(matches!(
+        interpreter.eval("\n  a * 2\n"),
+        Ok(RuntimeValue::Number(40))
+    ));
+    assert_eq!(
+        interpreter.eval("__name__").unwrap().to_string(),
+        "__main__"
+    );
+}
+
+#[test]
+fn globals() {
+    let interpreter = Interpreter::new();
+
+    interpreter.set_global("name", RuntimeValue::String("world".to_owned()));
+    interpreter.exec("greeting = \"hello \" + name").unwrap();
+
+    assert_eq!(
+        interpreter.get_global("greeting").unwrap().to_string(),
+        "hello world"
+    );
+    assert!(interpreter.get_global("missing").is_none());
+}
+
+#[test]
+fn errors() {
+    let interpreter = Interpreter::new();
+
+    let Err(Error::Syntax(diagnostic)) = interpreter.eval("a = 1") else {
+        panic!("statements are not expressions");
+    };
+    assert_eq!(
+        diagnostic.message,
+        "Unexpected token: Equal. Expected end of expression"
+    );
+
+    assert!(matches!(interpreter.exec("a = ("), Err(Error::Syntax(_))));
+
+    let Err(Error::Runtime(err)) = interpreter.eval("1 / 0") else {
+        panic!("division by zero should raise");
+    };
+    assert_eq!(err.kind(), Some(ExceptionKind::ZeroDivisionError));
+    assert_eq!(
+        err.traceback(&*interpreter.sources()).to_string(),
+        "Traceback (most recent call last):\n  \
+           File \"<string>\", line 1, in <module>\n    \
+             1 / 0\n\
+         ZeroDivisionError: division by zero"
+    );
+
+    // Failed snippets keep what they did before raising
+    assert!(interpreter.exec("b = 1\nc = d").is_err());
+    assert!(matches!(interpreter.eval("b"), Ok(RuntimeValue::Number(1))));
+}
+
+#[test]
+fn snippet_sources() {
+    let interpreter = Interpreter::new();
+
+    for idx in 0..10 {
+        interpreter.exec(&format!("a = {idx}")).unwrap();
+    }
+    assert!(interpreter.exec("a = (").is_err());
+    assert!(matches!(interpreter.eval("a"), Ok(RuntimeValue::Number(9))));
+    assert_eq!(interpreter.sources().files().count(), 1);
+
+    // Functions keep the file they were defined in
+    interpreter.exec("def f():\n  return 1 / 0").unwrap();
+    interpreter.exec("b = 2").unwrap();
+
+    let Err(Error::Runtime(err)) = interpreter.eval("f()") else {
+        panic!("division by zero should raise");
+    };
+    assert_eq!(interpreter.sources().files().count(), 2);
+    assert_eq!(
+        err.traceback(&*interpreter.sources()).to_string(),
+        "Traceback (most recent call last):\n  \
+           File \"<string>\", line 1, in <module>\n    \
+             f()\n  \
+           File \"<string>\", line 2, in f\n    \
+             return 1 / 0\n\
+         ZeroDivisionError: division by zero"
+    );
+}
+
+#[test]
+fn imports() {
+    let interpreter = Interpreter::with_loader(MemoryLoader::new().with_module("m", "x = 1"));
+
+    interpreter.exec("import m").unwrap();
+    assert!(matches!(
+        interpreter.eval("m.x + 1"),
+        Ok(RuntimeValue::Number(2))
+    ));
+
+    assert!(Interpreter::new().exec("import m").is_err());
+}
+
+#[test]
+fn native_functions() {
+    let interpreter = Interpreter::new();
+
+    interpreter.register_fn("add", |args| {
+        let [a, b] = args.exact()?;
+        match (a.as_int(), b.as_int()) {
+            (Some(a), Some(b)) => Ok(RuntimeValue::Number(a + b)),
+            _ => Err(RuntimeError::type_error("add() takes ints")),
+        }
+    });
+    interpreter.register_fn("repeat", |args| {
+        args.expect_between(1, 2)?;
+        let times = match args.len() {
+            2 => args.int(1)?,
+            _ => 2,
+        };
+        let times = usize::try_from(times)
+            .map_err(|_| RuntimeError::new(ExceptionKind::ValueError, "negative count"))?;
+        Ok(RuntimeValue::String(args.str(0)?.repeat(times)))
+    });
+    interpreter.register_fn("apply", |args| {
+        let function = args.get(0)?.clone();
+        args.call(&function, args.values()[1..].to_vec())
+    });
+
+    assert!(matches!(
+        interpreter.eval("add(1, 2)"),
+        Ok(RuntimeValue::Number(3))
+    ));
+    assert_eq!(
+        interpreter.eval("repeat(\"ab\")").unwrap().to_string(),
+        "abab"
+    );
+    assert!(matches!(
+        interpreter.eval("apply(lambda x: x * 2, 21)"),
+        Ok(RuntimeValue::Number(42))
+    ));
+    assert_eq!(
+        interpreter.eval("add").unwrap().to_string(),
+        "<built-in function add>"
+    );
+
+    let message = |expr: &str| match interpreter.eval(expr) {
+        Err(Error::Runtime(err)) => err.to_string(),
+        result => panic!("{expr} should raise, got {result:?}"),
+    };
+
+    assert_eq!(
+        message("add(1)"),
+        "TypeError: add() takes exactly 2 arguments (1 given)"
+    );
+    assert_eq!(
+        message("repeat(1)"),
+        "TypeError: repeat() argument 1 must be str, not int"
+    );
+    assert_eq!(
+        message("repeat(\"a\", 1, 2)"),
+        "TypeError: repeat() takes from 1 to 2 arguments (3 given)"
+    );
+    assert_eq!(
+        message("add(a=1)"),
+        "TypeError: add() takes no keyword arguments"
+    );
+
+    // Errors of native functions are regular exceptions
+    interpreter
+        .exec("try:\n  repeat(\"a\", -1)\nexcept ValueError as e:\n  caught = e")
+        .unwrap();
+    assert_eq!(
+        interpreter.eval("caught").unwrap().to_string(),
+        "negative count"
+    );
+
+    let Err(Error::Runtime(err)) = interpreter.exec("def f():\n  return repeat(\"a\", -1)\nf()")
+    else {
+        panic!("negative count should raise");
+    };
+    assert_eq!(
+        err.traceback(&*interpreter.sources()).to_string(),
+        "Traceback (most recent call last):\n  \
+           File \"<string>\", line 3, in <module>\n    \
+             f()\n  \
+           File \"<string>\", line 2, in f\n    \
+             return repeat(\"a\", -1)\n\
+         ValueError: negative count"
+    );
+}
+
+#[test]
+fn native_modules() {
+    let interpreter = Interpreter::with_loader(
+        MemoryLoader::new().with_module("script", "import host\nx = host.twice(host.base)"),
+    );
+
+    interpreter.register_module(
+        NativeModule::new("host")
+            .with_value("base", RuntimeValue::Number(21))
+            .with_fn("twice", |args| Ok(RuntimeValue::Number(args.int(0)? * 2))),
+    );
+
+    interpreter
+        .exec("import script\nfrom host import twice")
+        .unwrap();
+    assert!(matches!(
+        interpreter.eval("script.x"),
+        Ok(RuntimeValue::Number(42))
+    ));
+    assert!(matches!(
+        interpreter.eval("twice(2)"),
+        Ok(RuntimeValue::Number(4))
+    ));
+    assert_eq!(
+        interpreter.eval("script.host").unwrap().to_string(),
+        "<module 'host' (native)>"
+    );
+
+    let Err(Error::Runtime(err)) = interpreter.exec("from host import missing") else {
+        panic!("missing names should raise");
+    };
+    assert_eq!(
+        err.message(),
+        "cannot import name 'missing' from 'host' (unknown location)"
+    );
+}
+
+#[test]
+fn conversions() {
+    let interpreter = Interpreter::new();
+
+    let value = interpreter
+        .eval("[(1, \"a\", nil), (2, \"b\", True)]")
+        .unwrap();
+    let items = Vec::<(u8, String, Option<bool>)>::from_value(&value).unwrap();
+    assert_eq!(
+        items,
+        [(1, "a".to_owned(), None), (2, "b".to_owned(), Some(true))]
+    );
+
+    let value = interpreter.eval("{\"a\": [1, 2], \"b\": []}").unwrap();
+    let map = HashMap::<String, Vec<i64>>::from_value(&value).unwrap();
+    assert_eq!(map["a"], [1, 2]);
+    let round_trip = map.clone().into_value().unwrap();
+    assert_eq!(HashMap::from_value(&round_trip), Ok(map));
+    assert_eq!(
+        (1, "x", vec![Some(2.0), None]).into_value().unwrap().repr(),
+        "(1, \"x\", [2, nil])"
+    );
+
+    let value = interpreter.eval("[1, 2, \"3\"]").unwrap();
+    let err = Vec::<i64>::from_value(&value).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError);
+    assert_eq!(err.to_string(), "value[2] must be int, not str");
+
+    let value = interpreter.eval("{\"a\": {\"b\": 300}}").unwrap();
+    let err = HashMap::<String, HashMap<String, u8>>::from_value(&value).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::OverflowError);
+    assert_eq!(
+        err.to_string(),
+        "value[\"a\"][\"b\"] is out of range for u8 (300)"
+    );
+
+    let value = interpreter.eval("(1, 2, 3)").unwrap();
+    let err = <(i64, i64)>::from_value(&value).unwrap_err();
+    assert_eq!(err.to_string(), "value must have 2 items, not 3");
+    assert_eq!(
+        0.5.into_value().unwrap_err().to_string(),
+        "value must be a whole number to become an int (0.5)"
+    );
+}
+
+#[test]
+fn typed_functions() {
+    let interpreter = Interpreter::new();
+
+    fn add(a: i64, b: i64) -> i64 {
+        a + b
+    }
+
+    interpreter.register_typed("add", add);
+    interpreter.register_typed("join", |items: Vec<String>, sep: Option<String>| {
+        items.join(sep.as_deref().unwrap_or(", "))
+    });
+    interpreter.register_typed("halve", |n: i64| match n % 2 {
+        0 => Ok(n / 2),
+        _ => Err(RuntimeError::new(ExceptionKind::ValueError, "odd number")),
+    });
+    interpreter.register_typed("nothing", || ());
+
+    assert!(matches!(
+        interpreter.eval("add(40, 2)"),
+        Ok(RuntimeValue::Number(42))
+    ));
+    assert_eq!(
+        interpreter
+            .eval("join([\"a\", \"b\"], nil)")
+            .unwrap()
+            .to_string(),
+        "a, b"
+    );
+    assert!(matches!(
+        interpreter.eval("nothing()"),
+        Ok(RuntimeValue::Nil)
+    ));
+
+    let message = |source| match interpreter.eval(source) {
+        Err(Error::Runtime(err)) => (err.kind(), err.message()),
+        _ => panic!("{source} should raise"),
+    };
+
+    assert_eq!(
+        message("add(1, \"2\")"),
+        (
+            Some(ExceptionKind::TypeError),
+            "add() argument 2 must be int, not str".to_owned()
+        )
+    );
+    assert_eq!(
+        message("add(1)"),
+        (
+            Some(ExceptionKind::TypeError),
+            "add() takes exactly 2 arguments (1 given)".to_owned()
+        )
+    );
+    assert_eq!(
+        message("join([\"a\", 1], \"-\")"),
+        (
+            Some(ExceptionKind::TypeError),
+            "join() argument 1[1] must be str, not int".to_owned()
+        )
+    );
+    assert_eq!(
+        message("halve(3)"),
+        (Some(ExceptionKind::ValueError), "odd number".to_owned())
+    );
+}
+
+#[derive(ScriptClass)]
+struct Counter {
+    count: i64,
+    #[script(readonly)]
+    label: String,
+    #[script(skip)]
+    #[allow(dead_code)]
+    secret: Vec<u8>,
+}
+
+#[script_methods]
+impl Counter {
+    fn new(label: &str) -> Self {
+        Self {
+            count: 0,
+            label: label.to_owned(),
+            secret: Vec::new(),
+        }
+    }
+
+    fn increment(&mut self, by: Option<i64>) -> i64 {
+        self.count += by.unwrap_or(1);
+        self.count
+    }
+
+    fn describe(&self) -> String {
+        format!("{}: {}", self.label, self.count)
+    }
+
+    /// Calls `callback` while the counter is borrowed
+    fn visit(&self, callback: RuntimeValue, args: NativeArgs) -> RuntimeResult<RuntimeValue> {
+        args.call(&callback, Vec::new())
+    }
+
+    fn update(&mut self, callback: RuntimeValue, args: NativeArgs) -> RuntimeResult<RuntimeValue> {
+        args.call(&callback, Vec::new())
+    }
+
+    #[script(skip)]
+    #[allow(dead_code)]
+    fn reset(&mut self) {
+        self.count = 0;
+    }
+}
+
+#[test]
+fn script_classes() {
+    let interpreter = Interpreter::new();
+
+    interpreter.set_global("c", Counter::new("clicks").into_value().unwrap());
+    interpreter
+        .exec("c.increment(nil)\nc.increment(2)\nc.count = c.count * 10")
+        .unwrap();
+
+    assert!(matches!(
+        interpreter.eval("c.count"),
+        Ok(RuntimeValue::Number(30))
+    ));
+    assert_eq!(
+        interpreter.eval("c.describe()").unwrap().to_string(),
+        "clicks: 30"
+    );
+    assert_eq!(
+        interpreter.eval("c").unwrap().to_string(),
+        "<Counter object>"
+    );
+
+    let Some(RuntimeValue::NativeObject(object)) = interpreter.get_global("c") else {
+        panic!("c should be a native object");
+    };
+    assert_eq!(object.borrow::<Counter>().unwrap().count, 30);
+    object.borrow_mut::<Counter>().unwrap().count = 5;
+    assert!(matches!(
+        interpreter.eval("c.count"),
+        Ok(RuntimeValue::Number(5))
+    ));
+
+    let message = |source| match interpreter.exec(source) {
+        Err(Error::Runtime(err)) => (err.kind(), err.message()),
+        _ => panic!("{source} should raise"),
+    };
+
+    assert_eq!(
+        message("c.count = \"a\""),
+        (
+            Some(ExceptionKind::TypeError),
+            "Counter.count must be int, not str".to_owned()
+        )
+    );
+    assert_eq!(
+        message("c.label = \"x\""),
+        (
+            Some(ExceptionKind::AttributeError),
+            "Counter.label is read-only".to_owned()
+        )
+    );
+    assert_eq!(
+        message("c.secret"),
+        (
+            Some(ExceptionKind::AttributeError),
+            "'Counter' object has no attribute 'secret'".to_owned()
+        )
+    );
+    assert_eq!(
+        message("c.reset()"),
+        (
+            Some(ExceptionKind::AttributeError),
+            "'Counter' object has no attribute 'reset'".to_owned()
+        )
+    );
+    assert_eq!(
+        message("c.increment(1, 2)"),
+        (
+            Some(ExceptionKind::TypeError),
+            "increment() takes exactly 1 argument (2 given)".to_owned()
+        )
+    );
+
+    // Shared borrows can overlap, a mutable one excludes everything else
+    assert_eq!(
+        interpreter
+            .eval("c.visit(lambda: c.describe() + \" \" + c.label)")
+            .unwrap()
+            .to_string(),
+        "clicks: 5 clicks"
+    );
+    assert_eq!(
+        message("c.update(lambda: c.count)"),
+        (
+            Some(ExceptionKind::RuntimeError),
+            "Counter object is already mutably borrowed".to_owned()
+        )
+    );
+    assert_eq!(
+        message("c.visit(lambda: c.increment(nil))"),
+        (
+            Some(ExceptionKind::RuntimeError),
+            "Counter object is already borrowed".to_owned()
+        )
+    );
+    assert!(matches!(
+        interpreter.eval("c.update(lambda: 1)"),
+        Ok(RuntimeValue::Number(1))
+    ));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde() {
+    use serde::Deserialize;
+
+    use crate::runtime::serde::from_value;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Fast,
+        Safe { retries: u8 },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Server {
+        host: String,
+        port: u16,
+        tags: Vec<String>,
+        backup: Option<(String, u16)>,
+        mode: Mode,
+    }
+
+    let interpreter = Interpreter::new();
+    interpreter
+        .exec(concat!(
+            "class Options:\n",
+            "  def __init__(self, verbose):\n",
+            "    self.verbose = verbose\n",
+            "def helper():\n",
+            "  pass\n",
+            "host = \"localhost\"\n",
+            "port = 8000 + 80\n",
+            "tags = [\"a\", \"b\"]\n",
+            "backup = nil\n",
+            "mode = {\"safe\": {\"retries\": 3}}\n",
+            "options = Options(True)\n",
+        ))
+        .unwrap();
+
+    let server = interpreter.deserialize_globals::<Server>().unwrap();
+    assert_eq!(
+        server,
+        Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+            tags: vec!["a".to_owned(), "b".to_owned()],
+            backup: None,
+            mode: Mode::Safe { retries: 3 },
+        }
+    );
+
+    let options = interpreter.get_global("options").unwrap();
+    assert_eq!(
+        serde_json::to_string(&options).unwrap(),
+        r#"{"verbose":true}"#
+    );
+    assert_eq!(
+        serde_json::to_string(
+            &interpreter
+                .eval("{1: (nil, \"x\"), \"k\": {2, 3}}")
+                .unwrap()
+        )
+        .unwrap(),
+        r#"{"1":[null,"x"],"k":[2,3]}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&interpreter.get_global("helper").unwrap())
+            .unwrap_err()
+            .to_string(),
+        "Object of type function is not serializable"
+    );
+
+    let value: RuntimeValue = serde_json::from_str(r#"{"a": [1, 2.0, null], "b": true}"#).unwrap();
+    assert_eq!(value.repr(), r#"{"a": [1, 2, nil], "b": True}"#);
+    assert!(serde_json::from_str::<RuntimeValue>("1.5").is_err());
+    assert!(serde_json::from_str::<RuntimeValue>("9.223372036854775808e18").is_err());
+    assert!(matches!(
+        serde_json::from_str("-9.223372036854775808e18"),
+        Ok(RuntimeValue::Number(i64::MIN))
+    ));
+
+    let value = interpreter.eval("[\"fast\", \"slow\"]").unwrap();
+    let err = from_value::<Vec<Mode>>(&value).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    assert_eq!(
+        err.to_string(),
+        "value[1] is invalid: unknown variant `slow`, expected `fast` or `safe`"
+    );
+
+    interpreter.exec("port = \"80\"").unwrap();
+    let err = interpreter.deserialize_globals::<Server>().unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError);
+    assert_eq!(
+        err.to_string(),
+        "value[\"port\"] must be u16, not string \"80\""
+    );
+
+    interpreter.exec("del port").unwrap();
+    assert_eq!(
+        interpreter
+            .deserialize_globals::<Server>()
+            .unwrap_err()
+            .to_string(),
+        "value is missing field 'port'"
+    );
+}
+
+#[test]
+fn limits() {
+    let interpreter = Interpreter::new();
+    let limit = |source| match interpreter.exec(source) {
+        Err(Error::Limit(err)) => (err.limit(), err.message()),
+        result => panic!("{source} should go over a limit, got {result:?}"),
+    };
+
+    interpreter.set_limits(Limits {
+        max_steps: Some(1000),
+        ..Limits::default()
+    });
+    assert_eq!(
+        limit("while True:\n  pass"),
+        (
+            Some(Limit::Steps),
+            "script exceeded the limit of 1000 steps".to_owned()
+        )
+    );
+
+    // Neither `except` nor `finally` can stop the script from being aborted
+    let Err(err) = interpreter.exec(
+        "try:\n  while True:\n    pass\nexcept BaseException:\n  caught = True\n\
+         finally:\n  cleaned = True",
+    ) else {
+        panic!("the loop should be aborted");
+    };
+    assert!(interpreter.get_global("caught").is_none());
+    assert!(interpreter.get_global("cleaned").is_none());
+    assert_eq!(Diagnostic::from(err).code, codes::LIMIT_EXCEEDED);
+
+    // Every snippet gets its own budget
+    assert!(matches!(
+        interpreter.eval("1 + 1"),
+        Ok(RuntimeValue::Number(2))
+    ));
+
+    interpreter.set_limits(Limits {
+        max_call_depth: Some(50),
+        ..Limits::default()
+    });
+    interpreter
+        .exec("def down(n):\n  return 0 if n == 0 else down(n - 1)\ndown(40)")
+        .unwrap();
+    assert_eq!(
+        limit("down(60)"),
+        (
+            Some(Limit::CallDepth),
+            "script exceeded the limit of 50 nested calls".to_owned()
+        )
+    );
+    interpreter.exec("down(40)").unwrap();
+
+    // Generators count as calls while they run
+    assert_eq!(
+        limit("def g():\n  yield from g()\nnext(g())").0,
+        Some(Limit::CallDepth)
+    );
+
+    interpreter.set_limits(Limits {
+        max_allocated_bytes: Some(1 << 20),
+        ..Limits::default()
+    });
+    assert_eq!(
+        limit("s = \"x\" * 1000000000"),
+        (
+            Some(Limit::Memory),
+            "script exceeded the limit of 1048576 allocated bytes".to_owned()
+        )
+    );
+    assert_eq!(
+        limit("s = \"\"\nwhile True:\n  s = s + \"abcdefgh\"").0,
+        Some(Limit::Memory)
+    );
+    assert_eq!(
+        limit("xs = [1]\nwhile True:\n  xs = xs + xs").0,
+        Some(Limit::Memory)
+    );
+    // Concatenations are charged before the result is built
+    assert_eq!(
+        limit("s = \"x\" * 600000\nt = s + s").0,
+        Some(Limit::Memory)
+    );
+    assert_eq!(
+        limit("xs = (1,) * 20000\nys = xs + xs").0,
+        Some(Limit::Memory)
+    );
+    // Spreading `*args` doesn't turn the limit into a TypeError
+    assert_eq!(
+        limit(
+            "def f(*args):\n  pass\ntry:\n  f(*(\"x\" * 1000000000 for _ in (1,)))\n\
+             except TypeError:\n  pass"
+        )
+        .0,
+        Some(Limit::Memory)
+    );
+
+    let interrupt = Arc::new(AtomicBool::new(true));
+    interpreter.set_limits(Limits {
+        interrupt: Some(interrupt),
+        ..Limits::default()
+    });
+    assert_eq!(
+        limit("while True:\n  pass"),
+        (
+            Some(Limit::Interrupted),
+            "script was interrupted".to_owned()
+        )
+    );
+
+    interpreter.set_limits(Limits {
+        timeout: Some(Duration::from_millis(20)),
+        ..Limits::default()
+    });
+    assert_eq!(limit("while True:\n  pass").0, Some(Limit::Timeout));
+
+    // Without limits huge strings still can't overflow
+    interpreter.set_limits(Limits::default());
+    interpreter
+        .exec(
+            "def f(n):\n  return f(n + 1)\ndef g():\n  yield from g()\n\
+             try:\n  f(0)\nexcept RecursionError as e:\n  calls = str(e)\n\
+             try:\n  next(g())\nexcept RecursionError as e:\n  generators = str(e)",
+        )
+        .unwrap();
+    assert_eq!(
+        interpreter.eval("(calls, generators)").unwrap().repr(),
+        "(\"maximum recursion depth exceeded\", \"maximum recursion depth exceeded\")"
+    );
+
+    let Err(Error::Runtime(err)) = interpreter.eval("\"ab\" * 9223372036854775807") else {
+        panic!("the repetition should overflow");
+    };
+    assert_eq!(err.kind(), Some(ExceptionKind::OverflowError));
+    assert_eq!(err.message(), "repeated string is too long");
+}
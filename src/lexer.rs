@@ -8,35 +8,41 @@ pub mod utils;
 
 use std::collections::VecDeque;
 
-use source::{LexerResult, SourceLexer, SourceLexerExt};
-use span::IntoSpan;
+use source::{LexerError, LexerResult, SourceLexer, SourceLexerExt};
 use token::{SpannedToken, Token, TokenKeyword, TokenLiteral, TokenPunctuation};
 use utils::eat_spaces;
-use winnow::Parser;
 use winnow::combinator::{alt, delimited, peek};
 use winnow::stream::AsChar;
 use winnow::token::{any, take_while};
+use winnow::{LocatingSlice, Parser};
+
+use crate::diagnostic::Diagnostic;
+use crate::source_map::SourceFile;
 
 pub struct Lexer;
 
 impl Lexer {
     #[allow(clippy::should_implement_trait)]
-    pub fn from_str(input: &str) -> LexerResult<'_, VecDeque<SpannedToken>> {
-        let mut input = SourceLexer::new(input);
+    pub fn from_str(input: &str) -> Result<VecDeque<SpannedToken>, Diagnostic> {
+        Self::from_source(&SourceFile::anonymous(input))
+    }
+
+    pub fn from_source(file: &SourceFile) -> Result<VecDeque<SpannedToken>, Diagnostic> {
+        let mut input = SourceLexer {
+            input: LocatingSlice::new(file.contents()),
+            state: file.id(),
+        };
         let mut tokens = VecDeque::new();
 
-        while Self::next_token(&mut tokens, &mut input).unwrap_or_else(|err| {
-            print!("{err}");
-            std::process::exit(1)
-        }) {}
+        while Self::next_token(&mut tokens, &mut input)? {}
 
         Ok(tokens)
     }
 
-    fn next_token<'i>(
+    fn next_token(
         tokens: &mut VecDeque<SpannedToken>,
-        input: &mut SourceLexer<'i>,
-    ) -> LexerResult<'i, bool> {
+        input: &mut SourceLexer<'_>,
+    ) -> LexerResult<bool> {
         let Ok(char) = peek(any::<_, ()>).parse_next(input) else {
             return Ok(false);
         };
@@ -64,19 +70,33 @@ impl Lexer {
             "!".value(TokenPunctuation::Bang),
             ":".value(TokenPunctuation::Colon),
             ",".value(TokenPunctuation::Comma),
+            ".".value(TokenPunctuation::Dot),
             "==".value(TokenPunctuation::EqualEqual),
             "=".value(TokenPunctuation::Equal),
+            ">=".value(TokenPunctuation::GreaterEqual),
+            ">".value(TokenPunctuation::Greater),
             "  ".value(TokenPunctuation::Indentation),
+            "{".value(TokenPunctuation::LeftBrace),
+            "[".value(TokenPunctuation::LeftBracket),
+            "(".value(TokenPunctuation::LeftParen),
+            "<=".value(TokenPunctuation::LessEqual),
+            "<".value(TokenPunctuation::Less),
             "-".value(TokenPunctuation::Minus),
             "\n".value(TokenPunctuation::Newline),
+            "|".value(TokenPunctuation::Pipe),
+            "}".value(TokenPunctuation::RightBrace),
+            "]".value(TokenPunctuation::RightBracket),
+            ")".value(TokenPunctuation::RightParen),
+            ";".value(TokenPunctuation::Semicolon),
             "+".value(TokenPunctuation::Plus),
             "/".value(TokenPunctuation::Slash),
+            "**".value(TokenPunctuation::StarStar),
             "*".value(TokenPunctuation::Star),
         ])
         .with_span()
-        .map(IntoSpan::into_span)
         .parse_next(input)
-        .unwrap_or_else(|()| input.error(format!("Unexpected char: {char:#?}",)));
+        .map_err(|()| input.error(format!("Unexpected char: {char:#?}",)))?;
+        let span = input.span_of(span);
 
         // Don't eat indentation
         if token != TokenPunctuation::Newline && token != TokenPunctuation::Indentation {
@@ -91,24 +111,48 @@ impl Lexer {
         Ok(true)
     }
 
-    fn token_ident<'i>(
+    fn token_ident(
         tokens: &mut VecDeque<SpannedToken>,
-        input: &mut SourceLexer<'i>,
-    ) -> LexerResult<'i> {
+        input: &mut SourceLexer<'_>,
+    ) -> LexerResult {
         let (ident, span) = take_while(1.., |c: char| c.is_alphanumeric() || c == '_')
             .with_span()
-            .map(IntoSpan::into_span)
             .parse_next(input)?;
+        let span = input.span_of(span);
 
         let token = match ident {
             "nil" => Token::Literal(TokenLiteral::Nil),
             "True" => Token::Literal(TokenLiteral::Bool(true)),
             "False" => Token::Literal(TokenLiteral::Bool(false)),
 
+            "and" => Token::Keyword(TokenKeyword::And),
+            "as" => Token::Keyword(TokenKeyword::As),
+            "assert" => Token::Keyword(TokenKeyword::Assert),
+            "break" => Token::Keyword(TokenKeyword::Break),
+            "class" => Token::Keyword(TokenKeyword::Class),
+            "continue" => Token::Keyword(TokenKeyword::Continue),
+            "def" => Token::Keyword(TokenKeyword::Def),
+            "del" => Token::Keyword(TokenKeyword::Del),
             "elif" => Token::Keyword(TokenKeyword::Elif),
             "else" => Token::Keyword(TokenKeyword::Else),
+            "except" => Token::Keyword(TokenKeyword::Except),
+            "finally" => Token::Keyword(TokenKeyword::Finally),
+            "for" => Token::Keyword(TokenKeyword::For),
+            "from" => Token::Keyword(TokenKeyword::From),
             "global" => Token::Keyword(TokenKeyword::Global),
             "if" => Token::Keyword(TokenKeyword::If),
+            "import" => Token::Keyword(TokenKeyword::Import),
+            "in" => Token::Keyword(TokenKeyword::In),
+            "lambda" => Token::Keyword(TokenKeyword::Lambda),
+            "nonlocal" => Token::Keyword(TokenKeyword::Nonlocal),
+            "not" => Token::Keyword(TokenKeyword::Not),
+            "or" => Token::Keyword(TokenKeyword::Or),
+            "pass" => Token::Keyword(TokenKeyword::Pass),
+            "raise" => Token::Keyword(TokenKeyword::Raise),
+            "return" => Token::Keyword(TokenKeyword::Return),
+            "try" => Token::Keyword(TokenKeyword::Try),
+            "while" => Token::Keyword(TokenKeyword::While),
+            "yield" => Token::Keyword(TokenKeyword::Yield),
 
             _ => Token::Ident(ident.to_owned()),
         };
@@ -118,31 +162,34 @@ impl Lexer {
         eat_spaces(input)
     }
 
-    fn token_number<'i>(
+    fn token_number(
         tokens: &mut VecDeque<SpannedToken>,
-        input: &mut SourceLexer<'i>,
-    ) -> LexerResult<'i> {
+        input: &mut SourceLexer<'_>,
+    ) -> LexerResult {
         let (num, span) = take_while(1.., AsChar::is_dec_digit)
             .with_span()
-            .map(IntoSpan::into_span)
             .parse_next(input)?;
+        let span = input.span_of(span);
+        let num = num.parse().map_err(|_| {
+            LexerError::invalid_literal(span, format!("Integer literal is too large: {num}"))
+        })?;
 
         tokens.push_back(SpannedToken {
             span,
-            token: Token::Literal(TokenLiteral::Number(num.parse().unwrap())),
+            token: Token::Literal(TokenLiteral::Number(num)),
         });
 
         eat_spaces(input)
     }
 
-    fn token_string<'i>(
+    fn token_string(
         tokens: &mut VecDeque<SpannedToken>,
-        input: &mut SourceLexer<'i>,
-    ) -> LexerResult<'i> {
+        input: &mut SourceLexer<'_>,
+    ) -> LexerResult {
         let (str, span) = delimited('"', take_while(0.., |c| c != '"'), '"')
             .with_span()
-            .map(IntoSpan::into_span)
             .parse_next(input)?;
+        let span = input.span_of(span);
 
         tokens.push_back(SpannedToken {
             span,
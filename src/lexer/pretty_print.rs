@@ -70,10 +70,34 @@ impl fmt::Display for TokenKeyword {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(KEYWORD)?;
         match self {
+            TokenKeyword::And => f.write_str("and")?,
+            TokenKeyword::As => f.write_str("as")?,
+            TokenKeyword::Assert => f.write_str("assert")?,
+            TokenKeyword::Break => f.write_str("break")?,
+            TokenKeyword::Class => f.write_str("class")?,
+            TokenKeyword::Continue => f.write_str("continue")?,
+            TokenKeyword::Def => f.write_str("def")?,
+            TokenKeyword::Del => f.write_str("del")?,
             TokenKeyword::Elif => f.write_str("elif")?,
             TokenKeyword::Else => f.write_str("else")?,
+            TokenKeyword::Except => f.write_str("except")?,
+            TokenKeyword::Finally => f.write_str("finally")?,
+            TokenKeyword::For => f.write_str("for")?,
+            TokenKeyword::From => f.write_str("from")?,
             TokenKeyword::Global => f.write_str("global")?,
+            TokenKeyword::Lambda => f.write_str("lambda")?,
+            TokenKeyword::Nonlocal => f.write_str("nonlocal")?,
             TokenKeyword::If => f.write_str("if")?,
+            TokenKeyword::Import => f.write_str("import")?,
+            TokenKeyword::In => f.write_str("in")?,
+            TokenKeyword::Not => f.write_str("not")?,
+            TokenKeyword::Or => f.write_str("or")?,
+            TokenKeyword::Pass => f.write_str("pass")?,
+            TokenKeyword::Raise => f.write_str("raise")?,
+            TokenKeyword::Return => f.write_str("return")?,
+            TokenKeyword::Try => f.write_str("try")?,
+            TokenKeyword::While => f.write_str("while")?,
+            TokenKeyword::Yield => f.write_str("yield")?,
         }
         f.write_str(R)
     }
@@ -100,13 +124,27 @@ impl fmt::Display for TokenPunctuation {
             TokenPunctuation::BangEqual => f.write_str("!="),
             TokenPunctuation::Colon => f.write_char(':'),
             TokenPunctuation::Comma => f.write_char(','),
+            TokenPunctuation::Dot => f.write_char('.'),
             TokenPunctuation::Equal => f.write_char('='),
             TokenPunctuation::EqualEqual => f.write_str("=="),
+            TokenPunctuation::Greater => f.write_char('>'),
+            TokenPunctuation::GreaterEqual => f.write_str(">="),
             TokenPunctuation::Indentation => f.write_str("'  '"),
+            TokenPunctuation::LeftBrace => f.write_char('{'),
+            TokenPunctuation::LeftBracket => f.write_char('['),
+            TokenPunctuation::LeftParen => f.write_char('('),
+            TokenPunctuation::Less => f.write_char('<'),
+            TokenPunctuation::LessEqual => f.write_str("<="),
             TokenPunctuation::Minus => f.write_char('-'),
             TokenPunctuation::Newline => f.write_str("\\n"),
+            TokenPunctuation::Pipe => f.write_char('|'),
+            TokenPunctuation::RightBrace => f.write_char('}'),
+            TokenPunctuation::RightBracket => f.write_char(']'),
+            TokenPunctuation::RightParen => f.write_char(')'),
+            TokenPunctuation::Semicolon => f.write_char(';'),
             TokenPunctuation::Slash => f.write_char('/'),
             TokenPunctuation::Star => f.write_char('*'),
+            TokenPunctuation::StarStar => f.write_str("**"),
         }
     }
 }
@@ -1,65 +1,76 @@
 use core::fmt;
-use std::backtrace::Backtrace;
+use std::ops::Range;
 
-use ariadne::{Label, Report, ReportKind, Source};
 use winnow::LocatingSlice;
 use winnow::error::{AddContext, ParserError};
-use winnow::stream::{Location, Stream};
+use winnow::stream::{Location, Stateful, Stream};
 
 use super::span::Span;
+use crate::diagnostic::{Diagnostic, codes};
+use crate::source_map::SourceId;
 
-pub type SourceLexer<'i> = LocatingSlice<&'i str>;
-pub type LexerResult<'i, T = ()> = Result<T, LexerError<'i>>;
+pub type SourceLexer<'i> = Stateful<LocatingSlice<&'i str>, SourceId>;
+pub type LexerResult<T = ()> = Result<T, LexerError>;
 
 #[derive(Debug)]
-pub struct LexerError<'i> {
-    base: &'i str,
+pub struct LexerError {
     span: Span,
+    code: &'static str,
+    message: Option<String>,
     labels: Vec<(Span, String)>,
-    backtrace: Backtrace,
 }
 
 pub trait SourceLexerExt<'i> {
-    fn base(&self) -> &'i str;
     fn span(&self) -> Span;
-    fn error(&self, msg: impl fmt::Display) -> !;
+    fn span_of(&self, range: Range<usize>) -> Span;
+    fn error(&self, msg: impl fmt::Display) -> LexerError;
 }
 
 impl<'i> SourceLexerExt<'i> for SourceLexer<'i> {
-    fn base(&self) -> &'i str {
-        let mut base = *self;
-        base.reset_to_start();
-        *base
+    fn span(&self) -> Span {
+        let from = self.current_token_start();
+        let len = self.input.chars().next().map_or(0, char::len_utf8);
+
+        Span::new(self.state, from..from + len)
     }
 
-    fn span(&self) -> Span {
-        Span::char(self.current_token_start())
+    fn span_of(&self, range: Range<usize>) -> Span {
+        Span::new(self.state, range)
+    }
+
+    fn error(&self, msg: impl fmt::Display) -> LexerError {
+        let mut error = LexerError::from_input(self);
+        error.message = Some(msg.to_string());
+        error.labels.push((self.span(), msg.to_string()));
+        error
+    }
+}
+
+impl LexerError {
+    /// Literal at `span` that reads fine but has no value, like a too large number
+    pub fn invalid_literal(span: Span, msg: impl fmt::Display) -> Self {
+        Self {
+            span,
+            code: codes::INVALID_LITERAL,
+            message: Some(msg.to_string()),
+            labels: vec![(span, msg.to_string())],
+        }
     }
 
-    fn error(&self, msg: impl fmt::Display) -> ! {
-        _ = Report::build(ReportKind::Error, self.span())
-            .with_message(&msg)
-            .with_label(
-                Label::new(self.span())
-                    .with_message(msg)
-                    .with_color(ariadne::Color::BrightRed),
-            )
-            .finish()
-            .eprint(Source::from(self.base()));
-
-        std::process::exit(1);
+    pub fn span(&self) -> Span {
+        self.span
     }
 }
 
-impl<'i> ParserError<SourceLexer<'i>> for LexerError<'i> {
+impl<'i> ParserError<SourceLexer<'i>> for LexerError {
     type Inner = Self;
 
     fn from_input(input: &SourceLexer<'i>) -> Self {
         Self {
-            base: input.base(),
             span: input.span(),
+            code: codes::UNEXPECTED_CHAR,
+            message: None,
             labels: Vec::new(),
-            backtrace: Backtrace::force_capture(),
         }
     }
 
@@ -68,7 +79,7 @@ impl<'i> ParserError<SourceLexer<'i>> for LexerError<'i> {
     }
 }
 
-impl<'i, C: ToString> AddContext<SourceLexer<'i>, C> for LexerError<'i> {
+impl<'i, C: ToString> AddContext<SourceLexer<'i>, C> for LexerError {
     fn add_context(
         mut self,
         input: &SourceLexer<'i>,
@@ -80,49 +91,26 @@ impl<'i, C: ToString> AddContext<SourceLexer<'i>, C> for LexerError<'i> {
     }
 }
 
-impl fmt::Display for LexerError<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        _ = Report::build(ReportKind::Error, self.span)
-            .with_labels(
-                self.labels
-                    .iter()
-                    .map(|(span, msg)| Label::new(*span).with_message(msg)),
-            )
-            .finish()
-            .eprint(Source::from(self.base));
-
-        let backtrace = self.backtrace.to_string();
+impl From<LexerError> for Diagnostic {
+    fn from(error: LexerError) -> Self {
+        let message = error.message.as_deref().unwrap_or("Invalid token");
+        let diagnostic = Diagnostic::error(error.code, message);
 
-        let mut backtrace = backtrace.split('\n');
-
-        writeln!(f, "backtrace:")?;
-        while let Some(line) = backtrace.next() {
-            let Some(colon_idx) = line.find(':') else {
-                break;
-            };
-
-            let module = &line[colon_idx + 2..];
-            let Some(file_line) = backtrace.next() else {
-                break;
-            };
-
-            if module.starts_with("std") || module.starts_with("core") {
-                continue;
-            }
-
-            let Some(at_idx) = file_line.find("at ") else {
-                break;
-            };
-
-            let file = &file_line[at_idx + 3..];
-
-            if !file.starts_with('.') {
-                continue;
-            }
-
-            writeln!(f, "  \x1b[31mat \x1b[33m{file} \x1b[31m({module})\x1b[0m")?;
+        if error.labels.is_empty() {
+            return diagnostic.with_span(error.span);
         }
 
-        Ok(())
+        error
+            .labels
+            .into_iter()
+            .fold(diagnostic, |diagnostic, (span, msg)| {
+                diagnostic.with_label(span, msg)
+            })
+    }
+}
+
+impl fmt::Display for LexerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message.as_deref().unwrap_or("Invalid token"))
     }
 }
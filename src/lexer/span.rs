@@ -1,20 +1,19 @@
 use std::ops::Range;
 
+use crate::source_map::SourceId;
+
 #[derive(Clone, Copy, Debug)]
 pub struct Span {
+    pub source: SourceId,
     pub from: usize,
     pub to: usize,
 }
 
-pub trait IntoSpan<V> {
-    fn into_span(self) -> V;
-}
-
 impl ariadne::Span for Span {
-    type SourceId = ();
+    type SourceId = SourceId;
 
     fn source(&self) -> &Self::SourceId {
-        &()
+        &self.source
     }
 
     fn start(&self) -> usize {
@@ -26,28 +25,35 @@ impl ariadne::Span for Span {
     }
 }
 
-impl From<Range<usize>> for Span {
-    fn from(value: Range<usize>) -> Self {
+impl Span {
+    pub const ZERO: Self = Self {
+        source: SourceId::ANONYMOUS,
+        from: 0,
+        to: 0,
+    };
+
+    pub const fn new(source: SourceId, range: Range<usize>) -> Self {
         Self {
-            from: value.start,
-            to: value.end,
+            source,
+            from: range.start,
+            to: range.end,
         }
     }
-}
 
-impl Span {
-    pub const ZERO: Self = Self { from: 0, to: 0 };
-
-    pub const fn char(offset: usize) -> Self {
+    pub const fn char(source: SourceId, offset: usize) -> Self {
         Self {
+            source,
             from: offset,
             to: offset + 1,
         }
     }
-}
 
-impl<T> IntoSpan<(T, Span)> for (T, Range<usize>) {
-    fn into_span(self) -> (T, Span) {
-        (self.0, Span::from(self.1))
+    /// Smallest span that covers both `self` and `other`
+    pub fn join(self, other: Span) -> Self {
+        Self {
+            source: self.source,
+            from: self.from.min(other.from),
+            to: self.to.max(other.to),
+        }
     }
 }
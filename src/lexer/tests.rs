@@ -1,3 +1,4 @@
+use crate::diagnostic::codes;
 use crate::lexer::Lexer;
 use crate::lexer::utils::{T, ident, kw, literal};
 
@@ -49,3 +50,38 @@ fn conditional() {
         ]
     )
 }
+
+#[test]
+fn comparison() {
+    let res = Lexer::from_str("f(a) <= b and not c").unwrap();
+    assert_eq!(
+        Vec::from(res),
+        &[
+            ident!(f),
+            T!(LeftParen),
+            ident!(a),
+            T!(RightParen),
+            T!(LessEqual),
+            ident!(b),
+            kw!(And),
+            kw!(Not),
+            ident!(c)
+        ]
+    )
+}
+
+#[test]
+fn number_too_large() {
+    assert_eq!(
+        Vec::from(Lexer::from_str("9223372036854775807").unwrap()),
+        &[literal!(9223372036854775807)]
+    );
+
+    let err = Lexer::from_str("a = 99999999999999999999").unwrap_err();
+    assert_eq!(err.code, codes::INVALID_LITERAL);
+    assert_eq!(
+        err.message,
+        "Integer literal is too large: 99999999999999999999"
+    );
+    assert_eq!((err.labels[0].span.from, err.labels[0].span.to), (4, 24));
+}
@@ -18,10 +18,34 @@ pub enum Token {
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum TokenKeyword {
+    And,
+    As,
+    Assert,
+    Break,
+    Class,
+    Continue,
+    Def,
+    Del,
     Elif,
     Else,
+    Except,
+    Finally,
+    For,
+    From,
     Global,
     If,
+    Import,
+    In,
+    Lambda,
+    Nonlocal,
+    Not,
+    Or,
+    Pass,
+    Raise,
+    Return,
+    Try,
+    While,
+    Yield,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -44,20 +68,48 @@ pub enum TokenPunctuation {
     Colon,
     /// ,
     Comma,
+    /// .
+    Dot,
     /// =
     Equal,
     /// ==
     EqualEqual,
+    /// >
+    Greater,
+    /// >=
+    GreaterEqual,
     /// <Tab>
     Indentation,
+    /// {
+    LeftBrace,
+    /// [
+    LeftBracket,
+    /// (
+    LeftParen,
+    /// <
+    Less,
+    /// <=
+    LessEqual,
     /// -
     Minus,
     /// \n
     Newline,
+    /// |
+    Pipe,
+    /// }
+    RightBrace,
+    /// ]
+    RightBracket,
+    /// )
+    RightParen,
+    /// ;
+    Semicolon,
     /// /
     Slash,
     /// *
     Star,
+    /// **
+    StarStar,
 }
 
 impl Token {
@@ -5,7 +5,7 @@ use winnow::token::take_while;
 use super::source::{LexerResult, SourceLexer};
 use super::token::TokenLiteral;
 
-pub fn eat_spaces<'i>(input: &mut SourceLexer<'i>) -> LexerResult<'i> {
+pub fn eat_spaces(input: &mut SourceLexer<'_>) -> LexerResult {
     take_while(0.., AsChar::is_space).parse_next(input)?;
     Ok(())
 }
@@ -18,11 +18,16 @@ macro_rules! ident {
     (@raw $i:literal) => {
         String::from($i)
     };
+    (@name $i:ident) => {
+        $crate::ast::node::SpannedName::from(ident!(@raw $i))
+    };
     (@ast $i:ident) => {
-        $crate::ast::node::AstExpr::Ident(String::from(stringify!($i)))
+        $crate::ast::node::SpannedExpr::from($crate::ast::node::AstExpr::Ident(String::from(
+            stringify!($i),
+        )))
     };
     (@ast $i:literal) => {
-        $crate::ast::node::AstExpr::Ident(String::from($i))
+        $crate::ast::node::SpannedExpr::from($crate::ast::node::AstExpr::Ident(String::from($i)))
     };
     ($i:ident) => {
         $crate::lexer::token::Token::Ident(ident!(@raw $i))
@@ -38,7 +43,9 @@ macro_rules! literal {
         Into::<$crate::lexer::token::TokenLiteral>::into($i)
     };
     (@ast $i:expr) => {
-        $crate::ast::node::AstExpr::Literal(Into::<$crate::lexer::token::TokenLiteral>::into($i))
+        $crate::ast::node::SpannedExpr::from($crate::ast::node::AstExpr::Literal(Into::<
+            $crate::lexer::token::TokenLiteral,
+        >::into($i)))
     };
     ($i:expr) => {
         $crate::lexer::token::Token::Literal(Into::<$crate::lexer::token::TokenLiteral>::into($i))
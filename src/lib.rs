@@ -1,5 +1,16 @@
+// Lets the code generated by the derive macros name this crate from inside it
+extern crate self as ruspy;
+
 pub mod ast;
 pub mod bin;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod diagnostic;
+pub mod expression;
+pub mod interpreter;
 pub mod lexer;
 mod pretty_print;
 pub mod runtime;
+pub mod source_map;
+
+pub use ruspy_macros::{ScriptClass, script_methods};
@@ -1,60 +1,328 @@
+pub mod builtins;
+pub mod class;
+pub mod convert;
+pub mod dict;
+pub mod error;
+pub mod function;
+pub mod generator;
+pub mod limits;
+pub mod loader;
+pub mod module;
+pub mod native;
+mod operators;
+mod pattern;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod set;
+#[cfg(test)]
+mod tests;
 pub mod value;
 
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::ptr::NonNull;
 use std::rc::Rc;
 
-use value::{AsBool, AsNumber, AsString, RuntimeValue, RuntimeVariable};
+use builtins::Builtin;
+use class::{RuntimeClass, RuntimeObject};
+use dict::RuntimeDict;
+use error::{ExceptionKind, RuntimeError, RuntimeResult, new_exception};
+use function::{RuntimeFunction, RuntimeMethod};
+use generator::{Comprehension, GeneratorYielder, Resume, Resumed, RuntimeGenerator};
+use limits::Budget;
+use module::{Importer, MAIN_MODULE};
+use set::RuntimeSet;
+use value::{KeywordArgs, RuntimeValue, RuntimeVariable};
 
-use crate::ast::node::{AstBinaryOp, AstExpr, AstScope, AstStatement, AstUnaryOp};
+use crate::ast::node::{
+    AstBinaryOp, AstComprehension, AstComprehensionKind, AstExceptHandler, AstExpr, AstFunction,
+    AstKeyword, AstLogicalOp, AstNames, AstScope, AstStatement, AstUnaryOp, SpannedExpr,
+    SpannedName, SpannedStatement,
+};
 use crate::lexer::token::TokenLiteral;
 
+/// Frame name of code running at the top level of a file
+pub const MODULE_FRAME: &str = "<module>";
+
+/// Frame name of generator expressions
+const GENEXPR_FRAME: &str = "<genexpr>";
+
+/// How a statement finished, so enclosing loops and functions can react
+#[derive(Debug)]
+pub enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(RuntimeValue),
+}
+
 #[derive(Debug)]
 pub struct Scope {
     variables: Rc<RefCell<HashMap<String, RuntimeVariable>>>,
     is_function: bool,
+    /// Set on the scope running a class body
+    is_class: bool,
     parent: Option<Rc<Scope>>,
+    /// Name shown in tracebacks
+    frame: String,
+    /// Function being run, set on call frames
+    function: Option<Rc<RuntimeFunction>>,
+    /// Exceptions caught by the `except` blocks currently running, used by bare `raise`
+    handling: RefCell<Vec<RuntimeError>>,
+    /// Set on the call frame of a generator function while its body is running
+    yielder: Cell<Option<NonNull<GeneratorYielder>>>,
+    /// Shared by every scope of an interpreter
+    importer: Rc<Importer>,
+    /// Builtins the code can see, all of them if `None`
+    builtins: Option<Rc<HashSet<String>>>,
 }
 
 impl Scope {
+    /// Main module of an interpreter with an empty search path
     pub fn new() -> Rc<Scope> {
-        Scope {
+        Self::module(&Rc::default(), MAIN_MODULE, None)
+    }
+
+    /// Top-level scope of module `name`, relative imports are resolved from `package`
+    pub fn module(importer: &Rc<Importer>, name: &str, package: Option<&str>) -> Rc<Scope> {
+        Self::module_with_builtins(importer, name, package, None)
+    }
+
+    /// Same as [`Scope::module`], only the builtins named in `builtins` can be used
+    pub fn restricted_module(
+        importer: &Rc<Importer>,
+        name: &str,
+        builtins: Rc<HashSet<String>>,
+    ) -> Rc<Scope> {
+        Self::module_with_builtins(importer, name, None, Some(builtins))
+    }
+
+    fn module_with_builtins(
+        importer: &Rc<Importer>,
+        name: &str,
+        package: Option<&str>,
+        builtins: Option<Rc<HashSet<String>>>,
+    ) -> Rc<Scope> {
+        let scope = Rc::new(Scope {
             is_function: false,
+            is_class: false,
             variables: Rc::new(RefCell::new(HashMap::new())),
             parent: None,
-        }
-        .into()
+            frame: MODULE_FRAME.to_owned(),
+            function: None,
+            handling: RefCell::default(),
+            yielder: Cell::default(),
+            importer: importer.clone(),
+            builtins,
+        });
+
+        let package = package.map_or(RuntimeValue::Nil, |package| {
+            RuntimeValue::String(package.to_owned())
+        });
+
+        scope.set_variable("__name__".to_owned(), RuntimeValue::String(name.to_owned()));
+        scope.set_variable("__package__".to_owned(), package);
+
+        scope
     }
 
     pub fn child(self: &Rc<Self>, is_function: bool) -> Rc<Self> {
         Scope {
             is_function,
+            is_class: false,
+            variables: Rc::new(RefCell::new(HashMap::new())),
+            parent: Some(self.clone()),
+            frame: self.frame.clone(),
+            function: None,
+            handling: RefCell::default(),
+            yielder: Cell::default(),
+            importer: self.importer.clone(),
+            builtins: self.builtins.clone(),
+        }
+        .into()
+    }
+
+    /// Function scope for a call to `function`
+    pub fn call_frame(self: &Rc<Self>, function: &Rc<RuntimeFunction>) -> Rc<Self> {
+        Scope {
+            is_function: true,
+            is_class: false,
             variables: Rc::new(RefCell::new(HashMap::new())),
             parent: Some(self.clone()),
+            frame: function.name().to_owned(),
+            function: Some(function.clone()),
+            handling: RefCell::default(),
+            yielder: Cell::default(),
+            importer: self.importer.clone(),
+            builtins: self.builtins.clone(),
         }
         .into()
     }
 
-    pub fn run(self: &Rc<Self>, ast: AstScope) {
-        for stmt in ast.0 {
-            self.visit_stmt(stmt);
+    /// Scope running the body of class `name`, its variables become the class attributes
+    pub fn class_frame(self: &Rc<Self>, name: &str) -> Rc<Self> {
+        Scope {
+            is_function: true,
+            is_class: true,
+            variables: Rc::new(RefCell::new(HashMap::new())),
+            parent: Some(self.clone()),
+            frame: name.to_owned(),
+            function: None,
+            handling: RefCell::default(),
+            yielder: Cell::default(),
+            importer: self.importer.clone(),
+            builtins: self.builtins.clone(),
+        }
+        .into()
+    }
+
+    /// Scope running a generator expression, shown as its own frame in tracebacks
+    pub fn generator_frame(self: &Rc<Self>) -> Rc<Self> {
+        Scope {
+            is_function: false,
+            is_class: false,
+            variables: Rc::new(RefCell::new(HashMap::new())),
+            parent: Some(self.clone()),
+            frame: GENEXPR_FRAME.to_owned(),
+            function: None,
+            handling: RefCell::default(),
+            yielder: Cell::default(),
+            importer: self.importer.clone(),
+            builtins: self.builtins.clone(),
         }
+        .into()
     }
 
-    pub fn get_variable(self: &Rc<Self>, name: &String) -> Option<RuntimeVariable> {
-        self.variables
+    pub fn run(self: &Rc<Self>, ast: &AstScope) -> RuntimeResult {
+        self.visit_scope(ast).map(drop)
+    }
+
+    /// Limits of the interpreter running this scope
+    pub fn budget(&self) -> &Budget {
+        self.importer.budget()
+    }
+
+    pub fn get_variable(self: &Rc<Self>, name: &str) -> Option<RuntimeVariable> {
+        self.find_variable(name).ok().flatten()
+    }
+
+    /// Variable `name` as seen from this scope. Fails if a function binds
+    /// `name` but didn't assign it yet, instead of looking further out
+    fn find_variable(self: &Rc<Self>, name: &str) -> RuntimeResult<Option<RuntimeVariable>> {
+        let mut scope = self;
+        // Whether `scope` is a function enclosing the one running
+        let mut enclosing = false;
+
+        loop {
+            if let Some(var) = scope.variables.borrow().get(name) {
+                return Ok(Some(var.clone()));
+            }
+
+            if let Some(names) = scope.names() {
+                if names.locals.contains(name) {
+                    return Err(match enclosing {
+                        false => RuntimeError::new(
+                            ExceptionKind::UnboundLocalError,
+                            format!(
+                                "cannot access local variable '{name}' where it is not associated with a value"
+                            ),
+                        ),
+                        true => RuntimeError::new(
+                            ExceptionKind::NameError,
+                            format!(
+                                "cannot access free variable '{name}' where it is not associated with a value in enclosing scope"
+                            ),
+                        ),
+                    });
+                }
+
+                if names.globals.contains(name) {
+                    scope = scope.module_scope();
+                    continue;
+                }
+
+                enclosing = true;
+            }
+
+            match &scope.parent {
+                Some(parent) => scope = parent,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Names bound statically, set on the call frames of functions
+    fn names(&self) -> Option<&AstNames> {
+        self.function.as_ref().map(|function| &function.def.names)
+    }
+
+    /// Top-level scope of the module running this scope
+    fn module_scope(self: &Rc<Self>) -> &Rc<Self> {
+        let mut scope = self;
+
+        while let Some(parent) = &scope.parent {
+            scope = parent;
+        }
+
+        scope
+    }
+
+    /// Scope a `nonlocal` declaration of `name` in this scope refers to,
+    /// the nearest enclosing function with `name` as a local
+    fn nonlocal_scope(self: &Rc<Self>, name: &str) -> Option<&Rc<Self>> {
+        let mut scope = self.parent.as_ref()?;
+
+        loop {
+            if scope
+                .names()
+                .is_some_and(|names| names.locals.contains(name))
+            {
+                return Some(scope);
+            }
+
+            scope = scope.parent.as_ref()?;
+        }
+    }
+
+    /// Builtin `name`, unless the scope hides it
+    fn lookup_builtin(&self, name: &str) -> Option<RuntimeValue> {
+        match &self.builtins {
+            Some(allowed) if !allowed.contains(name) => None,
+            _ => builtins::lookup(name),
+        }
+    }
+
+    /// Variables defined in this scope itself, sorted by name
+    pub fn local_variables(&self) -> Vec<(String, RuntimeValue)> {
+        let mut variables = self
+            .variables
             .borrow()
-            .get(name)
-            .cloned()
-            .or_else(|| self.parent.as_ref().and_then(|p| p.get_variable(name)))
+            .iter()
+            .map(|(name, var)| (name.clone(), var.0.borrow().clone()))
+            .collect::<Vec<_>>();
+
+        variables.sort_by(|(a, _), (b, _)| a.cmp(b));
+        variables
     }
 
     pub fn set_variable(self: &Rc<Self>, name: String, value: RuntimeValue) -> RuntimeVariable {
         let Some(var) = self.variables.borrow().get(&name).cloned() else {
+            if let Some(names) = self.names() {
+                if names.globals.contains(&name) {
+                    return self.module_scope().set_variable(name, value);
+                }
+
+                if names.nonlocals.contains(&name)
+                    && let Some(scope) = self.nonlocal_scope(&name)
+                {
+                    return scope.set_variable(name, value);
+                }
+            }
+
             // Functions keep a secure context,
             // to manipulate external variables
-            // use `global` keyword, that clones
-            // variables to current scope
+            // use `global` or `nonlocal`, that clone
+            // variables to the current scope
             let Some(parent) = self.parent.as_ref().filter(|_| !self.is_function) else {
                 return self
                     .variables
@@ -68,130 +336,1195 @@ impl Scope {
             return parent.set_variable(name, value);
         };
 
-        *var.0.borrow_mut() = value;
+        // The old value is dropped after the borrow ends, finalizers may read the variable
+        var.0.replace(value);
 
         var
     }
 
-    pub fn visit_stmt(self: &Rc<Self>, stmt: AstStatement) {
-        match stmt {
+    /// Removes `name` from the scope it would be assigned in, `false` if it isn't bound there
+    fn delete_variable(self: &Rc<Self>, name: &str) -> bool {
+        let removed = self.variables.borrow_mut().remove(name);
+
+        if removed.is_some() {
+            return true;
+        }
+
+        if let Some(names) = self.names() {
+            if names.globals.contains(name) {
+                return self.module_scope().delete_variable(name);
+            }
+
+            if names.nonlocals.contains(name) {
+                return self
+                    .nonlocal_scope(name)
+                    .is_some_and(|scope| scope.delete_variable(name));
+            }
+        }
+
+        match self.parent.as_ref().filter(|_| !self.is_function) {
+            Some(parent) => parent.delete_variable(name),
+            None => false,
+        }
+    }
+
+    /// Binds `value` to the target of a `for` clause in this scope, unpacking tuples of names
+    fn bind_target(self: &Rc<Self>, target: &SpannedExpr, value: RuntimeValue) -> RuntimeResult {
+        match &target.node {
+            AstExpr::Ident(name) => {
+                let var = self.variables.borrow().get(name).cloned();
+
+                match var {
+                    Some(var) => {
+                        var.0.replace(value);
+                    }
+                    None => {
+                        self.variables
+                            .borrow_mut()
+                            .insert(name.clone(), value.into());
+                    }
+                }
+
+                Ok(())
+            }
+            AstExpr::Tuple(targets) => {
+                let items = self.items(&value)?;
+
+                if items.len() != targets.len() {
+                    return Err(RuntimeError::new(
+                        ExceptionKind::ValueError,
+                        match items.len() < targets.len() {
+                            true => format!(
+                                "not enough values to unpack (expected {}, got {})",
+                                targets.len(),
+                                items.len()
+                            ),
+                            false => {
+                                format!("too many values to unpack (expected {})", targets.len())
+                            }
+                        },
+                    ));
+                }
+
+                targets
+                    .iter()
+                    .zip(items)
+                    .try_for_each(|(target, item)| self.bind_target(target, item))
+            }
+            _ => unreachable!("the parser only allows names and tuples as targets"),
+        }
+    }
+
+    /// Exception caught by the innermost running `except` block
+    fn handled_exception(&self) -> Option<RuntimeError> {
+        self.handling
+            .borrow()
+            .last()
+            .cloned()
+            .or_else(|| self.parent.as_ref()?.handled_exception())
+    }
+
+    /// Arguments of a zero-argument `super()`: the class defining
+    /// the running method and the method's first argument
+    fn super_args(&self) -> RuntimeResult<(RuntimeValue, RuntimeValue)> {
+        let Some(function) = &self.function else {
+            return match self.parent.as_ref().filter(|_| !self.is_function) {
+                Some(parent) => parent.super_args(),
+                None => Err(RuntimeError::new(
+                    ExceptionKind::RuntimeError,
+                    "super(): no arguments",
+                )),
+            };
+        };
+
+        let class = function
+            .owner
+            .borrow()
+            .as_ref()
+            .and_then(std::rc::Weak::upgrade)
+            .ok_or_else(|| {
+                RuntimeError::new(
+                    ExceptionKind::RuntimeError,
+                    "super(): __class__ cell not found",
+                )
+            })?;
+
+        let receiver = function
+            .def
+            .params
+            .first()
+            .and_then(|param| self.variables.borrow().get(&param.name).cloned())
+            .ok_or_else(|| {
+                RuntimeError::new(ExceptionKind::RuntimeError, "super(): no arguments")
+            })?;
+
+        let receiver = receiver.0.borrow().clone();
+
+        Ok((RuntimeValue::Class(class), receiver))
+    }
+
+    pub fn visit_scope(self: &Rc<Self>, ast: &AstScope) -> RuntimeResult<Flow> {
+        // Like expressions, blocks move to a new stack when the current one runs out
+        self.budget().on_stack(|| {
+            for stmt in &ast.stmts {
+                match self.visit_stmt(stmt)? {
+                    Flow::Normal => {}
+                    flow => return Ok(flow),
+                }
+            }
+
+            Ok(Flow::Normal)
+        })
+    }
+
+    pub fn visit_stmt(self: &Rc<Self>, stmt: &SpannedStatement) -> RuntimeResult<Flow> {
+        let normal = |result: RuntimeResult| result.map(|()| Flow::Normal);
+
+        self.budget()
+            .step()
+            .map_err(|err| err.locate(&self.frame, stmt.span))?;
+
+        let flow = match &stmt.node {
             AstStatement::Conditional {
                 test,
                 body,
                 otherwise,
-            } => self.visit_conditional(*test, body, otherwise),
-            AstStatement::Expresion(expr) => {
-                self.visit_expr(*expr);
+            } => self.visit_conditional(test, body, otherwise.as_ref()),
+            AstStatement::Expresion(expr) => normal(self.visit_expr(expr).map(drop)),
+            AstStatement::Global(vars) => {
+                self.visit_global(vars);
+                Ok(Flow::Normal)
+            }
+            AstStatement::Nonlocal(vars) => normal(self.visit_nonlocal(vars)),
+            AstStatement::VariableDeclaration(var, expr) => normal(self.visit_var_decl(var, expr)),
+            AstStatement::Assignment { target, value } => {
+                normal(self.visit_assignment(target, value))
+            }
+            AstStatement::While { test, body } => self.visit_while(test, body),
+            AstStatement::Break => Ok(Flow::Break),
+            AstStatement::Continue => Ok(Flow::Continue),
+            AstStatement::FunctionDef(def) => normal(self.visit_function_def(def)),
+            AstStatement::ClassDef { name, bases, body } => {
+                normal(self.visit_class_def(name, bases, body))
+            }
+            AstStatement::Return(value) => match value {
+                Some(value) => self.visit_expr(value).map(Flow::Return),
+                None => Ok(Flow::Return(RuntimeValue::Nil)),
+            },
+            AstStatement::Try {
+                body,
+                handlers,
+                otherwise,
+                finally,
+            } => self.visit_try(body, handlers, otherwise.as_ref(), finally.as_ref()),
+            AstStatement::Raise { exception, cause } => {
+                Err(self.visit_raise(exception.as_deref(), cause.as_deref())?)
             }
-            AstStatement::Global(vars) => self.visit_global(vars),
-            AstStatement::VariableDeclaration(var, expr) => self.visit_var_decl(var, *expr),
+            AstStatement::Assert {
+                test,
+                message,
+                source,
+            } => normal(self.visit_assert(test, message.as_deref(), source)),
+            AstStatement::Pass => Ok(Flow::Normal),
+            AstStatement::Delete(targets) => normal(
+                targets
+                    .iter()
+                    .try_for_each(|target| self.visit_delete(target)),
+            ),
+            AstStatement::Match { subject, cases } => self.visit_match(subject, cases),
+            AstStatement::Import(names) => normal(self.visit_import(names)),
+            AstStatement::ImportFrom {
+                module,
+                level,
+                names,
+            } => normal(self.visit_import_from(module.as_deref(), *level, names)),
+        };
+
+        flow.map_err(|err| err.locate(&self.frame, stmt.span))
+    }
+
+    /// Raises `AssertionError` with `message`, or with the source of `test` if missing
+    pub fn visit_assert(
+        self: &Rc<Self>,
+        test: &SpannedExpr,
+        message: Option<&SpannedExpr>,
+        source: &str,
+    ) -> RuntimeResult {
+        if self.truthy(&self.visit_expr(test)?)? {
+            return Ok(());
         }
+
+        Err(match message {
+            Some(message) => RuntimeError::from_exception(new_exception(
+                ExceptionKind::AssertionError.class(),
+                Vec::from([self.visit_expr(message)?]),
+            ))
+            .with_note(format!("assert {source}")),
+            None => RuntimeError::new(ExceptionKind::AssertionError, source),
+        })
+    }
+
+    /// Unbinds a name, removes an item or deletes an attribute
+    pub fn visit_delete(self: &Rc<Self>, target: &SpannedExpr) -> RuntimeResult {
+        let result = match &target.node {
+            AstExpr::Ident(name) => match self.delete_variable(name) {
+                true => Ok(()),
+                false => Err(RuntimeError::name_error(name)),
+            },
+            AstExpr::Subscript { value, index } => {
+                let value = self.visit_expr(value)?;
+                let index = self.visit_expr(index)?;
+                self.del_subscript(&value, index)
+            }
+            AstExpr::Attribute { value, name } => {
+                let value = self.visit_expr(value)?;
+                self.del_attribute(&value, name)
+            }
+            _ => unreachable!("the parser only allows names, subscripts and attributes"),
+        };
+
+        result.map_err(|err| err.locate(&self.frame, target.span))
     }
 
     pub fn visit_conditional(
         self: &Rc<Self>,
-        test: AstExpr,
-        body: AstScope,
-        otherwise: Option<AstScope>,
-    ) {
-        let test = self.visit_expr(test);
+        test: &SpannedExpr,
+        body: &AstScope,
+        otherwise: Option<&AstScope>,
+    ) -> RuntimeResult<Flow> {
+        let test = self.visit_expr(test)?;
 
-        if test.as_bool() {
-            self.run(body);
+        if self.truthy(&test)? {
+            self.visit_scope(body)
         } else if let Some(otherwise) = otherwise {
-            self.run(otherwise);
+            self.visit_scope(otherwise)
+        } else {
+            Ok(Flow::Normal)
+        }
+    }
+
+    pub fn visit_while(
+        self: &Rc<Self>,
+        test: &SpannedExpr,
+        body: &AstScope,
+    ) -> RuntimeResult<Flow> {
+        while self.truthy(&self.visit_expr(test)?)? {
+            match self.visit_scope(body)? {
+                Flow::Normal | Flow::Continue => {}
+                Flow::Break => break,
+                flow @ Flow::Return(_) => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    pub fn visit_function_def(self: &Rc<Self>, def: &Rc<AstFunction>) -> RuntimeResult {
+        let function = self.new_function(def)?;
+        self.set_variable(def.name.clone(), RuntimeValue::Function(function.into()));
+
+        Ok(())
+    }
+
+    /// Function closing over this scope, evaluating the defaults of its parameters
+    fn new_function(self: &Rc<Self>, def: &Rc<AstFunction>) -> RuntimeResult<RuntimeFunction> {
+        let defaults = def
+            .params
+            .iter()
+            .map(|param| {
+                param
+                    .default
+                    .as_ref()
+                    .map(|default| self.visit_expr(default))
+                    .transpose()
+            })
+            .collect::<RuntimeResult<_>>()?;
+
+        // Like in Python, functions don't see the names of a class body
+        let scope = match &self.parent {
+            Some(parent) if self.is_class => parent.clone(),
+            _ => self.clone(),
+        };
+
+        Ok(RuntimeFunction {
+            def: def.clone(),
+            scope,
+            owner: RefCell::default(),
+            defaults,
+        })
+    }
+
+    pub fn visit_class_def(
+        self: &Rc<Self>,
+        name: &str,
+        bases: &[SpannedExpr],
+        body: &AstScope,
+    ) -> RuntimeResult {
+        let bases = bases
+            .iter()
+            .map(|base| match self.visit_expr(base)? {
+                RuntimeValue::Class(class) => Ok(class),
+                value => Err(RuntimeError::type_error(format!(
+                    "bases must be classes, not '{}'",
+                    value.type_name()
+                ))
+                .locate(&self.frame, base.span)),
+            })
+            .collect::<RuntimeResult<Vec<_>>>()?;
+
+        let frame = self.class_frame(name);
+        frame
+            .visit_scope(body)
+            .map_err(RuntimeError::unwind_frame)?;
+
+        let class = RuntimeClass {
+            attrs: frame
+                .variables
+                .borrow()
+                .iter()
+                .map(|(name, var)| (name.clone(), var.0.borrow().clone()))
+                .collect::<HashMap<_, _>>()
+                .into(),
+            ..RuntimeClass::new(name, bases)?
+        };
+        let class = Rc::new(class);
+
+        for value in class.attrs.borrow().values() {
+            if let RuntimeValue::Function(function) = value {
+                function
+                    .owner
+                    .borrow_mut()
+                    .get_or_insert_with(|| Rc::downgrade(&class));
+            }
+        }
+
+        self.set_variable(name.to_owned(), RuntimeValue::Class(class));
+
+        Ok(())
+    }
+
+    pub fn visit_try(
+        self: &Rc<Self>,
+        body: &AstScope,
+        handlers: &[AstExceptHandler],
+        otherwise: Option<&AstScope>,
+        finally: Option<&AstScope>,
+    ) -> RuntimeResult<Flow> {
+        let result = match self.visit_scope(body) {
+            Err(err) => self.visit_except(err, handlers),
+            Ok(Flow::Normal) => match otherwise {
+                Some(otherwise) => self.visit_scope(otherwise),
+                None => Ok(Flow::Normal),
+            },
+            flow => flow,
+        };
+
+        let Some(finally) = finally else {
+            return result;
+        };
+
+        // Scripts can't delay being aborted
+        if let Err(err) = &result
+            && err.limit().is_some()
+        {
+            return result;
+        }
+
+        // `finally` runs whatever happened, and overrides the outcome
+        // if it raises, breaks or returns by itself
+        match self.visit_scope(finally) {
+            Ok(Flow::Normal) => result,
+            Ok(flow) => Ok(flow),
+            Err(err) => Err(match result {
+                Err(pending) => err.with_context(&pending),
+                Ok(_) => err,
+            }),
+        }
+    }
+
+    /// Runs the first handler matching `err`, or propagates it
+    fn visit_except(
+        self: &Rc<Self>,
+        err: RuntimeError,
+        handlers: &[AstExceptHandler],
+    ) -> RuntimeResult<Flow> {
+        if err.limit().is_some() {
+            return Err(err);
+        }
+
+        for handler in handlers {
+            if let Some(class) = &handler.class {
+                let class = self.visit_expr(class)?;
+
+                if !self.exception_matches(&err, &class, handler)? {
+                    continue;
+                }
+            }
+
+            if let Some(name) = &handler.name {
+                self.set_variable(name.clone(), RuntimeValue::Object(err.exception.clone()));
+            }
+
+            self.handling.borrow_mut().push(err.clone());
+            let result = self.visit_scope(&handler.body);
+            self.handling.borrow_mut().pop();
+
+            return result.map_err(|new| new.with_context(&err));
+        }
+
+        Err(err)
+    }
+
+    fn exception_matches(
+        &self,
+        err: &RuntimeError,
+        class: &RuntimeValue,
+        handler: &AstExceptHandler,
+    ) -> RuntimeResult<bool> {
+        match class {
+            RuntimeValue::Class(class) if class.is_exception() => {
+                Ok(err.exception.class.is_subclass(class))
+            }
+            RuntimeValue::Tuple(classes) => classes.iter().try_fold(false, |matches, class| {
+                Ok(matches || self.exception_matches(err, class, handler)?)
+            }),
+            _ => Err(RuntimeError::type_error(
+                "catching classes that do not inherit from BaseException is not allowed",
+            )
+            .locate(
+                &self.frame,
+                handler.class.as_ref().map_or(handler.body.span, |c| c.span),
+            )),
+        }
+    }
+
+    /// Builds the error thrown by a `raise` statement
+    pub fn visit_raise(
+        self: &Rc<Self>,
+        exception: Option<&SpannedExpr>,
+        cause: Option<&SpannedExpr>,
+    ) -> RuntimeResult<RuntimeError> {
+        let Some(exception) = exception else {
+            return self.handled_exception().ok_or_else(|| {
+                RuntimeError::new(
+                    ExceptionKind::RuntimeError,
+                    "No active exception to reraise",
+                )
+            });
+        };
+
+        let exception = self.visit_expr(exception)?;
+        let mut err = self.exception_error(exception)?;
+
+        if let Some(cause) = cause {
+            let cause = match self.visit_expr(cause)? {
+                RuntimeValue::Nil => None,
+                cause => Some(self.exception_error(cause)?),
+            };
+
+            err = err.with_cause(cause);
         }
+
+        if let Some(handled) = self.handled_exception() {
+            err = err.with_context(&handled);
+        }
+
+        Ok(err)
+    }
+
+    /// Error raising `value`, which must be an exception class or instance
+    fn exception_error(self: &Rc<Self>, value: RuntimeValue) -> RuntimeResult<RuntimeError> {
+        let exception = match value {
+            RuntimeValue::Object(object) if object.class.is_exception() => object,
+            RuntimeValue::Class(class) if class.is_exception() => {
+                self.call_class(&class, Vec::new(), Vec::new())?
+            }
+            _ => {
+                return Err(RuntimeError::type_error(
+                    "exceptions must derive from BaseException",
+                ));
+            }
+        };
+
+        // Keep the traceback when raising again the exception being handled
+        Ok(self
+            .handled_exception()
+            .filter(|handled| Rc::ptr_eq(&handled.exception, &exception))
+            .unwrap_or_else(|| RuntimeError::from_exception(exception)))
+    }
+
+    pub fn visit_expr(self: &Rc<Self>, expr: &SpannedExpr) -> RuntimeResult<RuntimeValue> {
+        let budget = self.budget();
+        let _expr = budget
+            .step()
+            .and_then(|()| budget.enter_expr())
+            .map_err(|err| err.locate(&self.frame, expr.span))?;
+
+        budget.on_stack(|| self.visit_expr_node(expr))
     }
 
-    pub fn visit_expr(self: &Rc<Self>, expr: AstExpr) -> RuntimeValue {
-        match expr {
-            AstExpr::BinaryOp { op, left, right } => self.visit_expr_binop(op, *left, *right),
+    /// Evaluates `expr` without counting it, see [`Scope::visit_expr`]
+    fn visit_expr_node(self: &Rc<Self>, expr: &SpannedExpr) -> RuntimeResult<RuntimeValue> {
+        let value = match &expr.node {
+            AstExpr::Attribute { value, name } => {
+                let value = self.visit_expr(value)?;
+                self.get_attribute(&value, name)
+            }
+            AstExpr::BinaryOp { op, left, right } => self.visit_expr_binop(op, left, right),
+            AstExpr::Call {
+                func,
+                args,
+                keywords,
+            } => {
+                let func = self.visit_expr(func)?;
+                let (args, kwargs) = self.visit_call_args(&func, args, keywords)?;
+
+                self.call_with_keywords(&func, args, kwargs)
+            }
             AstExpr::Ident(var) => self
-                .get_variable(&var)
-                .map_or_else(|| RuntimeValue::Nil, |var| var.0.borrow().clone()),
-            AstExpr::Literal(TokenLiteral::Nil) => RuntimeValue::Nil,
-            AstExpr::Literal(TokenLiteral::Bool(b)) => RuntimeValue::Bool(b),
-            AstExpr::Literal(TokenLiteral::Number(n)) => RuntimeValue::Number(n),
-            AstExpr::Literal(TokenLiteral::String(s)) => RuntimeValue::String(s),
+                .find_variable(var)?
+                .map(|var| var.0.borrow().clone())
+                .or_else(|| self.lookup_builtin(var))
+                .ok_or_else(|| RuntimeError::name_error(var)),
+            AstExpr::Lambda(def) => self
+                .new_function(def)
+                .map(|function| RuntimeValue::Function(function.into())),
+            AstExpr::Comprehension(def) => self.visit_comprehension(def),
+            AstExpr::Yield(value) => {
+                let value = match value {
+                    Some(value) => self.visit_expr(value)?,
+                    None => RuntimeValue::Nil,
+                };
+
+                match self.suspend(value)? {
+                    Resume::Send(value) => Ok(value),
+                    Resume::Close => Err(RuntimeError::generator_exit()),
+                }
+            }
+            AstExpr::YieldFrom(iterable) => self.visit_yield_from(iterable),
+            AstExpr::Conditional {
+                test,
+                body,
+                otherwise,
+            } => {
+                let test = self.visit_expr(test)?;
+
+                if self.truthy(&test)? {
+                    self.visit_expr(body)
+                } else {
+                    self.visit_expr(otherwise)
+                }
+            }
+            AstExpr::Dict(entries) => {
+                self.budget()
+                    .allocate_values(entries.len() * 2)
+                    .map_err(|err| err.locate(&self.frame, expr.span))?;
+                let mut dict = RuntimeDict::default();
+
+                for (key, value) in entries {
+                    let key = self.visit_expr(key)?;
+                    let value = self.visit_expr(value)?;
+                    dict.insert(key, value)
+                        .map_err(|err| err.locate(&self.frame, expr.span))?;
+                }
+
+                Ok(RuntimeValue::Dict(Rc::new(RefCell::new(dict))))
+            }
+            AstExpr::List(items) => self.budget().allocate_values(items.len()).and_then(|()| {
+                items
+                    .iter()
+                    .map(|item| self.visit_expr(item))
+                    .collect::<RuntimeResult<_>>()
+                    .map(|items| RuntimeValue::List(Rc::new(RefCell::new(items))))
+            }),
+            AstExpr::Literal(TokenLiteral::Nil) => Ok(RuntimeValue::Nil),
+            AstExpr::Literal(TokenLiteral::Bool(b)) => Ok(RuntimeValue::Bool(*b)),
+            AstExpr::Literal(TokenLiteral::Number(n)) => Ok(RuntimeValue::Number(*n)),
+            AstExpr::Literal(TokenLiteral::String(s)) => Ok(RuntimeValue::String(s.clone())),
+            AstExpr::Logical { op, left, right } => {
+                let left = self.visit_expr(left)?;
+
+                match (op, self.truthy(&left)?) {
+                    (AstLogicalOp::And, false) | (AstLogicalOp::Or, true) => Ok(left),
+                    _ => self.visit_expr(right),
+                }
+            }
+            AstExpr::Set(items) => {
+                self.budget()
+                    .allocate_values(items.len())
+                    .map_err(|err| err.locate(&self.frame, expr.span))?;
+                let mut set = RuntimeSet::default();
+
+                for item in items {
+                    let value = self.visit_expr(item)?;
+                    set.insert(value)
+                        .map_err(|err| err.locate(&self.frame, item.span))?;
+                }
+
+                Ok(RuntimeValue::Set(Rc::new(RefCell::new(set))))
+            }
+            AstExpr::Starred(_) => unreachable!("the parser only allows `*` in call arguments"),
+            AstExpr::Subscript { value, index } => {
+                let value = self.visit_expr(value)?;
+                let index = self.visit_expr(index)?;
+                self.subscript(&value, index)
+            }
+            AstExpr::Tuple(items) => self.budget().allocate_values(items.len()).and_then(|()| {
+                items
+                    .iter()
+                    .map(|item| self.visit_expr(item))
+                    .collect::<RuntimeResult<_>>()
+                    .map(RuntimeValue::Tuple)
+            }),
             AstExpr::UnaryOp {
                 op: AstUnaryOp::Not,
                 right,
-            } => RuntimeValue::Bool(!self.visit_expr(*right).as_bool()),
+            } => Ok(RuntimeValue::Bool(!self.truthy(&self.visit_expr(right)?)?)),
+            AstExpr::UnaryOp {
+                op: AstUnaryOp::Neg,
+                right,
+            } => {
+                let value = self.visit_expr(right)?;
+                self.negate(value)
+            }
+        };
+
+        value.map_err(|err| err.locate(&self.frame, expr.span))
+    }
+
+    /// Builds the container of a comprehension, or a generator evaluating it lazily
+    fn visit_comprehension(
+        self: &Rc<Self>,
+        def: &Rc<AstComprehension>,
+    ) -> RuntimeResult<RuntimeValue> {
+        if def.kind == AstComprehensionKind::Generator {
+            let comprehension = Comprehension::new(self, self.generator_frame(), def)?;
+            return Ok(RuntimeValue::Generator(Rc::new(RuntimeGenerator::new(
+                comprehension,
+            ))));
+        }
+
+        let mut comprehension = Comprehension::new(self, self.child(false), def)?;
+        let scope = comprehension.scope.clone();
+        let element = |scope: &Rc<Scope>| scope.visit_expr(&def.element);
+
+        match def.kind {
+            AstComprehensionKind::List => {
+                let mut items = Vec::new();
+
+                while comprehension.advance()? {
+                    self.budget().allocate_values(1)?;
+                    items.push(element(&scope)?);
+                }
+
+                Ok(RuntimeValue::List(Rc::new(RefCell::new(items))))
+            }
+            AstComprehensionKind::Set => {
+                let mut set = RuntimeSet::default();
+
+                while comprehension.advance()? {
+                    self.budget().allocate_values(1)?;
+                    set.insert(element(&scope)?)
+                        .map_err(|err| err.locate(&self.frame, def.element.span))?;
+                }
+
+                Ok(RuntimeValue::Set(Rc::new(RefCell::new(set))))
+            }
+            AstComprehensionKind::Dict => {
+                let value = def.value.as_ref().expect("dict comprehensions have values");
+                let mut dict = RuntimeDict::default();
+
+                while comprehension.advance()? {
+                    self.budget().allocate_values(2)?;
+                    let key = element(&scope)?;
+                    dict.insert(key, scope.visit_expr(value)?)
+                        .map_err(|err| err.locate(&self.frame, def.element.span))?;
+                }
+
+                Ok(RuntimeValue::Dict(Rc::new(RefCell::new(dict))))
+            }
+            AstComprehensionKind::Generator => unreachable!("handled above"),
         }
     }
 
-    pub fn visit_expr_binop(
+    /// Evaluates the arguments of a call to `func`, spreading `*iterable` and `**mapping`
+    fn visit_call_args(
         self: &Rc<Self>,
-        op: AstBinaryOp,
-        left: AstExpr,
-        right: AstExpr,
-    ) -> RuntimeValue {
-        use AstBinaryOp::*;
-        use RuntimeValue::*;
-
-        match (op, self.visit_expr(left), self.visit_expr(right)) {
-            ////// Number Primitives //////
-            (Add, Number(a), Number(b)) => Number(a + b),
-            (Div, Number(a), Number(b)) => Number(a / b),
-            (Mul, Number(a), Number(b)) => Number(a * b),
-            (Sub, Number(a), Number(b)) => Number(a - b),
-
-            ////// Bool "Primitives" //////
-            (Add, Bool(a), Bool(b)) => Number(a.as_num() + b.as_num()),
-            (Add, Bool(false), Number(n)) | (Add, Number(n), Bool(false)) => Number(n),
-            (Add, Bool(true), Number(n)) | (Add, Number(n), Bool(true)) => Number(n + 1),
-            (Div, Bool(a), Bool(b)) => Number(a.as_num() / b.as_num()),
-            (Div, Bool(a), Number(b)) => Number(a.as_num() / b),
-            (Div, Number(a), Bool(b)) => Number(a / b.as_num()),
-            (Mul, Bool(a), Number(b)) => Number(a.as_num() * b),
-            (Mul, Bool(a), Bool(b)) => Number(a.as_num() * b.as_num()),
-            (Mul, Number(a), Bool(b)) => Number(a * b.as_num()),
-            (Sub, Bool(a), Bool(b)) => Number(a.as_num() - b.as_num()),
-            (Sub, Bool(a), Number(b)) => Number(a.as_num() - b),
-            (Sub, Number(a), Bool(b)) => Number(a - b.as_num()),
-
-            ////// Concatenation //////
-            (Add, String(a), Nil) => String(format!("{a}nil")),
-            (Add, Nil, String(b)) => String(format!("nil{b}")),
-            (Add, Bool(a), String(b)) => String(format!("{}{b}", a.as_string())),
-            (Add, String(a), Bool(b)) => String(format!("{a}{}", b.as_string())),
-            (Add, Number(a), String(b)) => String(format!("{a}{b}")),
-            (Add, String(a), Number(b)) => String(format!("{a}{b}")),
-            (Add, String(a), String(b)) => String(format!("{a}{b}")),
-
-            ////// Multiplication //////
-            (Mul, String(s), Bool(true)) | (Mul, Bool(true), String(s)) => String(s),
-            (Mul, String(_), Bool(false)) | (Mul, Bool(false), String(_)) => String(Default::default()),
-            (Mul, Number(a), String(b)) => String(
-                a.is_positive()
-                    .then(|| b.repeat(a.unsigned_abs() as usize))
-                    .unwrap_or_default(),
-            ),
-            (Mul, String(a), Number(b)) => String(
-                b.is_positive()
-                    .then(|| a.repeat(b.unsigned_abs() as usize))
-                    .unwrap_or_default(),
-            ),
+        func: &RuntimeValue,
+        args: &[SpannedExpr],
+        keywords: &[AstKeyword],
+    ) -> RuntimeResult<(Vec<RuntimeValue>, KeywordArgs)> {
+        let name = || match func {
+            RuntimeValue::Function(function) => function.qualname(),
+            RuntimeValue::Builtin(builtin) => builtin.name().to_owned(),
+            func => func.type_name().to_owned(),
+        };
+
+        let mut values = Vec::with_capacity(args.len());
+
+        for arg in args {
+            let AstExpr::Starred(iterable) = &arg.node else {
+                values.push(self.visit_expr(arg)?);
+                continue;
+            };
+
+            let iterable = self.visit_expr(iterable)?;
+
+            if !iterable.is_iterable() {
+                return Err(RuntimeError::type_error(format!(
+                    "{}() argument after * must be an iterable, not {}",
+                    name(),
+                    iterable.type_name()
+                ))
+                .locate(&self.frame, arg.span));
+            }
 
-            (_, _, String(_) | Nil) | (_, String(_) | Nil, _) => Nil,
+            values.extend(self.items(&iterable)?);
         }
+
+        let mut kwargs = Vec::<(String, RuntimeValue)>::with_capacity(keywords.len());
+
+        for keyword in keywords {
+            let value = self.visit_expr(&keyword.value)?;
+
+            let entries = match (&keyword.name, value) {
+                (Some(name), value) => Vec::from([(name.clone(), value)]),
+                (None, RuntimeValue::Dict(dict)) => dict
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| match key {
+                        RuntimeValue::String(key) => Ok((key.clone(), value.clone())),
+                        _ => Err(RuntimeError::type_error("keywords must be strings")),
+                    })
+                    .collect::<RuntimeResult<_>>()
+                    .map_err(|err| err.locate(&self.frame, keyword.value.span))?,
+                (None, value) => {
+                    return Err(RuntimeError::type_error(format!(
+                        "{}() argument after ** must be a mapping, not {}",
+                        name(),
+                        value.type_name()
+                    ))
+                    .locate(&self.frame, keyword.value.span));
+                }
+            };
+
+            for (key, value) in entries {
+                if kwargs.iter().any(|(name, _)| *name == key) {
+                    return Err(RuntimeError::type_error(format!(
+                        "{}() got multiple values for keyword argument '{key}'",
+                        name()
+                    ))
+                    .locate(&self.frame, keyword.value.span));
+                }
+
+                kwargs.push((key, value));
+            }
+        }
+
+        Ok((values, kwargs))
     }
 
-    pub fn visit_global(self: &Rc<Self>, vars: Vec<String>) {
-        let Some(parent) = self.parent.as_ref() else {
-            return;
+    /// Value of `value.name`, binding functions found on the class to the instance
+    pub fn get_attribute(
+        self: &Rc<Self>,
+        value: &RuntimeValue,
+        name: &str,
+    ) -> RuntimeResult<RuntimeValue> {
+        let bind = |function: RuntimeValue, receiver: RuntimeValue| match function {
+            RuntimeValue::Function(_)
+            | RuntimeValue::Builtin(_)
+            | RuntimeValue::NativeFunction(_) => {
+                RuntimeValue::Method(Rc::new(RuntimeMethod { receiver, function }))
+            }
+            value => value,
         };
 
+        let attribute = match (value, name) {
+            (RuntimeValue::Object(object), "__class__") => {
+                Some(RuntimeValue::Class(object.class.clone()))
+            }
+            (RuntimeValue::Object(object), _) => object.get_attr(name).or_else(|| {
+                let function = object.class.lookup(name)?;
+                Some(bind(function, value.clone()))
+            }),
+            (RuntimeValue::Class(class), "__name__") => {
+                Some(RuntimeValue::String(class.name.clone()))
+            }
+            (RuntimeValue::Class(class), "__mro__") => Some(RuntimeValue::Tuple(
+                std::iter::once(class.clone())
+                    .chain(class.mro.iter().cloned())
+                    .map(RuntimeValue::Class)
+                    .collect(),
+            )),
+            (RuntimeValue::Class(class), _) => class.lookup(name),
+            (RuntimeValue::Module(module), _) => module.get(name),
+            (RuntimeValue::NativeObject(object), _) => object.get_attr(name)?,
+            (RuntimeValue::Super(proxy), _) => {
+                let function = proxy.lookup(name);
+                function
+                    .map(|function| bind(function, RuntimeValue::Object(proxy.receiver.clone())))
+            }
+            (RuntimeValue::Function(function), "__name__") => {
+                Some(RuntimeValue::String(function.name().to_owned()))
+            }
+            (RuntimeValue::NativeFunction(function), "__name__") => {
+                Some(RuntimeValue::String(function.name.clone()))
+            }
+            (RuntimeValue::Generator(_), "send") => Some(bind(
+                RuntimeValue::Builtin(Builtin::GeneratorSend),
+                value.clone(),
+            )),
+            (RuntimeValue::Generator(_), "close") => Some(bind(
+                RuntimeValue::Builtin(Builtin::GeneratorClose),
+                value.clone(),
+            )),
+            (RuntimeValue::List(_), "append") => Some(bind(
+                RuntimeValue::Builtin(Builtin::ListAppend),
+                value.clone(),
+            )),
+            (RuntimeValue::List(_), "extend") => Some(bind(
+                RuntimeValue::Builtin(Builtin::ListExtend),
+                value.clone(),
+            )),
+            (RuntimeValue::List(_), "pop") => {
+                Some(bind(RuntimeValue::Builtin(Builtin::ListPop), value.clone()))
+            }
+            _ => None,
+        };
+
+        attribute.ok_or_else(|| RuntimeError::attribute_error(value, name))
+    }
+
+    /// Sets `object.name`, only instances, classes, modules and fields of native objects are writable
+    pub fn set_attribute(
+        self: &Rc<Self>,
+        object: &RuntimeValue,
+        name: &str,
+        value: RuntimeValue,
+    ) -> RuntimeResult {
+        match object {
+            RuntimeValue::Object(object) => object.set_attr(name, value),
+            RuntimeValue::Class(class) => {
+                let previous = class.attrs.borrow_mut().insert(name.to_owned(), value);
+                drop(previous);
+            }
+            RuntimeValue::Module(module) => {
+                module.scope.set_variable(name.to_owned(), value);
+            }
+            RuntimeValue::NativeObject(native) => {
+                if !native.set_attr(name, &value)? {
+                    return Err(RuntimeError::attribute_error(object, name));
+                }
+            }
+            _ => return Err(RuntimeError::attribute_error(object, name)),
+        }
+
+        Ok(())
+    }
+
+    /// `del object.name`, only instances, classes and modules have deletable attributes
+    pub fn del_attribute(self: &Rc<Self>, object: &RuntimeValue, name: &str) -> RuntimeResult {
+        let removed = match object {
+            RuntimeValue::Object(instance) => instance.attrs.borrow_mut().remove(name),
+            RuntimeValue::Class(class) => class.attrs.borrow_mut().remove(name),
+            RuntimeValue::Module(module) => {
+                let var = module.scope.variables.borrow_mut().remove(name);
+                var.map(|var| var.0.borrow().clone())
+            }
+            _ => None,
+        };
+
+        match removed {
+            Some(_) => Ok(()),
+            None => Err(RuntimeError::attribute_error(object, name)),
+        }
+    }
+
+    /// Calls `func`, which can be a function, a method, a class or an instance with `__call__`
+    pub fn call(
+        self: &Rc<Self>,
+        func: &RuntimeValue,
+        args: Vec<RuntimeValue>,
+    ) -> RuntimeResult<RuntimeValue> {
+        self.call_with_keywords(func, args, Vec::new())
+    }
+
+    /// Same as [`Scope::call`], also passing `name=value` arguments
+    pub fn call_with_keywords(
+        self: &Rc<Self>,
+        func: &RuntimeValue,
+        args: Vec<RuntimeValue>,
+        kwargs: KeywordArgs,
+    ) -> RuntimeResult<RuntimeValue> {
+        match func {
+            RuntimeValue::Function(function) => self.call_function(function, args, kwargs),
+            RuntimeValue::Builtin(builtin) => builtin.call(self, args, kwargs),
+            RuntimeValue::NativeFunction(function) => function.call(self, args, kwargs),
+            RuntimeValue::Method(method) => {
+                let args = std::iter::once(method.receiver.clone())
+                    .chain(args)
+                    .collect();
+                self.call_with_keywords(&method.function, args, kwargs)
+            }
+            RuntimeValue::Class(class) => self
+                .call_class(class, args, kwargs)
+                .map(RuntimeValue::Object),
+            RuntimeValue::Object(object) => match object.class.lookup("__call__") {
+                Some(method) => {
+                    let args = std::iter::once(func.clone()).chain(args).collect();
+                    self.call_with_keywords(&method, args, kwargs)
+                }
+                None => Err(RuntimeError::type_error(format!(
+                    "'{}' object is not callable",
+                    func.type_name()
+                ))),
+            },
+            _ => Err(RuntimeError::type_error(format!(
+                "'{}' object is not callable",
+                func.type_name()
+            ))),
+        }
+    }
+
+    fn call_function(
+        self: &Rc<Self>,
+        function: &Rc<RuntimeFunction>,
+        args: Vec<RuntimeValue>,
+        kwargs: KeywordArgs,
+    ) -> RuntimeResult<RuntimeValue> {
+        let bound = function
+            .bind(args, kwargs)
+            .map_err(|err| err.with_note(format!("signature: {}", function.signature())))?;
+
+        let frame = function.scope.call_frame(function);
+
+        for (name, value) in bound {
+            frame.set_variable(name, value);
+        }
+
+        if function.def.is_generator {
+            return Ok(RuntimeValue::Generator(Rc::new(
+                RuntimeGenerator::from_function(function.clone(), frame),
+            )));
+        }
+
+        let _call = self.budget().enter_call()?;
+        self.budget().on_stack(|| frame.run_frame(function))
+    }
+
+    /// Runs the body of `function` in this call frame
+    fn run_frame(self: &Rc<Self>, function: &RuntimeFunction) -> RuntimeResult<RuntimeValue> {
+        match self.visit_scope(&function.def.body) {
+            Ok(Flow::Return(value)) => Ok(value),
+            Ok(_) => Ok(RuntimeValue::Nil),
+            Err(err) => Err(err.unwind_frame()),
+        }
+    }
+
+    /// Suspends the generator function running this scope until it's resumed
+    fn suspend(self: &Rc<Self>, value: RuntimeValue) -> RuntimeResult<Resume> {
+        let mut frame = self;
+
+        while frame.function.is_none() {
+            // Generator expressions run lazily, maybe after the function is suspended
+            if frame.frame == GENEXPR_FRAME {
+                return Err(RuntimeError::new(
+                    ExceptionKind::SyntaxError,
+                    "'yield' inside generator expression",
+                ));
+            }
+
+            frame = frame.parent.as_ref().ok_or_else(|| {
+                RuntimeError::new(ExceptionKind::SyntaxError, "'yield' outside function")
+            })?;
+        }
+
+        let yielder = frame.yielder.take().ok_or_else(|| {
+            RuntimeError::new(ExceptionKind::SyntaxError, "'yield' outside generator")
+        })?;
+
+        // SAFETY: the yielder is only set while the coroutine that owns it is running
+        // this frame, and this code runs on its stack since it can only be reached
+        // synchronously from the frame's body
+        let input = unsafe { yielder.as_ref() }.suspend(value);
+        frame.yielder.set(Some(yielder));
+
+        Ok(input)
+    }
+
+    /// `yield from iterable`, evaluating to the value returned by a delegated generator
+    fn visit_yield_from(self: &Rc<Self>, iterable: &SpannedExpr) -> RuntimeResult<RuntimeValue> {
+        let iterable = self.visit_expr(iterable)?;
+
+        let RuntimeValue::Generator(generator) = iterable else {
+            let mut iterator = self.iterate(&iterable)?;
+
+            while let Some(item) = iterator.next_item()? {
+                if let Resume::Close = self.suspend(item)? {
+                    return Err(RuntimeError::generator_exit());
+                }
+            }
+
+            return Ok(RuntimeValue::Nil);
+        };
+
+        let mut input = Resume::Send(RuntimeValue::Nil);
+
+        loop {
+            let value = match generator.resume(input)? {
+                Resumed::Yielded(value) => value,
+                Resumed::Returned(value) => return Ok(value),
+            };
+
+            input = self.suspend(value)?;
+
+            if let Resume::Close = input {
+                generator.close()?;
+                return Err(RuntimeError::generator_exit());
+            }
+        }
+    }
+
+    /// Creates an instance and runs `__init__` on it
+    fn call_class(
+        self: &Rc<Self>,
+        class: &Rc<RuntimeClass>,
+        args: Vec<RuntimeValue>,
+        kwargs: KeywordArgs,
+    ) -> RuntimeResult<Rc<RuntimeObject>> {
+        let object = Rc::new(RuntimeObject::new(class.clone()));
+
+        // Exceptions keep their arguments even if `__init__` is overridden
+        if class.is_exception() {
+            object.set_attr("args", RuntimeValue::Tuple(args.iter().cloned().collect()));
+        }
+
+        let Some(init) = class.lookup("__init__") else {
+            if !args.is_empty() || !kwargs.is_empty() {
+                return Err(RuntimeError::type_error(format!(
+                    "{}() takes no arguments",
+                    class.name
+                )));
+            }
+
+            return Ok(object);
+        };
+
+        let args = std::iter::once(RuntimeValue::Object(object.clone()))
+            .chain(args)
+            .collect();
+
+        match self.call_with_keywords(&init, args, kwargs)? {
+            RuntimeValue::Nil => Ok(object),
+            value => Err(RuntimeError::type_error(format!(
+                "__init__() should return nil, not '{}'",
+                value.type_name()
+            ))),
+        }
+    }
+
+    pub fn visit_expr_binop(
+        self: &Rc<Self>,
+        op: &AstBinaryOp,
+        left: &SpannedExpr,
+        right: &SpannedExpr,
+    ) -> RuntimeResult<RuntimeValue> {
+        let left = self.visit_expr(left)?;
+        let right = self.visit_expr(right)?;
+
+        self.binary_op(op, left, right)
+    }
+
+    /// Shares the module's variables with a class body,
+    /// functions resolve their `global` names statically
+    pub fn visit_global(self: &Rc<Self>, vars: &[SpannedName]) {
+        let module = self.module_scope();
+
+        if Rc::ptr_eq(module, self) || self.function.is_some() {
+            return;
+        }
+
+        for var in vars {
+            let value = module
+                .get_variable(var)
+                .unwrap_or_else(|| module.set_variable(var.node.clone(), RuntimeValue::Nil));
+
+            let previous = self.variables.borrow_mut().insert(var.node.clone(), value);
+            drop(previous);
+        }
+    }
+
+    /// Shares the variables of the nearest enclosing functions with a class body,
+    /// functions resolve their `nonlocal` names statically
+    pub fn visit_nonlocal(self: &Rc<Self>, vars: &[SpannedName]) -> RuntimeResult {
+        if self.function.is_some() {
+            return Ok(());
+        }
+
         for var in vars {
-            let value = parent
-                .get_variable(&var)
-                .unwrap_or_else(|| parent.set_variable(var.clone(), RuntimeValue::Nil));
+            let value = self.enclosing_variable(var).ok_or_else(|| {
+                RuntimeError::new(
+                    ExceptionKind::SyntaxError,
+                    format!("no binding for nonlocal '{}' found", var.node),
+                )
+            })?;
+
+            let previous = self.variables.borrow_mut().insert(var.node.clone(), value);
+            drop(previous);
+        }
+
+        Ok(())
+    }
 
-            self.variables.borrow_mut().insert(var, value);
+    /// Variable bound by an enclosing function, class bodies and the module don't count
+    fn enclosing_variable(&self, name: &str) -> Option<RuntimeVariable> {
+        let mut scope = self.parent.as_ref()?;
+
+        while let Some(parent) = &scope.parent {
+            if !scope.is_class
+                && let Some(var) = scope.variables.borrow().get(name)
+            {
+                return Some(var.clone());
+            }
+
+            scope = parent;
+        }
+
+        None
+    }
+
+    pub fn visit_assignment(
+        self: &Rc<Self>,
+        target: &SpannedExpr,
+        value: &SpannedExpr,
+    ) -> RuntimeResult {
+        let value = self.visit_expr(value)?;
+
+        match &target.node {
+            AstExpr::Attribute {
+                value: object,
+                name,
+            } => {
+                let object = self.visit_expr(object)?;
+                self.set_attribute(&object, name, value)
+                    .map_err(|err| err.locate(&self.frame, target.span))
+            }
+            AstExpr::Subscript {
+                value: object,
+                index,
+            } => {
+                let object = self.visit_expr(object)?;
+                let index = self.visit_expr(index)?;
+                self.set_subscript(&object, index, value)
+                    .map_err(|err| err.locate(&self.frame, target.span))
+            }
+            _ => unreachable!("the parser only builds attribute and subscript assignments"),
         }
     }
 
-    pub fn visit_var_decl(self: &Rc<Self>, var: String, expr: AstExpr) {
-        self.set_variable(var, self.visit_expr(expr));
+    pub fn visit_var_decl(self: &Rc<Self>, var: &str, expr: &SpannedExpr) -> RuntimeResult {
+        let value = self.visit_expr(expr)?;
+        self.set_variable(var.to_owned(), value);
+        Ok(())
     }
 }
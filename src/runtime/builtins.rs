@@ -0,0 +1,416 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::Scope;
+use super::class::RuntimeSuper;
+use super::error::{ExceptionKind, RuntimeError, RuntimeResult};
+use super::generator::{Resume, Resumed};
+use super::set::RuntimeSet;
+use super::value::{KeywordArgs, RuntimeValue};
+use crate::ast::node::AstBinaryOp;
+
+/// Functions implemented by the interpreter
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    Isinstance,
+    Len,
+    List,
+    Next,
+    Repr,
+    Set,
+    Sorted,
+    Str,
+    Super,
+    Tuple,
+    /// `BaseException.__init__`, stores the arguments in `args`
+    ExceptionInit,
+    /// `generator.send(value)`
+    GeneratorSend,
+    /// `generator.close()`
+    GeneratorClose,
+    /// `list.append(item)`
+    ListAppend,
+    /// `list.extend(iterable)`
+    ListExtend,
+    /// `list.pop(index=-1)`
+    ListPop,
+}
+
+/// Names available everywhere unless a script shadows them
+pub fn lookup(name: &str) -> Option<RuntimeValue> {
+    let builtin = match name {
+        "isinstance" => Builtin::Isinstance,
+        "len" => Builtin::Len,
+        "list" => Builtin::List,
+        "next" => Builtin::Next,
+        "repr" => Builtin::Repr,
+        "set" => Builtin::Set,
+        "sorted" => Builtin::Sorted,
+        "str" => Builtin::Str,
+        "super" => Builtin::Super,
+        "tuple" => Builtin::Tuple,
+        "NotImplemented" => return Some(RuntimeValue::NotImplemented),
+        _ => return ExceptionKind::from_name(name).map(|kind| RuntimeValue::Class(kind.class())),
+    };
+
+    Some(RuntimeValue::Builtin(builtin))
+}
+
+impl Builtin {
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Isinstance => "isinstance",
+            Builtin::Len => "len",
+            Builtin::List => "list",
+            Builtin::Next => "next",
+            Builtin::Repr => "repr",
+            Builtin::Set => "set",
+            Builtin::Sorted => "sorted",
+            Builtin::Str => "str",
+            Builtin::Super => "super",
+            Builtin::Tuple => "tuple",
+            Builtin::ExceptionInit => "__init__",
+            Builtin::GeneratorSend => "send",
+            Builtin::GeneratorClose => "close",
+            Builtin::ListAppend => "append",
+            Builtin::ListExtend => "extend",
+            Builtin::ListPop => "pop",
+        }
+    }
+
+    /// Runs the builtin, `scope` is the one of the caller
+    pub fn call(
+        self,
+        scope: &Rc<Scope>,
+        args: Vec<RuntimeValue>,
+        mut kwargs: KeywordArgs,
+    ) -> RuntimeResult<RuntimeValue> {
+        if self == Builtin::Sorted {
+            let [iterable] = self.expect_args(args)?;
+            let key =
+                take_keyword(&mut kwargs, "key").filter(|key| !matches!(key, RuntimeValue::Nil));
+            let reverse = match take_keyword(&mut kwargs, "reverse") {
+                Some(reverse) => scope.truthy(&reverse)?,
+                None => false,
+            };
+
+            self.expect_no_keywords(kwargs)?;
+
+            let items = scope.items(&iterable)?;
+            return sorted(scope, items, key.as_ref(), reverse)
+                .map(|items| RuntimeValue::List(Rc::new(RefCell::new(items))));
+        }
+
+        if !kwargs.is_empty() {
+            return Err(RuntimeError::type_error(format!(
+                "{}() takes no keyword arguments",
+                self.name()
+            )));
+        }
+
+        match self {
+            Builtin::Sorted => unreachable!("handled above"),
+            Builtin::Isinstance => {
+                let [value, class] = self.expect_args(args)?;
+                isinstance(&value, &class).map(RuntimeValue::Bool)
+            }
+            Builtin::Len => {
+                let [value] = self.expect_args(args)?;
+                scope.length(&value).map(RuntimeValue::Number)
+            }
+            Builtin::List | Builtin::Set | Builtin::Tuple => {
+                let items = match &args[..] {
+                    [] => Vec::new(),
+                    [iterable] => scope.items(iterable)?,
+                    _ => {
+                        return Err(RuntimeError::type_error(format!(
+                            "{}() takes at most 1 argument ({} given)",
+                            self.name(),
+                            args.len()
+                        )));
+                    }
+                };
+
+                Ok(match self {
+                    Builtin::List => RuntimeValue::List(Rc::new(RefCell::new(items))),
+                    Builtin::Tuple => RuntimeValue::Tuple(items.into()),
+                    _ => {
+                        let mut set = RuntimeSet::default();
+
+                        for item in items {
+                            set.insert(item)?;
+                        }
+
+                        RuntimeValue::Set(Rc::new(RefCell::new(set)))
+                    }
+                })
+            }
+            Builtin::Next => {
+                let (iterator, default) = match <[_; 2]>::try_from(args) {
+                    Ok([iterator, default]) => (iterator, Some(default)),
+                    Err(args) => {
+                        let [iterator] = self.expect_args(args)?;
+                        (iterator, None)
+                    }
+                };
+
+                let RuntimeValue::Generator(generator) = &iterator else {
+                    return Err(RuntimeError::type_error(format!(
+                        "'{}' object is not an iterator",
+                        iterator.type_name()
+                    )));
+                };
+
+                match (generator.resume(Resume::Send(RuntimeValue::Nil))?, default) {
+                    (Resumed::Yielded(item), _) | (Resumed::Returned(_), Some(item)) => Ok(item),
+                    (Resumed::Returned(value), None) => Err(RuntimeError::stop_iteration(value)),
+                }
+            }
+            Builtin::Repr => {
+                let [value] = self.expect_args(args)?;
+                let repr = RuntimeValue::String(scope.repr_value(&value)?);
+                scope.budget().allocate_value(&repr)?;
+                Ok(repr)
+            }
+            Builtin::Str => match &args[..] {
+                [] => Ok(RuntimeValue::String(String::new())),
+                [value] => {
+                    let string = RuntimeValue::String(scope.str_value(value)?);
+                    scope.budget().allocate_value(&string)?;
+                    Ok(string)
+                }
+                _ => Err(RuntimeError::type_error(format!(
+                    "str() takes at most 1 argument ({} given)",
+                    args.len()
+                ))),
+            },
+            Builtin::Super => {
+                let (class, receiver) = match &args[..] {
+                    [] => scope.super_args()?,
+                    [class, receiver] => (class.clone(), receiver.clone()),
+                    _ => {
+                        return Err(RuntimeError::type_error(format!(
+                            "super() takes 0 or 2 arguments ({} given)",
+                            args.len()
+                        )));
+                    }
+                };
+
+                match (class, receiver) {
+                    (RuntimeValue::Class(class), RuntimeValue::Object(receiver))
+                        if receiver.class.is_subclass(&class) =>
+                    {
+                        Ok(RuntimeValue::Super(Rc::new(RuntimeSuper {
+                            class,
+                            receiver,
+                        })))
+                    }
+                    (RuntimeValue::Class(_), _) => Err(RuntimeError::type_error(
+                        "super(type, obj): obj must be an instance of type",
+                    )),
+                    (class, _) => Err(RuntimeError::type_error(format!(
+                        "super() argument 1 must be a type, not {}",
+                        class.type_name()
+                    ))),
+                }
+            }
+            Builtin::ExceptionInit => match &args[..] {
+                [RuntimeValue::Object(exception), args @ ..] if exception.class.is_exception() => {
+                    exception.set_attr("args", RuntimeValue::Tuple(args.into()));
+                    Ok(RuntimeValue::Nil)
+                }
+                _ => Err(RuntimeError::type_error(
+                    "descriptor '__init__' requires a 'BaseException' object",
+                )),
+            },
+            Builtin::GeneratorSend => {
+                let [generator, value] = self.expect_args(args)?;
+                let RuntimeValue::Generator(generator) = generator else {
+                    unreachable!("only bound to generators")
+                };
+
+                match generator.resume(Resume::Send(value))? {
+                    Resumed::Yielded(item) => Ok(item),
+                    Resumed::Returned(value) => Err(RuntimeError::stop_iteration(value)),
+                }
+            }
+            Builtin::GeneratorClose => {
+                let [generator] = self.expect_args(args)?;
+                let RuntimeValue::Generator(generator) = generator else {
+                    unreachable!("only bound to generators")
+                };
+
+                generator.close().map(|_| RuntimeValue::Nil)
+            }
+            Builtin::ListAppend => {
+                let [list, item] = self.expect_args(args)?;
+                let RuntimeValue::List(list) = list else {
+                    unreachable!("only bound to lists")
+                };
+
+                scope.budget().allocate_values(1)?;
+                list.borrow_mut().push(item);
+                Ok(RuntimeValue::Nil)
+            }
+            Builtin::ListExtend => {
+                let [list, iterable] = self.expect_args(args)?;
+                let RuntimeValue::List(list) = list else {
+                    unreachable!("only bound to lists")
+                };
+
+                let items = scope.items(&iterable)?;
+                scope.budget().allocate_values(items.len())?;
+                list.borrow_mut().extend(items);
+                Ok(RuntimeValue::Nil)
+            }
+            Builtin::ListPop => {
+                let (list, index) = match <[_; 2]>::try_from(args) {
+                    Ok([list, index]) => (list, Some(index)),
+                    Err(args) => {
+                        let [list] = self.expect_args(args)?;
+                        (list, None)
+                    }
+                };
+                let RuntimeValue::List(list) = list else {
+                    unreachable!("only bound to lists")
+                };
+
+                let mut items = list.borrow_mut();
+
+                if items.is_empty() {
+                    return Err(RuntimeError::new(
+                        ExceptionKind::IndexError,
+                        "pop from empty list",
+                    ));
+                }
+
+                let len = items.len() as i64;
+                let idx = match index.as_ref().map(RuntimeValue::as_int) {
+                    Some(Some(idx)) if idx < 0 => idx + len,
+                    Some(Some(idx)) => idx,
+                    Some(None) => {
+                        return Err(RuntimeError::type_error(format!(
+                            "'{}' object cannot be interpreted as an integer",
+                            index.unwrap_or_default().type_name()
+                        )));
+                    }
+                    None => len - 1,
+                };
+
+                let idx = usize::try_from(idx)
+                    .ok()
+                    .filter(|idx| *idx < items.len())
+                    .ok_or_else(|| {
+                        RuntimeError::new(ExceptionKind::IndexError, "pop index out of range")
+                    })?;
+
+                Ok(items.remove(idx))
+            }
+        }
+    }
+
+    /// Fails on keyword arguments left after taking the accepted ones
+    fn expect_no_keywords(self, kwargs: KeywordArgs) -> RuntimeResult {
+        match kwargs.first() {
+            Some((name, _)) => Err(RuntimeError::type_error(format!(
+                "{}() got an unexpected keyword argument '{name}'",
+                self.name()
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that exactly `N` arguments were given
+    fn expect_args<const N: usize>(
+        self,
+        args: Vec<RuntimeValue>,
+    ) -> RuntimeResult<[RuntimeValue; N]> {
+        let given = args.len();
+
+        args.try_into().map_err(|_| {
+            RuntimeError::type_error(format!(
+                "{}() takes exactly {N} argument{} ({given} given)",
+                self.name(),
+                if N == 1 { "" } else { "s" },
+            ))
+        })
+    }
+}
+
+fn isinstance(value: &RuntimeValue, class: &RuntimeValue) -> RuntimeResult<bool> {
+    match class {
+        RuntimeValue::Class(class) => Ok(match value {
+            RuntimeValue::Object(object) => object.class.is_subclass(class),
+            _ => false,
+        }),
+        RuntimeValue::Tuple(classes) => classes.iter().try_fold(false, |matches, class| {
+            Ok(matches || isinstance(value, class)?)
+        }),
+        _ => Err(RuntimeError::type_error(
+            "isinstance() arg 2 must be a type or tuple of types",
+        )),
+    }
+}
+
+fn take_keyword(kwargs: &mut KeywordArgs, name: &str) -> Option<RuntimeValue> {
+    let idx = kwargs.iter().position(|(keyword, _)| keyword == name)?;
+    Some(kwargs.remove(idx).1)
+}
+
+/// Stable sort comparing with `<`, on the result of `key` if given
+fn sorted(
+    scope: &Rc<Scope>,
+    items: Vec<RuntimeValue>,
+    key: Option<&RuntimeValue>,
+    reverse: bool,
+) -> RuntimeResult<Vec<RuntimeValue>> {
+    let keyed = items
+        .into_iter()
+        .map(|item| match key {
+            Some(key) => Ok((scope.call(key, Vec::from([item.clone()]))?, item)),
+            None => Ok((item.clone(), item)),
+        })
+        .collect::<RuntimeResult<Vec<_>>>()?;
+
+    let mut less = |a: &(RuntimeValue, RuntimeValue), b: &(RuntimeValue, RuntimeValue)| {
+        let (a, b) = if reverse { (&b.0, &a.0) } else { (&a.0, &b.0) };
+        let result = scope.binary_op(&AstBinaryOp::Less, a.clone(), b.clone())?;
+        scope.truthy(&result)
+    };
+
+    Ok(merge_sort(keyed, &mut less)?
+        .into_iter()
+        .map(|(_, item)| item)
+        .collect())
+}
+
+/// Merge sort with a fallible comparison, `slice::sort_by` could panic
+/// on user-defined orderings that aren't total
+fn merge_sort<T>(
+    mut items: Vec<T>,
+    less: &mut impl FnMut(&T, &T) -> RuntimeResult<bool>,
+) -> RuntimeResult<Vec<T>> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+
+    let right = items.split_off(items.len() / 2);
+    let mut left = merge_sort(items, less)?.into_iter().peekable();
+    let mut right = merge_sort(right, less)?.into_iter().peekable();
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // Equal items keep their order, taking from the left first
+        let item = if less(r, l)? {
+            right.next()
+        } else {
+            left.next()
+        };
+        merged.extend(item);
+    }
+
+    merged.extend(left);
+    merged.extend(right);
+
+    Ok(merged)
+}
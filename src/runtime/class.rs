@@ -0,0 +1,177 @@
+use core::fmt;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::error::{ExceptionKind, RuntimeError, RuntimeResult};
+use super::value::RuntimeValue;
+
+/// Class object, either built-in or defined by a script
+pub struct RuntimeClass {
+    pub name: String,
+    pub bases: Vec<Rc<RuntimeClass>>,
+    /// Method resolution order without the class itself
+    pub mro: Vec<Rc<RuntimeClass>>,
+    pub attrs: RefCell<HashMap<String, RuntimeValue>>,
+    /// Set on the classes of built-in exceptions
+    pub exception: Option<ExceptionKind>,
+}
+
+/// Instance of a [`RuntimeClass`]
+pub struct RuntimeObject {
+    pub class: Rc<RuntimeClass>,
+    pub attrs: RefCell<HashMap<String, RuntimeValue>>,
+}
+
+/// Proxy returned by `super()`, looks attributes up after `class`
+/// in the method resolution order of `receiver`
+pub struct RuntimeSuper {
+    pub class: Rc<RuntimeClass>,
+    pub receiver: Rc<RuntimeObject>,
+}
+
+impl RuntimeClass {
+    /// Creates a class, ordering its ancestors with the C3 linearization
+    pub fn new(name: impl Into<String>, bases: Vec<Rc<RuntimeClass>>) -> RuntimeResult<Self> {
+        for (idx, base) in bases.iter().enumerate() {
+            if bases[..idx].iter().any(|other| Rc::ptr_eq(other, base)) {
+                return Err(RuntimeError::type_error(format!(
+                    "duplicate base class {}",
+                    base.name
+                )));
+            }
+        }
+
+        let mut sequences = bases
+            .iter()
+            .map(|base| {
+                std::iter::once(base.clone())
+                    .chain(base.mro.iter().cloned())
+                    .collect::<Vec<_>>()
+            })
+            .chain(std::iter::once(bases.clone()))
+            .collect::<Vec<_>>();
+
+        let mut mro = Vec::new();
+
+        loop {
+            sequences.retain(|seq| !seq.is_empty());
+
+            if sequences.is_empty() {
+                break;
+            }
+
+            // First head that doesn't appear in the tail of any sequence
+            let head = sequences
+                .iter()
+                .map(|seq| &seq[0])
+                .find(|head| {
+                    !sequences
+                        .iter()
+                        .any(|seq| seq[1..].iter().any(|class| Rc::ptr_eq(class, head)))
+                })
+                .cloned();
+
+            let Some(head) = head else {
+                let names = bases
+                    .iter()
+                    .map(|base| base.name.as_str())
+                    .collect::<Vec<_>>();
+
+                return Err(RuntimeError::type_error(format!(
+                    "Cannot create a consistent method resolution order (MRO) for bases {}",
+                    names.join(", ")
+                )));
+            };
+
+            for seq in &mut sequences {
+                if Rc::ptr_eq(&seq[0], &head) {
+                    seq.remove(0);
+                }
+            }
+
+            mro.push(head);
+        }
+
+        Ok(Self {
+            name: name.into(),
+            bases,
+            mro,
+            attrs: RefCell::default(),
+            exception: None,
+        })
+    }
+
+    /// The class itself followed by its ancestors, in lookup order
+    pub fn linearization(&self) -> impl Iterator<Item = &RuntimeClass> {
+        std::iter::once(self).chain(self.mro.iter().map(|class| &**class))
+    }
+
+    pub fn is_subclass(&self, other: &RuntimeClass) -> bool {
+        self.linearization().any(|class| std::ptr::eq(class, other))
+    }
+
+    pub fn is_exception(&self) -> bool {
+        self.is_subclass(&ExceptionKind::BaseException.class())
+    }
+
+    /// Looks `name` up through the method resolution order
+    pub fn lookup(&self, name: &str) -> Option<RuntimeValue> {
+        self.linearization()
+            .find_map(|class| class.attrs.borrow().get(name).cloned())
+    }
+}
+
+impl RuntimeSuper {
+    /// Looks `name` up in the classes following `class`
+    pub fn lookup(&self, name: &str) -> Option<RuntimeValue> {
+        self.receiver
+            .class
+            .linearization()
+            .skip_while(|class| !std::ptr::eq(*class, &*self.class))
+            .skip(1)
+            .find_map(|class| class.attrs.borrow().get(name).cloned())
+    }
+}
+
+impl RuntimeObject {
+    pub fn new(class: Rc<RuntimeClass>) -> Self {
+        Self {
+            class,
+            attrs: RefCell::default(),
+        }
+    }
+
+    pub fn get_attr(&self, name: &str) -> Option<RuntimeValue> {
+        self.attrs.borrow().get(name).cloned()
+    }
+
+    pub fn set_attr(&self, name: impl Into<String>, value: RuntimeValue) {
+        let previous = self.attrs.borrow_mut().insert(name.into(), value);
+        drop(previous);
+    }
+}
+
+impl fmt::Debug for RuntimeClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<class '{}'>", self.name)
+    }
+}
+
+impl fmt::Debug for RuntimeObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(&self.class.name)
+            .field("attrs", &self.attrs.borrow())
+            .finish()
+    }
+}
+
+impl fmt::Debug for RuntimeSuper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "<super: <class '{}'>, <{} object>>",
+            self.class.name, self.receiver.class.name
+        )
+    }
+}
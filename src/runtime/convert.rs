@@ -0,0 +1,331 @@
+use core::fmt;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use super::dict::RuntimeDict;
+use super::error::{ExceptionKind, RuntimeError};
+use super::value::RuntimeValue;
+
+/// Rust values that can be taken out of script values
+pub trait FromValue: Sized {
+    fn from_value(value: &RuntimeValue) -> Result<Self, ConversionError>;
+}
+
+/// Rust values that can be handed to scripts
+pub trait IntoValue {
+    fn into_value(self) -> Result<RuntimeValue, ConversionError>;
+}
+
+/// Why a value couldn't be converted, raised as an exception in scripts
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConversionError {
+    pub kind: ExceptionKind,
+    /// Position of the bad value inside containers, outermost first, like `[2]` or `["key"]`
+    pub path: Vec<String>,
+    /// What's wrong with the value, like `must be int, not str`
+    pub message: String,
+}
+
+impl ConversionError {
+    pub fn wrong_type(expected: &str, found: &RuntimeValue) -> Self {
+        Self {
+            kind: ExceptionKind::TypeError,
+            path: Vec::new(),
+            message: format!("must be {expected}, not {}", found.type_name()),
+        }
+    }
+
+    /// The value has the right type but doesn't fit in `target`
+    pub fn out_of_range(value: impl fmt::Display, target: &str) -> Self {
+        Self {
+            kind: ExceptionKind::OverflowError,
+            path: Vec::new(),
+            message: format!("is out of range for {target} ({value})"),
+        }
+    }
+
+    /// Marks the error as coming from an item inside a container
+    pub fn inside(mut self, position: impl fmt::Display) -> Self {
+        self.path.insert(0, format!("[{position}]"));
+        self
+    }
+
+    /// Full message naming the converted value `subject`, like `f() argument 1`
+    pub fn describe(&self, subject: &str) -> String {
+        format!("{subject}{} {}", self.path.concat(), self.message)
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.describe("value"))
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl From<ConversionError> for RuntimeError {
+    fn from(error: ConversionError) -> Self {
+        RuntimeError::new(error.kind, error)
+    }
+}
+
+impl FromValue for RuntimeValue {
+    fn from_value(value: &RuntimeValue) -> Result<Self, ConversionError> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoValue for RuntimeValue {
+    fn into_value(self) -> Result<RuntimeValue, ConversionError> {
+        Ok(self)
+    }
+}
+
+impl FromValue for () {
+    fn from_value(value: &RuntimeValue) -> Result<Self, ConversionError> {
+        match value {
+            RuntimeValue::Nil => Ok(()),
+            value => Err(ConversionError::wrong_type("nil", value)),
+        }
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Result<RuntimeValue, ConversionError> {
+        Ok(RuntimeValue::Nil)
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &RuntimeValue) -> Result<Self, ConversionError> {
+        match value {
+            RuntimeValue::Bool(b) => Ok(*b),
+            value => Err(ConversionError::wrong_type("bool", value)),
+        }
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Result<RuntimeValue, ConversionError> {
+        Ok(RuntimeValue::Bool(self))
+    }
+}
+
+macro_rules! impl_int {
+    ($($int:ty),+) => {$(
+        impl FromValue for $int {
+            fn from_value(value: &RuntimeValue) -> Result<Self, ConversionError> {
+                let int = value
+                    .as_int()
+                    .ok_or_else(|| ConversionError::wrong_type("int", value))?;
+
+                <$int>::try_from(int)
+                    .map_err(|_| ConversionError::out_of_range(int, stringify!($int)))
+            }
+        }
+
+        impl IntoValue for $int {
+            fn into_value(self) -> Result<RuntimeValue, ConversionError> {
+                i64::try_from(self)
+                    .map(RuntimeValue::Number)
+                    .map_err(|_| ConversionError::out_of_range(self, "int"))
+            }
+        }
+    )+};
+}
+
+impl_int!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+);
+
+/// Scripts only have ints, floats convert when they have no fractional part
+macro_rules! impl_float {
+    ($($float:ty),+) => {$(
+        impl FromValue for $float {
+            fn from_value(value: &RuntimeValue) -> Result<Self, ConversionError> {
+                value
+                    .as_int()
+                    .map(|int| int as $float)
+                    .ok_or_else(|| ConversionError::wrong_type("int", value))
+            }
+        }
+
+        impl IntoValue for $float {
+            fn into_value(self) -> Result<RuntimeValue, ConversionError> {
+                let int = self as i64;
+
+                match int as $float == self {
+                    true => Ok(RuntimeValue::Number(int)),
+                    false => Err(ConversionError {
+                        kind: ExceptionKind::ValueError,
+                        path: Vec::new(),
+                        message: format!("must be a whole number to become an int ({self})"),
+                    }),
+                }
+            }
+        }
+    )+};
+}
+
+impl_float!(f32, f64);
+
+impl FromValue for String {
+    fn from_value(value: &RuntimeValue) -> Result<Self, ConversionError> {
+        match value {
+            RuntimeValue::String(s) => Ok(s.clone()),
+            value => Err(ConversionError::wrong_type("str", value)),
+        }
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Result<RuntimeValue, ConversionError> {
+        Ok(RuntimeValue::String(self))
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Result<RuntimeValue, ConversionError> {
+        Ok(RuntimeValue::String(self.to_owned()))
+    }
+}
+
+/// `nil` is `None`
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &RuntimeValue) -> Result<Self, ConversionError> {
+        match value {
+            RuntimeValue::Nil => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Result<RuntimeValue, ConversionError> {
+        match self {
+            Some(value) => value.into_value(),
+            None => Ok(RuntimeValue::Nil),
+        }
+    }
+}
+
+/// From lists and tuples
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &RuntimeValue) -> Result<Self, ConversionError> {
+        let items = match value {
+            RuntimeValue::List(items) => items.borrow().clone(),
+            RuntimeValue::Tuple(items) => items.to_vec(),
+            value => return Err(ConversionError::wrong_type("list", value)),
+        };
+
+        items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| T::from_value(item).map_err(|err| err.inside(idx)))
+            .collect()
+    }
+}
+
+/// Into lists
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Result<RuntimeValue, ConversionError> {
+        let items = self
+            .into_iter()
+            .enumerate()
+            .map(|(idx, item)| item.into_value().map_err(|err| err.inside(idx)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(RuntimeValue::List(Rc::new(RefCell::new(items))))
+    }
+}
+
+/// Dicts whose keys are all strings
+macro_rules! impl_map {
+    ($($map:ident),+) => {$(
+        impl<T: FromValue> FromValue for $map<String, T> {
+            fn from_value(value: &RuntimeValue) -> Result<Self, ConversionError> {
+                let RuntimeValue::Dict(dict) = value else {
+                    return Err(ConversionError::wrong_type("dict", value));
+                };
+
+                dict.borrow()
+                    .iter()
+                    .map(|(key, value)| {
+                        let RuntimeValue::String(name) = key else {
+                            return Err(ConversionError {
+                                kind: ExceptionKind::TypeError,
+                                path: Vec::new(),
+                                message: format!("keys must be str, not {}", key.type_name()),
+                            });
+                        };
+
+                        let value = T::from_value(value).map_err(|err| err.inside(key.repr()))?;
+                        Ok((name.clone(), value))
+                    })
+                    .collect()
+            }
+        }
+
+        impl<T: IntoValue> IntoValue for $map<String, T> {
+            fn into_value(self) -> Result<RuntimeValue, ConversionError> {
+                let mut dict = RuntimeDict::default();
+
+                for (key, value) in self {
+                    let key = RuntimeValue::String(key);
+                    let value = value.into_value().map_err(|err| err.inside(key.repr()))?;
+                    dict.insert(key, value).expect("strings are hashable");
+                }
+
+                Ok(RuntimeValue::Dict(Rc::new(RefCell::new(dict))))
+            }
+        }
+    )+};
+}
+
+impl_map!(HashMap, BTreeMap);
+
+/// From tuples and lists of the same length, into tuples
+macro_rules! impl_tuple {
+    ($len:literal; $($idx:tt $item:ident),+) => {
+        impl<$($item: FromValue),+> FromValue for ($($item,)+) {
+            fn from_value(value: &RuntimeValue) -> Result<Self, ConversionError> {
+                let items = match value {
+                    RuntimeValue::List(items) => items.borrow().clone(),
+                    RuntimeValue::Tuple(items) => items.to_vec(),
+                    value => return Err(ConversionError::wrong_type("tuple", value)),
+                };
+
+                if items.len() != $len {
+                    return Err(ConversionError {
+                        kind: ExceptionKind::ValueError,
+                        path: Vec::new(),
+                        message: format!("must have {} items, not {}", $len, items.len()),
+                    });
+                }
+
+                Ok(($(
+                    $item::from_value(&items[$idx]).map_err(|err| err.inside($idx))?,
+                )+))
+            }
+        }
+
+        impl<$($item: IntoValue),+> IntoValue for ($($item,)+) {
+            fn into_value(self) -> Result<RuntimeValue, ConversionError> {
+                let items = [$(
+                    self.$idx.into_value().map_err(|err| err.inside($idx))?,
+                )+];
+
+                Ok(RuntimeValue::Tuple(items.into()))
+            }
+        }
+    };
+}
+
+impl_tuple!(1; 0 A);
+impl_tuple!(2; 0 A, 1 B);
+impl_tuple!(3; 0 A, 1 B, 2 C);
+impl_tuple!(4; 0 A, 1 B, 2 C, 3 D);
+impl_tuple!(5; 0 A, 1 B, 2 C, 3 D, 4 E);
+impl_tuple!(6; 0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
@@ -0,0 +1,134 @@
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use super::builtins::Builtin;
+use super::error::{ExceptionKind, RuntimeError, RuntimeResult};
+use super::value::RuntimeValue;
+
+/// Hashable form of a value, equal for values that compare equal
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Nil,
+    NotImplemented,
+    /// Numbers and bools, `True` is the same key as `1`
+    Int(i64),
+    String(String),
+    Tuple(Vec<HashKey>),
+    Builtin(Builtin),
+    /// Functions, classes and instances are compared by identity
+    Identity(usize),
+}
+
+/// Insertion-ordered mapping, like Python's `dict`
+#[derive(Clone, Debug, Default)]
+pub struct RuntimeDict {
+    /// Original key along with the value
+    entries: IndexMap<HashKey, (RuntimeValue, RuntimeValue)>,
+}
+
+impl HashKey {
+    pub fn new(value: &RuntimeValue) -> RuntimeResult<Self> {
+        let identity = |ptr: *const ()| HashKey::Identity(ptr as usize);
+
+        Ok(match value {
+            RuntimeValue::Nil => HashKey::Nil,
+            RuntimeValue::NotImplemented => HashKey::NotImplemented,
+            RuntimeValue::Bool(_) | RuntimeValue::Number(_) => {
+                HashKey::Int(value.as_int().unwrap_or_default())
+            }
+            RuntimeValue::String(s) => HashKey::String(s.clone()),
+            RuntimeValue::Tuple(items) => HashKey::Tuple(
+                items
+                    .iter()
+                    .map(HashKey::new)
+                    .collect::<RuntimeResult<_>>()?,
+            ),
+            RuntimeValue::Builtin(builtin) => HashKey::Builtin(*builtin),
+            RuntimeValue::Function(function) => identity(Rc::as_ptr(function).cast()),
+            RuntimeValue::NativeFunction(function) => identity(Rc::as_ptr(function).cast()),
+            RuntimeValue::Method(method) => identity(Rc::as_ptr(method).cast()),
+            RuntimeValue::Class(class) => identity(Rc::as_ptr(class).cast()),
+            RuntimeValue::Object(object) => identity(Rc::as_ptr(object).cast()),
+            RuntimeValue::NativeObject(object) => identity(Rc::as_ptr(object).cast()),
+            RuntimeValue::Super(proxy) => identity(Rc::as_ptr(proxy).cast()),
+            RuntimeValue::Generator(generator) => identity(Rc::as_ptr(generator).cast()),
+            RuntimeValue::Module(module) => identity(Rc::as_ptr(module).cast()),
+            RuntimeValue::List(_) | RuntimeValue::Dict(_) | RuntimeValue::Set(_) => {
+                return Err(RuntimeError::type_error(format!(
+                    "unhashable type: '{}'",
+                    value.type_name()
+                )));
+            }
+        })
+    }
+}
+
+impl RuntimeDict {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &RuntimeValue) -> RuntimeResult<Option<RuntimeValue>> {
+        let hash = HashKey::new(key)?;
+        Ok(self.entries.get(&hash).map(|(_, value)| value.clone()))
+    }
+
+    /// Same as [`RuntimeDict::get`], raising `KeyError` if missing
+    pub fn get_item(&self, key: &RuntimeValue) -> RuntimeResult<RuntimeValue> {
+        self.get(key)?
+            .ok_or_else(|| RuntimeError::new(ExceptionKind::KeyError, key.repr()))
+    }
+
+    /// Inserts or replaces the value of `key`, keeping its position if present, returns the replaced value
+    pub fn insert(
+        &mut self,
+        key: RuntimeValue,
+        value: RuntimeValue,
+    ) -> RuntimeResult<Option<RuntimeValue>> {
+        let hash = HashKey::new(&key)?;
+
+        Ok(match self.entries.get_mut(&hash) {
+            Some((_, old)) => Some(std::mem::replace(old, value)),
+            None => {
+                self.entries.insert(hash, (key, value));
+                None
+            }
+        })
+    }
+
+    /// Removes `key` keeping the order of the others
+    pub fn remove(&mut self, key: &RuntimeValue) -> RuntimeResult<Option<RuntimeValue>> {
+        let hash = HashKey::new(key)?;
+        Ok(self.entries.shift_remove(&hash).map(|(_, value)| value))
+    }
+
+    /// Keys and values in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (&RuntimeValue, &RuntimeValue)> {
+        self.entries.values().map(|(key, value)| (key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &RuntimeValue> {
+        self.iter().map(|(key, _)| key)
+    }
+}
+
+impl FromIterator<(String, RuntimeValue)> for RuntimeDict {
+    fn from_iter<T: IntoIterator<Item = (String, RuntimeValue)>>(iter: T) -> Self {
+        Self {
+            entries: iter
+                .into_iter()
+                .map(|(key, value)| {
+                    (
+                        HashKey::String(key.clone()),
+                        (RuntimeValue::String(key), value),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,474 @@
+use core::fmt;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::builtins::Builtin;
+use super::class::{RuntimeClass, RuntimeObject};
+use super::limits::Limit;
+use super::value::RuntimeValue;
+use crate::diagnostic::{Diagnostic, codes};
+use crate::lexer::span::Span;
+use crate::source_map::SourceLookup;
+
+pub type RuntimeResult<T = ()> = Result<T, RuntimeError>;
+
+/// Built-in exception types
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExceptionKind {
+    BaseException,
+    Exception,
+    ArithmeticError,
+    AssertionError,
+    AttributeError,
+    GeneratorExit,
+    ImportError,
+    IndexError,
+    KeyError,
+    LookupError,
+    MemoryError,
+    ModuleNotFoundError,
+    NameError,
+    OverflowError,
+    RecursionError,
+    RuntimeError,
+    StopIteration,
+    SyntaxError,
+    TypeError,
+    UnboundLocalError,
+    ValueError,
+    ZeroDivisionError,
+}
+
+/// Location where an exception went through, one per frame
+#[derive(Clone, Debug)]
+pub struct TraceFrame {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct RuntimeError {
+    /// Instance of an exception class
+    pub exception: Rc<RuntimeObject>,
+    /// Innermost frame first
+    pub traceback: Vec<TraceFrame>,
+    /// Set by `raise ... from cause`
+    pub cause: Option<Box<RuntimeError>>,
+    /// Exception that was being handled when this one was raised
+    pub context: Option<Box<RuntimeError>>,
+    /// Set by `raise ... from nil`, hides the context in tracebacks
+    pub suppress_context: bool,
+    /// Set when the script went over one of its limits
+    limit: Option<Limit>,
+    /// Whether the current frame already recorded where it failed
+    located: bool,
+}
+
+thread_local! {
+    static EXCEPTION_CLASSES: RefCell<HashMap<ExceptionKind, Rc<RuntimeClass>>> =
+        RefCell::default();
+}
+
+impl ExceptionKind {
+    pub const ALL: &[Self] = &[
+        Self::BaseException,
+        Self::Exception,
+        Self::ArithmeticError,
+        Self::AssertionError,
+        Self::AttributeError,
+        Self::GeneratorExit,
+        Self::ImportError,
+        Self::IndexError,
+        Self::KeyError,
+        Self::LookupError,
+        Self::MemoryError,
+        Self::ModuleNotFoundError,
+        Self::NameError,
+        Self::OverflowError,
+        Self::RecursionError,
+        Self::RuntimeError,
+        Self::StopIteration,
+        Self::SyntaxError,
+        Self::TypeError,
+        Self::UnboundLocalError,
+        Self::ValueError,
+        Self::ZeroDivisionError,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExceptionKind::BaseException => "BaseException",
+            ExceptionKind::Exception => "Exception",
+            ExceptionKind::ArithmeticError => "ArithmeticError",
+            ExceptionKind::AssertionError => "AssertionError",
+            ExceptionKind::AttributeError => "AttributeError",
+            ExceptionKind::GeneratorExit => "GeneratorExit",
+            ExceptionKind::ImportError => "ImportError",
+            ExceptionKind::IndexError => "IndexError",
+            ExceptionKind::KeyError => "KeyError",
+            ExceptionKind::LookupError => "LookupError",
+            ExceptionKind::MemoryError => "MemoryError",
+            ExceptionKind::ModuleNotFoundError => "ModuleNotFoundError",
+            ExceptionKind::NameError => "NameError",
+            ExceptionKind::OverflowError => "OverflowError",
+            ExceptionKind::RecursionError => "RecursionError",
+            ExceptionKind::RuntimeError => "RuntimeError",
+            ExceptionKind::StopIteration => "StopIteration",
+            ExceptionKind::SyntaxError => "SyntaxError",
+            ExceptionKind::TypeError => "TypeError",
+            ExceptionKind::UnboundLocalError => "UnboundLocalError",
+            ExceptionKind::ValueError => "ValueError",
+            ExceptionKind::ZeroDivisionError => "ZeroDivisionError",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.name() == name)
+    }
+
+    pub fn base(self) -> Option<Self> {
+        match self {
+            ExceptionKind::BaseException => None,
+            ExceptionKind::Exception | ExceptionKind::GeneratorExit => {
+                Some(ExceptionKind::BaseException)
+            }
+            ExceptionKind::ArithmeticError
+            | ExceptionKind::AssertionError
+            | ExceptionKind::AttributeError
+            | ExceptionKind::ImportError
+            | ExceptionKind::LookupError
+            | ExceptionKind::MemoryError
+            | ExceptionKind::NameError
+            | ExceptionKind::RuntimeError
+            | ExceptionKind::StopIteration
+            | ExceptionKind::SyntaxError
+            | ExceptionKind::TypeError
+            | ExceptionKind::ValueError => Some(ExceptionKind::Exception),
+            ExceptionKind::OverflowError | ExceptionKind::ZeroDivisionError => {
+                Some(ExceptionKind::ArithmeticError)
+            }
+            ExceptionKind::IndexError | ExceptionKind::KeyError => Some(ExceptionKind::LookupError),
+            ExceptionKind::ModuleNotFoundError => Some(ExceptionKind::ImportError),
+            ExceptionKind::UnboundLocalError => Some(ExceptionKind::NameError),
+            ExceptionKind::RecursionError => Some(ExceptionKind::RuntimeError),
+        }
+    }
+
+    /// Class shared by every script running on this thread
+    pub fn class(self) -> Rc<RuntimeClass> {
+        if let Some(class) = EXCEPTION_CLASSES.with_borrow(|classes| classes.get(&self).cloned()) {
+            return class;
+        }
+
+        let bases = self.base().map(Self::class).into_iter().collect();
+        let class = Rc::new(RuntimeClass {
+            exception: Some(self),
+            ..RuntimeClass::new(self.name(), bases).expect("exceptions have a single base")
+        });
+
+        if self == ExceptionKind::BaseException {
+            class.attrs.borrow_mut().insert(
+                "__init__".to_owned(),
+                RuntimeValue::Builtin(Builtin::ExceptionInit),
+            );
+        }
+
+        EXCEPTION_CLASSES.with_borrow_mut(|classes| classes.insert(self, class.clone()));
+
+        class
+    }
+}
+
+/// Creates an exception instance, storing its arguments in `args`
+pub fn new_exception(class: Rc<RuntimeClass>, args: Vec<RuntimeValue>) -> Rc<RuntimeObject> {
+    let exception = RuntimeObject::new(class);
+    exception.set_attr("args", RuntimeValue::Tuple(args.into()));
+    Rc::new(exception)
+}
+
+impl RuntimeError {
+    pub fn new(kind: ExceptionKind, message: impl fmt::Display) -> Self {
+        Self::from_exception(new_exception(
+            kind.class(),
+            Vec::from([RuntimeValue::String(message.to_string())]),
+        ))
+    }
+
+    pub fn from_exception(exception: Rc<RuntimeObject>) -> Self {
+        Self {
+            exception,
+            traceback: Vec::new(),
+            cause: None,
+            context: None,
+            suppress_context: false,
+            limit: None,
+            located: false,
+        }
+    }
+
+    /// Aborts a script that went over one of its limits, `except` and `finally` don't run
+    pub fn limit_exceeded(limit: Limit, message: impl fmt::Display) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Self::new(ExceptionKind::RuntimeError, message)
+        }
+    }
+
+    pub fn name_error(name: &str) -> Self {
+        Self::new(
+            ExceptionKind::NameError,
+            format!("name '{name}' is not defined"),
+        )
+    }
+
+    pub fn type_error(message: impl fmt::Display) -> Self {
+        Self::new(ExceptionKind::TypeError, message)
+    }
+
+    /// Raised when a generator finishes, `value` is the one it returned
+    pub fn stop_iteration(value: RuntimeValue) -> Self {
+        let args = match value {
+            RuntimeValue::Nil => Vec::new(),
+            ref value => Vec::from([value.clone()]),
+        };
+
+        let exception = new_exception(ExceptionKind::StopIteration.class(), args);
+        exception.set_attr("value", value);
+        Self::from_exception(exception)
+    }
+
+    /// Raised at the `yield` a generator is suspended at when it's closed
+    pub fn generator_exit() -> Self {
+        Self::from_exception(new_exception(
+            ExceptionKind::GeneratorExit.class(),
+            Vec::new(),
+        ))
+    }
+
+    pub fn attribute_error(value: &RuntimeValue, name: &str) -> Self {
+        let message = match value {
+            RuntimeValue::Class(class) => {
+                format!("type object '{}' has no attribute '{name}'", class.name)
+            }
+            RuntimeValue::Module(module) => {
+                format!("module '{}' has no attribute '{name}'", module.name)
+            }
+            value => format!("'{}' object has no attribute '{name}'", value.type_name()),
+        };
+
+        Self::new(ExceptionKind::AttributeError, message)
+    }
+
+    /// Built-in kind of the exception, `None` for other classes
+    pub fn kind(&self) -> Option<ExceptionKind> {
+        self.exception.class.exception
+    }
+
+    /// Limit the script went over, `None` for exceptions
+    pub fn limit(&self) -> Option<Limit> {
+        self.limit
+    }
+
+    pub fn class_name(&self) -> &str {
+        &self.exception.class.name
+    }
+
+    /// Same as `str(exception)`
+    pub fn message(&self) -> String {
+        exception_message(&self.exception)
+    }
+
+    /// Chains the error given in `raise ... from cause`
+    pub fn with_cause(mut self, cause: Option<RuntimeError>) -> Self {
+        let value = cause.as_ref().map_or(RuntimeValue::Nil, |cause| {
+            RuntimeValue::Object(cause.exception.clone())
+        });
+
+        self.exception.set_attr("__cause__", value);
+        self.suppress_context = true;
+        self.cause = cause.map(Box::new);
+        self
+    }
+
+    /// Records the error that was being handled, unless it's the same exception
+    pub fn with_context(mut self, context: &RuntimeError) -> Self {
+        if self.context.is_none() && !Rc::ptr_eq(&self.exception, &context.exception) {
+            self.exception.set_attr(
+                "__context__",
+                RuntimeValue::Object(context.exception.clone()),
+            );
+            self.context = Some(Box::new(context.clone()));
+        }
+
+        self
+    }
+
+    /// Adds a line shown after the message, stored in `__notes__` like `add_note()`
+    pub fn with_note(self, note: impl fmt::Display) -> Self {
+        let note = RuntimeValue::String(note.to_string());
+
+        match self.exception.get_attr("__notes__") {
+            Some(RuntimeValue::List(notes)) => notes.borrow_mut().push(note),
+            _ => self.exception.set_attr(
+                "__notes__",
+                RuntimeValue::List(Rc::new(RefCell::new(Vec::from([note])))),
+            ),
+        }
+
+        self
+    }
+
+    /// Notes added with [`RuntimeError::with_note`]
+    pub fn notes(&self) -> Vec<String> {
+        match self.exception.get_attr("__notes__") {
+            Some(RuntimeValue::List(notes)) => {
+                notes.borrow().iter().map(|note| note.to_string()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Records the innermost expression of the current frame
+    /// that was being evaluated when the error happened
+    pub fn locate(mut self, name: &str, span: Span) -> Self {
+        if !self.located {
+            self.located = true;
+            self.traceback.push(TraceFrame {
+                name: name.to_owned(),
+                span,
+            });
+        }
+
+        self
+    }
+
+    /// Leaves the current frame, so the caller can record its own location
+    pub fn unwind_frame(mut self) -> Self {
+        self.located = false;
+        self
+    }
+
+    /// Python-like traceback, most recent call last
+    pub fn traceback<'a>(&'a self, sources: &'a dyn SourceLookup) -> impl fmt::Display + 'a {
+        struct Traceback<'a>(&'a RuntimeError, &'a dyn SourceLookup);
+
+        impl fmt::Display for Traceback<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let Traceback(error, sources) = *self;
+
+                if let Some(cause) = &error.cause {
+                    writeln!(f, "{}", cause.traceback(sources))?;
+                    writeln!(
+                        f,
+                        "\nThe above exception was the direct cause of the following exception:\n"
+                    )?;
+                } else if let Some(context) =
+                    error.context.as_ref().filter(|_| !error.suppress_context)
+                {
+                    writeln!(f, "{}", context.traceback(sources))?;
+                    writeln!(
+                        f,
+                        "\nDuring handling of the above exception, another exception occurred:\n"
+                    )?;
+                }
+
+                writeln!(f, "Traceback (most recent call last):")?;
+
+                for frame in error.traceback.iter().rev() {
+                    let Some(file) = sources.lookup(frame.span.source) else {
+                        writeln!(f, "  File \"<unknown>\", in {}", frame.name)?;
+                        continue;
+                    };
+
+                    let pos = file.line_col(frame.span.from);
+                    let line = file.contents().lines().nth(pos.line).unwrap_or_default();
+
+                    writeln!(
+                        f,
+                        "  File \"{}\", line {}, in {}",
+                        file.name(),
+                        pos.line + 1,
+                        frame.name
+                    )?;
+                    writeln!(f, "    {}", line.trim())?;
+                }
+
+                write!(f, "{error}")?;
+
+                for note in error.notes() {
+                    write!(f, "\n{note}")?;
+                }
+
+                Ok(())
+            }
+        }
+
+        Traceback(self, sources)
+    }
+}
+
+/// `str()` of an exception: its only argument, or all of them as a tuple
+pub fn exception_message(exception: &RuntimeObject) -> String {
+    match exception.get_attr("args") {
+        Some(RuntimeValue::Tuple(args)) => match &args[..] {
+            [] => String::new(),
+            [arg] => arg.to_string(),
+            _ => RuntimeValue::Tuple(args).to_string(),
+        },
+        _ => String::new(),
+    }
+}
+
+impl fmt::Display for ExceptionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = self.message();
+
+        if message.is_empty() {
+            write!(f, "{}", self.class_name())
+        } else {
+            write!(f, "{}: {message}", self.class_name())
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(error: RuntimeError) -> Self {
+        let mut frames = error.traceback.iter();
+        let code = match error.limit {
+            Some(_) => codes::LIMIT_EXCEEDED,
+            None => codes::UNCAUGHT_EXCEPTION,
+        };
+        let mut diagnostic = Diagnostic::error(code, &error);
+
+        if let Some(cause) = &error.cause {
+            diagnostic = diagnostic.with_note(format!("caused by {cause}"));
+        } else if let Some(context) = error.context.as_ref().filter(|_| !error.suppress_context) {
+            diagnostic = diagnostic.with_note(format!("raised while handling {context}"));
+        }
+
+        for note in error.notes() {
+            diagnostic = diagnostic.with_note(note);
+        }
+
+        let Some(innermost) = frames.next() else {
+            return diagnostic;
+        };
+
+        frames.fold(
+            diagnostic.with_label(
+                innermost.span,
+                format!("{} raised here", error.class_name()),
+            ),
+            |diagnostic, frame| diagnostic.with_secondary(frame.span, format!("in {}", frame.name)),
+        )
+    }
+}
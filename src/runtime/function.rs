@@ -0,0 +1,230 @@
+use core::fmt;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use super::Scope;
+use super::class::RuntimeClass;
+use super::dict::RuntimeDict;
+use super::error::{RuntimeError, RuntimeResult};
+use super::value::{KeywordArgs, RuntimeValue};
+use crate::ast::node::{AstFunction, AstParamKind};
+
+/// Function defined by a script
+pub struct RuntimeFunction {
+    pub def: Rc<AstFunction>,
+    /// Scope where the function was defined, the parent of every call frame
+    pub scope: Rc<Scope>,
+    /// Class whose body defined the function, used by `super()`
+    pub owner: RefCell<Option<Weak<RuntimeClass>>>,
+    /// Default value of each parameter, evaluated when the function was defined
+    pub defaults: Vec<Option<RuntimeValue>>,
+}
+
+/// Function bound to the object it was looked up on
+#[derive(Debug)]
+pub struct RuntimeMethod {
+    pub receiver: RuntimeValue,
+    pub function: RuntimeValue,
+}
+
+impl RuntimeFunction {
+    pub fn name(&self) -> &str {
+        &self.def.name
+    }
+
+    /// Name prefixed by the owner class, like `Class.method`
+    pub fn qualname(&self) -> String {
+        match self.owner.borrow().as_ref().and_then(Weak::upgrade) {
+            Some(class) => format!("{}.{}", class.name, self.name()),
+            None => self.name().to_owned(),
+        }
+    }
+
+    /// Parameters as they were declared, with the values of their defaults
+    pub fn signature(&self) -> String {
+        let params = &self.def.params;
+        let mut parts = Vec::new();
+
+        for (idx, (param, default)) in params.iter().zip(&self.defaults).enumerate() {
+            let next = params.get(idx + 1).map(|param| param.kind);
+
+            if param.kind == AstParamKind::KeywordOnly
+                && idx.checked_sub(1).map(|idx| params[idx].kind) < Some(AstParamKind::VarArgs)
+            {
+                parts.push("*".to_owned());
+            }
+
+            parts.push(match (param.kind, default) {
+                (AstParamKind::VarArgs, _) => format!("*{}", param.name),
+                (AstParamKind::VarKeywords, _) => format!("**{}", param.name),
+                (_, Some(default)) => format!("{}={}", param.name, default.repr()),
+                (_, None) => param.name.clone(),
+            });
+
+            if param.kind == AstParamKind::PositionalOnly
+                && next != Some(AstParamKind::PositionalOnly)
+            {
+                parts.push("/".to_owned());
+            }
+        }
+
+        format!("{}({})", self.name(), parts.join(", "))
+    }
+
+    /// Binds the arguments of a call to the parameters, in declaration order
+    pub fn bind(
+        &self,
+        args: Vec<RuntimeValue>,
+        kwargs: KeywordArgs,
+    ) -> RuntimeResult<Vec<(String, RuntimeValue)>> {
+        let params = &self.def.params;
+        let mut values = vec![None; params.len()];
+
+        let positional = params
+            .iter()
+            .take_while(|param| param.kind <= AstParamKind::Normal)
+            .count();
+        let var_args = params
+            .iter()
+            .position(|param| param.kind == AstParamKind::VarArgs);
+        let var_keywords = params
+            .iter()
+            .position(|param| param.kind == AstParamKind::VarKeywords);
+
+        let given = args.len();
+        let mut args = args.into_iter();
+
+        for (value, arg) in values.iter_mut().zip(args.by_ref().take(positional)) {
+            *value = Some(arg);
+        }
+
+        match var_args {
+            Some(idx) => values[idx] = Some(RuntimeValue::Tuple(args.collect())),
+            None if given > positional => {
+                let required = self.defaults[..positional]
+                    .iter()
+                    .filter(|default| default.is_none())
+                    .count();
+                let takes = match required == positional {
+                    true => positional.to_string(),
+                    false => format!("from {required} to {positional}"),
+                };
+
+                return Err(RuntimeError::type_error(format!(
+                    "{}() takes {takes} positional argument{} but {given} {} given",
+                    self.name(),
+                    if positional == 1 { "" } else { "s" },
+                    if given == 1 { "was" } else { "were" },
+                )));
+            }
+            None => {}
+        }
+
+        let mut extra = RuntimeDict::default();
+        let mut positional_only = Vec::new();
+
+        for (name, value) in kwargs {
+            let idx = params.iter().position(|param| param.name == name);
+
+            match idx.map(|idx| (idx, params[idx].kind)) {
+                Some((idx, AstParamKind::Normal | AstParamKind::KeywordOnly)) => {
+                    if values[idx].replace(value).is_some() {
+                        return Err(RuntimeError::type_error(format!(
+                            "{}() got multiple values for argument '{name}'",
+                            self.name(),
+                        )));
+                    }
+                }
+                // Positional-only names are free to be used by `**kwargs`
+                _ if var_keywords.is_some() => {
+                    extra.insert(RuntimeValue::String(name), value)?;
+                }
+                Some((_, AstParamKind::PositionalOnly)) => positional_only.push(name),
+                _ => {
+                    return Err(RuntimeError::type_error(format!(
+                        "{}() got an unexpected keyword argument '{name}'",
+                        self.name(),
+                    )));
+                }
+            }
+        }
+
+        if !positional_only.is_empty() {
+            return Err(RuntimeError::type_error(format!(
+                "{}() got some positional-only arguments passed as keyword arguments: '{}'",
+                self.name(),
+                positional_only.join(", "),
+            )));
+        }
+
+        if let Some(idx) = var_keywords {
+            values[idx] = Some(RuntimeValue::Dict(Rc::new(RefCell::new(extra))));
+        }
+
+        for (value, default) in values.iter_mut().zip(&self.defaults) {
+            if value.is_none() {
+                *value = default.clone();
+            }
+        }
+
+        for keyword_only in [false, true] {
+            let missing = params
+                .iter()
+                .zip(&values)
+                .filter(|(param, value)| {
+                    value.is_none() && (param.kind == AstParamKind::KeywordOnly) == keyword_only
+                })
+                .map(|(param, _)| format!("'{}'", param.name))
+                .collect::<Vec<_>>();
+
+            if !missing.is_empty() {
+                return Err(RuntimeError::type_error(format!(
+                    "{}() missing {} required {} argument{}: {}",
+                    self.name(),
+                    missing.len(),
+                    if keyword_only {
+                        "keyword-only"
+                    } else {
+                        "positional"
+                    },
+                    if missing.len() == 1 { "" } else { "s" },
+                    name_list(&missing),
+                )));
+            }
+        }
+
+        Ok(params
+            .iter()
+            .zip(values)
+            .map(|(param, value)| (param.name.clone(), value.unwrap_or_default()))
+            .collect())
+    }
+}
+
+/// Joins names like Python's error messages, `'a', 'b', and 'c'`
+fn name_list(names: &[String]) -> String {
+    match names {
+        [] => String::new(),
+        [name] => name.clone(),
+        [first, second] => format!("{first} and {second}"),
+        [rest @ .., last] => format!("{}, and {last}", rest.join(", ")),
+    }
+}
+
+impl fmt::Display for RuntimeMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match &self.function {
+            RuntimeValue::Function(function) => function.qualname(),
+            RuntimeValue::Builtin(builtin) => builtin.name().to_owned(),
+            function => function.to_string(),
+        };
+
+        write!(f, "<bound method {name} of {}>", self.receiver.repr())
+    }
+}
+
+impl fmt::Debug for RuntimeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<function {}>", self.name())
+    }
+}
@@ -0,0 +1,303 @@
+use core::fmt;
+use std::cell::RefCell;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use corosensei::stack::Stack;
+use corosensei::{Coroutine, CoroutineResult, Yielder};
+
+use super::Scope;
+use super::error::{ExceptionKind, RuntimeError, RuntimeResult};
+use super::function::RuntimeFunction;
+use super::limits::new_stack;
+use super::value::RuntimeValue;
+use crate::ast::node::AstComprehension;
+
+/// Sent into a suspended generator function
+pub enum Resume {
+    Send(RuntimeValue),
+    /// Raises `GeneratorExit` at the `yield`
+    Close,
+}
+
+/// How a generator gave control back
+pub enum Resumed {
+    Yielded(RuntimeValue),
+    Returned(RuntimeValue),
+}
+
+pub type GeneratorYielder = Yielder<Resume, RuntimeValue>;
+
+type FunctionCoroutine = Coroutine<Resume, RuntimeValue, RuntimeResult<RuntimeValue>>;
+
+/// Source of the items a `for` clause goes through
+pub enum RuntimeIterator {
+    /// Items collected up front from a sequence or mapping
+    Items(std::vec::IntoIter<RuntimeValue>),
+    Generator(Rc<RuntimeGenerator>),
+}
+
+/// Runs the `for` clauses of a comprehension, one combination of items at a time
+pub struct Comprehension {
+    pub def: Rc<AstComprehension>,
+    /// Scope where the targets are bound, so they don't leak to the enclosing one
+    pub scope: Rc<Scope>,
+    /// One per clause entered, the innermost last
+    iterators: Vec<RuntimeIterator>,
+}
+
+/// Lazy iterator created by a generator expression or by calling a generator function
+pub struct RuntimeGenerator {
+    /// Shown in the repr
+    name: String,
+    /// Frame of the generator, whose budget counts it as a call while it runs
+    scope: Rc<Scope>,
+    state: RefCell<GeneratorState>,
+}
+
+enum GeneratorState {
+    Expression(Comprehension),
+    /// Generator function not started yet, its stack is allocated when it starts
+    Created(Rc<RuntimeFunction>),
+    /// Body of a generator function, suspended at a `yield`
+    Function {
+        coroutine: FunctionCoroutine,
+        /// Lowest address of the stack the body was suspended on
+        stack_limit: usize,
+    },
+    /// Exhausted, closed or after raising
+    Finished,
+}
+
+impl RuntimeIterator {
+    pub fn next_item(&mut self) -> RuntimeResult<Option<RuntimeValue>> {
+        match self {
+            RuntimeIterator::Items(items) => Ok(items.next()),
+            RuntimeIterator::Generator(generator) => generator.next(),
+        }
+    }
+}
+
+impl Comprehension {
+    /// Evaluates the outermost iterable in `enclosing`, the others are evaluated
+    /// in `scope` every time their clause is entered
+    pub fn new(
+        enclosing: &Rc<Scope>,
+        scope: Rc<Scope>,
+        def: &Rc<AstComprehension>,
+    ) -> RuntimeResult<Self> {
+        let outermost = &def.clauses[0].iter;
+        let iterable = enclosing.visit_expr(outermost)?;
+        let iterator = enclosing
+            .iterate(&iterable)
+            .map_err(|err| err.locate(&enclosing.frame, outermost.span))?;
+
+        Ok(Self {
+            def: def.clone(),
+            scope,
+            iterators: Vec::from([iterator]),
+        })
+    }
+
+    /// Binds the targets to the next items passing every condition, `false` once exhausted
+    pub fn advance(&mut self) -> RuntimeResult<bool> {
+        let scope = &self.scope;
+
+        'items: while let Some(depth) = self.iterators.len().checked_sub(1) {
+            let clause = &self.def.clauses[depth];
+
+            let Some(item) = self.iterators[depth].next_item()? else {
+                self.iterators.pop();
+                continue;
+            };
+
+            scope
+                .bind_target(&clause.target, item)
+                .map_err(|err| err.locate(&scope.frame, clause.target.span))?;
+
+            for condition in &clause.conditions {
+                if !scope.truthy(&scope.visit_expr(condition)?)? {
+                    continue 'items;
+                }
+            }
+
+            let Some(inner) = self.def.clauses.get(depth + 1) else {
+                return Ok(true);
+            };
+
+            let iterable = scope.visit_expr(&inner.iter)?;
+            let iterator = scope
+                .iterate(&iterable)
+                .map_err(|err| err.locate(&scope.frame, inner.iter.span))?;
+            self.iterators.push(iterator);
+        }
+
+        Ok(false)
+    }
+}
+
+impl RuntimeGenerator {
+    pub fn new(comprehension: Comprehension) -> Self {
+        Self {
+            name: "<genexpr>".to_owned(),
+            scope: comprehension.scope.clone(),
+            state: RefCell::new(GeneratorState::Expression(comprehension)),
+        }
+    }
+
+    /// Generator running the body of `function` in `frame`, whose arguments are already bound
+    pub fn from_function(function: Rc<RuntimeFunction>, frame: Rc<Scope>) -> Self {
+        Self {
+            name: function.name().to_owned(),
+            scope: frame,
+            state: RefCell::new(GeneratorState::Created(function)),
+        }
+    }
+
+    /// Coroutine running the body of `function`, on a stack of its own
+    fn start(&self, function: Rc<RuntimeFunction>) -> RuntimeResult<GeneratorState> {
+        let stack = new_stack()?;
+        let stack_limit = stack.limit().get();
+        let frame = self.scope.clone();
+
+        let coroutine = Coroutine::with_stack(stack, move |yielder: &GeneratorYielder, _| {
+            frame.yielder.set(Some(NonNull::from(yielder)));
+            let result = frame.run_frame(&function);
+            frame.yielder.set(None);
+            result
+        });
+
+        Ok(GeneratorState::Function {
+            coroutine,
+            stack_limit,
+        })
+    }
+
+    /// Runs until the next `yield` or until the generator finishes
+    pub fn resume(&self, input: Resume) -> RuntimeResult<Resumed> {
+        let Ok(mut state) = self.state.try_borrow_mut() else {
+            return Err(RuntimeError::new(
+                ExceptionKind::ValueError,
+                "generator already executing",
+            ));
+        };
+
+        if matches!(*state, GeneratorState::Finished) {
+            return Ok(Resumed::Returned(RuntimeValue::Nil));
+        }
+
+        let budget = self.scope.budget();
+        let _call = budget.enter_call()?;
+
+        if let GeneratorState::Created(function) = &*state {
+            match input {
+                Resume::Send(RuntimeValue::Nil) => *state = self.start(function.clone())?,
+                Resume::Send(_) => {
+                    return Err(RuntimeError::type_error(
+                        "can't send non-None value to a just-started generator",
+                    ));
+                }
+                Resume::Close => {
+                    *state = GeneratorState::Finished;
+                    return Ok(Resumed::Returned(RuntimeValue::Nil));
+                }
+            }
+        }
+
+        let result = match (&mut *state, input) {
+            (GeneratorState::Finished | GeneratorState::Created(_), _) => {
+                unreachable!("handled above")
+            }
+            (GeneratorState::Expression(_), Resume::Close) => {
+                Ok(Resumed::Returned(RuntimeValue::Nil))
+            }
+            (GeneratorState::Expression(comprehension), Resume::Send(_)) => budget.on_stack(|| {
+                comprehension.advance().and_then(|found| match found {
+                    true => comprehension
+                        .scope
+                        .visit_expr(&comprehension.def.element)
+                        .map(Resumed::Yielded),
+                    false => Ok(Resumed::Returned(RuntimeValue::Nil)),
+                })
+            }),
+            (
+                GeneratorState::Function {
+                    coroutine,
+                    stack_limit,
+                },
+                input,
+            ) => {
+                // The body runs on the coroutine's stack, not on the caller's,
+                // or on the one it moved to if it yielded from deep inside
+                let outer = budget.enter_stack(*stack_limit);
+                let result = Self::resume_function(coroutine, input);
+                *stack_limit = budget.leave_stack(outer).unwrap_or(*stack_limit);
+                result
+            }
+        };
+
+        if !matches!(result, Ok(Resumed::Yielded(_))) {
+            *state = GeneratorState::Finished;
+        }
+
+        result.map_err(RuntimeError::unwind_frame)
+    }
+
+    fn resume_function(coroutine: &mut FunctionCoroutine, input: Resume) -> RuntimeResult<Resumed> {
+        match coroutine.resume(input) {
+            CoroutineResult::Yield(value) => Ok(Resumed::Yielded(value)),
+            CoroutineResult::Return(Ok(value)) => Ok(Resumed::Returned(value)),
+            CoroutineResult::Return(Err(err))
+                if err.kind() == Some(ExceptionKind::StopIteration) =>
+            {
+                Err(RuntimeError::new(
+                    ExceptionKind::RuntimeError,
+                    "generator raised StopIteration",
+                )
+                .with_cause(Some(err)))
+            }
+            CoroutineResult::Return(Err(err)) => Err(err),
+        }
+    }
+
+    /// Evaluates the next item, `None` once exhausted
+    pub fn next(&self) -> RuntimeResult<Option<RuntimeValue>> {
+        match self.resume(Resume::Send(RuntimeValue::Nil))? {
+            Resumed::Yielded(value) => Ok(Some(value)),
+            Resumed::Returned(_) => Ok(None),
+        }
+    }
+
+    /// Raises `GeneratorExit` where the generator is suspended, so `finally` blocks run
+    pub fn close(&self) -> RuntimeResult {
+        match self.resume(Resume::Close) {
+            Ok(Resumed::Returned(_)) => Ok(()),
+            Err(err) if err.kind() == Some(ExceptionKind::GeneratorExit) => Ok(()),
+            Err(err) => Err(err),
+            Ok(Resumed::Yielded(_)) => Err(RuntimeError::new(
+                ExceptionKind::RuntimeError,
+                "generator ignored GeneratorExit",
+            )),
+        }
+    }
+}
+
+impl Drop for RuntimeGenerator {
+    fn drop(&mut self) {
+        let suspended = matches!(
+            self.state.get_mut(),
+            GeneratorState::Function { coroutine, .. } if !coroutine.done()
+        );
+
+        // Errors raised while closing are ignored, like Python does in `__del__`
+        if suspended && !std::thread::panicking() {
+            let _ = self.close();
+        }
+    }
+}
+
+impl fmt::Debug for RuntimeGenerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<generator object {}>", self.name)
+    }
+}
@@ -0,0 +1,278 @@
+use core::fmt;
+use std::cell::{Cell, RefCell};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use corosensei::stack::{DefaultStack, Stack};
+
+use super::error::{ExceptionKind, RuntimeError, RuntimeResult};
+use super::value::RuntimeValue;
+use crate::ast::MAX_NESTING;
+
+/// Steps between two checks of the clock and of the interruption flag
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Same as Python's default, deeper calls raise `RecursionError` even without
+/// [`Limits::max_call_depth`]
+pub const RECURSION_LIMIT: usize = 1000;
+
+/// Size of the stacks scripts run on, calls and generators alike
+pub(crate) const STACK_SIZE: usize = 1 << 20;
+
+/// Calls move to a new stack once less than this is left on the current one
+const STACK_RED_ZONE: usize = 256 << 10;
+
+/// Caps on the resources used by scripts, to run untrusted code.
+/// Going over one aborts the script with an error it can't catch, see [`RuntimeError::limit`]
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    /// Statements and expressions evaluated
+    pub max_steps: Option<u64>,
+    /// Calls to script functions and running generators, nested in each other
+    pub max_call_depth: Option<usize>,
+    /// Bytes of the strings and collections created, freed ones included
+    pub max_allocated_bytes: Option<usize>,
+    /// Wall-clock time, measured from the last [`Budget::reset`]
+    pub timeout: Option<Duration>,
+    /// Aborts the script once set, from any thread
+    pub interrupt: Option<Arc<AtomicBool>>,
+}
+
+/// Which of the [`Limits`] was exceeded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Steps,
+    CallDepth,
+    Memory,
+    Timeout,
+    Interrupted,
+}
+
+/// Usage of the [`Limits`], shared by every scope of an interpreter
+#[derive(Default)]
+pub struct Budget {
+    limits: RefCell<Limits>,
+    steps: Cell<u64>,
+    depth: Cell<usize>,
+    /// Expressions being evaluated by the innermost call
+    expressions: Cell<usize>,
+    allocated: Cell<usize>,
+    deadline: Cell<Option<Instant>>,
+    /// Lowest address of the stack scripts are running on, `None` on the host's stack
+    stack_limit: Cell<Option<usize>>,
+    /// Stacks of the calls that returned, reused by the next ones
+    spare_stacks: RefCell<Vec<DefaultStack>>,
+}
+
+/// Counts a running call until it's dropped, see [`Budget::enter_call`].
+/// Keeps the expressions of the caller being evaluated, to restore them
+pub struct CallGuard<'a>(&'a Budget, usize);
+
+/// Counts an expression being evaluated until it's dropped, see [`Budget::enter_expr`].
+/// Keeps how many were being evaluated before it, to restore them
+pub struct ExprGuard<'a>(&'a Budget, usize);
+
+impl Budget {
+    /// Replaces the limits and resets their usage
+    pub fn set_limits(&self, limits: Limits) {
+        *self.limits.borrow_mut() = limits;
+        self.reset();
+    }
+
+    /// Starts a new run: steps and allocations count from zero and the timeout starts now
+    pub fn reset(&self) {
+        self.steps.set(0);
+        self.allocated.set(0);
+        self.deadline.set(
+            self.limits
+                .borrow()
+                .timeout
+                .map(|timeout| Instant::now() + timeout),
+        );
+    }
+
+    /// Counts the evaluation of a statement or expression
+    pub fn step(&self) -> RuntimeResult {
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+
+        let limits = self.limits.borrow();
+
+        if let Some(max) = limits.max_steps
+            && steps > max
+        {
+            return Err(exceeded(Limit::Steps, format!("{max} steps")));
+        }
+
+        if steps % CHECK_INTERVAL != 1 {
+            return Ok(());
+        }
+
+        if let Some(interrupt) = &limits.interrupt
+            && interrupt.load(Ordering::Relaxed)
+        {
+            return Err(RuntimeError::limit_exceeded(
+                Limit::Interrupted,
+                "script was interrupted",
+            ));
+        }
+
+        match (limits.timeout, self.deadline.get()) {
+            (Some(timeout), Some(deadline)) if Instant::now() > deadline => {
+                Err(RuntimeError::limit_exceeded(
+                    Limit::Timeout,
+                    format!("script exceeded the timeout of {timeout:?}"),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts a call to a script function, or a running generator, until the guard is dropped
+    pub fn enter_call(&self) -> RuntimeResult<CallGuard<'_>> {
+        let depth = self.depth.get() + 1;
+
+        if let Some(max) = self.limits.borrow().max_call_depth
+            && depth > max
+        {
+            return Err(exceeded(Limit::CallDepth, format!("{max} nested calls")));
+        }
+
+        if depth > RECURSION_LIMIT {
+            return Err(RuntimeError::new(
+                ExceptionKind::RecursionError,
+                "maximum recursion depth exceeded",
+            ));
+        }
+
+        self.depth.set(depth);
+        Ok(CallGuard(self, self.expressions.replace(0)))
+    }
+
+    /// Counts an expression being evaluated until the guard is dropped. Calls count
+    /// their own, so this only bounds how deeply one body nests its expressions
+    pub fn enter_expr(&self) -> RuntimeResult<ExprGuard<'_>> {
+        let outer = self.expressions.get();
+
+        if outer >= MAX_NESTING {
+            return Err(RuntimeError::new(
+                ExceptionKind::RecursionError,
+                "maximum expression depth exceeded",
+            ));
+        }
+
+        self.expressions.set(outer + 1);
+        Ok(ExprGuard(self, outer))
+    }
+
+    /// Runs `f` on a new stack if the current one is running out, or is the host's,
+    /// whose size isn't known. Deep recursion then fails with [`RECURSION_LIMIT`]
+    /// instead of overflowing the host's stack
+    pub fn on_stack<T>(&self, f: impl FnOnce() -> RuntimeResult<T>) -> RuntimeResult<T> {
+        let marker = 0u8;
+        let sp = std::hint::black_box(&marker) as *const u8 as usize;
+
+        if let Some(limit) = self.stack_limit.get()
+            && sp.saturating_sub(limit) > STACK_RED_ZONE
+        {
+            return f();
+        }
+
+        let mut stack = match self.spare_stacks.borrow_mut().pop() {
+            Some(stack) => stack,
+            None => new_stack()?,
+        };
+
+        let outer = self.enter_stack(stack.limit().get());
+        let result = corosensei::on_stack(&mut stack, f);
+        self.leave_stack(outer);
+
+        self.spare_stacks.borrow_mut().push(stack);
+        result
+    }
+
+    /// Marks the stack whose lowest address is `limit` as the one scripts run on,
+    /// returns the previous one to restore once they leave it
+    pub(crate) fn enter_stack(&self, limit: usize) -> Option<usize> {
+        self.stack_limit.replace(Some(limit))
+    }
+
+    /// Restores the stack of [`Budget::enter_stack`], returns the limit of the one left
+    pub(crate) fn leave_stack(&self, outer: Option<usize>) -> Option<usize> {
+        self.stack_limit.replace(outer)
+    }
+
+    /// Counts `bytes` allocated for a string or collection, before creating it
+    pub fn allocate(&self, bytes: usize) -> RuntimeResult {
+        let allocated = self.allocated.get().saturating_add(bytes);
+
+        if let Some(max) = self.limits.borrow().max_allocated_bytes
+            && allocated > max
+        {
+            return Err(exceeded(Limit::Memory, format!("{max} allocated bytes")));
+        }
+
+        self.allocated.set(allocated);
+        Ok(())
+    }
+
+    /// Same as [`Budget::allocate`] for a collection of `count` values
+    pub fn allocate_values(&self, count: usize) -> RuntimeResult {
+        self.allocate(count.saturating_mul(size_of::<RuntimeValue>()))
+    }
+
+    /// Counts a string or collection just created, when its size is bounded by existing values
+    pub fn allocate_value(&self, value: &RuntimeValue) -> RuntimeResult {
+        match value {
+            RuntimeValue::String(s) => self.allocate(s.len()),
+            RuntimeValue::Tuple(items) => self.allocate_values(items.len()),
+            RuntimeValue::List(items) => self.allocate_values(items.borrow().len()),
+            RuntimeValue::Dict(dict) => self.allocate_values(dict.borrow().len() * 2),
+            RuntimeValue::Set(set) => self.allocate_values(set.borrow().len()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Leaves out the spare stacks, which don't implement `Debug`
+impl fmt::Debug for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Budget")
+            .field("limits", &self.limits)
+            .field("steps", &self.steps)
+            .field("depth", &self.depth)
+            .field("expressions", &self.expressions)
+            .field("allocated", &self.allocated)
+            .field("deadline", &self.deadline)
+            .field("stack_limit", &self.stack_limit)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for CallGuard<'_> {
+    fn drop(&mut self) {
+        self.0.depth.set(self.0.depth.get() - 1);
+        self.0.expressions.set(self.1);
+    }
+}
+
+impl Drop for ExprGuard<'_> {
+    fn drop(&mut self) {
+        self.0.expressions.set(self.1);
+    }
+}
+
+/// Stack of [`STACK_SIZE`] bytes, failing with `MemoryError` if it can't be mapped
+pub(crate) fn new_stack() -> RuntimeResult<DefaultStack> {
+    DefaultStack::new(STACK_SIZE).map_err(|err| {
+        RuntimeError::new(
+            ExceptionKind::MemoryError,
+            format!("cannot allocate a stack: {err}"),
+        )
+    })
+}
+
+fn exceeded(limit: Limit, what: impl fmt::Display) -> RuntimeError {
+    RuntimeError::limit_exceeded(limit, format!("script exceeded the limit of {what}"))
+}
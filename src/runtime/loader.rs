@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Extension of module files
+pub const EXTENSION: &str = "pi";
+
+/// File run when importing a package directory
+pub const PACKAGE_INIT: &str = "__init__.pi";
+
+/// Code of a module found by a [`ModuleLoader`]
+#[derive(Clone, Debug)]
+pub struct ModuleSource {
+    /// Shown in tracebacks and in the module's repr, it doesn't need to exist
+    pub path: PathBuf,
+    pub contents: String,
+    /// Packages can hold submodules and resolve relative imports from themselves
+    pub is_package: bool,
+}
+
+/// Where imported modules come from
+pub trait ModuleLoader {
+    /// Source of the module with the absolute dotted `name`, `None` if there isn't one
+    fn load(&self, name: &str) -> io::Result<Option<ModuleSource>>;
+}
+
+/// Looks for `name.pi` or `name/__init__.pi` in each directory of a search path
+#[derive(Clone, Debug, Default)]
+pub struct FileLoader {
+    search_path: Vec<PathBuf>,
+}
+
+/// Modules kept in memory, by dotted name
+#[derive(Clone, Debug, Default)]
+pub struct MemoryLoader {
+    modules: HashMap<String, ModuleSource>,
+}
+
+impl FileLoader {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        Self { search_path }
+    }
+
+    pub fn search_path(&self) -> &[PathBuf] {
+        &self.search_path
+    }
+}
+
+impl ModuleLoader for FileLoader {
+    fn load(&self, name: &str) -> io::Result<Option<ModuleSource>> {
+        let relative = name.split('.').collect::<PathBuf>();
+
+        for dir in &self.search_path {
+            let base = dir.join(&relative);
+
+            for (path, is_package) in [
+                (base.join(PACKAGE_INIT), true),
+                (base.with_extension(EXTENSION), false),
+            ] {
+                if path.is_file() {
+                    let contents = fs::read_to_string(&path)?;
+
+                    return Ok(Some(ModuleSource {
+                        path,
+                        contents,
+                        is_package,
+                    }));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl MemoryLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds module `name`, shown as if it was loaded from `a/b.pi`
+    pub fn with_module(mut self, name: &str, contents: impl Into<String>) -> Self {
+        self.insert(name, contents.into(), false);
+        self
+    }
+
+    /// Adds package `name`, shown as if it was loaded from `a/b/__init__.pi`
+    pub fn with_package(mut self, name: &str, contents: impl Into<String>) -> Self {
+        self.insert(name, contents.into(), true);
+        self
+    }
+
+    fn insert(&mut self, name: &str, contents: String, is_package: bool) {
+        let base = name.split('.').collect::<PathBuf>();
+        let path = match is_package {
+            true => base.join(PACKAGE_INIT),
+            false => base.with_extension(EXTENSION),
+        };
+
+        self.modules.insert(
+            name.to_owned(),
+            ModuleSource {
+                path,
+                contents,
+                is_package,
+            },
+        );
+    }
+}
+
+impl ModuleLoader for MemoryLoader {
+    fn load(&self, name: &str) -> io::Result<Option<ModuleSource>> {
+        Ok(self.modules.get(name).cloned())
+    }
+}
@@ -0,0 +1,343 @@
+use core::fmt;
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use super::Scope;
+use super::error::{ExceptionKind, RuntimeError, RuntimeResult};
+use super::limits::Budget;
+use super::loader::{FileLoader, ModuleLoader, ModuleSource};
+use super::native::NativeModule;
+use super::value::RuntimeValue;
+use crate::ast::node::{AstAlias, AstScope};
+use crate::diagnostic::Diagnostic;
+use crate::lexer::Lexer;
+use crate::source_map::{SourceFile, SourceMap};
+
+/// `__name__` of the file given to the interpreter
+pub const MAIN_MODULE: &str = "__main__";
+
+/// Module object, its attributes are the variables of its top-level scope
+pub struct RuntimeModule {
+    pub name: String,
+    /// Where the loader found the module, `None` for native modules
+    pub path: Option<PathBuf>,
+    pub is_package: bool,
+    pub scope: Rc<Scope>,
+}
+
+/// Runs and caches the modules imported by scripts, getting their code from a [`ModuleLoader`]
+pub struct Importer {
+    loader: Box<dyn ModuleLoader>,
+    /// Files of the loaded modules, so tracebacks can show them
+    sources: RefCell<SourceMap>,
+    /// Every module imported so far, by full name
+    modules: RefCell<HashMap<String, Rc<RuntimeModule>>>,
+    /// Modules whose body is running, outermost first
+    loading: RefCell<Vec<String>>,
+    /// Limits of every module and their usage
+    budget: Budget,
+}
+
+impl RuntimeModule {
+    /// Top-level variable `name`
+    pub fn get(&self, name: &str) -> Option<RuntimeValue> {
+        self.scope
+            .variables
+            .borrow()
+            .get(name)
+            .map(|var| var.0.borrow().clone())
+    }
+}
+
+impl Importer {
+    pub fn new(loader: impl ModuleLoader + 'static) -> Self {
+        Self {
+            loader: Box::new(loader),
+            sources: RefCell::default(),
+            modules: RefCell::default(),
+            loading: RefCell::default(),
+            budget: Budget::default(),
+        }
+    }
+
+    /// Files loaded so far, including the modules
+    pub fn sources(&self) -> Ref<'_, SourceMap> {
+        self.sources.borrow()
+    }
+
+    /// Used to register the main script, must not be held while scripts run
+    pub fn sources_mut(&self) -> RefMut<'_, SourceMap> {
+        self.sources.borrow_mut()
+    }
+
+    pub fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    /// Module with the absolute dotted `name`, running it
+    /// and its parent packages the first time they are imported
+    pub fn import(self: &Rc<Self>, name: &str) -> RuntimeResult<Rc<RuntimeModule>> {
+        self.try_import(name)?.ok_or_else(|| {
+            RuntimeError::new(
+                ExceptionKind::ModuleNotFoundError,
+                format!("No module named '{name}'"),
+            )
+        })
+    }
+
+    /// Makes `module` importable, replacing any module with the same name
+    pub fn register(self: &Rc<Self>, module: NativeModule) {
+        let scope = Scope::module(self, &module.name, None);
+
+        for (name, value) in module.attrs {
+            scope.set_variable(name, value);
+        }
+
+        let module = RuntimeModule {
+            name: module.name,
+            path: None,
+            is_package: false,
+            scope,
+        };
+
+        self.modules
+            .borrow_mut()
+            .insert(module.name.clone(), Rc::new(module));
+    }
+
+    /// Same as [`Importer::import`], `None` if the loader doesn't know the module
+    fn try_import(self: &Rc<Self>, name: &str) -> RuntimeResult<Option<Rc<RuntimeModule>>> {
+        if let Some(module) = self.modules.borrow().get(name) {
+            return Ok(Some(module.clone()));
+        }
+
+        let parent = match name.rsplit_once('.') {
+            Some((parent, _)) => Some(self.import(parent)?),
+            None => None,
+        };
+
+        // Running the parent package may have imported it already
+        if let Some(module) = self.modules.borrow().get(name) {
+            return Ok(Some(module.clone()));
+        }
+
+        let source = self.loader.load(name).map_err(|err| {
+            RuntimeError::new(
+                ExceptionKind::ImportError,
+                format!("cannot load module '{name}': {err}"),
+            )
+        })?;
+
+        let Some(source) = source else {
+            return Ok(None);
+        };
+
+        let package = match (source.is_package, name.rsplit_once('.')) {
+            (true, _) => Some(name),
+            (false, Some((package, _))) => Some(package),
+            (false, None) => None,
+        };
+
+        let module = Rc::new(RuntimeModule {
+            name: name.to_owned(),
+            path: Some(source.path.clone()),
+            is_package: source.is_package,
+            scope: Scope::module(self, name, package),
+        });
+
+        // Cached before running, so circular imports get the partially initialized module
+        self.modules
+            .borrow_mut()
+            .insert(name.to_owned(), module.clone());
+        self.loading.borrow_mut().push(name.to_owned());
+
+        let result = self.run_source(&module, source);
+
+        self.loading.borrow_mut().pop();
+
+        if let Err(err) = result {
+            self.modules.borrow_mut().remove(name);
+            return Err(err);
+        }
+
+        if let Some(parent) = parent {
+            let (_, attr) = name.rsplit_once('.').expect("Already checked above");
+            parent
+                .scope
+                .set_variable(attr.to_owned(), RuntimeValue::Module(module.clone()));
+        }
+
+        Ok(Some(module))
+    }
+
+    fn run_source(&self, module: &RuntimeModule, source: ModuleSource) -> RuntimeResult {
+        let id = self.sources.borrow_mut().add(source.path, source.contents);
+
+        let tree = {
+            let sources = self.sources.borrow();
+            let file = &sources[id];
+
+            Lexer::from_source(file)
+                .and_then(|tokens| AstScope::from_tokens(file, tokens))
+                .map_err(|diagnostic| syntax_error(&diagnostic, file))?
+        };
+
+        module.scope.run(&tree).map_err(RuntimeError::unwind_frame)
+    }
+
+    /// `from module import name` found nothing, mentioning
+    /// the import cycle if `module` is still running
+    fn cannot_import(&self, name: &str, module: &RuntimeModule) -> RuntimeError {
+        let loading = self.loading.borrow();
+
+        let message = match loading.iter().position(|loading| *loading == module.name) {
+            Some(idx) => format!(
+                "cannot import name '{name}' from partially initialized module '{}' \
+                 (most likely due to a circular import: {} -> {})",
+                module.name,
+                loading[idx..].join(" -> "),
+                module.name
+            ),
+            None => format!(
+                "cannot import name '{name}' from '{}' ({})",
+                module.name,
+                module
+                    .path
+                    .as_ref()
+                    .map_or("unknown location".into(), |path| path.display().to_string())
+            ),
+        };
+
+        RuntimeError::new(ExceptionKind::ImportError, message)
+    }
+}
+
+impl Default for Importer {
+    /// Importer with an empty search path
+    fn default() -> Self {
+        Self::new(FileLoader::default())
+    }
+}
+
+/// Modules that fail to parse raise `SyntaxError` where they're imported
+fn syntax_error(diagnostic: &Diagnostic, file: &SourceFile) -> RuntimeError {
+    let message = match diagnostic.primary_span() {
+        Some(span) => format!(
+            "{} ({}, line {})",
+            diagnostic.message,
+            file.name(),
+            file.line_col(span.from).line + 1
+        ),
+        None => format!("{} ({})", diagnostic.message, file.name()),
+    };
+
+    RuntimeError::new(ExceptionKind::SyntaxError, message)
+}
+
+impl Scope {
+    /// `import a.b` binds the top-level package `a`, `import a.b as c` the module itself
+    pub fn visit_import(self: &Rc<Self>, names: &[AstAlias]) -> RuntimeResult {
+        for name in names {
+            let module = self.importer.import(&name.name)?;
+
+            let (bound, module) = match &name.alias {
+                Some(alias) => (alias.clone(), module),
+                None => {
+                    let top = name.name.split('.').next().unwrap_or_default();
+                    (top.to_owned(), self.importer.import(top)?)
+                }
+            };
+
+            self.set_variable(bound, RuntimeValue::Module(module));
+        }
+
+        Ok(())
+    }
+
+    /// Binds attributes of a module, or its submodules if it's a package
+    pub fn visit_import_from(
+        self: &Rc<Self>,
+        module: Option<&str>,
+        level: usize,
+        names: &[AstAlias],
+    ) -> RuntimeResult {
+        let module = self.importer.import(&self.resolve_module(module, level)?)?;
+
+        for name in names {
+            let value = match module.get(&name.name) {
+                Some(value) => value,
+                None if module.is_package => {
+                    let submodule = format!("{}.{}", module.name, name.name);
+
+                    match self.importer.try_import(&submodule)? {
+                        Some(submodule) => RuntimeValue::Module(submodule),
+                        None => return Err(self.importer.cannot_import(&name.name, &module)),
+                    }
+                }
+                None => return Err(self.importer.cannot_import(&name.name, &module)),
+            };
+
+            let bound = name.alias.as_ref().unwrap_or(&name.name);
+            self.set_variable(bound.clone(), value);
+        }
+
+        Ok(())
+    }
+
+    /// Absolute name of the module imported with `level` leading dots
+    fn resolve_module(
+        self: &Rc<Self>,
+        module: Option<&str>,
+        level: usize,
+    ) -> RuntimeResult<String> {
+        if level == 0 {
+            return Ok(module
+                .expect("the parser requires a module in absolute imports")
+                .to_owned());
+        }
+
+        let package = self
+            .get_variable("__package__")
+            .map(|var| var.0.borrow().clone());
+
+        let Some(RuntimeValue::String(package)) = package else {
+            return Err(RuntimeError::new(
+                ExceptionKind::ImportError,
+                "attempted relative import with no known parent package",
+            ));
+        };
+
+        let mut parts = package.split('.').collect::<Vec<_>>();
+
+        if level > parts.len() {
+            return Err(RuntimeError::new(
+                ExceptionKind::ImportError,
+                "attempted relative import beyond top-level package",
+            ));
+        }
+
+        parts.truncate(parts.len() + 1 - level);
+        parts.extend(module);
+
+        Ok(parts.join("."))
+    }
+}
+
+impl fmt::Debug for RuntimeModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "<module '{}' from '{}'>", self.name, path.display()),
+            None => write!(f, "<module '{}' (native)>", self.name),
+        }
+    }
+}
+
+impl fmt::Debug for Importer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Importer")
+            .field("modules", &self.modules.borrow().keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
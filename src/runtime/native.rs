@@ -0,0 +1,404 @@
+use core::fmt;
+use std::any::Any;
+use std::cell::{Ref, RefCell, RefMut};
+use std::rc::Rc;
+
+use super::Scope;
+use super::convert::{ConversionError, FromValue, IntoValue};
+use super::error::{ExceptionKind, RuntimeError, RuntimeResult};
+use super::value::{KeywordArgs, RuntimeValue};
+
+/// Closure behind a [`NativeFunction`]
+pub type NativeFn = dyn Fn(NativeArgs) -> RuntimeResult<RuntimeValue>;
+
+/// Function implemented by the host application
+pub struct NativeFunction {
+    pub name: String,
+    function: Box<NativeFn>,
+}
+
+/// Positional arguments of a call to a native function, with helpers
+/// raising the same `TypeError`s as the builtins
+pub struct NativeArgs<'a> {
+    function: &'a str,
+    values: Vec<RuntimeValue>,
+    scope: &'a Rc<Scope>,
+}
+
+/// Rust functions whose arguments convert with [`FromValue`] and whose result converts
+/// with [`IntoValue`], possibly wrapped in a [`RuntimeResult`]
+pub trait TypedFn<Args> {
+    fn into_native(self, name: &str) -> NativeFunction;
+}
+
+/// Results of a [`TypedFn`]
+pub trait IntoNativeResult {
+    fn into_result(self) -> RuntimeResult<RuntimeValue>;
+}
+
+/// Rust type exposed to scripts, implemented by `#[derive(ScriptClass)]`
+/// which maps the fields to attributes
+pub trait ScriptClass: ScriptMethods + 'static {
+    /// Class name shown in scripts
+    const NAME: &'static str;
+
+    /// Fields scripts can see
+    const FIELDS: &'static [&'static str];
+
+    /// Value of field `name`, `None` if there is no such field
+    fn get_field(&self, name: &str) -> Option<Result<RuntimeValue, ConversionError>>;
+
+    /// Sets field `name`, `None` if there is no such field
+    fn set_field(
+        &mut self,
+        name: &str,
+        value: &RuntimeValue,
+    ) -> Option<Result<(), ConversionError>>;
+}
+
+/// Methods of a [`ScriptClass`], implemented by `#[script_methods]` on an `impl` block
+pub trait ScriptMethods: Sized {
+    fn method(name: &str) -> Option<ScriptMethod<Self>>;
+}
+
+/// Method of a [`ScriptClass`], by how it borrows the object
+pub enum ScriptMethod<T> {
+    Ref(fn(&T, NativeArgs) -> RuntimeResult<RuntimeValue>),
+    Mut(fn(&mut T, NativeArgs) -> RuntimeResult<RuntimeValue>),
+}
+
+/// Result of accessing a field of a [`ScriptClass`], `None` if there is no such field
+type FieldResult<T> = Option<Result<T, ConversionError>>;
+
+/// Instance of a [`ScriptClass`], borrowed like a `RefCell` when scripts use it:
+/// reading a field or calling a `&self` method while a `&mut self` method runs raises
+pub struct NativeObject {
+    pub class: &'static str,
+    pub fields: &'static [&'static str],
+    value: RefCell<Box<dyn Any>>,
+    get_field: fn(&dyn Any, &str) -> FieldResult<RuntimeValue>,
+    set_field: fn(&mut dyn Any, &str, &RuntimeValue) -> FieldResult<()>,
+    method: fn(&Rc<NativeObject>, &str) -> Option<NativeFunction>,
+}
+
+/// Module implemented by the host application, see [`crate::interpreter::Interpreter::register_module`]
+pub struct NativeModule {
+    pub name: String,
+    pub attrs: Vec<(String, RuntimeValue)>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        name: impl Into<String>,
+        function: impl Fn(NativeArgs) -> RuntimeResult<RuntimeValue> + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            function: Box::new(function),
+        }
+    }
+
+    /// Runs the closure, `scope` is the one of the caller
+    pub fn call(
+        &self,
+        scope: &Rc<Scope>,
+        args: Vec<RuntimeValue>,
+        kwargs: KeywordArgs,
+    ) -> RuntimeResult<RuntimeValue> {
+        if !kwargs.is_empty() {
+            return Err(RuntimeError::type_error(format!(
+                "{}() takes no keyword arguments",
+                self.name
+            )));
+        }
+
+        (self.function)(NativeArgs {
+            function: &self.name,
+            values: args,
+            scope,
+        })
+    }
+}
+
+impl<'a> NativeArgs<'a> {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn values(&self) -> &[RuntimeValue] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<RuntimeValue> {
+        self.values
+    }
+
+    /// Scope of the caller
+    pub fn scope(&self) -> &'a Rc<Scope> {
+        self.scope
+    }
+
+    /// Checks that exactly `N` arguments were given
+    pub fn exact<const N: usize>(self) -> RuntimeResult<[RuntimeValue; N]> {
+        self.expect_len(N)?;
+        Ok(self.values.try_into().expect("Already checked above"))
+    }
+
+    /// Checks that exactly `count` arguments were given
+    pub fn expect_len(&self, count: usize) -> RuntimeResult {
+        let given = self.values.len();
+
+        if given == count {
+            return Ok(());
+        }
+
+        Err(RuntimeError::type_error(format!(
+            "{}() takes exactly {count} argument{} ({given} given)",
+            self.function,
+            if count == 1 { "" } else { "s" },
+        )))
+    }
+
+    /// Checks that between `min` and `max` arguments were given
+    pub fn expect_between(&self, min: usize, max: usize) -> RuntimeResult {
+        let given = self.values.len();
+
+        if (min..=max).contains(&given) {
+            return Ok(());
+        }
+
+        Err(RuntimeError::type_error(format!(
+            "{}() takes from {min} to {max} arguments ({given} given)",
+            self.function
+        )))
+    }
+
+    /// Argument at `idx`, which must exist
+    pub fn get(&self, idx: usize) -> RuntimeResult<&RuntimeValue> {
+        self.values.get(idx).ok_or_else(|| {
+            RuntimeError::type_error(format!(
+                "{}() missing required argument {}",
+                self.function,
+                idx + 1
+            ))
+        })
+    }
+
+    /// Argument at `idx`, which must be an int or a bool
+    pub fn int(&self, idx: usize) -> RuntimeResult<i64> {
+        let value = self.get(idx)?;
+        value
+            .as_int()
+            .ok_or_else(|| self.wrong_type(idx, "int", value))
+    }
+
+    /// Argument at `idx`, which must be a string
+    pub fn str(&self, idx: usize) -> RuntimeResult<&str> {
+        match self.get(idx)? {
+            RuntimeValue::String(s) => Ok(s),
+            value => Err(self.wrong_type(idx, "str", value)),
+        }
+    }
+
+    /// Argument at `idx` converted to `T`
+    pub fn convert<T: FromValue>(&self, idx: usize) -> RuntimeResult<T> {
+        T::from_value(self.get(idx)?).map_err(|err| {
+            let subject = format!("{}() argument {}", self.function, idx + 1);
+            RuntimeError::new(err.kind, err.describe(&subject))
+        })
+    }
+
+    /// Calls a script function, or anything callable, from the native one
+    pub fn call(
+        &self,
+        function: &RuntimeValue,
+        args: Vec<RuntimeValue>,
+    ) -> RuntimeResult<RuntimeValue> {
+        self.scope.call(function, args)
+    }
+
+    /// `TypeError` for argument `idx`, which should have been of type `expected`
+    pub fn wrong_type(&self, idx: usize, expected: &str, value: &RuntimeValue) -> RuntimeError {
+        RuntimeError::type_error(format!(
+            "{}() argument {} must be {expected}, not {}",
+            self.function,
+            idx + 1,
+            value.type_name()
+        ))
+    }
+}
+
+impl NativeModule {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            attrs: Vec::new(),
+        }
+    }
+
+    /// Adds native function `name`
+    pub fn with_fn(
+        self,
+        name: &str,
+        function: impl Fn(NativeArgs) -> RuntimeResult<RuntimeValue> + 'static,
+    ) -> Self {
+        let function = NativeFunction::new(name, function);
+        self.with_value(name, RuntimeValue::NativeFunction(Rc::new(function)))
+    }
+
+    /// Adds native function `name`, converting its arguments and result
+    pub fn with_typed<Args>(self, name: &str, function: impl TypedFn<Args>) -> Self {
+        let function = function.into_native(name);
+        self.with_value(name, RuntimeValue::NativeFunction(Rc::new(function)))
+    }
+
+    /// Adds attribute `name`
+    pub fn with_value(mut self, name: &str, value: RuntimeValue) -> Self {
+        self.attrs.push((name.to_owned(), value));
+        self
+    }
+}
+
+impl NativeObject {
+    pub fn new<T: ScriptClass>(value: T) -> Self {
+        Self {
+            class: T::NAME,
+            fields: T::FIELDS,
+            value: RefCell::new(Box::new(value)),
+            get_field: |value, name| value.downcast_ref::<T>()?.get_field(name),
+            set_field: |value, name, field| value.downcast_mut::<T>()?.set_field(name, field),
+            method: |object, name| {
+                let method = T::method(name)?;
+                let object = object.clone();
+
+                Some(NativeFunction::new(name, move |args| match method {
+                    ScriptMethod::Ref(method) => method(&*object.borrow::<T>()?, args),
+                    ScriptMethod::Mut(method) => method(&mut *object.borrow_mut::<T>()?, args),
+                }))
+            },
+        }
+    }
+
+    /// The Rust value, raises if it isn't a `T` or a method is mutating it
+    pub fn borrow<T: ScriptClass>(&self) -> RuntimeResult<Ref<'_, T>> {
+        let value = self
+            .value
+            .try_borrow()
+            .map_err(|_| self.borrow_error("mutably "))?;
+
+        Ref::filter_map(value, |value| value.downcast_ref::<T>())
+            .map_err(|_| self.downcast_error::<T>())
+    }
+
+    /// The Rust value, raises if it isn't a `T` or it's already borrowed
+    pub fn borrow_mut<T: ScriptClass>(&self) -> RuntimeResult<RefMut<'_, T>> {
+        let value = self
+            .value
+            .try_borrow_mut()
+            .map_err(|_| self.borrow_error(""))?;
+
+        RefMut::filter_map(value, |value| value.downcast_mut::<T>())
+            .map_err(|_| self.downcast_error::<T>())
+    }
+
+    /// Field `name`, or method `name` bound to the object
+    pub fn get_attr(self: &Rc<Self>, name: &str) -> RuntimeResult<Option<RuntimeValue>> {
+        if let Some(method) = (self.method)(self, name) {
+            return Ok(Some(RuntimeValue::NativeFunction(Rc::new(method))));
+        }
+
+        let value = self
+            .value
+            .try_borrow()
+            .map_err(|_| self.borrow_error("mutably "))?;
+
+        match (self.get_field)(&**value, name) {
+            Some(field) => Ok(Some(field.map_err(|err| self.field_error(name, err))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Sets field `name`, `false` if there is no such field
+    pub fn set_attr(&self, name: &str, value: &RuntimeValue) -> RuntimeResult<bool> {
+        let mut object = self
+            .value
+            .try_borrow_mut()
+            .map_err(|_| self.borrow_error(""))?;
+
+        match (self.set_field)(&mut **object, name, value) {
+            Some(result) => result
+                .map(|()| true)
+                .map_err(|err| self.field_error(name, err)),
+            None => Ok(false),
+        }
+    }
+
+    fn borrow_error(&self, how: &str) -> RuntimeError {
+        RuntimeError::new(
+            ExceptionKind::RuntimeError,
+            format!("{} object is already {how}borrowed", self.class),
+        )
+    }
+
+    fn downcast_error<T: ScriptClass>(&self) -> RuntimeError {
+        RuntimeError::type_error(format!("expected {}, not {}", T::NAME, self.class))
+    }
+
+    fn field_error(&self, name: &str, err: ConversionError) -> RuntimeError {
+        RuntimeError::new(err.kind, err.describe(&format!("{}.{name}", self.class)))
+    }
+}
+
+impl<T: IntoValue> IntoNativeResult for T {
+    fn into_result(self) -> RuntimeResult<RuntimeValue> {
+        Ok(self.into_value()?)
+    }
+}
+
+impl<T: IntoValue> IntoNativeResult for RuntimeResult<T> {
+    fn into_result(self) -> RuntimeResult<RuntimeValue> {
+        Ok(self?.into_value()?)
+    }
+}
+
+macro_rules! impl_typed_fn {
+    ($len:literal; $($idx:tt $arg:ident),*) => {
+        impl<Func, Ret, $($arg),*> TypedFn<($($arg,)*)> for Func
+        where
+            Func: Fn($($arg),*) -> Ret + 'static,
+            Ret: IntoNativeResult,
+            $($arg: FromValue,)*
+        {
+            fn into_native(self, name: &str) -> NativeFunction {
+                NativeFunction::new(name, move |args| {
+                    args.expect_len($len)?;
+                    self($(args.convert::<$arg>($idx)?),*).into_result()
+                })
+            }
+        }
+    };
+}
+
+impl_typed_fn!(0;);
+impl_typed_fn!(1; 0 A);
+impl_typed_fn!(2; 0 A, 1 B);
+impl_typed_fn!(3; 0 A, 1 B, 2 C);
+impl_typed_fn!(4; 0 A, 1 B, 2 C, 3 D);
+impl_typed_fn!(5; 0 A, 1 B, 2 C, 3 D, 4 E);
+impl_typed_fn!(6; 0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<built-in function {}>", self.name)
+    }
+}
+
+impl fmt::Debug for NativeObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{} object>", self.class)
+    }
+}
@@ -0,0 +1,716 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::Scope;
+use super::error::{ExceptionKind, RuntimeError, RuntimeResult};
+use super::generator::RuntimeIterator;
+use super::value::{AsBool, AsString, MAX_NESTING, RuntimeValue};
+use crate::ast::node::AstBinaryOp;
+
+/// Special method implementing `op`, and the one tried on the right operand
+fn special_methods(op: &AstBinaryOp) -> (&'static str, &'static str) {
+    match op {
+        AstBinaryOp::Add => ("__add__", "__radd__"),
+        AstBinaryOp::Sub => ("__sub__", "__rsub__"),
+        AstBinaryOp::Mul => ("__mul__", "__rmul__"),
+        AstBinaryOp::Div => ("__truediv__", "__rtruediv__"),
+        AstBinaryOp::Equal => ("__eq__", "__eq__"),
+        AstBinaryOp::NotEqual => ("__ne__", "__ne__"),
+        AstBinaryOp::Less => ("__lt__", "__gt__"),
+        AstBinaryOp::LessEqual => ("__le__", "__ge__"),
+        AstBinaryOp::Greater => ("__gt__", "__lt__"),
+        AstBinaryOp::GreaterEqual => ("__ge__", "__le__"),
+    }
+}
+
+impl Scope {
+    /// Calls the special method `name` of an instance's class,
+    /// `None` if the value doesn't define it
+    pub fn call_special(
+        self: &Rc<Self>,
+        value: &RuntimeValue,
+        name: &str,
+        args: Vec<RuntimeValue>,
+    ) -> RuntimeResult<Option<RuntimeValue>> {
+        let RuntimeValue::Object(object) = value else {
+            return Ok(None);
+        };
+
+        let Some(method) = object.class.lookup(name) else {
+            return Ok(None);
+        };
+
+        let args = std::iter::once(value.clone()).chain(args).collect();
+        self.call(&method, args).map(Some)
+    }
+
+    pub fn binary_op(
+        self: &Rc<Self>,
+        op: &AstBinaryOp,
+        left: RuntimeValue,
+        right: RuntimeValue,
+    ) -> RuntimeResult<RuntimeValue> {
+        use AstBinaryOp::*;
+        use RuntimeValue::*;
+
+        if let Some(value) = self.binary_special(op, &left, &right)? {
+            return Ok(value);
+        }
+
+        match op {
+            Equal => return self.equals(&left, &right, 0).map(Bool),
+            NotEqual => return self.equals(&left, &right, 0).map(|equal| Bool(!equal)),
+            Less | LessEqual | Greater | GreaterEqual => {
+                return compare(op, &left, &right).map(Bool);
+            }
+            Add | Div | Mul | Sub => {}
+        }
+
+        if let (Some(a), Some(b)) = (left.as_int(), right.as_int()) {
+            let overflow = || RuntimeError::new(ExceptionKind::OverflowError, "integer overflow");
+
+            return Ok(Number(match op {
+                Add => a.checked_add(b).ok_or_else(overflow)?,
+                Sub => a.checked_sub(b).ok_or_else(overflow)?,
+                Mul => a.checked_mul(b).ok_or_else(overflow)?,
+                Div if b == 0 => {
+                    return Err(RuntimeError::new(
+                        ExceptionKind::ZeroDivisionError,
+                        "division by zero",
+                    ));
+                }
+                Div => a.checked_div(b).ok_or_else(overflow)?,
+                _ => unreachable!("comparisons are handled above"),
+            }));
+        }
+
+        Ok(match (op, &left, &right) {
+            ////// Concatenation //////
+            // Counted before building, like repetitions, to never go past the limit
+            (Add, String(_), String(_) | Nil | Bool(_) | Number(_))
+            | (Add, Nil | Bool(_) | Number(_), String(_)) => {
+                let [a, b] = [&left, &right].map(|value| match value {
+                    String(s) => Cow::Borrowed(s.as_str()),
+                    Nil => Cow::Borrowed("nil"),
+                    Bool(b) => Cow::Borrowed(b.as_string()),
+                    Number(n) => Cow::Owned(n.to_string()),
+                    _ => unreachable!("matched above"),
+                });
+                self.budget().allocate(a.len() + b.len())?;
+                String(format!("{a}{b}"))
+            }
+            (Add, Tuple(a), Tuple(b)) => {
+                self.budget().allocate_values(a.len() + b.len())?;
+                Tuple(a.iter().chain(b.iter()).cloned().collect())
+            }
+            (Add, List(a), List(b)) => {
+                let (a, b) = (a.borrow(), b.borrow());
+                self.budget().allocate_values(a.len() + b.len())?;
+                List(Rc::new(RefCell::new(
+                    a.iter().chain(b.iter()).cloned().collect(),
+                )))
+            }
+
+            ////// Multiplication //////
+            (Mul, n, String(s)) | (Mul, String(s), n) if n.as_int().is_some() => {
+                let times = n.as_int().unwrap_or_default().max(0).unsigned_abs() as usize;
+                let len = s
+                    .len()
+                    .checked_mul(times)
+                    .filter(|len| *len <= isize::MAX as usize)
+                    .ok_or_else(|| {
+                        RuntimeError::new(
+                            ExceptionKind::OverflowError,
+                            "repeated string is too long",
+                        )
+                    })?;
+
+                self.budget().allocate(len)?;
+                return Ok(String(s.repeat(times)));
+            }
+            (Mul, n, seq @ (List(_) | Tuple(_))) | (Mul, seq @ (List(_) | Tuple(_)), n)
+                if n.as_int().is_some() =>
+            {
+                let times = n.as_int().unwrap_or_default().max(0).unsigned_abs() as usize;
+                let items = match seq {
+                    List(items) => items.borrow().clone(),
+                    Tuple(items) => items.to_vec(),
+                    _ => unreachable!("matched above"),
+                };
+                let len = items
+                    .len()
+                    .checked_mul(times)
+                    .filter(|len| *len <= isize::MAX as usize / size_of::<RuntimeValue>())
+                    .ok_or_else(|| {
+                        RuntimeError::new(
+                            ExceptionKind::OverflowError,
+                            format!("repeated {} is too long", seq.type_name()),
+                        )
+                    })?;
+
+                self.budget().allocate_values(len)?;
+                let repeated = items.iter().cycle().take(len).cloned();
+
+                return Ok(match seq {
+                    List(_) => List(Rc::new(RefCell::new(repeated.collect()))),
+                    _ => Tuple(repeated.collect()),
+                });
+            }
+
+            _ => {
+                return Err(RuntimeError::type_error(format!(
+                    "unsupported operand type(s) for {op}: '{}' and '{}'",
+                    left.type_name(),
+                    right.type_name()
+                )));
+            }
+        })
+    }
+
+    /// Tries the special methods of both operands, like Python does:
+    /// the reflected method of the right operand goes first if its class
+    /// is a subclass of the left one, and `NotImplemented` moves to the next
+    fn binary_special(
+        self: &Rc<Self>,
+        op: &AstBinaryOp,
+        left: &RuntimeValue,
+        right: &RuntimeValue,
+    ) -> RuntimeResult<Option<RuntimeValue>> {
+        let class = |value: &RuntimeValue| match value {
+            RuntimeValue::Object(object) => Some(object.class.clone()),
+            _ => None,
+        };
+
+        let (left_class, right_class) = (class(left), class(right));
+
+        if left_class.is_none() && right_class.is_none() {
+            return Ok(None);
+        }
+
+        let (method, reflected) = special_methods(op);
+        let same_class =
+            matches!((&left_class, &right_class), (Some(a), Some(b)) if Rc::ptr_eq(a, b));
+
+        let mut attempts = Vec::from([(left, method, right)]);
+
+        // Arithmetic between instances of the same class only tries the left operand
+        if !same_class || op.is_comparison() {
+            attempts.push((right, reflected, left));
+        }
+
+        if let (Some(left_class), Some(right_class)) = (&left_class, &right_class)
+            && !same_class
+            && right_class.is_subclass(left_class)
+        {
+            attempts.reverse();
+        }
+
+        for (receiver, method, other) in attempts {
+            match self.call_special(receiver, method, Vec::from([other.clone()]))? {
+                None | Some(RuntimeValue::NotImplemented) => {}
+                Some(value) => return Ok(Some(value)),
+            }
+        }
+
+        // `!=` defaults to the negation of `==`
+        if *op == AstBinaryOp::NotEqual
+            && let Some(equal) = self.binary_special(&AstBinaryOp::Equal, left, right)?
+        {
+            return Ok(Some(RuntimeValue::Bool(!self.truthy(&equal)?)));
+        }
+
+        Ok(None)
+    }
+
+    /// `==` once the special methods of both operands were tried. Containers
+    /// compare their items through `__eq__`, see [`Scope::item_equals`]
+    fn equals(
+        self: &Rc<Self>,
+        left: &RuntimeValue,
+        right: &RuntimeValue,
+        depth: usize,
+    ) -> RuntimeResult<bool> {
+        use RuntimeValue::*;
+
+        if let (Some(a), Some(b)) = (left.container_id(), right.container_id()) {
+            if a == b {
+                return Ok(true);
+            }
+
+            if depth >= MAX_NESTING {
+                return Err(RuntimeError::new(
+                    ExceptionKind::RecursionError,
+                    "maximum recursion depth exceeded in comparison",
+                ));
+            }
+        }
+
+        match (left, right) {
+            (Tuple(a), Tuple(b)) => self.items_equal(a, b, depth),
+            (List(a), List(b)) => {
+                // `__eq__` may change the lists, they can't stay borrowed
+                let (a, b) = (a.borrow().clone(), b.borrow().clone());
+                self.items_equal(&a, &b, depth)
+            }
+            (Dict(a), Dict(b)) => {
+                let entries = a
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect::<Vec<_>>();
+
+                if entries.len() != b.borrow().len() {
+                    return Ok(false);
+                }
+
+                for (key, value) in entries {
+                    let other = b.borrow().get(&key)?;
+
+                    match other {
+                        Some(other) if self.item_equals(&value, &other, depth + 1)? => {}
+                        _ => return Ok(false),
+                    }
+                }
+
+                Ok(true)
+            }
+            _ => left.try_equals(right),
+        }
+    }
+
+    fn items_equal(
+        self: &Rc<Self>,
+        a: &[RuntimeValue],
+        b: &[RuntimeValue],
+        depth: usize,
+    ) -> RuntimeResult<bool> {
+        if a.len() != b.len() {
+            return Ok(false);
+        }
+
+        for (a, b) in a.iter().zip(b) {
+            if !self.item_equals(a, b, depth + 1)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Items of a container are equal if they're the same object,
+    /// otherwise `==` decides, like Python does
+    fn item_equals(
+        self: &Rc<Self>,
+        left: &RuntimeValue,
+        right: &RuntimeValue,
+        depth: usize,
+    ) -> RuntimeResult<bool> {
+        if let (RuntimeValue::Object(a), RuntimeValue::Object(b)) = (left, right)
+            && Rc::ptr_eq(a, b)
+        {
+            return Ok(true);
+        }
+
+        match self.binary_special(&AstBinaryOp::Equal, left, right)? {
+            Some(equal) => self.truthy(&equal),
+            None => self.equals(left, right, depth),
+        }
+    }
+
+    pub fn negate(self: &Rc<Self>, value: RuntimeValue) -> RuntimeResult<RuntimeValue> {
+        if let Some(result) = self.call_special(&value, "__neg__", Vec::new())? {
+            return Ok(result);
+        }
+
+        match value {
+            RuntimeValue::Number(_) | RuntimeValue::Bool(_) => value
+                .as_int()
+                .and_then(i64::checked_neg)
+                .map(RuntimeValue::Number)
+                .ok_or_else(|| RuntimeError::new(ExceptionKind::OverflowError, "integer overflow")),
+            value => Err(RuntimeError::type_error(format!(
+                "bad operand type for unary -: '{}'",
+                value.type_name()
+            ))),
+        }
+    }
+
+    /// Truth value, through `__bool__` or `__len__` for instances
+    pub fn truthy(self: &Rc<Self>, value: &RuntimeValue) -> RuntimeResult<bool> {
+        match self.call_special(value, "__bool__", Vec::new())? {
+            Some(RuntimeValue::Bool(b)) => return Ok(b),
+            Some(result) => {
+                return Err(RuntimeError::type_error(format!(
+                    "__bool__ should return bool, returned {}",
+                    result.type_name()
+                )));
+            }
+            None => {}
+        }
+
+        if let RuntimeValue::Object(object) = value
+            && object.class.lookup("__len__").is_some()
+        {
+            return Ok(self.length(value)? != 0);
+        }
+
+        Ok(value.as_bool())
+    }
+
+    /// Same as Python's `len()`
+    pub fn length(self: &Rc<Self>, value: &RuntimeValue) -> RuntimeResult<i64> {
+        let length = match value {
+            RuntimeValue::String(s) => s.chars().count(),
+            RuntimeValue::Tuple(items) => items.len(),
+            RuntimeValue::List(items) => items.borrow().len(),
+            RuntimeValue::Dict(dict) => dict.borrow().len(),
+            RuntimeValue::Set(set) => set.borrow().len(),
+            value => {
+                return match self.call_special(value, "__len__", Vec::new())? {
+                    Some(length) => match length.as_int() {
+                        Some(length) if length >= 0 => Ok(length),
+                        Some(_) => Err(RuntimeError::new(
+                            ExceptionKind::ValueError,
+                            "__len__() should return >= 0",
+                        )),
+                        None => Err(RuntimeError::type_error(format!(
+                            "'{}' object cannot be interpreted as an integer",
+                            length.type_name()
+                        ))),
+                    },
+                    None => Err(RuntimeError::type_error(format!(
+                        "object of type '{}' has no len()",
+                        value.type_name()
+                    ))),
+                };
+            }
+        };
+
+        Ok(length as i64)
+    }
+
+    /// Items of an iterable, going through generators until they're exhausted
+    pub fn items(self: &Rc<Self>, value: &RuntimeValue) -> RuntimeResult<Vec<RuntimeValue>> {
+        // Generators are counted as they go, they may never end
+        match value {
+            RuntimeValue::Tuple(_)
+            | RuntimeValue::List(_)
+            | RuntimeValue::Dict(_)
+            | RuntimeValue::Set(_) => self
+                .budget()
+                .allocate_values(self.length(value)? as usize)?,
+            RuntimeValue::String(s) => self.budget().allocate(
+                s.len()
+                    .saturating_add(s.chars().count() * size_of::<RuntimeValue>()),
+            )?,
+            _ => {}
+        }
+
+        match value {
+            RuntimeValue::Tuple(items) => Ok(items.to_vec()),
+            RuntimeValue::List(items) => Ok(items.borrow().clone()),
+            RuntimeValue::Dict(dict) => Ok(dict.borrow().keys().cloned().collect()),
+            RuntimeValue::Set(set) => Ok(set.borrow().iter().cloned().collect()),
+            RuntimeValue::Generator(generator) => {
+                let mut items = Vec::new();
+
+                while let Some(item) = generator.next()? {
+                    self.budget().allocate_values(1)?;
+                    items.push(item);
+                }
+
+                Ok(items)
+            }
+            RuntimeValue::String(s) => {
+                Ok(s.chars().map(|c| RuntimeValue::String(c.into())).collect())
+            }
+            value => Err(RuntimeError::type_error(format!(
+                "'{}' object is not iterable",
+                value.type_name()
+            ))),
+        }
+    }
+
+    /// Iterator over `value`, generators are consumed lazily
+    pub fn iterate(self: &Rc<Self>, value: &RuntimeValue) -> RuntimeResult<RuntimeIterator> {
+        match value {
+            RuntimeValue::Generator(generator) => Ok(RuntimeIterator::Generator(generator.clone())),
+            value => self
+                .items(value)
+                .map(|items| RuntimeIterator::Items(items.into_iter())),
+        }
+    }
+
+    /// Value of `value[index]`
+    pub fn subscript(
+        self: &Rc<Self>,
+        value: &RuntimeValue,
+        index: RuntimeValue,
+    ) -> RuntimeResult<RuntimeValue> {
+        match value {
+            RuntimeValue::Tuple(items) => {
+                let idx = sequence_index(value, &index, items.len())?;
+                Ok(items[idx].clone())
+            }
+            RuntimeValue::List(items) => {
+                let items = items.borrow();
+                let idx = sequence_index(value, &index, items.len())?;
+                Ok(items[idx].clone())
+            }
+            RuntimeValue::Dict(dict) => dict.borrow().get_item(&index),
+            RuntimeValue::String(s) => {
+                let idx = sequence_index(value, &index, s.chars().count())?;
+                Ok(RuntimeValue::String(
+                    s.chars().nth(idx).map(String::from).unwrap_or_default(),
+                ))
+            }
+            value => self
+                .call_special(value, "__getitem__", Vec::from([index]))?
+                .ok_or_else(|| {
+                    RuntimeError::type_error(format!(
+                        "'{}' object is not subscriptable",
+                        value.type_name()
+                    ))
+                }),
+        }
+    }
+
+    /// Runs `object[index] = value`
+    pub fn set_subscript(
+        self: &Rc<Self>,
+        object: &RuntimeValue,
+        index: RuntimeValue,
+        value: RuntimeValue,
+    ) -> RuntimeResult {
+        if let RuntimeValue::List(items) = object {
+            let idx = sequence_index(object, &index, items.borrow().len())?;
+            let previous = std::mem::replace(&mut items.borrow_mut()[idx], value);
+            drop(previous);
+            return Ok(());
+        }
+
+        if let RuntimeValue::Dict(dict) = object {
+            let previous = dict.borrow_mut().insert(index, value)?;
+            drop(previous);
+            return Ok(());
+        }
+
+        match self.call_special(object, "__setitem__", Vec::from([index, value]))? {
+            Some(_) => Ok(()),
+            None => Err(RuntimeError::type_error(format!(
+                "'{}' object does not support item assignment",
+                object.type_name()
+            ))),
+        }
+    }
+
+    /// `del object[index]`
+    pub fn del_subscript(
+        self: &Rc<Self>,
+        object: &RuntimeValue,
+        index: RuntimeValue,
+    ) -> RuntimeResult {
+        if let RuntimeValue::List(items) = object {
+            let idx = sequence_index(object, &index, items.borrow().len())?;
+            let removed = items.borrow_mut().remove(idx);
+            drop(removed);
+            return Ok(());
+        }
+
+        if let RuntimeValue::Dict(dict) = object {
+            let removed = dict.borrow_mut().remove(&index)?;
+
+            return match removed {
+                Some(_) => Ok(()),
+                None => Err(RuntimeError::new(ExceptionKind::KeyError, index.repr())),
+            };
+        }
+
+        match self.call_special(object, "__delitem__", Vec::from([index]))? {
+            Some(_) => Ok(()),
+            None => Err(RuntimeError::type_error(format!(
+                "'{}' object doesn't support item deletion",
+                object.type_name()
+            ))),
+        }
+    }
+
+    /// Same as Python's `str()`, through `__str__` for instances
+    pub fn str_value(self: &Rc<Self>, value: &RuntimeValue) -> RuntimeResult<String> {
+        match value {
+            RuntimeValue::Object(object) => {
+                match self.call_special(value, "__str__", Vec::new())? {
+                    Some(RuntimeValue::String(s)) => Ok(s),
+                    Some(result) => Err(RuntimeError::type_error(format!(
+                        "__str__ returned non-string (type {})",
+                        result.type_name()
+                    ))),
+                    None if object.class.is_exception() => Ok(value.to_string()),
+                    None => self.repr_value(value),
+                }
+            }
+            RuntimeValue::Tuple(_)
+            | RuntimeValue::List(_)
+            | RuntimeValue::Dict(_)
+            | RuntimeValue::Set(_) => self.repr_value(value),
+            value => Ok(value.to_string()),
+        }
+    }
+
+    /// Same as Python's `repr()`, through `__repr__` for instances
+    pub fn repr_value(self: &Rc<Self>, value: &RuntimeValue) -> RuntimeResult<String> {
+        self.repr_nested(value, &mut Vec::new())
+    }
+
+    /// `printing` holds the containers being printed, repeated ones are shown as `[...]`
+    fn repr_nested(
+        self: &Rc<Self>,
+        value: &RuntimeValue,
+        printing: &mut Vec<*const ()>,
+    ) -> RuntimeResult<String> {
+        let Some(id) = value.container_id() else {
+            return self.repr_item(value, printing);
+        };
+
+        if printing.contains(&id) {
+            return Ok(value.recursive_repr().to_owned());
+        }
+
+        if printing.len() >= MAX_NESTING {
+            return Err(RuntimeError::new(
+                ExceptionKind::RecursionError,
+                "maximum recursion depth exceeded while getting the repr of an object",
+            ));
+        }
+
+        printing.push(id);
+        let repr = self.repr_item(value, printing);
+        printing.pop();
+
+        repr
+    }
+
+    fn repr_item(
+        self: &Rc<Self>,
+        value: &RuntimeValue,
+        printing: &mut Vec<*const ()>,
+    ) -> RuntimeResult<String> {
+        match value {
+            RuntimeValue::Object(_) => match self.call_special(value, "__repr__", Vec::new())? {
+                Some(RuntimeValue::String(s)) => Ok(s),
+                Some(result) => Err(RuntimeError::type_error(format!(
+                    "__repr__ returned non-string (type {})",
+                    result.type_name()
+                ))),
+                None => Ok(value.repr()),
+            },
+            RuntimeValue::Tuple(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| self.repr_nested(item, printing))
+                    .collect::<RuntimeResult<Vec<_>>>()?;
+
+                Ok(match &items[..] {
+                    [item] => format!("({item},)"),
+                    items => format!("({})", items.join(", ")),
+                })
+            }
+            RuntimeValue::List(items) => {
+                // `__repr__` may change the list while it's printed
+                let items = items.borrow().clone();
+                let items = items
+                    .iter()
+                    .map(|item| self.repr_nested(item, printing))
+                    .collect::<RuntimeResult<Vec<_>>>()?;
+
+                Ok(format!("[{}]", items.join(", ")))
+            }
+            RuntimeValue::Dict(dict) => {
+                let entries = dict
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect::<Vec<_>>();
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        Ok(format!(
+                            "{}: {}",
+                            self.repr_nested(key, printing)?,
+                            self.repr_nested(value, printing)?
+                        ))
+                    })
+                    .collect::<RuntimeResult<Vec<_>>>()?;
+
+                Ok(format!("{{{}}}", entries.join(", ")))
+            }
+            RuntimeValue::Set(set) if !set.borrow().is_empty() => {
+                let items = set.borrow().iter().cloned().collect::<Vec<_>>();
+                let items = items
+                    .iter()
+                    .map(|item| self.repr_nested(item, printing))
+                    .collect::<RuntimeResult<Vec<_>>>()?;
+
+                Ok(format!("{{{}}}", items.join(", ")))
+            }
+            value => Ok(value.repr()),
+        }
+    }
+}
+
+/// Position of `index` in a sequence of `len` items, counting from the end if negative
+fn sequence_index(
+    sequence: &RuntimeValue,
+    index: &RuntimeValue,
+    len: usize,
+) -> RuntimeResult<usize> {
+    let type_name = match sequence {
+        RuntimeValue::String(_) => "string",
+        sequence => sequence.type_name(),
+    };
+
+    let Some(idx) = index.as_int() else {
+        return Err(RuntimeError::type_error(format!(
+            "{type_name} indices must be integers, not '{}'",
+            index.type_name()
+        )));
+    };
+
+    let idx = if idx < 0 { idx + len as i64 } else { idx };
+
+    usize::try_from(idx)
+        .ok()
+        .filter(|idx| *idx < len)
+        .ok_or_else(|| {
+            RuntimeError::new(
+                ExceptionKind::IndexError,
+                format!("{type_name} index out of range"),
+            )
+        })
+}
+
+/// Ordering comparisons, defined between numbers and between strings
+fn compare(op: &AstBinaryOp, left: &RuntimeValue, right: &RuntimeValue) -> RuntimeResult<bool> {
+    let ordering = match (left, right) {
+        (RuntimeValue::String(a), RuntimeValue::String(b)) => a.cmp(b),
+        _ => match (left.as_int(), right.as_int()) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => {
+                return Err(RuntimeError::type_error(format!(
+                    "'{op}' not supported between instances of '{}' and '{}'",
+                    left.type_name(),
+                    right.type_name()
+                )));
+            }
+        },
+    };
+
+    Ok(match op {
+        AstBinaryOp::Less => ordering.is_lt(),
+        AstBinaryOp::LessEqual => ordering.is_le(),
+        AstBinaryOp::Greater => ordering.is_gt(),
+        AstBinaryOp::GreaterEqual => ordering.is_ge(),
+        _ => unreachable!("only ordering operators are compared"),
+    })
+}
@@ -0,0 +1,310 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::builtins::Builtin;
+use super::error::{ExceptionKind, RuntimeError, RuntimeResult};
+use super::value::RuntimeValue;
+use super::{Flow, Scope};
+use crate::ast::node::{AstBinaryOp, AstMatchCase, AstPattern, SpannedExpr, SpannedPattern};
+
+/// Names captured by a pattern, bound only once the whole pattern matches
+type Bindings<'p> = Vec<(&'p str, RuntimeValue)>;
+
+impl Scope {
+    /// Runs the first case whose pattern matches the subject and whose guard passes
+    pub fn visit_match(
+        self: &Rc<Self>,
+        subject: &SpannedExpr,
+        cases: &[AstMatchCase],
+    ) -> RuntimeResult<Flow> {
+        let subject = self.visit_expr(subject)?;
+
+        for case in cases {
+            let mut bindings = Vec::new();
+
+            if !self.match_pattern(&case.pattern, &subject, &mut bindings)? {
+                continue;
+            }
+
+            for (name, value) in bindings {
+                self.set_variable(name.to_owned(), value);
+            }
+
+            if let Some(guard) = &case.guard
+                && !self.truthy(&self.visit_expr(guard)?)?
+            {
+                continue;
+            }
+
+            return self.visit_scope(&case.body);
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    /// Whether `subject` matches `pattern`, adding the names it captures to `bindings`
+    fn match_pattern<'p>(
+        self: &Rc<Self>,
+        pattern: &'p SpannedPattern,
+        subject: &RuntimeValue,
+        bindings: &mut Bindings<'p>,
+    ) -> RuntimeResult<bool> {
+        let matched = match &pattern.node {
+            AstPattern::Wildcard => Ok(true),
+            AstPattern::Capture(name) => {
+                bindings.push((name, subject.clone()));
+                Ok(true)
+            }
+            AstPattern::Value(value) => self.match_value(value, subject),
+            AstPattern::Sequence(items) => self.match_sequence(items, subject, bindings),
+            AstPattern::Star(_) => unreachable!("the parser only allows `*` in sequence patterns"),
+            AstPattern::Mapping { entries, rest } => {
+                self.match_mapping(entries, rest.as_deref(), subject, bindings)
+            }
+            AstPattern::Class {
+                class,
+                args,
+                keywords,
+            } => self.match_class(class, args, keywords, subject, bindings),
+            AstPattern::Or(alternatives) => {
+                for alternative in alternatives {
+                    let mut captured = Vec::new();
+
+                    if self.match_pattern(alternative, subject, &mut captured)? {
+                        bindings.extend(captured);
+                        return Ok(true);
+                    }
+                }
+
+                Ok(false)
+            }
+            AstPattern::As { pattern, name } => {
+                let matched = self.match_pattern(pattern, subject, bindings)?;
+
+                if matched {
+                    bindings.push((name, subject.clone()));
+                }
+
+                Ok(matched)
+            }
+        };
+
+        matched.map_err(|err| err.locate(&self.frame, pattern.span))
+    }
+
+    /// `nil` and booleans must be the same value, so `case True` doesn't match `1`
+    fn match_value(
+        self: &Rc<Self>,
+        value: &SpannedExpr,
+        subject: &RuntimeValue,
+    ) -> RuntimeResult<bool> {
+        match self.visit_expr(value)? {
+            RuntimeValue::Nil => Ok(matches!(subject, RuntimeValue::Nil)),
+            RuntimeValue::Bool(value) => {
+                Ok(matches!(subject, RuntimeValue::Bool(b) if *b == value))
+            }
+            value => {
+                let equal = self.binary_op(&AstBinaryOp::Equal, subject.clone(), value)?;
+                self.truthy(&equal)
+            }
+        }
+    }
+
+    /// Lists and tuples with as many items as the patterns, any number more with `*rest`
+    fn match_sequence<'p>(
+        self: &Rc<Self>,
+        patterns: &'p [SpannedPattern],
+        subject: &RuntimeValue,
+        bindings: &mut Bindings<'p>,
+    ) -> RuntimeResult<bool> {
+        let items = match subject {
+            RuntimeValue::List(items) => items.borrow().clone(),
+            RuntimeValue::Tuple(items) => items.to_vec(),
+            _ => return Ok(false),
+        };
+
+        let star = patterns
+            .iter()
+            .position(|pattern| matches!(pattern.node, AstPattern::Star(_)));
+
+        let (before, after) = match star {
+            Some(idx) => (idx, patterns.len() - idx - 1),
+            None => (patterns.len(), 0),
+        };
+
+        let fits = match star {
+            Some(_) => items.len() >= before + after,
+            None => items.len() == before,
+        };
+
+        if !fits {
+            return Ok(false);
+        }
+
+        let tail = items.len() - after;
+
+        for (pattern, item) in patterns[..before].iter().zip(&items[..before]) {
+            if !self.match_pattern(pattern, item, bindings)? {
+                return Ok(false);
+            }
+        }
+
+        for (pattern, item) in patterns[patterns.len() - after..]
+            .iter()
+            .zip(&items[tail..])
+        {
+            if !self.match_pattern(pattern, item, bindings)? {
+                return Ok(false);
+            }
+        }
+
+        if let Some(idx) = star
+            && let AstPattern::Star(Some(name)) = &patterns[idx].node
+        {
+            let rest = items[before..tail].to_vec();
+            bindings.push((name, RuntimeValue::List(Rc::new(RefCell::new(rest)))));
+        }
+
+        Ok(true)
+    }
+
+    /// Dicts with at least the given keys, `**rest` captures the others in a new dict
+    fn match_mapping<'p>(
+        self: &Rc<Self>,
+        entries: &'p [(SpannedExpr, SpannedPattern)],
+        rest: Option<&'p str>,
+        subject: &RuntimeValue,
+        bindings: &mut Bindings<'p>,
+    ) -> RuntimeResult<bool> {
+        let RuntimeValue::Dict(dict) = subject else {
+            return Ok(false);
+        };
+
+        // Patterns may run scripts, which could change the dict
+        let mut remaining = dict.borrow().clone();
+
+        for (key, pattern) in entries {
+            let key = self.visit_expr(key)?;
+
+            let Some(value) = remaining.remove(&key)? else {
+                return Ok(false);
+            };
+
+            if !self.match_pattern(pattern, &value, bindings)? {
+                return Ok(false);
+            }
+        }
+
+        if let Some(rest) = rest {
+            bindings.push((rest, RuntimeValue::Dict(Rc::new(RefCell::new(remaining)))));
+        }
+
+        Ok(true)
+    }
+
+    /// Instances of `class`, positional patterns match the attributes named in
+    /// `__match_args__`, or the subject itself for built-in types
+    fn match_class<'p>(
+        self: &Rc<Self>,
+        class: &SpannedExpr,
+        args: &'p [SpannedPattern],
+        keywords: &'p [(String, SpannedPattern)],
+        subject: &RuntimeValue,
+        bindings: &mut Bindings<'p>,
+    ) -> RuntimeResult<bool> {
+        let class = self.visit_expr(class)?;
+
+        let (is_instance, positional) = match &class {
+            RuntimeValue::Class(class) => {
+                let is_instance = matches!(
+                    subject,
+                    RuntimeValue::Object(object) if object.class.is_subclass(class)
+                );
+
+                let names = match class.lookup("__match_args__") {
+                    None => Vec::new(),
+                    Some(RuntimeValue::Tuple(names)) => names
+                        .iter()
+                        .map(|name| match name {
+                            RuntimeValue::String(name) => Ok(name.clone()),
+                            name => Err(RuntimeError::type_error(format!(
+                                "__match_args__ elements must be strings (got {})",
+                                name.type_name()
+                            ))),
+                        })
+                        .collect::<RuntimeResult<_>>()?,
+                    Some(_) => {
+                        return Err(RuntimeError::type_error("__match_args__ must be a tuple"));
+                    }
+                };
+
+                if args.len() > names.len() {
+                    return Err(RuntimeError::type_error(format!(
+                        "{}() accepts {} positional sub-pattern{} ({} given)",
+                        class.name,
+                        names.len(),
+                        if names.len() == 1 { "" } else { "s" },
+                        args.len()
+                    )));
+                }
+
+                (is_instance, names.into_iter().map(Some).collect())
+            }
+            RuntimeValue::Builtin(
+                builtin @ (Builtin::List | Builtin::Set | Builtin::Str | Builtin::Tuple),
+            ) => {
+                let is_instance = matches!(
+                    (builtin, subject),
+                    (Builtin::List, RuntimeValue::List(_))
+                        | (Builtin::Set, RuntimeValue::Set(_))
+                        | (Builtin::Str, RuntimeValue::String(_))
+                        | (Builtin::Tuple, RuntimeValue::Tuple(_))
+                );
+
+                if args.len() > 1 {
+                    return Err(RuntimeError::type_error(format!(
+                        "{}() accepts 1 positional sub-pattern ({} given)",
+                        builtin.name(),
+                        args.len()
+                    )));
+                }
+
+                (is_instance, Vec::from([None]))
+            }
+            _ => {
+                return Err(RuntimeError::type_error(
+                    "called match pattern must be a class",
+                ));
+            }
+        };
+
+        if !is_instance {
+            return Ok(false);
+        }
+
+        let attributes = positional.iter().map(Option::as_deref).zip(args).chain(
+            keywords
+                .iter()
+                .map(|(name, pattern)| (Some(name.as_str()), pattern)),
+        );
+
+        for (name, pattern) in attributes {
+            let value = match name {
+                Some(name) => match self.get_attribute(subject, name) {
+                    Ok(value) => value,
+                    Err(err) if err.kind() == Some(ExceptionKind::AttributeError) => {
+                        return Ok(false);
+                    }
+                    Err(err) => return Err(err),
+                },
+                None => subject.clone(),
+            };
+
+            if !self.match_pattern(pattern, &value, bindings)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
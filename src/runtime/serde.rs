@@ -0,0 +1,619 @@
+use core::fmt;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::vec;
+
+use ::serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess,
+    SeqAccess, Unexpected, VariantAccess, Visitor,
+};
+use ::serde::ser::{self, Serialize, SerializeMap, Serializer};
+use ::serde::{Deserialize, forward_to_deserialize_any};
+
+use super::Scope;
+use super::convert::ConversionError;
+use super::dict::RuntimeDict;
+use super::error::ExceptionKind;
+use super::value::{MAX_NESTING, RuntimeValue};
+
+/// Deserializes `T` from a script value, errors name where in `value` the mismatch is
+pub fn from_value<T: DeserializeOwned>(value: &RuntimeValue) -> Result<T, ConversionError> {
+    T::deserialize(value.clone())
+}
+
+/// Deserializes `T` from the variables of `scope`, except the `__dunder__` ones, as a map
+pub fn from_scope<T: DeserializeOwned>(scope: &Scope) -> Result<T, ConversionError> {
+    let variables = scope
+        .local_variables()
+        .into_iter()
+        .filter(|(name, _)| !(name.starts_with("__") && name.ends_with("__")))
+        .collect::<RuntimeDict>();
+
+    from_value(&RuntimeValue::Dict(Rc::new(RefCell::new(variables))))
+}
+
+/// Dicts become maps, tuples, lists and sets sequences, and instances of script
+/// classes maps of their attributes. Functions, classes and modules can't be serialized,
+/// nor containers that contain themselves.
+impl Serialize for RuntimeValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        NestedValue {
+            value: self,
+            visiting: &RefCell::default(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Value being serialized inside the containers and instances in `visiting`
+struct NestedValue<'v> {
+    value: &'v RuntimeValue,
+    visiting: &'v RefCell<Vec<*const ()>>,
+}
+
+impl<'v> NestedValue<'v> {
+    fn child(&self, value: &'v RuntimeValue) -> Self {
+        Self {
+            value,
+            visiting: self.visiting,
+        }
+    }
+
+    fn serialize_value<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.value {
+            RuntimeValue::Nil => serializer.serialize_none(),
+            RuntimeValue::Bool(b) => serializer.serialize_bool(*b),
+            RuntimeValue::Number(n) => serializer.serialize_i64(*n),
+            RuntimeValue::String(s) => serializer.serialize_str(s),
+            RuntimeValue::Tuple(items) => {
+                serializer.collect_seq(items.iter().map(|item| self.child(item)))
+            }
+            RuntimeValue::List(items) => {
+                serializer.collect_seq(items.borrow().iter().map(|item| self.child(item)))
+            }
+            RuntimeValue::Set(set) => {
+                serializer.collect_seq(set.borrow().iter().map(|item| self.child(item)))
+            }
+            RuntimeValue::Dict(dict) => serializer.collect_map(
+                dict.borrow()
+                    .iter()
+                    .map(|(key, value)| (self.child(key), self.child(value))),
+            ),
+            RuntimeValue::Object(object) if !object.class.is_exception() => {
+                let attrs = object.attrs.borrow();
+                let mut names = attrs.keys().collect::<Vec<_>>();
+                names.sort();
+
+                let mut map = serializer.serialize_map(Some(names.len()))?;
+
+                for name in names {
+                    map.serialize_entry(name, &self.child(&attrs[name]))?;
+                }
+
+                map.end()
+            }
+            value => Err(ser::Error::custom(format!(
+                "Object of type {} is not serializable",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+impl Serialize for NestedValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Some(id) = visit_id(self.value) else {
+            return self.serialize_value(serializer);
+        };
+
+        check_nesting(&self.visiting.borrow(), id).map_err(ser::Error::custom)?;
+
+        self.visiting.borrow_mut().push(id);
+        let result = self.serialize_value(serializer);
+        self.visiting.borrow_mut().pop();
+        result
+    }
+}
+
+/// Identifies the values that can contain others, and so themselves
+fn visit_id(value: &RuntimeValue) -> Option<*const ()> {
+    match value {
+        RuntimeValue::Object(object) => Some(Rc::as_ptr(object) as *const ()),
+        value => value.container_id(),
+    }
+}
+
+/// Fails if the value `id` is already being visited, or values nest too deep
+fn check_nesting(visiting: &[*const ()], id: *const ()) -> Result<(), String> {
+    if visiting.contains(&id) {
+        return Err("circular reference detected".to_owned());
+    }
+
+    if visiting.len() >= MAX_NESTING {
+        return Err(format!(
+            "values are nested more than {MAX_NESTING} levels deep"
+        ));
+    }
+
+    Ok(())
+}
+
+impl<'de> Deserialize<'de> for RuntimeValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = RuntimeValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a value scripts can hold")
+    }
+
+    fn visit_unit<E>(self) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::Nil)
+    }
+
+    fn visit_none<E>(self) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::Nil)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<RuntimeValue, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::Bool(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::Number(n))
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<RuntimeValue, E> {
+        i64::try_from(n)
+            .map(RuntimeValue::Number)
+            .map_err(|_| E::invalid_value(Unexpected::Unsigned(n), &"an int"))
+    }
+
+    /// Scripts only have ints, whole floats are accepted
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<RuntimeValue, E> {
+        // `as` saturates, so the range is checked first, 2^63 itself is out of it
+        if !(i64::MIN as f64..-(i64::MIN as f64)).contains(&n) {
+            return Err(E::invalid_value(Unexpected::Float(n), &"an int"));
+        }
+
+        let int = n as i64;
+
+        match int as f64 == n {
+            true => Ok(RuntimeValue::Number(int)),
+            false => Err(E::invalid_type(Unexpected::Float(n), &"an int")),
+        }
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::String(s.to_owned()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<RuntimeValue, E> {
+        Ok(RuntimeValue::String(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<RuntimeValue, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+
+        Ok(RuntimeValue::List(Rc::new(RefCell::new(items))))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RuntimeValue, A::Error> {
+        let mut dict = RuntimeDict::default();
+
+        while let Some((key, value)) = map.next_entry()? {
+            dict.insert(key, value)
+                .map_err(|err| de::Error::custom(err.message()))?;
+        }
+
+        Ok(RuntimeValue::Dict(Rc::new(RefCell::new(dict))))
+    }
+}
+
+/// Reads the value like a self-describing format, so it can fill any `Deserialize` type
+impl<'de> Deserializer<'de> for RuntimeValue {
+    type Error = ConversionError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        NestedDeserializer::root(self).deserialize_any(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        NestedDeserializer::root(self).deserialize_option(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        NestedDeserializer::root(self).deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        NestedDeserializer::root(self).deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+impl IntoDeserializer<'_, ConversionError> for RuntimeValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Value being deserialized inside the containers and instances in `visiting`
+struct NestedDeserializer {
+    value: RuntimeValue,
+    visiting: Rc<RefCell<Vec<*const ()>>>,
+}
+
+impl NestedDeserializer {
+    fn root(value: RuntimeValue) -> Self {
+        Self {
+            value,
+            visiting: Rc::default(),
+        }
+    }
+
+    fn child(&self, value: RuntimeValue) -> Self {
+        Self {
+            value,
+            visiting: self.visiting.clone(),
+        }
+    }
+
+    fn deserialize_value<'de, V: Visitor<'de>>(
+        &self,
+        visitor: V,
+    ) -> Result<V::Value, ConversionError> {
+        match &self.value {
+            RuntimeValue::Nil => visitor.visit_unit(),
+            RuntimeValue::Bool(b) => visitor.visit_bool(*b),
+            RuntimeValue::Number(n) => visitor.visit_i64(*n),
+            RuntimeValue::String(s) => visitor.visit_str(s),
+            RuntimeValue::Tuple(items) => self.visit_seq(items.to_vec(), visitor),
+            RuntimeValue::List(items) => self.visit_seq(items.borrow().clone(), visitor),
+            RuntimeValue::Set(set) => {
+                self.visit_seq(set.borrow().iter().cloned().collect(), visitor)
+            }
+            RuntimeValue::Dict(dict) => {
+                let entries = dict
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect();
+
+                self.visit_map(entries, visitor)
+            }
+            RuntimeValue::Object(object) if !object.class.is_exception() => {
+                let entries = object
+                    .attrs
+                    .borrow()
+                    .iter()
+                    .map(|(name, value)| (RuntimeValue::String(name.clone()), value.clone()))
+                    .collect();
+
+                self.visit_map(entries, visitor)
+            }
+            value => Err(de::Error::invalid_type(
+                Unexpected::Other(value.type_name()),
+                &visitor,
+            )),
+        }
+    }
+
+    fn visit_seq<'de, V: Visitor<'de>>(
+        &self,
+        items: Vec<RuntimeValue>,
+        visitor: V,
+    ) -> Result<V::Value, ConversionError> {
+        let len = items.len();
+        let mut seq = Seq {
+            items: items.into_iter(),
+            idx: 0,
+            visiting: self.visiting.clone(),
+        };
+
+        let value = visitor.visit_seq(&mut seq)?;
+
+        match seq.items.len() {
+            0 => Ok(value),
+            _ => Err(de::Error::invalid_length(len, &"fewer items")),
+        }
+    }
+
+    fn visit_map<'de, V: Visitor<'de>>(
+        &self,
+        entries: Vec<(RuntimeValue, RuntimeValue)>,
+        visitor: V,
+    ) -> Result<V::Value, ConversionError> {
+        visitor.visit_map(Map {
+            entries: entries.into_iter(),
+            value: None,
+            visiting: self.visiting.clone(),
+        })
+    }
+}
+
+impl<'de> Deserializer<'de> for NestedDeserializer {
+    type Error = ConversionError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let Some(id) = visit_id(&self.value) else {
+            return self.deserialize_value(visitor);
+        };
+
+        check_nesting(&self.visiting.borrow(), id).map_err(de::Error::custom)?;
+
+        self.visiting.borrow_mut().push(id);
+        let result = self.deserialize_value(visitor);
+        self.visiting.borrow_mut().pop();
+        result
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            RuntimeValue::Nil => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants are strings, the others dicts with a single entry
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match &self.value {
+            RuntimeValue::String(variant) => visitor.visit_enum(Enum {
+                variant: variant.clone(),
+                value: None,
+            }),
+            RuntimeValue::Dict(dict) if dict.borrow().len() == 1 => {
+                let (variant, value) = {
+                    let dict = dict.borrow();
+                    let (variant, value) = dict.iter().next().expect("Already checked above");
+                    (variant.clone(), value.clone())
+                };
+
+                let RuntimeValue::String(variant) = variant else {
+                    return Err(de::Error::invalid_type(
+                        Unexpected::Other(variant.type_name()),
+                        &"a variant name",
+                    ));
+                };
+
+                visitor
+                    .visit_enum(Enum {
+                        variant: variant.clone(),
+                        value: Some(self.child(value)),
+                    })
+                    .map_err(|err| err.inside(RuntimeValue::String(variant).repr()))
+            }
+            value => Err(de::Error::invalid_type(
+                Unexpected::Other(value.type_name()),
+                &"a string or a dict with a single entry",
+            )),
+        }
+    }
+
+    /// Unknown fields of structs are skipped without looking at them,
+    /// so they can hold functions and other values that don't deserialize
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+struct Seq {
+    items: vec::IntoIter<RuntimeValue>,
+    idx: usize,
+    visiting: Rc<RefCell<Vec<*const ()>>>,
+}
+
+impl<'de> SeqAccess<'de> for Seq {
+    type Error = ConversionError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        let Some(item) = self.items.next() else {
+            return Ok(None);
+        };
+
+        let idx = self.idx;
+        self.idx += 1;
+
+        seed.deserialize(NestedDeserializer {
+            value: item,
+            visiting: self.visiting.clone(),
+        })
+        .map(Some)
+        .map_err(|err| err.inside(idx))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct Map {
+    entries: vec::IntoIter<(RuntimeValue, RuntimeValue)>,
+    /// Value of the last key, with the key's repr for errors
+    value: Option<(String, RuntimeValue)>,
+    visiting: Rc<RefCell<Vec<*const ()>>>,
+}
+
+impl<'de> MapAccess<'de> for Map {
+    type Error = ConversionError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+
+        let repr = key.repr();
+        self.value = Some((repr.clone(), value));
+
+        seed.deserialize(key)
+            .map(Some)
+            .map_err(|err| err.inside(repr))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let (repr, value) = self
+            .value
+            .take()
+            .expect("serde asks for a value after its key");
+
+        seed.deserialize(NestedDeserializer {
+            value,
+            visiting: self.visiting.clone(),
+        })
+        .map_err(|err| err.inside(repr))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct Enum {
+    variant: String,
+    value: Option<NestedDeserializer>,
+}
+
+impl<'de> EnumAccess<'de> for Enum {
+    type Error = ConversionError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), Self::Error> {
+        let variant = seed.deserialize(RuntimeValue::String(self.variant.clone()))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Enum {
+    type Error = ConversionError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.value.map(|value| value.value) {
+            None | Some(RuntimeValue::Nil) => Ok(()),
+            Some(value) => Err(de::Error::invalid_type(
+                Unexpected::Other(value.type_name()),
+                &"nil",
+            )),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self.into_value())
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.into_value().deserialize_any(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.into_value().deserialize_any(visitor)
+    }
+}
+
+impl Enum {
+    /// Value of the variant, `nil` for unit variants written as strings
+    fn into_value(self) -> NestedDeserializer {
+        self.value
+            .unwrap_or_else(|| NestedDeserializer::root(RuntimeValue::Nil))
+    }
+}
+
+/// Mismatches read `value["port"] must be u16, not string "80"`
+impl de::Error for ConversionError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ConversionError {
+            kind: ExceptionKind::ValueError,
+            path: Vec::new(),
+            message: format!("is invalid: {msg}"),
+        }
+    }
+
+    fn invalid_type(unexpected: Unexpected, expected: &dyn de::Expected) -> Self {
+        ConversionError {
+            kind: ExceptionKind::TypeError,
+            path: Vec::new(),
+            message: format!("must be {expected}, not {unexpected}"),
+        }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        ConversionError {
+            kind: ExceptionKind::ValueError,
+            path: Vec::new(),
+            message: format!("is missing field '{field}'"),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use indexmap::IndexMap;
+
+use super::dict::HashKey;
+use super::error::RuntimeResult;
+use super::value::RuntimeValue;
+
+/// Insertion-ordered set, like Python's `set` but iterating in a stable order
+#[derive(Clone, Debug, Default)]
+pub struct RuntimeSet {
+    items: IndexMap<HashKey, RuntimeValue>,
+}
+
+impl RuntimeSet {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn contains(&self, value: &RuntimeValue) -> RuntimeResult<bool> {
+        Ok(self.items.contains_key(&HashKey::new(value)?))
+    }
+
+    /// Adds `value`, keeping the first one added if an equal value is present
+    pub fn insert(&mut self, value: RuntimeValue) -> RuntimeResult {
+        self.items.entry(HashKey::new(&value)?).or_insert(value);
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RuntimeValue> {
+        self.items.values()
+    }
+}
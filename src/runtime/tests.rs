@@ -0,0 +1,966 @@
+use std::fs;
+use std::rc::Rc;
+
+use crate::ast::MAX_NESTING;
+use crate::ast::node::{AstExpr, AstScope, AstUnaryOp, SpannedExpr};
+use crate::diagnostic::{Diagnostic, codes};
+use crate::lexer::Lexer;
+use crate::literal;
+use crate::runtime::Scope;
+use crate::runtime::error::{ExceptionKind, RuntimeError};
+use crate::runtime::loader::{FileLoader, MemoryLoader, ModuleLoader};
+use crate::runtime::module::{Importer, MAIN_MODULE};
+use crate::runtime::value::RuntimeValue;
+use crate::source_map::SourceMap;
+
+fn run(sources: &mut SourceMap, content: &str) -> Result<Rc<Scope>, RuntimeError> {
+    let id = sources.add("main.py", content);
+    let file = &sources[id];
+    let tree = AstScope::from_tokens(file, Lexer::from_source(file).unwrap()).unwrap();
+
+    let scope = Scope::new();
+    scope.run(&tree).map(|_| scope)
+}
+
+fn run_err(content: &str) -> RuntimeError {
+    run(&mut SourceMap::new(), content).expect_err("script should raise")
+}
+
+/// `repr()` of a global after running `content`
+fn global(content: &str, name: &str) -> String {
+    let scope = run(&mut SourceMap::new(), content).unwrap();
+    scope.get_variable(name).unwrap().0.borrow().repr()
+}
+
+#[test]
+fn arithmetic() {
+    let scope = run(
+        &mut SourceMap::new(),
+        "a = 7 * 3\nb = True + 1\nc = \"ab\" * 2",
+    )
+    .unwrap();
+
+    let get = |name| scope.get_variable(name).unwrap().0.borrow().clone();
+
+    assert!(matches!(get("a"), RuntimeValue::Number(21)));
+    assert!(matches!(get("b"), RuntimeValue::Number(2)));
+    assert!(matches!(get("c"), RuntimeValue::String(s) if s == "abab"));
+}
+
+#[test]
+fn type_error() {
+    let err = run_err("a = 1 - \"a\"");
+
+    assert_eq!(err.kind(), Some(ExceptionKind::TypeError));
+    assert_eq!(
+        err.message(),
+        "unsupported operand type(s) for -: 'int' and 'str'"
+    );
+}
+
+#[test]
+fn name_error() {
+    let err = run_err("a = 1\nb = a + c");
+
+    assert_eq!(err.kind(), Some(ExceptionKind::NameError));
+    assert_eq!(err.to_string(), "NameError: name 'c' is not defined");
+}
+
+#[test]
+fn zero_division_error() {
+    let err = run_err("a = 1 / 0");
+
+    assert_eq!(err.kind(), Some(ExceptionKind::ZeroDivisionError));
+    assert_eq!(err.message(), "division by zero");
+}
+
+#[test]
+fn overflow_error() {
+    let err = run_err("a = 9223372036854775807 + 1");
+
+    assert_eq!(err.kind(), Some(ExceptionKind::OverflowError));
+}
+
+#[test]
+fn traceback() {
+    let mut sources = SourceMap::new();
+    let err = run(&mut sources, "a = 1\nif a:\n  b = a / 0\n").unwrap_err();
+
+    assert_eq!(
+        err.traceback(&sources).to_string(),
+        "Traceback (most recent call last):\n  \
+           File \"main.py\", line 3, in <module>\n    \
+             b = a / 0\n\
+         ZeroDivisionError: division by zero"
+    );
+}
+
+#[test]
+fn into_diagnostic() {
+    let mut sources = SourceMap::new();
+    let err = run(&mut sources, "a = \"x\" - 1").unwrap_err();
+    let diagnostic = Diagnostic::from(err);
+
+    assert_eq!(diagnostic.code, codes::UNCAUGHT_EXCEPTION);
+    assert_eq!(
+        diagnostic.to_string(),
+        "error[E0201]: TypeError: unsupported operand type(s) for -: 'str' and 'int'"
+    );
+
+    let span = diagnostic.primary_span().unwrap();
+    assert_eq!(
+        &sources[span.source].contents()[span.from..span.to],
+        "\"x\" - 1"
+    );
+}
+
+#[test]
+fn functions() {
+    let src = "def add(a, b):\n  return a + b\nr = add(1, 2) * 2";
+    assert_eq!(global(src, "r"), "6");
+
+    let err = run_err("def f(a):\n  a = 1\nf(1, 2)");
+    assert_eq!(
+        err.message(),
+        "f() takes 1 positional argument but 2 were given"
+    );
+
+    let err = run_err("def f(a, b):\n  a = 1\nf()");
+    assert_eq!(
+        err.message(),
+        "f() missing 2 required positional arguments: 'a' and 'b'"
+    );
+}
+
+#[test]
+fn logic_and_comparisons() {
+    assert_eq!(global("r = 0 or \"a\"", "r"), "\"a\"");
+    assert_eq!(global("r = 1 and 0", "r"), "0");
+    assert_eq!(global("r = not 1 > 2 and 2 != 3", "r"), "True");
+    assert_eq!(global("r = (1, \"a\") == (1, \"a\")", "r"), "True");
+    assert_eq!(global("r = 10 - 3 - 2", "r"), "5");
+    assert_eq!(global("r = 1 if 0 else 1 / 0 if 0 else 3", "r"), "3");
+    assert_eq!(
+        global("r = (1 / 0 if False else \"b\", 2)", "r"),
+        "(\"b\", 2)"
+    );
+
+    let err = run_err("r = 1 < \"a\"");
+    assert_eq!(
+        err.message(),
+        "'<' not supported between instances of 'int' and 'str'"
+    );
+}
+
+#[test]
+fn except_hierarchy() {
+    let src = "try:\n  raise KeyError(\"k\")\nexcept ValueError:\n  r = 1\nexcept (TypeError, LookupError) as e:\n  r = e";
+    assert_eq!(global(src, "r"), "KeyError(\"k\")");
+
+    let err = run_err("try:\n  1 / 0\nexcept LookupError:\n  r = 1");
+    assert_eq!(err.kind(), Some(ExceptionKind::ZeroDivisionError));
+
+    let err = run_err("try:\n  1 / 0\nexcept 1:\n  r = 1");
+    assert_eq!(
+        err.message(),
+        "catching classes that do not inherit from BaseException is not allowed"
+    );
+}
+
+#[test]
+fn else_and_finally() {
+    let src = "log = \"\"\ntry:\n  log = log + \"try;\"\nexcept:\n  log = log + \"except;\"\nelse:\n  log = log + \"else;\"\nfinally:\n  log = log + \"finally;\"";
+    assert_eq!(global(src, "log"), "\"try;else;finally;\"");
+
+    let src = "log = \"\"\ntry:\n  try:\n    1 / 0\n  finally:\n    log = log + \"finally;\"\nexcept ArithmeticError:\n  log = log + \"except;\"";
+    assert_eq!(global(src, "log"), "\"finally;except;\"");
+}
+
+#[test]
+fn finally_on_break_and_return() {
+    let src = "log = \"\"\ni = 0\nwhile True:\n  i = i + 1\n  try:\n    if i == 2:\n      break\n    continue\n  finally:\n    log = log + i";
+    assert_eq!(global(src, "log"), "\"12\"");
+
+    let src =
+        "def f():\n  global log\n  try:\n    return 1\n  finally:\n    log = \"finally\"\nr = f()";
+    assert_eq!(global(src, "r"), "1");
+    assert_eq!(global(src, "log"), "\"finally\"");
+
+    // `finally` overrides the pending return and swallows exceptions
+    let src = "def f():\n  try:\n    1 / 0\n  finally:\n    return 2\nr = f()";
+    assert_eq!(global(src, "r"), "2");
+}
+
+#[test]
+fn raise_from() {
+    let mut sources = SourceMap::new();
+    let err = run(
+        &mut sources,
+        "try:\n  1 / 0\nexcept ZeroDivisionError as e:\n  raise ValueError(\"bad\") from e",
+    )
+    .unwrap_err();
+
+    assert_eq!(err.to_string(), "ValueError: bad");
+    assert_eq!(
+        err.cause.as_ref().map(|cause| cause.to_string()).as_deref(),
+        Some("ZeroDivisionError: division by zero")
+    );
+    assert_eq!(
+        err.traceback(&sources).to_string(),
+        "Traceback (most recent call last):\n  \
+           File \"main.py\", line 2, in <module>\n    \
+             1 / 0\n\
+         ZeroDivisionError: division by zero\n\
+         \n\
+         The above exception was the direct cause of the following exception:\n\
+         \n\
+         Traceback (most recent call last):\n  \
+           File \"main.py\", line 4, in <module>\n    \
+             raise ValueError(\"bad\") from e\n\
+         ValueError: bad"
+    );
+
+    let err = run_err("try:\n  1 / 0\nexcept:\n  raise ValueError from nil");
+    assert!(err.cause.is_none() && err.suppress_context);
+}
+
+#[test]
+fn implicit_context() {
+    let err = run_err("try:\n  1 / 0\nexcept:\n  a = b");
+
+    assert_eq!(err.kind(), Some(ExceptionKind::NameError));
+    assert_eq!(
+        err.context.as_ref().and_then(|context| context.kind()),
+        Some(ExceptionKind::ZeroDivisionError)
+    );
+}
+
+#[test]
+fn bare_raise() {
+    let src = "def f():\n  try:\n    raise KeyError(\"k\")\n  except KeyError:\n    raise\nf()";
+    let err = run_err(src);
+
+    assert_eq!(err.kind(), Some(ExceptionKind::KeyError));
+    assert!(err.context.is_none());
+    assert_eq!(err.traceback.len(), 2);
+
+    let err = run_err("raise");
+    assert_eq!(err.message(), "No active exception to reraise");
+
+    let err = run_err("raise 1");
+    assert_eq!(err.message(), "exceptions must derive from BaseException");
+}
+
+#[test]
+fn call_traceback() {
+    let mut sources = SourceMap::new();
+    let err = run(
+        &mut sources,
+        "def inner(x):\n  return x / 0\ndef outer():\n  return inner(1)\nouter()",
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err.traceback(&sources).to_string(),
+        "Traceback (most recent call last):\n  \
+           File \"main.py\", line 5, in <module>\n    \
+             outer()\n  \
+           File \"main.py\", line 4, in outer\n    \
+             return inner(1)\n  \
+           File \"main.py\", line 2, in inner\n    \
+             return x / 0\n\
+         ZeroDivisionError: division by zero"
+    );
+}
+
+#[test]
+fn classes() {
+    let src = "class Counter:\n  start = 10\n  def __init__(self, step):\n    self.count = self.start\n    self.step = step\n  def bump(self):\n    self.count = self.count + self.step\n    return self\nc = Counter(2)\nc.bump().bump()\nCounter.start = 0\nd = Counter(1)\nbump = d.bump\nbump()\nresult = (c.count, d.count, Counter.__name__)";
+
+    assert_eq!(global(src, "result"), "(14, 1, \"Counter\")");
+    assert_eq!(
+        global(src, "bump"),
+        "<bound method Counter.bump of <Counter object>>"
+    );
+
+    let err = run_err("class A:\n  x = 1\na = A()\nb = a.y");
+    assert_eq!(err.kind(), Some(ExceptionKind::AttributeError));
+    assert_eq!(err.message(), "'A' object has no attribute 'y'");
+
+    let err = run_err("class A:\n  x = 1\na = A(1)");
+    assert_eq!(err.message(), "A() takes no arguments");
+
+    let err = run_err("class A:\n  def __init__(self):\n    return 1\na = A()");
+    assert_eq!(err.message(), "__init__() should return nil, not 'int'");
+
+    let err = run_err("a = 1\na.b = 2");
+    assert_eq!(err.message(), "'int' object has no attribute 'b'");
+}
+
+#[test]
+fn method_resolution_order() {
+    let src = "class O:\n  x = 1\nclass A(O):\n  x = 2\nclass B(O):\n  x = 3\n  y = 3\nclass C(A, B):\n  z = 0\nnames = (C.__mro__, C.x, C.y)";
+    assert_eq!(
+        global(src, "names"),
+        "((<class 'C'>, <class 'A'>, <class 'B'>, <class 'O'>), 2, 3)"
+    );
+
+    let err = run_err("class X:\n  a = 1\nclass Y(X):\n  a = 2\nclass Z(X, Y):\n  a = 3");
+    assert_eq!(err.kind(), Some(ExceptionKind::TypeError));
+    assert_eq!(
+        err.message(),
+        "Cannot create a consistent method resolution order (MRO) for bases X, Y"
+    );
+}
+
+#[test]
+fn super_calls() {
+    let src = "class A:\n  def name(self):\n    return \"A\"\nclass B(A):\n  def name(self):\n    return \"B\" + super().name()\nclass C(A):\n  def name(self):\n    return \"C\" + super().name()\nclass D(B, C):\n  def name(self):\n    return \"D\" + super().name()\nresult = D().name()";
+    assert_eq!(global(src, "result"), "\"DBCA\"");
+
+    let src = "class AppError(ValueError):\n  def __init__(self, code):\n    super().__init__(\"failed\", code)\n    self.code = code\ntry:\n  raise AppError(3)\nexcept ValueError as e:\n  result = (e.args, e.code, isinstance(e, Exception))";
+    assert_eq!(global(src, "result"), "((\"failed\", 3), 3, True)");
+
+    let err = run_err("def f():\n  return super()\nf()");
+    assert_eq!(err.kind(), Some(ExceptionKind::RuntimeError));
+    assert_eq!(err.message(), "super(): __class__ cell not found");
+}
+
+#[test]
+fn operator_overloading() {
+    let vector = "class Vec:\n  def __init__(self, x, y):\n    self.x = x\n    self.y = y\n  def __add__(self, other):\n    if not isinstance(other, Vec):\n      return NotImplemented\n    return Vec(self.x + other.x, self.y + other.y)\n  def __mul__(self, k):\n    return Vec(self.x * k, self.y * k)\n  def __rmul__(self, k):\n    return self * k\n  def __neg__(self):\n    return self * -1\n  def __eq__(self, other):\n    return isinstance(other, Vec) and self.x == other.x and self.y == other.y\n  def __lt__(self, other):\n    return self.x < other.x\n  def __repr__(self):\n    return \"Vec(\" + self.x + \", \" + self.y + \")\"\n";
+
+    let src = format!(
+        "{vector}a = Vec(1, 2) + Vec(3, 4)\nb = 2 * a\nc = -a\nresult = (repr(a), repr(b), str(c), a == Vec(4, 6), a != Vec(4, 6), Vec(5, 0) > a)"
+    );
+    assert_eq!(
+        global(&src, "result"),
+        "(\"Vec(4, 6)\", \"Vec(8, 12)\", \"Vec(-4, -6)\", True, False, True)"
+    );
+
+    let src = format!(
+        "{vector}a = Vec(1, 2)\nresult = ([a] == [Vec(1, 2)], (a, 1) == (Vec(1, 2), 1), {{\"k\": a}} == {{\"k\": Vec(1, 2)}}, [a] != [Vec(1, 2)], [a] == [Vec(2, 2)])"
+    );
+    assert_eq!(global(&src, "result"), "(True, True, True, False, False)");
+
+    let err = run_err(&format!("{vector}a = Vec(1, 2) + 1"));
+    assert_eq!(
+        err.message(),
+        "unsupported operand type(s) for +: 'Vec' and 'int'"
+    );
+
+    let err = run_err(&format!("{vector}a = Vec(1, 2) <= Vec(3, 4)"));
+    assert_eq!(
+        err.message(),
+        "'<=' not supported between instances of 'Vec' and 'Vec'"
+    );
+}
+
+#[test]
+fn reflected_subclass_first() {
+    let src = "class A:\n  def __add__(self, other):\n    return \"A\"\n  def __radd__(self, other):\n    return \"rA\"\nclass B(A):\n  def __radd__(self, other):\n    return \"rB\"\nresult = (A() + B(), B() + A(), 1 + A())";
+    assert_eq!(global(src, "result"), "(\"rB\", \"A\", \"rA\")");
+}
+
+#[test]
+fn container_protocols() {
+    let src = "class Grid:\n  def __init__(self):\n    self.cells = (0, 0, 0)\n  def __len__(self):\n    return len(self.cells)\n  def __getitem__(self, idx):\n    return self.cells[idx]\n  def __setitem__(self, idx, value):\n    before = self.cells\n    self.cells = (value, before[1], before[2])\n  def __call__(self, n):\n    return n * 2\n  def __str__(self):\n    return \"grid\"\nclass Empty:\n  def __len__(self):\n    return 0\ng = Grid()\ng[0] = 5\nt = (1, 2, 3)\nresult = (g[0], g[-1], len(g), g(21), str(g), \"abc\"[-1], t[1], len(\"héllo\"), not Empty(), str((g, 1)))";
+    assert_eq!(
+        global(src, "result"),
+        "(5, 0, 3, 42, \"grid\", \"c\", 2, 5, True, \"(<Grid object>, 1)\")"
+    );
+
+    let err = run_err("t = (1, 2)\nx = t[2]");
+    assert_eq!(err.kind(), Some(ExceptionKind::IndexError));
+    assert_eq!(err.message(), "tuple index out of range");
+
+    let err = run_err("x = 1[0]");
+    assert_eq!(err.message(), "'int' object is not subscriptable");
+
+    let err = run_err("t = (1, 2)\nt[0] = 1");
+    assert_eq!(
+        err.message(),
+        "'tuple' object does not support item assignment"
+    );
+
+    let err = run_err("class A:\n  def __bool__(self):\n    return 1\nif A():\n  x = 1");
+    assert_eq!(err.message(), "__bool__ should return bool, returned int");
+
+    let err = run_err("class A:\n  x = 1\nA()()");
+    assert_eq!(err.message(), "'A' object is not callable");
+}
+
+#[test]
+fn cyclic_containers() {
+    let src = "a = [1, 2]\na[0] = a\nd = {}\nd[\"k\"] = d\nd[\"a\"] = a\nresult = (repr(a), str(d), a == a, d == d)";
+    assert_eq!(
+        global(src, "result"),
+        "(\"[[...], 2]\", \"{\\\"k\\\": {...}, \\\"a\\\": [[...], 2]}\", True, True)"
+    );
+    assert_eq!(global(src, "a"), "[[...], 2]");
+
+    let deep =
+        "def nest(n):\n  x = []\n  i = 0\n  while i < n:\n    x = [x]\n    i = i + 1\n  return x\n";
+
+    let err = run_err(&format!("{deep}x = repr(nest(300))"));
+    assert_eq!(err.kind(), Some(ExceptionKind::RecursionError));
+
+    let err = run_err(&format!("{deep}x = nest(300) == nest(300)"));
+    assert_eq!(err.kind(), Some(ExceptionKind::RecursionError));
+    assert_eq!(
+        err.message(),
+        "maximum recursion depth exceeded in comparison"
+    );
+
+    let src = format!("{deep}x = nest(100)\nresult = (x == nest(100), x == nest(99))");
+    assert_eq!(global(&src, "result"), "(True, False)");
+}
+
+#[test]
+fn deep_nesting() {
+    let depth = MAX_NESTING - 10;
+    let negated = "-".repeat(depth);
+    assert_eq!(global(&format!("result = {negated}1"), "result"), "1");
+
+    let blocks: String = (0..depth)
+        .map(|level| format!("{}if True:\n", "  ".repeat(level)))
+        .collect();
+    let src = format!("{blocks}{}result = 1", "  ".repeat(depth));
+    assert_eq!(global(&src, "result"), "1");
+
+    // A generator suspended deep inside an expression resumes on the same stack
+    let src =
+        format!("def gen():\n  yield {negated}(yield 1)\ng = gen()\nnext(g)\nresult = g.send(2)");
+    assert_eq!(global(&src, "result"), "2");
+
+    // Trees built without the parser are still bounded
+    let mut expr = literal!(@ast 1);
+    for _ in 0..MAX_NESTING {
+        expr = SpannedExpr::from(AstExpr::UnaryOp {
+            op: AstUnaryOp::Neg,
+            right: expr.into(),
+        });
+    }
+    let err = Scope::new().visit_expr(&expr).unwrap_err();
+    assert_eq!(err.kind(), Some(ExceptionKind::RecursionError));
+    assert_eq!(err.message(), "maximum expression depth exceeded");
+}
+
+#[test]
+fn list_methods() {
+    let src = "a = [1, 2]\na.append(3)\na.extend((4, 5))\nb = a.pop()\nc = a.pop(0)\nd = [0] * 3\ne = 2 * [1, 2]\nresult = (a, b, c, d, e, [1] * -1)";
+    assert_eq!(
+        global(src, "result"),
+        "([2, 3, 4], 5, 1, [0, 0, 0], [1, 2, 1, 2], [])"
+    );
+
+    let src = "t = (1,)\nresult = (t * 3, 2 * (1, 2), t * 0, t * 2 == (1, 1))";
+    assert_eq!(global(src, "result"), "((1, 1, 1), (1, 2, 1, 2), (), True)");
+
+    let err = run_err("a = []\na.pop()");
+    assert_eq!(err.kind(), Some(ExceptionKind::IndexError));
+    assert_eq!(err.message(), "pop from empty list");
+
+    let err = run_err("a = [1]\na.pop(5)");
+    assert_eq!(err.kind(), Some(ExceptionKind::IndexError));
+    assert_eq!(err.message(), "pop index out of range");
+}
+
+#[test]
+fn closures() {
+    let src = "def counter(start):\n  count = start\n  def inc():\n    nonlocal count\n    count = count + 1\n    return count\n  def get():\n    return count\n  return (inc, get)\na = counter(0)\nb = counter(10)\na[0]()\na[0]()\nresult = (a[0](), a[1](), b[0](), b[1]())";
+    assert_eq!(global(src, "result"), "(3, 3, 11, 11)");
+
+    // Closures see the value a variable has when they're called
+    let src = "def make():\n  fs = ()\n  i = 0\n  while i < 3:\n    def f():\n      return i\n    fs = fs + (f,)\n    i = i + 1\n  return fs\nfs = make()\nresult = (fs[0](), fs[2]())";
+    assert_eq!(global(src, "result"), "(3, 3)");
+
+    let src = "def outer():\n  x = 1\n  def middle():\n    def inner():\n      nonlocal x\n      x = x + 1\n    inner()\n  middle()\n  return x\nresult = outer()";
+    assert_eq!(global(src, "result"), "2");
+
+    let src = "x = 1\ndef outer():\n  x = 2\n  def inner():\n    global x\n    x = 3\n  inner()\n  return x\nresult = (outer(), x)";
+    assert_eq!(global(src, "result"), "(2, 3)");
+
+    let src = "def outer():\n  x = 1\n  def middle():\n    nonlocal x\n    def inner():\n      nonlocal x\n      x = x + 1\n    inner()\n  middle()\n  return x\nresult = outer()";
+    assert_eq!(global(src, "result"), "2");
+
+    let err = run_err("y = 0\ndef f():\n  global y\n  del y\nf()\nresult = y");
+    assert_eq!(err.message(), "name 'y' is not defined");
+}
+
+#[test]
+fn static_scoping() {
+    let err = run_err("x2 = 5\ndef unbound():\n  y = x2\n  x2 = 1\n  return y\nunbound()");
+    assert_eq!(err.kind(), Some(ExceptionKind::UnboundLocalError));
+    assert_eq!(
+        err.message(),
+        "cannot access local variable 'x2' where it is not associated with a value"
+    );
+
+    // Assigned on a branch that didn't run, still local
+    let err = run_err("x = 5\ndef f(flag):\n  if flag:\n    x = 1\n  return x\nf(False)");
+    assert_eq!(err.kind(), Some(ExceptionKind::UnboundLocalError));
+
+    let err = run_err("def f():\n  x = 1\n  del x\n  return x\nf()");
+    assert_eq!(err.kind(), Some(ExceptionKind::UnboundLocalError));
+
+    let err = run_err("def outer():\n  def inner():\n    return x\n  inner()\n  x = 1\nouter()");
+    assert_eq!(err.kind(), Some(ExceptionKind::NameError));
+    assert_eq!(
+        err.message(),
+        "cannot access free variable 'x' where it is not associated with a value in enclosing scope"
+    );
+
+    let src = "x = 5\ndef f(flag):\n  if flag:\n    x = 1\n  return x\nresult = f(True)";
+    assert_eq!(global(src, "result"), "1");
+
+    let src = "def f():\n  global g\n  g = 1\nf()\nresult = g";
+    assert_eq!(global(src, "result"), "1");
+
+    let src = "x = 1\ndef f():\n  return (lambda: x)()\nresult = f()";
+    assert_eq!(global(src, "result"), "1");
+
+    let src = "def f():\n  x = 1\n  class A:\n    nonlocal x\n    x = 2\n    y = x\n  return (x, A.y)\nresult = f()";
+    assert_eq!(global(src, "result"), "(2, 2)");
+
+    let err = run_err("def f():\n  global missing\n  return missing\nf()");
+    assert_eq!(err.kind(), Some(ExceptionKind::NameError));
+    assert_eq!(err.message(), "name 'missing' is not defined");
+}
+
+#[test]
+fn first_class_functions() {
+    let src = "class Person:\n  def __init__(self, name, age):\n    self.name = name\n    self.age = age\n  def __repr__(self):\n    return self.name\npeople = [Person(\"ann\", 31), Person(\"bob\", 25), Person(\"cid\", 31)]\nby_age = sorted(people, key=lambda p: p.age)\noldest = sorted(people, key=lambda p: p.age, reverse=True)\nresult = (repr(by_age), repr(oldest), sorted(\"cab\"), sorted((3, 1, 2)))";
+    assert_eq!(
+        global(src, "result"),
+        "(\"[bob, ann, cid]\", \"[ann, cid, bob]\", [\"a\", \"b\", \"c\"], [1, 2, 3])"
+    );
+
+    let src = "def twice(f):\n  return lambda x: f(f(x))\nadd_one = lambda x: x + 1\nfs = [add_one, twice(add_one)]\nfs[0] = twice(fs[1])\nresult = (fs[0](0), fs[1](0), add_one == add_one, add_one == twice(add_one), add_one.__name__)";
+    assert_eq!(global(src, "result"), "(4, 2, True, False, \"<lambda>\")");
+    assert_eq!(global(src, "add_one"), "<function <lambda>>");
+
+    let src = "def sub(a, b):\n  return a - b\nresult = (sub(b=1, a=5), sub(5, b=2))";
+    assert_eq!(global(src, "result"), "(4, 3)");
+
+    let err = run_err("def sub(a, b):\n  return a - b\nsub(1, a=2)");
+    assert_eq!(err.message(), "sub() got multiple values for argument 'a'");
+
+    let err = run_err("def sub(a, b):\n  return a - b\nsub(1, c=2)");
+    assert_eq!(
+        err.message(),
+        "sub() got an unexpected keyword argument 'c'"
+    );
+
+    let err = run_err("x = sorted((1, \"a\"))");
+    assert_eq!(
+        err.message(),
+        "'<' not supported between instances of 'str' and 'int'"
+    );
+
+    let err = run_err("x = len([], key=1)");
+    assert_eq!(err.message(), "len() takes no keyword arguments");
+}
+
+#[test]
+fn argument_binding() {
+    let src = "def f(a, /, b=1, *args, c, d=2, **kw):\n  return (a, b, args, c, d, kw)\nfirst = f(1, c=3)\nthird = f(1, 2, 3, 4, c=5, a=6, e=7)";
+    assert_eq!(global(src, "first"), "(1, 1, (), 3, 2, {})");
+    assert_eq!(
+        global(src, "third"),
+        "(1, 2, (3, 4), 5, 2, {\"a\": 6, \"e\": 7})"
+    );
+
+    let src = "def f(*args, **kw):\n  return (args, kw)\nxs = [1, 2]\nd = {\"a\": 1}\nresult = f(0, *xs, *\"ab\", b=2, **d)";
+    assert_eq!(
+        global(src, "result"),
+        "((0, 1, 2, \"a\", \"b\"), {\"b\": 2, \"a\": 1})"
+    );
+
+    let src = "def count(seen=[0]):\n  seen[0] = seen[0] + 1\n  return seen[0]\nresult = (count(), count(), count([5]))";
+    assert_eq!(global(src, "result"), "(1, 2, 6)");
+
+    let src =
+        "n = 1\ndef f(x=n):\n  return x\nn = 2\nresult = (f(), {1: \"a\", True: \"b\", (1, 2): n})";
+    assert_eq!(global(src, "result"), "(1, {1: \"b\", (1, 2): 2})");
+
+    let err = run_err("def f(a, b=1):\n  return a\nf(1, 2, 3)");
+    assert_eq!(
+        err.message(),
+        "f() takes from 1 to 2 positional arguments but 3 were given"
+    );
+    assert_eq!(err.notes(), ["signature: f(a, b=1)"]);
+
+    let err = run_err("def f(a, b, c, *, d, e):\n  return a\nf(d=1)");
+    assert_eq!(
+        err.message(),
+        "f() missing 3 required positional arguments: 'a', 'b', and 'c'"
+    );
+
+    let err = run_err("def f(a, *, d, e):\n  return a\nf(1)");
+    assert_eq!(
+        err.message(),
+        "f() missing 2 required keyword-only arguments: 'd' and 'e'"
+    );
+    assert_eq!(err.notes(), ["signature: f(a, *, d, e)"]);
+
+    let err = run_err("def f(a, b, /):\n  return a\nf(a=1, b=2)");
+    assert_eq!(
+        err.message(),
+        "f() got some positional-only arguments passed as keyword arguments: 'a, b'"
+    );
+
+    let err = run_err("def f(**kw):\n  return kw\nf(a=1, **{\"a\": 2})");
+    assert_eq!(
+        err.message(),
+        "f() got multiple values for keyword argument 'a'"
+    );
+
+    let err = run_err("def f(*args):\n  return args\nf(*1)");
+    assert_eq!(
+        err.message(),
+        "f() argument after * must be an iterable, not int"
+    );
+
+    let err = run_err(
+        "def f(*args):\n  return args\ndef g():\n  yield 1\n  raise ValueError(\"bad\")\nf(*g())",
+    );
+    assert_eq!(err.to_string(), "ValueError: bad");
+
+    let err = run_err("d = {[]: 1}");
+    assert_eq!(err.message(), "unhashable type: 'list'");
+
+    let err = run_err("d = {\"a\": 1}\nx = d[\"b\"]");
+    assert_eq!(err.kind(), Some(ExceptionKind::KeyError));
+    assert_eq!(err.message(), "\"b\"");
+}
+
+#[test]
+fn comprehensions() {
+    let src = "xs = [1, 2, 3, 4]\nsquares = [x * x for x in xs if x != 2]\npairs = [(x, y) for x in xs if x < 3 for y in \"ab\"]\nindex = {name: i for i, name in [(0, \"a\"), (1, \"b\")]}\nparity = {x - x / 2 * 2 for x in xs}\nresult = (squares, pairs, index, parity, {1, 2, 1}, set())";
+    assert_eq!(
+        global(src, "result"),
+        "([1, 9, 16], [(1, \"a\"), (1, \"b\"), (2, \"a\"), (2, \"b\")], {\"a\": 0, \"b\": 1}, {1, 0}, {1, 2}, set())"
+    );
+
+    let src = "fs = [lambda: x for x in (1, 2)]\nresult = [f() for f in fs]";
+    assert_eq!(global(src, "result"), "[2, 2]");
+
+    let err = run_err("ys = [x for x in (1, 2)]\ny = x");
+    assert_eq!(err.message(), "name 'x' is not defined");
+
+    let err = run_err("ys = [x for x, y in (1, 2)]");
+    assert_eq!(err.message(), "'int' object is not iterable");
+
+    let err = run_err("ys = [x for x, y in [(1, 2, 3)]]");
+    assert_eq!(err.message(), "too many values to unpack (expected 2)");
+
+    let err = run_err("ys = {[] for x in (1, 2)}");
+    assert_eq!(err.message(), "unhashable type: 'list'");
+}
+
+#[test]
+fn generator_expressions() {
+    let src = "calls = [0]\ndef f(x):\n  calls[0] = calls[0] + 1\n  return x * 10\ng = (f(x) for x in [1, 2, 3])\nbefore = calls[0]\nfirst = next(g)\nafter = calls[0]\nrest = list(g)\nresult = (before, first, after, rest, next(g, \"done\"), g)";
+    assert_eq!(
+        global(src, "result"),
+        "(0, 10, 1, [20, 30], \"done\", <generator object <genexpr>>)"
+    );
+
+    let src = "result = sorted(y for y in (x * 2 for x in [3, 1, 2]) if y > 2)";
+    assert_eq!(global(src, "result"), "[4, 6]");
+
+    let err = run_err("g = (x for x in 1)");
+    assert_eq!(err.message(), "'int' object is not iterable");
+
+    let err = run_err("g = (x for x in [])\nnext(g)");
+    assert_eq!(err.kind(), Some(ExceptionKind::StopIteration));
+
+    let mut sources = SourceMap::new();
+    let err = run(
+        &mut sources,
+        "g = (1 / x for x in [1, 0])\na = next(g)\nb = next(g)",
+    )
+    .expect_err("script should raise");
+    let frames = err
+        .traceback
+        .iter()
+        .map(|frame| frame.name.as_str())
+        .collect::<Vec<_>>();
+
+    assert_eq!(frames, ["<genexpr>", "<module>"]);
+    assert_eq!(err.kind(), Some(ExceptionKind::ZeroDivisionError));
+}
+
+#[test]
+fn generator_functions() {
+    let src = "def count(n):\n  while n > 0:\n    yield n\n    n = n - 1\n  return \"done\"\ng = count(2)\nresult = (g, list(g), next(g, 0))";
+    assert_eq!(
+        global(src, "result"),
+        "(<generator object count>, [2, 1], 0)"
+    );
+
+    let src = "def f():\n  yield 1\n  return 2\ng = f()\nnext(g)\ntry:\n  next(g)\nexcept StopIteration as e:\n  result = (e.value, e.args)";
+    assert_eq!(global(src, "result"), "(2, (2,))");
+
+    let src = "def acc():\n  total = 0\n  while True:\n    total = total + (yield total)\ng = acc()\nresult = [next(g), g.send(1), g.send(2)]";
+    assert_eq!(global(src, "result"), "[0, 1, 3]");
+
+    let err = run_err("def f():\n  yield 1\nf().send(1)");
+    assert_eq!(
+        err.message(),
+        "can't send non-None value to a just-started generator"
+    );
+
+    let err = run_err("def f():\n  raise StopIteration\n  yield\nnext(f())");
+    assert_eq!(err.kind(), Some(ExceptionKind::RuntimeError));
+    assert_eq!(err.message(), "generator raised StopIteration");
+}
+
+#[test]
+fn generator_close() {
+    let src = "def f():\n  try:\n    yield 1\n    yield 2\n  finally:\n    seen[0] = seen[0] + 1\nseen = [0]\ng = f()\nnext(g)\ng.close()\ng.close()\nresult = (seen, next(g, \"done\"))";
+    assert_eq!(global(src, "result"), "([1], \"done\")");
+
+    let src = "seen = [0]\ndef f():\n  try:\n    yield 1\n  finally:\n    seen[0] = seen[0] + 1\ng = f()\nnext(g)\ng = nil\nresult = seen";
+    assert_eq!(global(src, "result"), "[1]");
+
+    let err = run_err(
+        "def f():\n  try:\n    yield 1\n  except GeneratorExit:\n    yield 2\ng = f()\nnext(g)\ng.close()",
+    );
+    assert_eq!(err.message(), "generator ignored GeneratorExit");
+
+    let src = "def f():\n  try:\n    yield 1\n  except Exception:\n    result[0] = \"caught\"\nresult = [\"not caught\"]\ng = f()\nnext(g)\ng.close()";
+    assert_eq!(global(src, "result"), "[\"not caught\"]");
+
+    // Finalizers run after the replaced or deleted value is released
+    let src = "def g():\n  try:\n    yield 1\n  finally:\n    y = x\nx = g()\nnext(x)\nx = 2";
+    assert_eq!(global(src, "x"), "2");
+
+    for store in [
+        "del x", "d[0] = 2", "del d[0]", "l[0] = 2", "del l[0]", "o.a = 2",
+    ] {
+        let src = format!(
+            "class O:\n  pass\ndef g():\n  try:\n    yield 1\n  finally:\n    y = (x, d, l, o.a)\nx = g()\nd = {{0: x}}\nl = [x]\no = O()\no.a = x\nnext(x)\nx = 1\n{store}"
+        );
+        run(&mut SourceMap::new(), &src).unwrap();
+    }
+}
+
+#[test]
+fn yield_from() {
+    let src = "def inner():\n  x = yield 1\n  yield x\n  return \"inner\"\ndef outer():\n  r = yield from inner()\n  yield from [r, \"list\"]\ng = outer()\nresult = [next(g), g.send(\"sent\"), next(g), next(g), next(g, \"end\")]";
+    assert_eq!(
+        global(src, "result"),
+        "[1, \"sent\", \"inner\", \"list\", \"end\"]"
+    );
+
+    let src = "seen = [0]\ndef inner():\n  try:\n    yield 1\n  finally:\n    seen[0] = seen[0] + 1\ndef outer():\n  yield from inner()\ng = outer()\nnext(g)\ng.close()\nresult = seen";
+    assert_eq!(global(src, "result"), "[1]");
+
+    let err = run_err("def f():\n  g = (yield x for x in [1])\n  next(g)\n  yield\nnext(f())");
+    assert_eq!(err.message(), "'yield' inside generator expression");
+}
+
+#[test]
+fn match_patterns() {
+    let describe = "class Point:\n  __match_args__ = (\"x\", \"y\")\n  def __init__(self, x, y):\n    self.x = x\n    self.y = y\ndef describe(value):\n  match value:\n    case nil:\n      return \"nil\"\n    case True:\n      return \"true\"\n    case 0 | 1 as small:\n      return (\"small\", small)\n    case [x, *middle, y]:\n      return (\"seq\", x, middle, y)\n    case {\"kind\": \"move\", **others}:\n      return (\"move\", others)\n    case Point(0, y=y):\n      return (\"on y axis\", y)\n    case Point(x, y) if x == y:\n      return (\"diagonal\", x)\n    case str(s):\n      return (\"text\", s)\n    case _:\n      return \"other\"\n";
+
+    let cases = [
+        ("nil", "\"nil\""),
+        ("True", "\"true\""),
+        ("1", "(\"small\", 1)"),
+        ("2", "\"other\""),
+        ("[1, 2, 3, 4]", "(\"seq\", 1, [2, 3], 4)"),
+        ("(1, 2)", "(\"seq\", 1, [], 2)"),
+        ("[1]", "\"other\""),
+        ("{\"kind\": \"move\", \"dx\": 1}", "(\"move\", {\"dx\": 1})"),
+        ("{\"kind\": \"stop\"}", "\"other\""),
+        ("Point(0, 5)", "(\"on y axis\", 5)"),
+        ("Point(2, 2)", "(\"diagonal\", 2)"),
+        ("Point(1, 2)", "\"other\""),
+        ("\"hi\"", "(\"text\", \"hi\")"),
+    ];
+
+    for (value, expected) in cases {
+        let src = format!("{describe}result = describe({value})");
+        assert_eq!(global(&src, "result"), expected, "describe({value})");
+    }
+
+    let src = "match 1, 2:\n  case a, b:\n    result = a + b";
+    assert_eq!(global(src, "result"), "3");
+
+    let src =
+        "x = 5\nmatch x:\n  case y if y > 10:\n    result = \"big\"\n  case _:\n    result = y";
+    assert_eq!(global(src, "result"), "5");
+
+    let err = run_err("class A:\n  x = 1\nmatch A():\n  case A(1):\n    nil");
+    assert_eq!(
+        err.message(),
+        "A() accepts 0 positional sub-patterns (1 given)"
+    );
+
+    let err = run_err("match 1:\n  case x.y():\n    nil");
+    assert_eq!(err.kind(), Some(ExceptionKind::NameError));
+}
+
+#[test]
+fn assert_pass_and_del() {
+    let err = run_err("x = 1\nassert x  >  2");
+    assert_eq!(err.kind(), Some(ExceptionKind::AssertionError));
+    assert_eq!(err.message(), "x  >  2");
+
+    let err = run_err("x = 1\nassert x > 2, \"x is \" + str(x)");
+    assert_eq!(err.message(), "x is 1");
+    assert_eq!(err.notes(), ["assert x > 2"]);
+
+    let src = "def f():\n  pass\nclass C:\n  pass\nif True:\n  pass\nresult = (f(), C.__name__)";
+    assert_eq!(global(src, "result"), "(nil, \"C\")");
+
+    let src = "xs = [1, 2, 3]; d = {\"a\": 1, \"b\": 2}\ndel xs[-1], d[\"a\"]\nresult = (xs, d)";
+    assert_eq!(global(src, "result"), "([1, 2], {\"b\": 2})");
+
+    let src = "def f():\n  return; x = 1\ndef g():\n  yield; yield 2\ndef h():\n  try:\n    1 / 0\n  except:\n    raise; x = 1\nresult = (f(), list(g()))";
+    assert_eq!(global(src, "result"), "(nil, [nil, 2])");
+    let err = run_err(&format!("{src}\nh()"));
+    assert_eq!(err.kind(), Some(ExceptionKind::ZeroDivisionError));
+
+    let err = run_err("x = 1\ndel x\nx");
+    assert_eq!(err.message(), "name 'x' is not defined");
+
+    let err = run_err("d = {}\ndel d[\"k\"]");
+    assert_eq!(err.kind(), Some(ExceptionKind::KeyError));
+
+    let err = run_err("class A:\n  pass\na = A()\na.x = 1\ndel a.x\na.x");
+    assert_eq!(err.message(), "'A' object has no attribute 'x'");
+
+    let err = run_err("del (1, 2)[0]");
+    assert_eq!(
+        err.message(),
+        "'tuple' object doesn't support item deletion"
+    );
+}
+
+/// Runs `main` as the main module, importing from `loader`
+fn run_modules(loader: impl ModuleLoader + 'static, main: &str) -> Result<Rc<Scope>, RuntimeError> {
+    let importer = Rc::new(Importer::new(loader));
+    let id = importer.sources_mut().add("main.py", main);
+    let tree = {
+        let sources = importer.sources();
+        let file = &sources[id];
+        AstScope::from_tokens(file, Lexer::from_source(file).unwrap()).unwrap()
+    };
+
+    let scope = Scope::module(&importer, MAIN_MODULE, None);
+    scope.run(&tree).map(|_| scope)
+}
+
+#[test]
+fn imports() {
+    let loader = MemoryLoader::new()
+        .with_module("counter", "count = 0")
+        .with_module("once", "import counter\ncounter.count = counter.count + 1")
+        .with_package("pkg", "from .mod import value\nname = __name__")
+        .with_module("pkg.mod", "from . import helper\nvalue = helper.twice(21)")
+        .with_module("pkg.helper", "def twice(x):\n  return x * 2")
+        .with_package(
+            "pkg.sub",
+            "from ..helper import twice as double\nfour = double(2)",
+        );
+
+    let scope = run_modules(
+        loader.clone(),
+        "import once\nimport once\nfrom counter import count\n\
+         import pkg.sub\nimport pkg.mod as m\nfrom pkg import helper, value as v\n\
+         r = (count, pkg.name, pkg.sub.four, m.value, v, helper.twice(1), __name__)",
+    )
+    .unwrap();
+
+    assert_eq!(
+        scope.get_variable("r").unwrap().0.borrow().repr(),
+        "(1, \"pkg\", 4, 42, 42, 2, \"__main__\")"
+    );
+    assert_eq!(
+        scope.get_variable("m").unwrap().0.borrow().to_string(),
+        "<module 'pkg.mod' from 'pkg/mod.pi'>"
+    );
+
+    let err = run_modules(loader.clone(), "import missing").unwrap_err();
+    assert_eq!(err.kind(), Some(ExceptionKind::ModuleNotFoundError));
+    assert_eq!(err.message(), "No module named 'missing'");
+
+    let err = run_modules(loader.clone(), "from pkg import missing").unwrap_err();
+    assert_eq!(err.kind(), Some(ExceptionKind::ImportError));
+    assert_eq!(
+        err.message(),
+        "cannot import name 'missing' from 'pkg' (pkg/__init__.pi)"
+    );
+
+    let err = run_modules(loader.clone(), "from . import counter").unwrap_err();
+    assert_eq!(
+        err.message(),
+        "attempted relative import with no known parent package"
+    );
+
+    let err = run_modules(loader, "import counter\ncounter.missing").unwrap_err();
+    assert_eq!(err.message(), "module 'counter' has no attribute 'missing'");
+}
+
+#[test]
+fn module_errors() {
+    let loader = MemoryLoader::new()
+        .with_module("broken", "a = 1\nb = (")
+        .with_module("fails", "def f():\n  return 1 / 0\nf()");
+
+    let err = run_modules(loader.clone(), "import broken").unwrap_err();
+    assert_eq!(err.kind(), Some(ExceptionKind::SyntaxError));
+    assert!(err.message().ends_with("(broken.pi, line 2)"));
+
+    let err = run_modules(loader, "x = 1\nimport fails").unwrap_err();
+    let frames = err
+        .traceback
+        .iter()
+        .map(|frame| frame.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(frames, ["f", "<module>", "<module>"]);
+}
+
+#[test]
+fn circular_imports() {
+    let loader = MemoryLoader::new()
+        .with_module("a", "from b import f\nvalue = 1")
+        .with_module("b", "from a import value\ndef f():\n  return value");
+
+    let err = run_modules(loader, "import a").unwrap_err();
+    assert_eq!(err.kind(), Some(ExceptionKind::ImportError));
+    assert_eq!(
+        err.message(),
+        "cannot import name 'value' from partially initialized module 'a' \
+         (most likely due to a circular import: a -> b -> a)"
+    );
+    assert_eq!(err.traceback.len(), 3);
+
+    // Plain imports get the partially initialized module
+    let loader = MemoryLoader::new()
+        .with_module("a", "import b\nvalue = 1")
+        .with_module("b", "import a\ndef f():\n  return a.value");
+
+    let scope = run_modules(loader, "import a\nr = a.b.f()").unwrap();
+    assert_eq!(scope.get_variable("r").unwrap().0.borrow().repr(), "1");
+}
+
+#[test]
+fn file_loader() {
+    let dir = std::env::temp_dir().join(format!("ruspy-{}", std::process::id()));
+    fs::create_dir_all(dir.join("pkg")).unwrap();
+    fs::write(dir.join("pkg/__init__.pi"), "from .mod import value").unwrap();
+    fs::write(dir.join("pkg/mod.pi"), "value = 42").unwrap();
+
+    let loader = FileLoader::new(Vec::from([dir.join("missing"), dir.clone()]));
+    let result = run_modules(loader, "from pkg import value");
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    let scope = result.unwrap();
+    assert_eq!(scope.get_variable("value").unwrap().0.borrow().repr(), "42");
+}
@@ -1,6 +1,17 @@
+use core::fmt;
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use super::builtins::Builtin;
+use super::class::{RuntimeClass, RuntimeObject, RuntimeSuper};
+use super::dict::RuntimeDict;
+use super::error::{ExceptionKind, RuntimeError, RuntimeResult, exception_message};
+use super::function::{RuntimeFunction, RuntimeMethod};
+use super::generator::RuntimeGenerator;
+use super::module::RuntimeModule;
+use super::native::{NativeFunction, NativeObject};
+use super::set::RuntimeSet;
+
 pub trait AsBool {
     fn as_bool(&self) -> bool;
 }
@@ -13,6 +24,9 @@ pub trait AsString {
     fn as_string(&self) -> &'static str;
 }
 
+/// `name=value` arguments of a call, in the order they were given
+pub type KeywordArgs = Vec<(String, RuntimeValue)>;
+
 #[derive(Debug, Clone)]
 pub struct RuntimeVariable(pub Rc<RefCell<RuntimeValue>>);
 
@@ -20,9 +34,26 @@ pub struct RuntimeVariable(pub Rc<RefCell<RuntimeValue>>);
 pub enum RuntimeValue {
     #[default]
     Nil,
+    /// Returned by special methods that don't support their operands
+    NotImplemented,
     Bool(bool),
     Number(i64),
     String(String),
+    Tuple(Rc<[RuntimeValue]>),
+    List(Rc<RefCell<Vec<RuntimeValue>>>),
+    Dict(Rc<RefCell<RuntimeDict>>),
+    Set(Rc<RefCell<RuntimeSet>>),
+    Generator(Rc<RuntimeGenerator>),
+    Function(Rc<RuntimeFunction>),
+    Builtin(Builtin),
+    NativeFunction(Rc<NativeFunction>),
+    Method(Rc<RuntimeMethod>),
+    Class(Rc<RuntimeClass>),
+    Object(Rc<RuntimeObject>),
+    /// Instance of a Rust type, see [`super::native::ScriptClass`]
+    NativeObject(Rc<NativeObject>),
+    Super(Rc<RuntimeSuper>),
+    Module(Rc<RuntimeModule>),
 }
 
 impl From<RuntimeValue> for RuntimeVariable {
@@ -35,6 +66,310 @@ impl RuntimeValue {
     pub fn wrap(self) -> RuntimeVariable {
         RuntimeVariable(Rc::new(RefCell::new(self)))
     }
+
+    /// Integer value of numbers and bools, which count as `0` and `1`
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            RuntimeValue::Bool(b) => Some(b.as_num()),
+            RuntimeValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Whether [`Scope::items`](super::Scope::items) can iterate the value
+    pub fn is_iterable(&self) -> bool {
+        matches!(
+            self,
+            RuntimeValue::String(_)
+                | RuntimeValue::Tuple(_)
+                | RuntimeValue::List(_)
+                | RuntimeValue::Dict(_)
+                | RuntimeValue::Set(_)
+                | RuntimeValue::Generator(_)
+        )
+    }
+
+    /// Name of the value's type, as shown in error messages
+    pub fn type_name(&self) -> &str {
+        match self {
+            RuntimeValue::Nil => "nil",
+            RuntimeValue::NotImplemented => "NotImplementedType",
+            RuntimeValue::Bool(_) => "bool",
+            RuntimeValue::Number(_) => "int",
+            RuntimeValue::String(_) => "str",
+            RuntimeValue::Tuple(_) => "tuple",
+            RuntimeValue::List(_) => "list",
+            RuntimeValue::Dict(_) => "dict",
+            RuntimeValue::Set(_) => "set",
+            RuntimeValue::Generator(_) => "generator",
+            RuntimeValue::Function(_) => "function",
+            RuntimeValue::Builtin(_) | RuntimeValue::NativeFunction(_) => {
+                "builtin_function_or_method"
+            }
+            RuntimeValue::Method(_) => "method",
+            RuntimeValue::Class(_) => "type",
+            RuntimeValue::Object(object) => &object.class.name,
+            RuntimeValue::NativeObject(object) => object.class,
+            RuntimeValue::Super(_) => "super",
+            RuntimeValue::Module(_) => "module",
+        }
+    }
+
+    /// Same as Python's `repr()`
+    pub fn repr(&self) -> String {
+        Repr(self).to_string()
+    }
+
+    /// Identifies lists, dicts, sets and tuples while they're printed or compared,
+    /// see [`MAX_NESTING`]
+    pub(crate) fn container_id(&self) -> Option<*const ()> {
+        match self {
+            RuntimeValue::Tuple(items) => Some(Rc::as_ptr(items) as *const ()),
+            RuntimeValue::List(items) => Some(Rc::as_ptr(items) as *const ()),
+            RuntimeValue::Dict(dict) => Some(Rc::as_ptr(dict) as *const ()),
+            RuntimeValue::Set(set) => Some(Rc::as_ptr(set) as *const ()),
+            _ => None,
+        }
+    }
+
+    /// Shown in place of a container that contains itself
+    pub(crate) fn recursive_repr(&self) -> &'static str {
+        match self {
+            RuntimeValue::Tuple(_) => "(...)",
+            RuntimeValue::List(_) => "[...]",
+            _ => "{...}",
+        }
+    }
+
+    /// Same as Python's `==`: numbers compare by value, objects by identity
+    pub fn equals(&self, other: &RuntimeValue) -> bool {
+        self.try_equals(other).unwrap_or(false)
+    }
+
+    /// Same as [`RuntimeValue::equals`], fails on containers nested too deep
+    pub fn try_equals(&self, other: &RuntimeValue) -> RuntimeResult<bool> {
+        self.equals_nested(other, 0)
+    }
+
+    fn equals_nested(&self, other: &RuntimeValue, depth: usize) -> RuntimeResult<bool> {
+        use RuntimeValue::*;
+
+        if let (Some(a), Some(b)) = (self.as_int(), other.as_int()) {
+            return Ok(a == b);
+        }
+
+        if let (Some(a), Some(b)) = (self.container_id(), other.container_id()) {
+            if a == b {
+                return Ok(true);
+            }
+
+            if depth >= MAX_NESTING {
+                return Err(RuntimeError::new(
+                    ExceptionKind::RecursionError,
+                    "maximum recursion depth exceeded in comparison",
+                ));
+            }
+        }
+
+        let items_equal = |a: &[RuntimeValue], b: &[RuntimeValue]| -> RuntimeResult<bool> {
+            if a.len() != b.len() {
+                return Ok(false);
+            }
+
+            for (a, b) in a.iter().zip(b) {
+                if !a.equals_nested(b, depth + 1)? {
+                    return Ok(false);
+                }
+            }
+
+            Ok(true)
+        };
+
+        Ok(match (self, other) {
+            (Nil, Nil) | (NotImplemented, NotImplemented) => true,
+            (String(a), String(b)) => a == b,
+            (Tuple(a), Tuple(b)) => items_equal(a, b)?,
+            (List(a), List(b)) => {
+                // Comparing may not change the lists, but they can't stay borrowed
+                let (a, b) = (a.borrow().clone(), b.borrow().clone());
+                items_equal(&a, &b)?
+            }
+            (Dict(a), Dict(b)) => {
+                let entries = a
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect::<Vec<_>>();
+
+                if entries.len() != b.borrow().len() {
+                    return Ok(false);
+                }
+
+                for (key, value) in entries {
+                    let other = b.borrow().get(&key);
+
+                    match other {
+                        Ok(Some(other)) if value.equals_nested(&other, depth + 1)? => {}
+                        _ => return Ok(false),
+                    }
+                }
+
+                true
+            }
+            (Set(a), Set(b)) => {
+                let (a, b) = (a.borrow(), b.borrow());
+                a.len() == b.len() && a.iter().all(|item| b.contains(item).unwrap_or(false))
+            }
+            (Generator(a), Generator(b)) => Rc::ptr_eq(a, b),
+            (Function(a), Function(b)) => Rc::ptr_eq(a, b),
+            (Builtin(a), Builtin(b)) => a == b,
+            (NativeFunction(a), NativeFunction(b)) => Rc::ptr_eq(a, b),
+            (Method(a), Method(b)) => {
+                a.receiver.equals_nested(&b.receiver, depth + 1)? && a.function.equals(&b.function)
+            }
+            (Class(a), Class(b)) => Rc::ptr_eq(a, b),
+            (Object(a), Object(b)) => Rc::ptr_eq(a, b),
+            (NativeObject(a), NativeObject(b)) => Rc::ptr_eq(a, b),
+            (Module(a), Module(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        })
+    }
+
+    /// Writes `str()` of the value, or `repr()` if `repr` is set.
+    /// `printing` holds the containers being written, to stop on cycles
+    fn fmt_nested(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        printing: &mut Vec<*const ()>,
+        repr: bool,
+    ) -> fmt::Result {
+        if let Some(id) = self.container_id() {
+            if printing.contains(&id) || printing.len() >= MAX_NESTING {
+                return f.write_str(self.recursive_repr());
+            }
+
+            printing.push(id);
+            let result = self.fmt_items(f, printing);
+            printing.pop();
+
+            return result;
+        }
+
+        match self {
+            RuntimeValue::Nil => f.write_str("nil"),
+            RuntimeValue::NotImplemented => f.write_str("NotImplemented"),
+            RuntimeValue::Bool(b) => f.write_str(b.as_string()),
+            RuntimeValue::Number(n) => write!(f, "{n}"),
+            RuntimeValue::String(s) if repr => write!(f, "{s:?}"),
+            RuntimeValue::String(s) => f.write_str(s),
+            RuntimeValue::Generator(generator) => write!(f, "{generator:?}"),
+            RuntimeValue::Function(function) => write!(f, "{function:?}"),
+            RuntimeValue::Builtin(builtin) => write!(f, "<built-in function {}>", builtin.name()),
+            RuntimeValue::NativeFunction(function) => write!(f, "{function:?}"),
+            RuntimeValue::Method(method) => write!(f, "{method}"),
+            RuntimeValue::Class(class) => write!(f, "{class:?}"),
+            RuntimeValue::Super(proxy) => write!(f, "{proxy:?}"),
+            RuntimeValue::Module(module) => write!(f, "{module:?}"),
+            RuntimeValue::Object(object) if object.class.is_exception() && repr => {
+                match object.get_attr("args") {
+                    Some(RuntimeValue::Tuple(args)) if args.len() == 1 => {
+                        write!(f, "{}(", object.class.name)?;
+                        args[0].fmt_nested(f, printing, true)?;
+                        f.write_str(")")
+                    }
+                    Some(args @ RuntimeValue::Tuple(_)) => {
+                        f.write_str(&object.class.name)?;
+                        args.fmt_nested(f, printing, true)
+                    }
+                    _ => write!(f, "{}()", object.class.name),
+                }
+            }
+            RuntimeValue::Object(object) if object.class.is_exception() => {
+                f.write_str(&exception_message(object))
+            }
+            RuntimeValue::Object(object) => write!(f, "<{} object>", object.class.name),
+            RuntimeValue::NativeObject(object) => write!(f, "{object:?}"),
+            RuntimeValue::Tuple(_)
+            | RuntimeValue::List(_)
+            | RuntimeValue::Dict(_)
+            | RuntimeValue::Set(_) => unreachable!("containers are written by `fmt_items`"),
+        }
+    }
+
+    /// Writes the items of a container, which `str()` shows the same as `repr()`
+    fn fmt_items(&self, f: &mut fmt::Formatter<'_>, printing: &mut Vec<*const ()>) -> fmt::Result {
+        let mut write_items = |f: &mut fmt::Formatter<'_>, items: &[RuntimeValue]| {
+            for (idx, item) in items.iter().enumerate() {
+                if idx != 0 {
+                    f.write_str(", ")?;
+                }
+
+                item.fmt_nested(f, printing, true)?;
+            }
+
+            Ok(())
+        };
+
+        match self {
+            RuntimeValue::Tuple(items) => {
+                f.write_str("(")?;
+                write_items(f, items)?;
+
+                if items.len() == 1 {
+                    f.write_str(",")?;
+                }
+
+                f.write_str(")")
+            }
+            RuntimeValue::List(items) => {
+                f.write_str("[")?;
+                write_items(f, &items.borrow())?;
+                f.write_str("]")
+            }
+            RuntimeValue::Dict(dict) => {
+                f.write_str("{")?;
+
+                for (idx, (key, value)) in dict.borrow().iter().enumerate() {
+                    if idx != 0 {
+                        f.write_str(", ")?;
+                    }
+
+                    key.fmt_nested(f, printing, true)?;
+                    f.write_str(": ")?;
+                    value.fmt_nested(f, printing, true)?;
+                }
+
+                f.write_str("}")
+            }
+            RuntimeValue::Set(set) if set.borrow().is_empty() => f.write_str("set()"),
+            RuntimeValue::Set(set) => {
+                f.write_str("{")?;
+                write_items(f, &set.borrow().iter().cloned().collect::<Vec<_>>())?;
+                f.write_str("}")
+            }
+            _ => unreachable!("only containers have items"),
+        }
+    }
+}
+
+/// Nesting of containers printed or compared, past it cycles are assumed
+/// when printing and comparisons fail with `RecursionError`
+pub(crate) const MAX_NESTING: usize = 200;
+
+/// Writes `repr()` of a value
+struct Repr<'a>(&'a RuntimeValue);
+
+impl fmt::Display for Repr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_nested(f, &mut Vec::new(), true)
+    }
+}
+
+impl fmt::Display for RuntimeValue {
+    /// Same as Python's `str()`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_nested(f, &mut Vec::new(), false)
+    }
 }
 
 impl AsBool for RuntimeValue {
@@ -44,6 +379,21 @@ impl AsBool for RuntimeValue {
             RuntimeValue::Bool(b) => *b,
             RuntimeValue::Number(n) => *n != 0,
             RuntimeValue::String(s) => !s.is_empty(),
+            RuntimeValue::Tuple(items) => !items.is_empty(),
+            RuntimeValue::List(items) => !items.borrow().is_empty(),
+            RuntimeValue::Dict(dict) => !dict.borrow().is_empty(),
+            RuntimeValue::Set(set) => !set.borrow().is_empty(),
+            RuntimeValue::NotImplemented
+            | RuntimeValue::Function(_)
+            | RuntimeValue::Builtin(_)
+            | RuntimeValue::NativeFunction(_)
+            | RuntimeValue::Method(_)
+            | RuntimeValue::Class(_)
+            | RuntimeValue::Object(_)
+            | RuntimeValue::NativeObject(_)
+            | RuntimeValue::Super(_)
+            | RuntimeValue::Module(_)
+            | RuntimeValue::Generator(_) => true,
         }
     }
 }
@@ -0,0 +1,202 @@
+#[cfg(test)]
+mod tests;
+
+use core::fmt;
+use std::fs;
+use std::io;
+use std::ops;
+use std::path::{Path, PathBuf};
+
+use ariadne::Source;
+
+/// Identifies a file registered in a [`SourceMap`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SourceId(usize);
+
+/// Anything that can resolve a [`SourceId`] back to its file
+pub trait SourceLookup {
+    fn lookup(&self, id: SourceId) -> Option<&SourceFile>;
+}
+
+/// Zero-based position of a byte offset inside a [`SourceFile`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineCol {
+    pub line: usize,
+    /// Column counted in characters
+    pub char: usize,
+    /// Column counted in UTF-8 bytes
+    pub utf8: usize,
+    /// Column counted in UTF-16 code units, as used by editors
+    pub utf16: usize,
+}
+
+#[derive(Debug)]
+pub struct SourceFile {
+    id: SourceId,
+    path: Option<PathBuf>,
+    source: Source<String>,
+    /// Byte offset where each line starts
+    lines: Vec<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceId {
+    /// Source of code that doesn't belong to any [`SourceMap`]
+    pub const ANONYMOUS: Self = Self(usize::MAX);
+}
+
+impl SourceFile {
+    fn new(id: SourceId, path: Option<PathBuf>, contents: String) -> Self {
+        let lines = std::iter::once(0)
+            .chain(contents.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+
+        Self {
+            id,
+            path,
+            source: Source::from(contents),
+            lines,
+        }
+    }
+
+    /// File that is not registered in any map, useful for one-off snippets
+    pub fn anonymous(contents: impl Into<String>) -> Self {
+        Self::new(SourceId::ANONYMOUS, None, contents.into())
+    }
+
+    pub fn id(&self) -> SourceId {
+        self.id
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn contents(&self) -> &str {
+        self.source.text()
+    }
+
+    pub fn name(&self) -> impl fmt::Display + '_ {
+        struct Name<'a>(Option<&'a Path>);
+
+        impl fmt::Display for Name<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self.0 {
+                    Some(path) => path.display().fmt(f),
+                    None => f.write_str("<anonymous>"),
+                }
+            }
+        }
+
+        Name(self.path())
+    }
+
+    /// Converts a byte offset into its line and column.
+    ///
+    /// Offsets past the end are clamped to the end of the file, and offsets
+    /// inside a multi-byte character point to that character.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let contents = self.contents();
+        let mut offset = offset.min(contents.len());
+
+        while !contents.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let line = self.lines.partition_point(|start| *start <= offset) - 1;
+        let line_start = self.lines[line];
+
+        let prefix = &contents[line_start..offset];
+
+        LineCol {
+            line,
+            char: prefix.chars().count(),
+            utf8: prefix.len(),
+            utf16: prefix.encode_utf16().count(),
+        }
+    }
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, path: impl Into<PathBuf>, contents: impl Into<String>) -> SourceId {
+        let id = SourceId(self.files.len());
+
+        self.files
+            .push(SourceFile::new(id, Some(path.into()), contents.into()));
+
+        id
+    }
+
+    /// Swaps the contents of the file registered as `id`, keeping its path
+    ///
+    /// Panics if `id` is not registered in this map
+    pub fn replace(&mut self, id: SourceId, contents: impl Into<String>) {
+        let file = &mut self.files[id.0];
+        *file = SourceFile::new(id, file.path.take(), contents.into());
+    }
+
+    /// Reads `path` from disk and registers it
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<SourceId> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+
+        Ok(self.add(path, contents))
+    }
+
+    pub fn get(&self, id: SourceId) -> Option<&SourceFile> {
+        self.files.get(id.0)
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &SourceFile> {
+        self.files.iter()
+    }
+}
+
+impl ops::Index<SourceId> for SourceMap {
+    type Output = SourceFile;
+
+    fn index(&self, id: SourceId) -> &Self::Output {
+        self.get(id)
+            .unwrap_or_else(|| panic!("{id:?} is not registered in this source map"))
+    }
+}
+
+impl fmt::Display for LineCol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line + 1, self.char + 1)
+    }
+}
+
+impl SourceLookup for SourceFile {
+    fn lookup(&self, id: SourceId) -> Option<&SourceFile> {
+        (id == self.id).then_some(self)
+    }
+}
+
+impl SourceLookup for SourceMap {
+    fn lookup(&self, id: SourceId) -> Option<&SourceFile> {
+        self.get(id)
+    }
+}
+
+impl ariadne::Cache<SourceId> for &dyn SourceLookup {
+    type Storage = String;
+
+    fn fetch(&mut self, id: &SourceId) -> Result<&Source<Self::Storage>, impl fmt::Debug> {
+        self.lookup(*id)
+            .map(|file| &file.source)
+            .ok_or_else(|| format!("{id:?} is not registered"))
+    }
+
+    fn display<'a>(&self, id: &'a SourceId) -> Option<impl fmt::Display + 'a> {
+        self.lookup(*id).map(|file| file.name().to_string())
+    }
+}
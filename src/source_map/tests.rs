@@ -0,0 +1,71 @@
+use ariadne::{Config, Label, Report, ReportKind};
+
+use crate::lexer::span::Span;
+use crate::source_map::{LineCol, SourceFile, SourceLookup, SourceMap};
+
+fn line_col(line: usize, char: usize, utf8: usize, utf16: usize) -> LineCol {
+    LineCol {
+        line,
+        char,
+        utf8,
+        utf16,
+    }
+}
+
+#[test]
+fn line_col_ascii() {
+    let file = SourceFile::anonymous("a = 1\nb = 2\n");
+
+    assert_eq!(file.line_col(0), line_col(0, 0, 0, 0));
+    assert_eq!(file.line_col(4), line_col(0, 4, 4, 4));
+    assert_eq!(file.line_col(5), line_col(0, 5, 5, 5));
+    assert_eq!(file.line_col(6), line_col(1, 0, 0, 0));
+    assert_eq!(file.line_col(8), line_col(1, 2, 2, 2));
+    assert_eq!(file.line_col(12), line_col(2, 0, 0, 0));
+    assert_eq!(file.line_col(100), line_col(2, 0, 0, 0));
+}
+
+#[test]
+fn line_col_unicode() {
+    // 'é' is 2 bytes and 1 UTF-16 unit, '🦀' is 4 bytes and 2 UTF-16 units
+    let file = SourceFile::anonymous("x\né🦀 = 1");
+
+    assert_eq!(file.line_col(4), line_col(1, 1, 2, 1));
+    assert_eq!(file.line_col(8), line_col(1, 2, 6, 3));
+    // Inside the crab
+    assert_eq!(file.line_col(6), line_col(1, 1, 2, 1));
+    assert_eq!(file.line_col(8).to_string(), "2:3");
+}
+
+#[test]
+fn map_files() {
+    let mut map = SourceMap::new();
+    let main = map.add("main.pi", "a = 1");
+    let lib = map.add("lib/utils.pi", "b = 2");
+
+    assert_ne!(main, lib);
+    assert_eq!(map[main].contents(), "a = 1");
+    assert_eq!(map[lib].name().to_string(), "lib/utils.pi");
+    assert_eq!(SourceFile::anonymous("").name().to_string(), "<anonymous>");
+    assert_eq!(map.files().count(), 2);
+}
+
+#[test]
+fn report_file_name() {
+    let mut map = SourceMap::new();
+    map.add("main.pi", "a = 1");
+    let lib = map.add("lib.pi", "b = %");
+
+    let span = Span::new(lib, 4..5);
+    let mut out = Vec::new();
+
+    Report::build(ReportKind::Error, span)
+        .with_config(Config::default().with_color(false))
+        .with_label(Label::new(span).with_message("here"))
+        .finish()
+        .write(&map as &dyn SourceLookup, &mut out)
+        .unwrap();
+
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("lib.pi:1:5"), "{out}");
+}